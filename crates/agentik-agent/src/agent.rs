@@ -1,1664 +0,0 @@
-//! Core agent implementation.
-//!
-//! The [`Agent`] struct is the main orchestration layer that connects:
-//! - AI providers (LLM interaction)
-//! - Tool execution (via [`ToolExecutor`])
-//! - Session management (persistence and context)
-//!
-//! ## Architecture
-//!
-//! ```text
-//! Agent
-//! ├── Provider (LLM interaction)
-//! ├── ToolExecutor (permission + execution)
-//! ├── SessionStore (persistence)
-//! ├── ContextManager (token tracking)
-//! └── AgentEventHandler (UI callbacks)
-//! ```
-
-use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
-
-use agentik_core::{Message, Session, ToolCall, ToolDefinition, ToolResult};
-use agentik_providers::traits::{ToolCallDelta, Usage};
-use agentik_providers::{CompletionRequest, CompletionResponse, Provider, StreamChunk};
-use agentik_repomap::{RepoMap, RepoMapSerializer, SerializeConfig};
-use agentik_session::{ContextManager, SessionStore};
-use async_trait::async_trait;
-use futures::StreamExt;
-use thiserror::Error;
-use tokio_util::sync::CancellationToken;
-use tracing::{debug, info, warn};
-
-use crate::executor::ToolExecutor;
-use crate::modes::AgentMode;
-
-// ============================================================================
-// Error Types
-// ============================================================================
-
-/// Errors that can occur during agent operations.
-#[derive(Error, Debug)]
-pub enum AgentError {
-    /// Error from the AI provider.
-    #[error("Provider error: {0}")]
-    Provider(#[from] anyhow::Error),
-
-    /// Error during session operations.
-    #[error("Session error: {0}")]
-    Session(String),
-
-    /// Error during tool execution.
-    #[error("Tool error: {0}")]
-    Tool(String),
-
-    /// Context window exceeded.
-    #[error("Context exceeded: {current} tokens (max: {max})")]
-    ContextExceeded { current: u32, max: u32 },
-
-    /// Maximum turns exceeded.
-    #[error("Maximum turns exceeded: {0}")]
-    MaxTurnsExceeded(usize),
-
-    /// Operation was cancelled.
-    #[error("Operation cancelled")]
-    Cancelled,
-
-    /// Agent not properly configured.
-    #[error("Not configured: {0}")]
-    NotConfigured(String),
-}
-
-/// Result type for agent operations.
-pub type AgentResult<T> = Result<T, AgentError>;
-
-// ============================================================================
-// Configuration
-// ============================================================================
-
-/// Configuration for the agent.
-#[derive(Debug, Clone)]
-pub struct AgentConfig {
-    /// Model identifier to use.
-    pub model: String,
-    /// System prompt.
-    pub system_prompt: Option<String>,
-    /// Maximum tokens per response.
-    pub max_tokens: u32,
-    /// Temperature for generation.
-    pub temperature: f32,
-    /// Maximum number of turns (loop iterations).
-    pub max_turns: usize,
-    /// Automatically compact when context is high.
-    pub auto_compact: bool,
-}
-
-impl Default for AgentConfig {
-    fn default() -> Self {
-        Self {
-            model: "claude-sonnet-4-20250514".to_string(),
-            system_prompt: None,
-            max_tokens: 8192,
-            temperature: 0.7,
-            max_turns: 100,
-            auto_compact: true,
-        }
-    }
-}
-
-// ============================================================================
-// Response Types
-// ============================================================================
-
-/// Token and cost usage for a single turn.
-#[derive(Debug, Clone, Default)]
-pub struct TurnUsage {
-    /// Input tokens used.
-    pub input_tokens: u32,
-    /// Output tokens generated.
-    pub output_tokens: u32,
-    /// Cached tokens used.
-    pub cached_tokens: u32,
-    /// Estimated cost in USD.
-    pub cost_usd: f64,
-}
-
-impl From<Usage> for TurnUsage {
-    fn from(usage: Usage) -> Self {
-        Self {
-            input_tokens: usage.input_tokens,
-            output_tokens: usage.output_tokens,
-            cached_tokens: usage.cached_tokens,
-            cost_usd: 0.0, // Cost calculated externally based on model pricing
-        }
-    }
-}
-
-/// Result of a single step in the agent loop.
-#[derive(Debug, Clone)]
-pub struct StepResult {
-    /// Text content from the assistant.
-    pub content: String,
-    /// Tool calls requested (empty if none).
-    pub tool_calls: Vec<ToolCall>,
-    /// Tool results from execution.
-    pub tool_results: Vec<ToolResult>,
-    /// Usage for this step.
-    pub usage: TurnUsage,
-}
-
-/// Final response from the agent.
-#[derive(Debug, Clone)]
-pub struct AgentResponse {
-    /// Final text content.
-    pub content: String,
-    /// All steps taken to reach this response.
-    pub steps: Vec<StepResult>,
-    /// Total turns taken.
-    pub turns: usize,
-    /// Total usage across all turns.
-    pub total_usage: TurnUsage,
-}
-
-// ============================================================================
-// Event Handler
-// ============================================================================
-
-/// Handler for agent events during execution.
-///
-/// Implement this trait to receive callbacks during agent operation.
-/// This is the primary interface for UI integration.
-#[async_trait]
-pub trait AgentEventHandler: Send + Sync {
-    /// Called when the agent starts thinking (waiting for LLM).
-    fn on_thinking(&self) {}
-
-    /// Called when new text is streamed from the LLM.
-    fn on_text_delta(&self, _delta: &str) {}
-
-    /// Called when a tool execution starts.
-    fn on_tool_start(&self, _call: &ToolCall) {}
-
-    /// Called when a tool execution completes.
-    fn on_tool_complete(&self, _call: &ToolCall, _result: &ToolResult) {}
-
-    /// Called when approval is needed for a tool call.
-    ///
-    /// Return `true` to approve, `false` to deny.
-    async fn on_approval_needed(&self, _call: &ToolCall, _tool: &ToolDefinition) -> bool {
-        true
-    }
-
-    /// Called when the agent completes successfully.
-    fn on_complete(&self, _response: &AgentResponse) {}
-
-    /// Called when an error occurs.
-    fn on_error(&self, _error: &AgentError) {}
-
-    /// Called when compaction starts.
-    fn on_compacting(&self) {}
-
-    /// Called with usage statistics for each turn.
-    fn on_usage(&self, _usage: &TurnUsage) {}
-}
-
-/// Default event handler that does nothing.
-pub struct NoOpEventHandler;
-
-impl AgentEventHandler for NoOpEventHandler {}
-
-// ============================================================================
-// Tool Call Builder (for streaming)
-// ============================================================================
-
-/// Builder for accumulating tool calls from streaming deltas.
-#[derive(Debug, Default)]
-struct ToolCallBuilder {
-    calls: Vec<PartialToolCall>,
-}
-
-#[derive(Debug, Default)]
-struct PartialToolCall {
-    id: Option<String>,
-    name: Option<String>,
-    arguments: String,
-}
-
-impl ToolCallBuilder {
-    fn new() -> Self {
-        Self::default()
-    }
-
-    fn apply_delta(&mut self, delta: &ToolCallDelta) {
-        // Find or create the tool call entry
-        let call = if let Some(id) = &delta.id {
-            // New tool call starting
-            self.calls.push(PartialToolCall {
-                id: Some(id.clone()),
-                name: delta.name.clone(),
-                arguments: String::new(),
-            });
-            self.calls.last_mut().unwrap()
-        } else if let Some(call) = self.calls.last_mut() {
-            call
-        } else {
-            // No active call, create one
-            self.calls.push(PartialToolCall::default());
-            self.calls.last_mut().unwrap()
-        };
-
-        // Apply name if present
-        if let Some(name) = &delta.name {
-            call.name = Some(name.clone());
-        }
-
-        // Append arguments
-        if let Some(args) = &delta.arguments {
-            call.arguments.push_str(args);
-        }
-    }
-
-    fn build(self) -> Vec<ToolCall> {
-        self.calls
-            .into_iter()
-            .filter_map(|partial| {
-                let id = partial.id?;
-                let name = partial.name?;
-                let arguments: serde_json::Value =
-                    serde_json::from_str(&partial.arguments).unwrap_or(serde_json::json!({}));
-                Some(ToolCall::new(id, name, arguments))
-            })
-            .collect()
-    }
-}
-
-// ============================================================================
-// Agent
-// ============================================================================
-
-/// The main agent orchestration struct.
-///
-/// `Agent` manages the conversation loop between the user, LLM, and tools.
-/// It handles:
-/// - Sending messages to the provider
-/// - Streaming responses
-/// - Executing tool calls
-/// - Managing session state
-/// - Context window tracking
-pub struct Agent {
-    /// AI provider for completions.
-    provider: Arc<dyn Provider>,
-    /// Tool executor for running tools.
-    executor: ToolExecutor,
-    /// Session store for persistence.
-    store: Arc<dyn SessionStore>,
-    /// Context manager for token tracking.
-    context_manager: ContextManager,
-    /// Current session.
-    session: Session,
-    /// Agent configuration.
-    config: AgentConfig,
-    /// Event handler for UI callbacks.
-    event_handler: Arc<dyn AgentEventHandler>,
-    /// Current operating mode.
-    mode: AgentMode,
-    /// Cancellation token for stopping operations.
-    cancel_token: CancellationToken,
-    /// Repository map for codebase context (shared with GetRepoMapTool).
-    repo_map: Arc<RwLock<Option<RepoMap>>>,
-}
-
-impl Agent {
-    /// Create a new agent with all dependencies.
-    pub fn new(
-        provider: Arc<dyn Provider>,
-        executor: ToolExecutor,
-        store: Arc<dyn SessionStore>,
-        session: Session,
-        config: AgentConfig,
-        event_handler: Arc<dyn AgentEventHandler>,
-    ) -> Self {
-        Self {
-            provider,
-            executor,
-            store,
-            context_manager: ContextManager::new(),
-            session,
-            config,
-            event_handler,
-            mode: AgentMode::default(),
-            cancel_token: CancellationToken::new(),
-            repo_map: Arc::new(RwLock::new(None)),
-        }
-    }
-
-    // ========================================================================
-    // Accessors
-    // ========================================================================
-
-    /// Get a reference to the current session.
-    pub fn session(&self) -> &Session {
-        &self.session
-    }
-
-    /// Get a mutable reference to the current session.
-    pub fn session_mut(&mut self) -> &mut Session {
-        &mut self.session
-    }
-
-    /// Get the current agent mode.
-    pub fn mode(&self) -> AgentMode {
-        self.mode
-    }
-
-    /// Set the agent mode.
-    pub fn set_mode(&mut self, mode: AgentMode) {
-        self.mode = mode;
-        self.executor.set_mode(mode);
-    }
-
-    /// Get a reference to the configuration.
-    pub fn config(&self) -> &AgentConfig {
-        &self.config
-    }
-
-    /// Get a reference to the context manager.
-    pub fn context_manager(&self) -> &ContextManager {
-        &self.context_manager
-    }
-
-    /// Get the shared repo map reference.
-    ///
-    /// This can be passed to GetRepoMapTool so it shares the same repo map.
-    pub fn repo_map_ref(&self) -> Arc<RwLock<Option<RepoMap>>> {
-        Arc::clone(&self.repo_map)
-    }
-
-    /// Set the repository map.
-    pub fn set_repo_map(&self, map: RepoMap) {
-        let mut guard = self.repo_map.write().unwrap();
-        *guard = Some(map);
-    }
-
-    /// Check if a repo map is loaded.
-    pub fn has_repo_map(&self) -> bool {
-        self.repo_map.read().unwrap().is_some()
-    }
-
-    /// Get the serialized repo map for prompt injection.
-    ///
-    /// Returns a compact text representation of the most important files,
-    /// limited by the specified token budget. Focus files are given priority.
-    fn get_repo_map_for_prompt(
-        &self,
-        token_budget: usize,
-        focus_files: &[PathBuf],
-    ) -> Option<String> {
-        let guard = self.repo_map.read().unwrap();
-        let map = guard.as_ref()?;
-
-        let config = SerializeConfig::with_budget(token_budget);
-        let serialized = if focus_files.is_empty() {
-            RepoMapSerializer::serialize_for_prompt(map, &config)
-        } else {
-            RepoMapSerializer::serialize_for_tool(map, Some(focus_files), None, &config)
-        };
-
-        if serialized.is_empty() {
-            None
-        } else {
-            Some(format!("<repository_map>\n{}</repository_map>", serialized))
-        }
-    }
-
-    /// Get the content of explicitly added files for prompt injection.
-    ///
-    /// Returns the full content of files that were added via `/add` command,
-    /// formatted for inclusion in the system prompt.
-    fn get_added_files_content(&self) -> Option<String> {
-        let files = &self.session.metadata.added_files;
-        if files.is_empty() {
-            return None;
-        }
-
-        let mut content = String::from("<added_files>\n");
-        let mut total_size = 0;
-        const MAX_TOTAL_SIZE: usize = 200_000; // ~50k tokens
-
-        for path in files {
-            let full_path = self.session.metadata.working_directory.join(path);
-            if let Ok(file_content) = std::fs::read_to_string(&full_path) {
-                // Check size limit
-                if total_size + file_content.len() > MAX_TOTAL_SIZE {
-                    content.push_str(&format!(
-                        "<file path=\"{}\" truncated=\"true\">Content truncated due to size limits</file>\n",
-                        path.display()
-                    ));
-                    break;
-                }
-                total_size += file_content.len();
-                content.push_str(&format!(
-                    "<file path=\"{}\">\n{}\n</file>\n",
-                    path.display(),
-                    file_content
-                ));
-            } else {
-                content.push_str(&format!(
-                    "<file path=\"{}\" error=\"true\">File not found or not readable</file>\n",
-                    path.display()
-                ));
-            }
-        }
-        content.push_str("</added_files>");
-        Some(content)
-    }
-
-    // ========================================================================
-    // Cancellation
-    // ========================================================================
-
-    /// Cancel any ongoing operation.
-    pub fn cancel(&self) {
-        self.cancel_token.cancel();
-    }
-
-    /// Check if cancellation has been requested.
-    pub fn is_cancelled(&self) -> bool {
-        self.cancel_token.is_cancelled()
-    }
-
-    /// Reset the cancellation token for a new operation.
-    pub fn reset_cancel(&mut self) {
-        self.cancel_token = CancellationToken::new();
-    }
-
-    // ========================================================================
-    // Session Management
-    // ========================================================================
-
-    /// Save the current session to the store.
-    pub async fn save(&self) -> AgentResult<()> {
-        self.store
-            .update_metadata(&self.session.metadata)
-            .await
-            .map_err(|e| AgentError::Session(e.to_string()))
-    }
-
-    /// Load a session by ID.
-    pub async fn load_session(store: Arc<dyn SessionStore>, id: &str) -> AgentResult<Session> {
-        store
-            .get(id)
-            .await
-            .map_err(|e| AgentError::Session(e.to_string()))
-    }
-
-    /// Create a new session.
-    pub async fn new_session(
-        store: Arc<dyn SessionStore>,
-        working_dir: PathBuf,
-    ) -> AgentResult<Session> {
-        let session = Session::new(working_dir);
-        store
-            .create(&session)
-            .await
-            .map_err(|e| AgentError::Session(e.to_string()))?;
-        Ok(session)
-    }
-
-    /// Trigger context compaction.
-    pub async fn compact(&mut self) -> AgentResult<()> {
-        self.event_handler.on_compacting();
-
-        // Find compaction boundary
-        let boundary = self.context_manager.find_compaction_boundary(&self.session);
-
-        if boundary.messages_to_compact == 0 {
-            debug!("No messages to compact");
-            return Ok(());
-        }
-
-        info!(
-            messages = boundary.messages_to_compact,
-            tokens = boundary.tokens_to_compact,
-            "Compacting session"
-        );
-
-        // For now, create a simple summary
-        // In a full implementation, this would use an LLM to generate the summary
-        let summary = agentik_core::session::CompactedSummary {
-            text: format!(
-                "Previous conversation contained {} messages.",
-                boundary.messages_to_compact
-            ),
-            key_decisions: vec![],
-            modified_files: vec![],
-            created_at: chrono::Utc::now(),
-            messages_compacted: boundary.messages_to_compact as u32,
-        };
-
-        // Apply compaction to session
-        self.session.summary = Some(summary.clone());
-        self.session.compact_boundary = boundary.index;
-
-        // Persist to store
-        self.store
-            .apply_compaction(self.session.id(), &summary, boundary.index)
-            .await
-            .map_err(|e| AgentError::Session(e.to_string()))?;
-
-        Ok(())
-    }
-
-    // ========================================================================
-    // Mode Helpers
-    // ========================================================================
-
-    /// Check if streaming should be used based on mode.
-    fn should_stream(&self) -> bool {
-        // Always stream for better UX, unless explicitly disabled
-        true
-    }
-
-    /// Check if tools should be executed based on mode.
-    fn should_execute_tools(&self) -> bool {
-        self.mode != AgentMode::AskOnly
-    }
-
-    /// Get mode-specific system prompt additions.
-    fn mode_system_prompt(&self) -> Option<String> {
-        match self.mode {
-            AgentMode::Planning => Some(
-                "You are in planning mode. Create detailed plans but do not execute any changes. \
-                 Present your plan clearly and ask for approval before proceeding."
-                    .to_string(),
-            ),
-            AgentMode::Architect => Some(
-                "You are in architect mode. Focus on high-level design and architecture. \
-                 Discuss patterns, trade-offs, and recommendations without implementing."
-                    .to_string(),
-            ),
-            AgentMode::AskOnly => Some(
-                "You are in ask-only mode. Answer questions and provide information, \
-                 but do not make any changes or execute any tools."
-                    .to_string(),
-            ),
-            AgentMode::Supervised | AgentMode::Autonomous => None,
-        }
-    }
-
-    // ========================================================================
-    // Core Loop
-    // ========================================================================
-
-    /// Run the agent with user input.
-    ///
-    /// This is the main entry point for interacting with the agent.
-    /// It adds the user message, runs the completion loop, and returns
-    /// the final response.
-    pub async fn run(&mut self, input: &str) -> AgentResult<AgentResponse> {
-        // Reset cancellation for new run
-        self.reset_cancel();
-
-        // Add user message
-        let user_msg = Message::user(input);
-        self.session.add_message(user_msg.clone());
-
-        // Persist the user message
-        self.store
-            .append_message(self.session.id(), &user_msg)
-            .await
-            .map_err(|e| AgentError::Session(e.to_string()))?;
-
-        // Check context and compact if needed
-        if self.config.auto_compact && self.context_manager.needs_compaction(&self.session) {
-            self.compact().await?;
-        }
-
-        // Run the loop
-        self.run_loop().await
-    }
-
-    /// Internal loop that continues until completion or max turns.
-    async fn run_loop(&mut self) -> AgentResult<AgentResponse> {
-        let mut steps = Vec::new();
-        let mut total_usage = TurnUsage::default();
-
-        for turn in 0..self.config.max_turns {
-            // Check for cancellation
-            if self.is_cancelled() {
-                return Err(AgentError::Cancelled);
-            }
-
-            debug!(turn, "Running agent turn");
-
-            // Execute a single step
-            let step = self.step().await?;
-
-            // Accumulate usage
-            total_usage.input_tokens += step.usage.input_tokens;
-            total_usage.output_tokens += step.usage.output_tokens;
-            total_usage.cached_tokens += step.usage.cached_tokens;
-            total_usage.cost_usd += step.usage.cost_usd;
-
-            // Report usage
-            self.event_handler.on_usage(&step.usage);
-
-            let has_tool_calls = !step.tool_calls.is_empty();
-            steps.push(step);
-
-            // If no tool calls, we're done
-            if !has_tool_calls {
-                break;
-            }
-
-            // Check if we've hit max turns
-            if turn + 1 >= self.config.max_turns {
-                warn!(max_turns = self.config.max_turns, "Max turns exceeded");
-                return Err(AgentError::MaxTurnsExceeded(self.config.max_turns));
-            }
-        }
-
-        // Get final content from last step
-        let content = steps.last().map(|s| s.content.clone()).unwrap_or_default();
-
-        let response = AgentResponse {
-            content,
-            turns: steps.len(),
-            steps,
-            total_usage,
-        };
-
-        self.event_handler.on_complete(&response);
-        Ok(response)
-    }
-
-    /// Execute a single step (completion + optional tool execution).
-    async fn step(&mut self) -> AgentResult<StepResult> {
-        self.event_handler.on_thinking();
-
-        // Prepare context
-        let system = self.build_system_prompt();
-        let prepared = self
-            .context_manager
-            .prepare_context(&self.session, system.as_deref());
-
-        // Build completion request
-        let tools: Vec<ToolDefinition> = if self.should_execute_tools() {
-            self.executor.registry().definitions()
-        } else {
-            vec![]
-        };
-
-        let request = CompletionRequest {
-            model: self.config.model.clone(),
-            messages: prepared.messages,
-            system: prepared.system_message,
-            max_tokens: self.config.max_tokens,
-            temperature: self.config.temperature,
-            tools,
-            stop: vec![],
-        };
-
-        // Execute completion
-        let (content, tool_calls, usage) = if self.should_stream() {
-            self.step_streaming(request).await?
-        } else {
-            self.step_non_streaming(request).await?
-        };
-
-        // Create and store assistant message
-        let mut assistant_msg = Message::assistant(&content);
-        assistant_msg.tool_calls = tool_calls.clone();
-        self.session.add_message(assistant_msg.clone());
-
-        self.store
-            .append_message(self.session.id(), &assistant_msg)
-            .await
-            .map_err(|e| AgentError::Session(e.to_string()))?;
-
-        // Execute tool calls if any
-        let tool_results = if !tool_calls.is_empty() && self.should_execute_tools() {
-            self.handle_tool_calls(&tool_calls).await?
-        } else {
-            vec![]
-        };
-
-        Ok(StepResult {
-            content,
-            tool_calls,
-            tool_results,
-            usage,
-        })
-    }
-
-    /// Execute completion with streaming.
-    async fn step_streaming(
-        &mut self,
-        request: CompletionRequest,
-    ) -> AgentResult<(String, Vec<ToolCall>, TurnUsage)> {
-        let mut stream = self.provider.complete_stream(request).await?;
-        let mut content = String::new();
-        let mut tool_builder = ToolCallBuilder::new();
-        let mut usage = TurnUsage::default();
-
-        while let Some(chunk_result) = stream.next().await {
-            // Check for cancellation
-            if self.is_cancelled() {
-                return Err(AgentError::Cancelled);
-            }
-
-            let chunk: StreamChunk = chunk_result?;
-
-            // Handle text delta
-            if let Some(delta) = &chunk.delta {
-                content.push_str(delta);
-                self.event_handler.on_text_delta(delta);
-            }
-
-            // Handle tool call delta
-            if let Some(tool_delta) = &chunk.tool_call_delta {
-                tool_builder.apply_delta(tool_delta);
-            }
-
-            // Handle final chunk with usage
-            if chunk.is_final {
-                if let Some(u) = chunk.usage {
-                    usage = u.into();
-                }
-            }
-        }
-
-        let tool_calls = tool_builder.build();
-        Ok((content, tool_calls, usage))
-    }
-
-    /// Execute completion without streaming.
-    async fn step_non_streaming(
-        &mut self,
-        request: CompletionRequest,
-    ) -> AgentResult<(String, Vec<ToolCall>, TurnUsage)> {
-        let response: CompletionResponse = self.provider.complete(request).await?;
-
-        // Send full content as single delta for consistency
-        self.event_handler.on_text_delta(&response.content);
-
-        let usage = response.usage.into();
-        Ok((response.content, response.tool_calls, usage))
-    }
-
-    /// Build the system prompt including mode additions, repo map, and added files.
-    fn build_system_prompt(&self) -> Option<String> {
-        let mut parts = Vec::new();
-
-        if let Some(base) = &self.config.system_prompt {
-            parts.push(base.clone());
-        }
-
-        if let Some(mode_prompt) = self.mode_system_prompt() {
-            parts.push(mode_prompt);
-        }
-
-        // Inject repo map with ~2000 token budget, prioritizing focus files
-        let focus_files = &self.session.metadata.added_files;
-        if let Some(repo_map) = self.get_repo_map_for_prompt(2000, focus_files) {
-            parts.push(repo_map);
-        }
-
-        // Inject full content of added files
-        if let Some(files_content) = self.get_added_files_content() {
-            parts.push(files_content);
-        }
-
-        if parts.is_empty() {
-            None
-        } else {
-            Some(parts.join("\n\n"))
-        }
-    }
-
-    /// Handle execution of tool calls.
-    async fn handle_tool_calls(&mut self, calls: &[ToolCall]) -> AgentResult<Vec<ToolResult>> {
-        let results = self.executor.execute_batch(calls).await;
-
-        // Store tool results in session
-        for result in &results {
-            let tool_msg = Message::tool_result(
-                result.tool_call_id.clone(),
-                if result.success {
-                    result.output.clone()
-                } else {
-                    result.error.clone().unwrap_or_default()
-                },
-                !result.success,
-            );
-            self.session.add_message(tool_msg.clone());
-
-            self.store
-                .append_message(self.session.id(), &tool_msg)
-                .await
-                .map_err(|e| AgentError::Session(e.to_string()))?;
-        }
-
-        Ok(results)
-    }
-}
-
-// ============================================================================
-// Agent Builder
-// ============================================================================
-
-/// Builder for constructing an [`Agent`].
-pub struct AgentBuilder {
-    provider: Option<Arc<dyn Provider>>,
-    executor: Option<ToolExecutor>,
-    store: Option<Arc<dyn SessionStore>>,
-    session: Option<Session>,
-    config: AgentConfig,
-    event_handler: Option<Arc<dyn AgentEventHandler>>,
-    mode: AgentMode,
-    repo_map: Option<RepoMap>,
-}
-
-impl Default for AgentBuilder {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl AgentBuilder {
-    /// Create a new builder with default configuration.
-    pub fn new() -> Self {
-        Self {
-            provider: None,
-            executor: None,
-            store: None,
-            session: None,
-            config: AgentConfig::default(),
-            event_handler: None,
-            mode: AgentMode::default(),
-            repo_map: None,
-        }
-    }
-
-    /// Set the AI provider.
-    pub fn provider(mut self, provider: Arc<dyn Provider>) -> Self {
-        self.provider = Some(provider);
-        self
-    }
-
-    /// Set the tool executor.
-    pub fn executor(mut self, executor: ToolExecutor) -> Self {
-        self.executor = Some(executor);
-        self
-    }
-
-    /// Set the session store.
-    pub fn store(mut self, store: Arc<dyn SessionStore>) -> Self {
-        self.store = Some(store);
-        self
-    }
-
-    /// Set the session.
-    pub fn session(mut self, session: Session) -> Self {
-        self.session = Some(session);
-        self
-    }
-
-    /// Set the model to use.
-    pub fn model(mut self, model: impl Into<String>) -> Self {
-        self.config.model = model.into();
-        self
-    }
-
-    /// Set the system prompt.
-    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
-        self.config.system_prompt = Some(prompt.into());
-        self
-    }
-
-    /// Set the maximum tokens per response.
-    pub fn max_tokens(mut self, max: u32) -> Self {
-        self.config.max_tokens = max;
-        self
-    }
-
-    /// Set the temperature.
-    pub fn temperature(mut self, temp: f32) -> Self {
-        self.config.temperature = temp;
-        self
-    }
-
-    /// Set the maximum number of turns.
-    pub fn max_turns(mut self, max: usize) -> Self {
-        self.config.max_turns = max;
-        self
-    }
-
-    /// Enable or disable auto-compaction.
-    pub fn auto_compact(mut self, enabled: bool) -> Self {
-        self.config.auto_compact = enabled;
-        self
-    }
-
-    /// Set the full configuration.
-    pub fn config(mut self, config: AgentConfig) -> Self {
-        self.config = config;
-        self
-    }
-
-    /// Set the event handler.
-    pub fn event_handler(mut self, handler: Arc<dyn AgentEventHandler>) -> Self {
-        self.event_handler = Some(handler);
-        self
-    }
-
-    /// Set the operating mode.
-    pub fn mode(mut self, mode: AgentMode) -> Self {
-        self.mode = mode;
-        self
-    }
-
-    /// Set the repository map for codebase context.
-    pub fn repo_map(mut self, map: RepoMap) -> Self {
-        self.repo_map = Some(map);
-        self
-    }
-
-    /// Build the agent.
-    ///
-    /// Returns an error if required components are missing.
-    pub fn build(self) -> AgentResult<Agent> {
-        let provider = self
-            .provider
-            .ok_or_else(|| AgentError::NotConfigured("provider is required".into()))?;
-        let executor = self
-            .executor
-            .ok_or_else(|| AgentError::NotConfigured("executor is required".into()))?;
-        let store = self
-            .store
-            .ok_or_else(|| AgentError::NotConfigured("store is required".into()))?;
-        let session = self
-            .session
-            .ok_or_else(|| AgentError::NotConfigured("session is required".into()))?;
-        let event_handler = self
-            .event_handler
-            .unwrap_or_else(|| Arc::new(NoOpEventHandler));
-
-        let mut agent = Agent::new(
-            provider,
-            executor,
-            store,
-            session,
-            self.config,
-            event_handler,
-        );
-        agent.set_mode(self.mode);
-
-        // Set repo map if provided
-        if let Some(map) = self.repo_map {
-            agent.set_repo_map(map);
-        }
-
-        Ok(agent)
-    }
-}
-
-// ============================================================================
-// Tests
-// ============================================================================
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use agentik_providers::traits::{FinishReason, Usage};
-    use agentik_providers::{CompletionResponse, StreamChunk};
-    use agentik_session::store::StoreError;
-    use futures::stream;
-    use std::pin::Pin;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::Mutex;
-
-    // ========================================================================
-    // Mock Provider
-    // ========================================================================
-
-    struct MockProvider {
-        responses: Mutex<Vec<CompletionResponse>>,
-        call_count: AtomicUsize,
-    }
-
-    impl MockProvider {
-        fn new(responses: Vec<CompletionResponse>) -> Self {
-            Self {
-                responses: Mutex::new(responses),
-                call_count: AtomicUsize::new(0),
-            }
-        }
-
-        fn with_response(content: &str) -> Self {
-            Self::new(vec![CompletionResponse {
-                content: content.to_string(),
-                tool_calls: vec![],
-                finish_reason: FinishReason::Stop,
-                usage: Usage::default(),
-            }])
-        }
-
-        // Companion to `new` for exercising the tool-call path; kept for the
-        // tool-call tests even while no test currently calls it.
-        #[allow(dead_code)]
-        fn with_tool_call(tool_name: &str, args: serde_json::Value, final_response: &str) -> Self {
-            Self::new(vec![
-                CompletionResponse {
-                    content: String::new(),
-                    tool_calls: vec![ToolCall::new("call_1", tool_name, args)],
-                    finish_reason: FinishReason::ToolUse,
-                    usage: Usage::default(),
-                },
-                CompletionResponse {
-                    content: final_response.to_string(),
-                    tool_calls: vec![],
-                    finish_reason: FinishReason::Stop,
-                    usage: Usage::default(),
-                },
-            ])
-        }
-    }
-
-    #[async_trait]
-    impl Provider for MockProvider {
-        fn id(&self) -> &str {
-            "mock"
-        }
-
-        fn name(&self) -> &str {
-            "Mock Provider"
-        }
-
-        fn available_models(&self) -> Vec<agentik_providers::ModelInfo> {
-            vec![]
-        }
-
-        fn is_configured(&self) -> bool {
-            true
-        }
-
-        async fn complete(
-            &self,
-            _request: CompletionRequest,
-        ) -> anyhow::Result<CompletionResponse> {
-            let idx = self.call_count.fetch_add(1, Ordering::SeqCst);
-            let responses = self.responses.lock().unwrap();
-            let response = responses
-                .get(idx)
-                .cloned()
-                .unwrap_or_else(|| CompletionResponse {
-                    content: "No more responses".to_string(),
-                    tool_calls: vec![],
-                    finish_reason: FinishReason::Stop,
-                    usage: Usage::default(),
-                });
-            Ok(response)
-        }
-
-        async fn complete_stream(
-            &self,
-            request: CompletionRequest,
-        ) -> anyhow::Result<Pin<Box<dyn futures::Stream<Item = anyhow::Result<StreamChunk>> + Send>>>
-        {
-            // For simplicity, just convert complete to a single-chunk stream
-            let response = self.complete(request).await?;
-
-            // Create chunks for text and tool calls
-            let mut chunks = vec![];
-
-            if !response.content.is_empty() {
-                chunks.push(Ok(StreamChunk {
-                    delta: Some(response.content),
-                    tool_call_delta: None,
-                    is_final: false,
-                    usage: None,
-                }));
-            }
-
-            for call in response.tool_calls {
-                chunks.push(Ok(StreamChunk {
-                    delta: None,
-                    tool_call_delta: Some(ToolCallDelta {
-                        id: Some(call.id),
-                        name: Some(call.name),
-                        arguments: Some(call.arguments.to_string()),
-                    }),
-                    is_final: false,
-                    usage: None,
-                }));
-            }
-
-            // Final chunk with usage
-            chunks.push(Ok(StreamChunk {
-                delta: None,
-                tool_call_delta: None,
-                is_final: true,
-                usage: Some(response.usage),
-            }));
-
-            Ok(Box::pin(stream::iter(chunks)))
-        }
-    }
-
-    // ========================================================================
-    // Mock Session Store
-    // ========================================================================
-
-    struct MockSessionStore {
-        sessions: Mutex<std::collections::HashMap<String, Session>>,
-    }
-
-    impl MockSessionStore {
-        fn new() -> Self {
-            Self {
-                sessions: Mutex::new(std::collections::HashMap::new()),
-            }
-        }
-    }
-
-    #[async_trait]
-    impl SessionStore for MockSessionStore {
-        async fn create(&self, session: &Session) -> Result<(), StoreError> {
-            let mut sessions = self.sessions.lock().unwrap();
-            sessions.insert(session.id().to_string(), session.clone());
-            Ok(())
-        }
-
-        async fn get(&self, id: &str) -> Result<Session, StoreError> {
-            let sessions = self.sessions.lock().unwrap();
-            sessions
-                .get(id)
-                .cloned()
-                .ok_or_else(|| StoreError::NotFound(id.to_string()))
-        }
-
-        async fn get_metadata(
-            &self,
-            id: &str,
-        ) -> Result<agentik_core::SessionMetadata, StoreError> {
-            self.get(id).await.map(|s| s.metadata)
-        }
-
-        async fn update_metadata(
-            &self,
-            metadata: &agentik_core::SessionMetadata,
-        ) -> Result<(), StoreError> {
-            let mut sessions = self.sessions.lock().unwrap();
-            if let Some(session) = sessions.get_mut(&metadata.id) {
-                session.metadata = metadata.clone();
-            }
-            Ok(())
-        }
-
-        async fn delete(&self, id: &str) -> Result<(), StoreError> {
-            let mut sessions = self.sessions.lock().unwrap();
-            sessions.remove(id);
-            Ok(())
-        }
-
-        async fn list(
-            &self,
-            _query: &agentik_session::SessionQuery,
-        ) -> Result<Vec<agentik_session::SessionSummary>, StoreError> {
-            Ok(vec![])
-        }
-
-        async fn get_most_recent(
-            &self,
-        ) -> Result<Option<agentik_session::SessionSummary>, StoreError> {
-            Ok(None)
-        }
-
-        async fn append_message(
-            &self,
-            session_id: &str,
-            message: &Message,
-        ) -> Result<agentik_session::AppendResult, StoreError> {
-            let mut sessions = self.sessions.lock().unwrap();
-            if let Some(session) = sessions.get_mut(session_id) {
-                session.messages.push(message.clone());
-            }
-            Ok(agentik_session::AppendResult {
-                file_offset: 0,
-                byte_length: 0,
-                message_index: 0,
-            })
-        }
-
-        async fn get_messages(
-            &self,
-            session_id: &str,
-            _from: Option<i64>,
-            _limit: Option<usize>,
-        ) -> Result<Vec<Message>, StoreError> {
-            let sessions = self.sessions.lock().unwrap();
-            Ok(sessions
-                .get(session_id)
-                .map(|s| s.messages.clone())
-                .unwrap_or_default())
-        }
-
-        async fn apply_compaction(
-            &self,
-            session_id: &str,
-            summary: &agentik_core::session::CompactedSummary,
-            boundary: usize,
-        ) -> Result<(), StoreError> {
-            let mut sessions = self.sessions.lock().unwrap();
-            if let Some(session) = sessions.get_mut(session_id) {
-                session.summary = Some(summary.clone());
-                session.compact_boundary = boundary;
-            }
-            Ok(())
-        }
-
-        async fn set_state(
-            &self,
-            _id: &str,
-            _state: agentik_core::SessionState,
-        ) -> Result<(), StoreError> {
-            Ok(())
-        }
-
-        async fn touch(&self, _id: &str) -> Result<(), StoreError> {
-            Ok(())
-        }
-
-        async fn find_by_prefix(
-            &self,
-            _prefix: &str,
-        ) -> Result<Vec<agentik_session::SessionSummary>, StoreError> {
-            Ok(vec![])
-        }
-
-        async fn get_aggregated_stats(
-            &self,
-            _since: Option<chrono::DateTime<chrono::Utc>>,
-        ) -> Result<agentik_session::AggregatedStats, StoreError> {
-            Ok(agentik_session::AggregatedStats {
-                session_count: 0,
-                total_tokens_in: 0,
-                total_tokens_out: 0,
-                total_cost: 0.0,
-                total_turns: 0,
-                total_tool_calls: 0,
-            })
-        }
-    }
-
-    // ========================================================================
-    // Test Event Handler
-    // ========================================================================
-
-    struct TestEventHandler {
-        text_deltas: Mutex<Vec<String>>,
-        tool_starts: AtomicUsize,
-        tool_completes: AtomicUsize,
-    }
-
-    impl TestEventHandler {
-        fn new() -> Self {
-            Self {
-                text_deltas: Mutex::new(vec![]),
-                tool_starts: AtomicUsize::new(0),
-                tool_completes: AtomicUsize::new(0),
-            }
-        }
-
-        fn collected_text(&self) -> String {
-            self.text_deltas.lock().unwrap().join("")
-        }
-    }
-
-    impl AgentEventHandler for TestEventHandler {
-        fn on_text_delta(&self, delta: &str) {
-            self.text_deltas.lock().unwrap().push(delta.to_string());
-        }
-
-        fn on_tool_start(&self, _call: &ToolCall) {
-            self.tool_starts.fetch_add(1, Ordering::SeqCst);
-        }
-
-        fn on_tool_complete(&self, _call: &ToolCall, _result: &ToolResult) {
-            self.tool_completes.fetch_add(1, Ordering::SeqCst);
-        }
-    }
-
-    // ========================================================================
-    // Helper Functions
-    // ========================================================================
-
-    fn create_test_executor() -> ToolExecutor {
-        use crate::executor::{AutoApproveHandler, ExecutorBuilder};
-
-        ExecutorBuilder::new()
-            .with_builtins()
-            .mode(AgentMode::Autonomous)
-            .build(Arc::new(AutoApproveHandler))
-    }
-
-    async fn create_test_agent(provider: Arc<dyn Provider>) -> Agent {
-        let store = Arc::new(MockSessionStore::new());
-        let session = Session::new(PathBuf::from("/tmp/test"));
-        store.create(&session).await.unwrap();
-
-        let executor = create_test_executor();
-
-        AgentBuilder::new()
-            .provider(provider)
-            .executor(executor)
-            .store(store)
-            .session(session)
-            .max_turns(10)
-            .build()
-            .unwrap()
-    }
-
-    // ========================================================================
-    // Tests
-    // ========================================================================
-
-    #[test]
-    fn test_agent_config_default() {
-        let config = AgentConfig::default();
-        assert_eq!(config.max_tokens, 8192);
-        assert_eq!(config.temperature, 0.7);
-        assert_eq!(config.max_turns, 100);
-        assert!(config.auto_compact);
-    }
-
-    #[test]
-    fn test_turn_usage_from_provider_usage() {
-        let provider_usage = Usage {
-            input_tokens: 100,
-            output_tokens: 50,
-            cached_tokens: 25,
-        };
-        let turn_usage: TurnUsage = provider_usage.into();
-        assert_eq!(turn_usage.input_tokens, 100);
-        assert_eq!(turn_usage.output_tokens, 50);
-        assert_eq!(turn_usage.cached_tokens, 25);
-    }
-
-    #[test]
-    fn test_tool_call_builder() {
-        let mut builder = ToolCallBuilder::new();
-
-        // First tool call
-        builder.apply_delta(&ToolCallDelta {
-            id: Some("call_1".to_string()),
-            name: Some("Read".to_string()),
-            arguments: Some("{\"file".to_string()),
-        });
-        builder.apply_delta(&ToolCallDelta {
-            id: None,
-            name: None,
-            arguments: Some("_path\": \"/tmp/test\"}".to_string()),
-        });
-
-        let calls = builder.build();
-        assert_eq!(calls.len(), 1);
-        assert_eq!(calls[0].id, "call_1");
-        assert_eq!(calls[0].name, "Read");
-    }
-
-    #[tokio::test]
-    async fn test_basic_run() {
-        let provider = Arc::new(MockProvider::with_response("Hello, how can I help you?"));
-        let mut agent = create_test_agent(provider).await;
-
-        let response = agent.run("Hi there!").await.unwrap();
-
-        assert_eq!(response.content, "Hello, how can I help you?");
-        assert_eq!(response.turns, 1);
-        assert!(response.steps[0].tool_calls.is_empty());
-    }
-
-    #[tokio::test]
-    async fn test_event_handler_receives_deltas() {
-        let provider = Arc::new(MockProvider::with_response("Test response"));
-        let handler = Arc::new(TestEventHandler::new());
-
-        let store = Arc::new(MockSessionStore::new());
-        let session = Session::new(PathBuf::from("/tmp/test"));
-        store.create(&session).await.unwrap();
-
-        let executor = create_test_executor();
-
-        let mut agent = AgentBuilder::new()
-            .provider(provider)
-            .executor(executor)
-            .store(store)
-            .session(session)
-            .event_handler(handler.clone())
-            .build()
-            .unwrap();
-
-        agent.run("Test").await.unwrap();
-
-        assert_eq!(handler.collected_text(), "Test response");
-    }
-
-    #[test]
-    fn test_cancellation() {
-        // Test the cancellation API directly
-        let provider = Arc::new(MockProvider::with_response("test"));
-        let store = Arc::new(MockSessionStore::new());
-        let session = Session::new(PathBuf::from("/tmp/test"));
-        let executor = create_test_executor();
-
-        let agent = Agent::new(
-            provider,
-            executor,
-            store,
-            session,
-            AgentConfig::default(),
-            Arc::new(NoOpEventHandler),
-        );
-
-        // Initially not cancelled
-        assert!(!agent.is_cancelled());
-
-        // Cancel
-        agent.cancel();
-        assert!(agent.is_cancelled());
-    }
-
-    #[tokio::test]
-    async fn test_cancellation_during_run() {
-        use std::sync::Arc as StdArc;
-        use tokio::sync::Notify;
-
-        // Create a provider that waits for cancellation signal
-        struct SlowProvider {
-            notify: StdArc<Notify>,
-        }
-
-        #[async_trait]
-        impl Provider for SlowProvider {
-            fn id(&self) -> &str {
-                "slow"
-            }
-            fn name(&self) -> &str {
-                "Slow Provider"
-            }
-            fn available_models(&self) -> Vec<agentik_providers::ModelInfo> {
-                vec![]
-            }
-            fn is_configured(&self) -> bool {
-                true
-            }
-            async fn complete(
-                &self,
-                _request: CompletionRequest,
-            ) -> anyhow::Result<CompletionResponse> {
-                // Wait for notification (simulating slow response)
-                self.notify.notified().await;
-                Ok(CompletionResponse {
-                    content: "test".to_string(),
-                    tool_calls: vec![],
-                    finish_reason: FinishReason::Stop,
-                    usage: Usage::default(),
-                })
-            }
-            async fn complete_stream(
-                &self,
-                request: CompletionRequest,
-            ) -> anyhow::Result<
-                Pin<Box<dyn futures::Stream<Item = anyhow::Result<StreamChunk>> + Send>>,
-            > {
-                // Use non-streaming for simplicity in test
-                let response = self.complete(request).await?;
-                Ok(Box::pin(stream::iter(vec![Ok(StreamChunk {
-                    delta: Some(response.content),
-                    tool_call_delta: None,
-                    is_final: true,
-                    usage: Some(response.usage),
-                })])))
-            }
-        }
-
-        let notify = StdArc::new(Notify::new());
-        let store = Arc::new(MockSessionStore::new());
-        let session = Session::new(PathBuf::from("/tmp/test"));
-        store.create(&session).await.unwrap();
-
-        let provider = Arc::new(SlowProvider {
-            notify: notify.clone(),
-        });
-        let executor = create_test_executor();
-
-        let mut agent = AgentBuilder::new()
-            .provider(provider)
-            .executor(executor)
-            .store(store)
-            .session(session)
-            .build()
-            .unwrap();
-
-        // Get a handle to the cancel token before running
-        let cancel_token = agent.cancel_token.clone();
-
-        // Spawn the agent run in a separate task
-        let handle = tokio::spawn(async move { agent.run("Test").await });
-
-        // Give the agent time to start
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-
-        // Cancel the operation
-        cancel_token.cancel();
-
-        // Notify the provider to continue (it will check cancellation)
-        notify.notify_one();
-
-        // The run should return a cancellation error
-        let result = handle.await.unwrap();
-        // Note: Due to how the test is structured, the provider completes before
-        // cancellation is checked, so we just verify the test completes
-        // In real usage, cancellation happens during streaming
-        assert!(result.is_ok() || matches!(result, Err(AgentError::Cancelled)));
-    }
-
-    #[tokio::test]
-    async fn test_mode_changes() {
-        let provider = Arc::new(MockProvider::with_response("OK"));
-        let mut agent = create_test_agent(provider).await;
-
-        assert_eq!(agent.mode(), AgentMode::Autonomous);
-
-        agent.set_mode(AgentMode::Planning);
-        assert_eq!(agent.mode(), AgentMode::Planning);
-
-        agent.set_mode(AgentMode::AskOnly);
-        assert_eq!(agent.mode(), AgentMode::AskOnly);
-        assert!(!agent.should_execute_tools());
-    }
-
-    #[tokio::test]
-    async fn test_builder_missing_provider() {
-        let store = Arc::new(MockSessionStore::new());
-        let session = Session::new(PathBuf::from("/tmp/test"));
-        let executor = create_test_executor();
-
-        let result = AgentBuilder::new()
-            .executor(executor)
-            .store(store)
-            .session(session)
-            .build();
-
-        assert!(matches!(result, Err(AgentError::NotConfigured(_))));
-    }
-
-    #[tokio::test]
-    async fn test_max_turns_exceeded() {
-        // Create provider that always returns tool calls
-        let responses: Vec<CompletionResponse> = (0..15)
-            .map(|i| CompletionResponse {
-                content: String::new(),
-                tool_calls: vec![ToolCall::new(
-                    format!("call_{}", i),
-                    "nonexistent_tool",
-                    serde_json::json!({}),
-                )],
-                finish_reason: FinishReason::ToolUse,
-                usage: Usage::default(),
-            })
-            .collect();
-
-        let provider = Arc::new(MockProvider::new(responses));
-        let mut agent = create_test_agent(provider).await;
-
-        // Set low max turns to trigger the error quickly
-        agent.config.max_turns = 3;
-
-        let result = agent.run("Test").await;
-        assert!(matches!(result, Err(AgentError::MaxTurnsExceeded(3))));
-    }
-
-    #[test]
-    fn test_mode_system_prompts() {
-        let provider = Arc::new(MockProvider::with_response("OK"));
-        let store = Arc::new(MockSessionStore::new());
-        let session = Session::new(PathBuf::from("/tmp/test"));
-        let executor = create_test_executor();
-
-        // Create agent synchronously using the builder pattern
-        let mut agent = Agent::new(
-            provider,
-            executor,
-            store,
-            session,
-            AgentConfig::default(),
-            Arc::new(NoOpEventHandler),
-        );
-
-        // Test different modes
-        agent.set_mode(AgentMode::Autonomous);
-        assert!(agent.mode_system_prompt().is_none());
-
-        agent.set_mode(AgentMode::Planning);
-        assert!(agent.mode_system_prompt().is_some());
-        assert!(agent
-            .mode_system_prompt()
-            .unwrap()
-            .contains("planning mode"));
-
-        agent.set_mode(AgentMode::Architect);
-        assert!(agent
-            .mode_system_prompt()
-            .unwrap()
-            .contains("architect mode"));
-
-        agent.set_mode(AgentMode::AskOnly);
-        assert!(agent
-            .mode_system_prompt()
-            .unwrap()
-            .contains("ask-only mode"));
-    }
-}
@@ -0,0 +1,1974 @@
+//! Core agent implementation.
+//!
+//! The [`Agent`] struct is the main orchestration layer that connects:
+//! - AI providers (LLM interaction)
+//! - Tool execution (via [`ToolExecutor`])
+//! - Session management (persistence and context)
+//!
+//! ## Architecture
+//!
+//! ```text
+//! Agent
+//! ├── Provider (LLM interaction)
+//! ├── ToolExecutor (permission + execution)
+//! ├── SessionStore (persistence)
+//! ├── ContextManager (token tracking)
+//! ├── AgentEventHandler (UI callbacks)
+//! └── EventBus (serializable event stream)
+//! ```
+//!
+//! Plan execution, the architect/editor pipeline and verification of edits
+//! are implemented in submodules.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use agentik_core::config::{LoopDetectionConfig, VerifyConfig};
+use agentik_core::session::SessionMetrics;
+use agentik_core::tool::ToolCategory;
+use agentik_core::{Message, Plan, Role, Session, ToolCall, ToolDefinition, ToolResult};
+use agentik_providers::traits::{FinishReason, ToolCallDelta, Usage};
+use agentik_providers::{
+    adapt_request, CompletionRequest, CompletionResponse, Provider, StreamChunk,
+};
+use agentik_repomap::{RepoMap, RepoMapSerializer, SerializeConfig};
+use agentik_session::{
+    CompactionConfig, Compactor, CompletionProvider, ContextConfig, ContextManager,
+    LlmSummaryConfig, LlmSummaryGenerator, SessionStore, SimpleSummaryGenerator,
+};
+use agentik_tools::{Changeset, ToolRegistry, UpdatePlanTool};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::architect::{self, ModelRole};
+use crate::artifacts::ReadArtifactTool;
+use crate::checkpoint::{Checkpoint, RewindTarget};
+use crate::events::{AgentEvent, EventBus, EventEnvelope, PublishingPermissionHandler};
+use crate::executor::{ExecutorServices, ToolExecutor};
+use crate::hooks::PromptDecision;
+use crate::loops::{LoopDetection, LoopDetector};
+use crate::memory::{ForgetTool, MemoryStore, RecallTool, RememberTool};
+use crate::modes::AgentMode;
+use crate::planning::PlanningState;
+use crate::questions::{AskUserTool, Question, QuestionQueue, DEFAULT_QUESTION_TIMEOUT};
+use crate::steering::SteeringQueue;
+use crate::task::{SubAgentState, TaskTool};
+use crate::verify::{Verifier, VerifyReport};
+
+mod pipeline;
+mod plan;
+mod verify;
+
+/// Follow-up asking a provider without prefill support to continue a
+/// response cut off by the output token limit.
+const CONTINUE_PROMPT: &str = "Your response was cut off by the output token limit. \
+     Continue exactly where it stopped, without repeating anything.";
+
+/// Tool result for a call whose arguments were cut off by the output token
+/// limit.
+const TRUNCATED_CALL_ERROR: &str = "The arguments of this call were cut off by the output \
+     token limit, so it was not run. Retry with less content per call, e.g. write a large \
+     file in several smaller edits.";
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+/// Errors that can occur during agent operations.
+#[derive(Error, Debug)]
+pub enum AgentError {
+    /// Error from the AI provider.
+    #[error("Provider error: {0}")]
+    Provider(#[from] anyhow::Error),
+
+    /// Error during session operations.
+    #[error("Session error: {0}")]
+    Session(String),
+
+    /// Error during tool execution.
+    #[error("Tool error: {0}")]
+    Tool(String),
+
+    /// Context window exceeded.
+    #[error("Context exceeded: {current} tokens (max: {max})")]
+    ContextExceeded { current: u32, max: u32 },
+
+    /// Maximum turns exceeded.
+    #[error("Maximum turns exceeded: {0}")]
+    MaxTurnsExceeded(usize),
+
+    /// Operation was cancelled.
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    /// Agent not properly configured.
+    #[error("Not configured: {0}")]
+    NotConfigured(String),
+
+    /// Plan missing, unapproved or invalid.
+    #[error("Plan error: {0}")]
+    Plan(String),
+
+    /// A hook blocked the prompt.
+    #[error("Prompt blocked by hook: {0}")]
+    Blocked(String),
+
+    /// Checkpoints missing or unreadable.
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(String),
+
+    /// The agent kept repeating itself.
+    #[error("Loop detected: {0}")]
+    LoopDetected(String),
+}
+
+/// Result type for agent operations.
+pub type AgentResult<T> = Result<T, AgentError>;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Configuration for the agent.
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    /// Model identifier to use.
+    pub model: String,
+    /// System prompt.
+    pub system_prompt: Option<String>,
+    /// Maximum tokens per response.
+    pub max_tokens: u32,
+    /// Temperature for generation.
+    pub temperature: f32,
+    /// Maximum number of turns (loop iterations).
+    pub max_turns: usize,
+    /// Automatically compact when context is high.
+    pub auto_compact: bool,
+    /// Model used to summarize compacted history (`None` uses `model`).
+    pub compaction_model: Option<String>,
+    /// How long to wait for answers to questions before using their
+    /// defaults (`None` waits indefinitely).
+    pub question_timeout: Option<Duration>,
+    /// Model that writes proposals in architect mode (`None` uses `model`).
+    pub architect_model: Option<String>,
+    /// Model that implements proposals in architect mode (`None` uses `model`).
+    pub editor_model: Option<String>,
+    /// Checks run after turns that edit files.
+    pub verify: VerifyConfig,
+    /// Maximum continuations of a response cut off by the output token
+    /// limit (0 disables continuation).
+    pub max_continuations: u32,
+    /// When to step in on repeated calls, errors and edits.
+    pub loop_detection: LoopDetectionConfig,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            model: "claude-sonnet-4-20250514".to_string(),
+            system_prompt: None,
+            max_tokens: 8192,
+            temperature: 0.7,
+            max_turns: 100,
+            auto_compact: true,
+            compaction_model: None,
+            question_timeout: Some(DEFAULT_QUESTION_TIMEOUT),
+            architect_model: None,
+            editor_model: None,
+            verify: VerifyConfig::default(),
+            max_continuations: 3,
+            loop_detection: LoopDetectionConfig::default(),
+        }
+    }
+}
+
+// ============================================================================
+// Response Types
+// ============================================================================
+
+/// Token and cost usage for a single turn.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TurnUsage {
+    /// Input tokens used.
+    pub input_tokens: u32,
+    /// Output tokens generated.
+    pub output_tokens: u32,
+    /// Cached tokens used.
+    pub cached_tokens: u32,
+    /// Estimated cost in USD.
+    pub cost_usd: f64,
+}
+
+impl TurnUsage {
+    /// Add another turn's usage to this one.
+    pub fn accumulate(&mut self, other: &TurnUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cached_tokens += other.cached_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+impl From<Usage> for TurnUsage {
+    fn from(usage: Usage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cached_tokens: usage.cached_tokens,
+            cost_usd: 0.0, // Cost calculated externally based on model pricing
+        }
+    }
+}
+
+/// Result of a single step in the agent loop.
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    /// Text content from the assistant.
+    pub content: String,
+    /// Tool calls requested (empty if none).
+    pub tool_calls: Vec<ToolCall>,
+    /// Tool results from execution.
+    pub tool_results: Vec<ToolResult>,
+    /// Usage for this step.
+    pub usage: TurnUsage,
+    /// Model role that handled this step.
+    pub role: ModelRole,
+}
+
+impl StepResult {
+    /// Whether the step successfully ran a tool that edits files.
+    pub fn modified_files(&self, registry: &ToolRegistry) -> bool {
+        self.tool_calls
+            .iter()
+            .zip(&self.tool_results)
+            .any(|(call, result)| {
+                result.success
+                    && registry.get(&call.name).is_some_and(|tool| {
+                        let definition = tool.definition();
+                        definition.is_destructive && definition.category == ToolCategory::FileSystem
+                    })
+            })
+    }
+}
+
+/// Final response from the agent.
+#[derive(Debug, Clone)]
+pub struct AgentResponse {
+    /// Final text content.
+    pub content: String,
+    /// All steps taken to reach this response.
+    pub steps: Vec<StepResult>,
+    /// Total turns taken.
+    pub turns: usize,
+    /// Total usage across all turns.
+    pub total_usage: TurnUsage,
+}
+
+impl AgentResponse {
+    /// Usage of the steps handled by one model role.
+    pub fn usage_for(&self, role: ModelRole) -> TurnUsage {
+        architect::usage_for_role(&self.steps, role)
+    }
+
+    /// Append the steps of a follow-up run.
+    fn extend(&mut self, other: AgentResponse) {
+        self.content = other.content;
+        self.turns += other.turns;
+        self.steps.extend(other.steps);
+        self.total_usage.accumulate(&other.total_usage);
+    }
+}
+
+// ============================================================================
+// Event Handler
+// ============================================================================
+
+/// Handler for agent events during execution.
+///
+/// Implement this trait to receive callbacks during agent operation.
+/// This is the primary interface for UI integration.
+#[async_trait]
+pub trait AgentEventHandler: Send + Sync {
+    /// Called when the agent starts thinking (waiting for LLM).
+    fn on_thinking(&self) {}
+
+    /// Called when new text is streamed from the LLM.
+    fn on_text_delta(&self, _delta: &str) {}
+
+    /// Called when a tool execution starts.
+    fn on_tool_start(&self, _call: &ToolCall) {}
+
+    /// Called when a tool execution completes.
+    fn on_tool_complete(&self, _call: &ToolCall, _result: &ToolResult) {}
+
+    /// Called when the model asks the user a question.
+    ///
+    /// Return the user's reply, or `None` to use the question's default
+    /// answer. Non-blocking questions are asked while the agent keeps working.
+    async fn on_question(&self, _question: &Question) -> Option<String> {
+        None
+    }
+
+    /// Called when approval is needed for a tool call.
+    ///
+    /// Return `true` to approve, `false` to deny.
+    async fn on_approval_needed(&self, _call: &ToolCall, _tool: &ToolDefinition) -> bool {
+        true
+    }
+
+    /// Called once when a run completes successfully, with its final response.
+    fn on_complete(&self, _response: &AgentResponse) {}
+
+    /// Called when an error occurs.
+    fn on_error(&self, _error: &AgentError) {}
+
+    /// Called when compaction starts.
+    fn on_compacting(&self) {}
+
+    /// Called with usage statistics for each turn.
+    fn on_usage(&self, _usage: &TurnUsage) {}
+
+    /// Called when a phase of the architect/editor pipeline starts.
+    fn on_role_change(&self, _role: ModelRole, _model: &str) {}
+
+    /// Called after the verification checks run.
+    fn on_verify(&self, _report: &VerifyReport) {}
+
+    /// Called when a steering message is added to the conversation.
+    fn on_steering(&self, _message: &str) {}
+
+    /// Called when the agent is found repeating itself.
+    fn on_loop_detected(&self, _detection: &LoopDetection) {}
+
+    /// Called when instruction files in nested directories are loaded.
+    fn on_instructions_loaded(&self, _paths: &[PathBuf]) {}
+}
+
+/// Default event handler that does nothing.
+pub struct NoOpEventHandler;
+
+impl AgentEventHandler for NoOpEventHandler {}
+
+// ============================================================================
+// Tool Call Builder (for streaming)
+// ============================================================================
+
+/// Builder for accumulating tool calls from streaming deltas.
+#[derive(Debug, Default)]
+struct ToolCallBuilder {
+    calls: Vec<PartialToolCall>,
+}
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: Option<String>,
+    name: Option<String>,
+    arguments: String,
+}
+
+impl ToolCallBuilder {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn apply_delta(&mut self, delta: &ToolCallDelta) {
+        // Find or create the tool call entry
+        let call = if let Some(id) = &delta.id {
+            // New tool call starting
+            self.calls.push(PartialToolCall {
+                id: Some(id.clone()),
+                name: delta.name.clone(),
+                arguments: String::new(),
+            });
+            self.calls.last_mut().unwrap()
+        } else if let Some(call) = self.calls.last_mut() {
+            call
+        } else {
+            // No active call, create one
+            self.calls.push(PartialToolCall::default());
+            self.calls.last_mut().unwrap()
+        };
+
+        // Apply name if present
+        if let Some(name) = &delta.name {
+            call.name = Some(name.clone());
+        }
+
+        // Append arguments
+        if let Some(args) = &delta.arguments {
+            call.arguments.push_str(args);
+        }
+    }
+
+    /// Build the accumulated tool calls.
+    ///
+    /// If the response was `truncated` by the output token limit, the last
+    /// call's arguments are incomplete even if they happen to parse, so it is
+    /// returned separately with empty arguments. Malformed arguments of other
+    /// calls are repaired where possible.
+    fn build(self, truncated: bool) -> (Vec<ToolCall>, Option<ToolCall>) {
+        let mut calls: Vec<(ToolCall, String)> = self
+            .calls
+            .into_iter()
+            .filter_map(|partial| {
+                let call = ToolCall::new(partial.id?, partial.name?, serde_json::json!({}));
+                Some((call, partial.arguments))
+            })
+            .collect();
+
+        let truncated_call = if truncated {
+            calls.pop().map(|(call, _)| call)
+        } else {
+            None
+        };
+        let calls = calls
+            .into_iter()
+            .map(|(mut call, arguments)| {
+                call.arguments = serde_json::from_str(&arguments)
+                    .ok()
+                    .or_else(|| repair_json(&arguments))
+                    .unwrap_or(serde_json::json!({}));
+                call
+            })
+            .collect();
+        (calls, truncated_call)
+    }
+}
+
+/// Repair tool call arguments that stop short of valid JSON, e.g. a model
+/// leaving out closing braces, by closing the open string and brackets.
+fn repair_json(raw: &str) -> Option<serde_json::Value> {
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in raw.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => closers.push('}'),
+            '[' => closers.push(']'),
+            '}' | ']' if closers.pop() != Some(c) => return None,
+            _ => {}
+        }
+    }
+
+    let mut repaired = raw.to_string();
+    if in_string {
+        if escaped {
+            repaired.pop();
+        }
+        repaired.push('"');
+    }
+    let trimmed = repaired.trim_end().trim_end_matches(',').len();
+    repaired.truncate(trimmed);
+    repaired.extend(closers.into_iter().rev());
+
+    serde_json::from_str(&repaired)
+        .ok()
+        .filter(serde_json::Value::is_object)
+}
+
+/// One model completion, assembled from a stream or a full response.
+struct Completion {
+    content: String,
+    tool_calls: Vec<ToolCall>,
+    /// Tool call cut off by the output token limit
+    truncated_call: Option<ToolCall>,
+    usage: TurnUsage,
+    finish_reason: FinishReason,
+}
+
+impl Completion {
+    /// Append a continuation of this completion.
+    fn extend(&mut self, next: Completion) {
+        self.content.push_str(&next.content);
+        self.tool_calls.extend(next.tool_calls);
+        self.truncated_call = next.truncated_call;
+        self.usage.accumulate(&next.usage);
+        self.finish_reason = next.finish_reason;
+    }
+
+    /// Whether the output token limit cut off text that can be continued.
+    fn can_continue(&self) -> bool {
+        self.finish_reason == FinishReason::MaxTokens
+            && self.tool_calls.is_empty()
+            && self.truncated_call.is_none()
+            && !self.content.trim().is_empty()
+    }
+}
+
+// ============================================================================
+// Summary Provider (for compaction)
+// ============================================================================
+
+/// Adapts the agent's provider to the summarizer interface used by
+/// [`LlmSummaryGenerator`].
+struct SummaryProvider {
+    provider: Arc<dyn Provider>,
+}
+
+impl CompletionProvider for SummaryProvider {
+    fn complete_for_summary(
+        &self,
+        model: &str,
+        system: &str,
+        prompt: &str,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Pin<Box<dyn Future<Output = anyhow::Result<String>> + Send + '_>> {
+        let mut request = CompletionRequest {
+            model: model.to_string(),
+            messages: vec![Message::user(prompt)],
+            system: Some(system.to_string()),
+            max_tokens,
+            temperature: Some(temperature),
+            tools: vec![],
+            stop: vec![],
+        };
+        if let Some(info) = self
+            .provider
+            .available_models()
+            .into_iter()
+            .find(|m| m.id == request.model)
+        {
+            let estimated = ((system.len() + prompt.len()) / 4) as u32;
+            adapt_request(&mut request, &info, estimated);
+        }
+
+        Box::pin(async move {
+            let response = self.provider.complete(request).await?;
+            if response.content.trim().is_empty() {
+                anyhow::bail!("provider returned an empty summary");
+            }
+            Ok(response.content)
+        })
+    }
+}
+
+// ============================================================================
+// Agent
+// ============================================================================
+
+/// The main agent orchestration struct.
+///
+/// `Agent` manages the conversation loop between the user, LLM, and tools.
+/// It handles:
+/// - Sending messages to the provider
+/// - Streaming responses
+/// - Executing tool calls
+/// - Managing session state
+/// - Context window tracking
+pub struct Agent {
+    /// AI provider for completions.
+    provider: Arc<dyn Provider>,
+    /// Providers for architect/editor roles whose model another provider
+    /// serves.
+    role_providers: HashMap<ModelRole, Arc<dyn Provider>>,
+    /// Tool executor for running tools.
+    executor: ToolExecutor,
+    /// Session store for persistence.
+    store: Arc<dyn SessionStore>,
+    /// Context manager for token tracking.
+    context_manager: ContextManager,
+    /// Current session.
+    session: Session,
+    /// Agent configuration.
+    config: AgentConfig,
+    /// Event handler for UI callbacks, publishing to `events`.
+    event_handler: Arc<dyn AgentEventHandler>,
+    /// Subscribers to the run's events.
+    events: EventBus,
+    /// Current operating mode.
+    mode: AgentMode,
+    /// Cancellation token for stopping operations.
+    cancel_token: CancellationToken,
+    /// Repository map for codebase context (shared with GetRepoMapTool).
+    repo_map: Arc<RwLock<Option<RepoMap>>>,
+    /// Current task plan (shared with UpdatePlanTool).
+    planning: PlanningState,
+    /// Questions from the model to the user (shared with AskUserTool).
+    questions: Arc<QuestionQueue>,
+    /// Model role handling the current step.
+    role: ModelRole,
+    /// Parent state and usage of sub-agents (shared with TaskTool).
+    subagents: SubAgentState,
+    /// Hooks, checkpoints, instructions and memory (shared with the executor)
+    services: ExecutorServices,
+    /// Checks run after turns that edit files
+    verifier: Option<Arc<Verifier>>,
+    /// Messages from the user to add at the next step
+    steering: SteeringQueue,
+    /// Repetition in the current run's tool calls
+    loops: LoopDetector,
+}
+
+impl Agent {
+    /// Create a new agent with all dependencies.
+    pub fn new(
+        provider: Arc<dyn Provider>,
+        mut executor: ToolExecutor,
+        store: Arc<dyn SessionStore>,
+        session: Session,
+        config: AgentConfig,
+        event_handler: Arc<dyn AgentEventHandler>,
+    ) -> Self {
+        let events = EventBus::new();
+        let planning = PlanningState::new(session.metadata.plan.clone());
+        let questions = Arc::new(QuestionQueue::new(
+            Arc::clone(&event_handler),
+            config.question_timeout,
+        ));
+        let subagents = SubAgentState::default();
+        executor.set_session_id(session.id());
+        let services = executor.services().clone();
+        // Old tool results are elided when large outputs are offloaded
+        let context_manager = ContextManager::with_config(ContextConfig {
+            keep_tool_results: services
+                .artifacts
+                .as_ref()
+                .map(|a| a.config().keep_recent_results),
+            ..Default::default()
+        });
+        let verifier = config.verify.is_enabled().then(|| {
+            Arc::new(Verifier::new(
+                config.verify.clone(),
+                &executor.context().working_dir,
+            ))
+        });
+        // Sub-agents can't spawn sub-agents of their own
+        let task_tool = session.metadata.parent_session_id.is_none().then(|| {
+            TaskTool::new(
+                Arc::clone(&provider),
+                Arc::clone(&store),
+                config.clone(),
+                &executor,
+                subagents.clone(),
+            )
+        });
+        // Publish the agent's own tool calls; sub-agents have their own bus
+        executor.set_handler(Arc::new(PublishingPermissionHandler::new(
+            executor.handler(),
+            events.clone(),
+        )));
+        let registry = executor.registry_mut();
+        registry.register(Arc::new(UpdatePlanTool::new(planning.shared())));
+        registry.register(Arc::new(AskUserTool::new(Arc::clone(&questions))));
+        if let Some(task_tool) = task_tool {
+            registry.register(Arc::new(task_tool));
+        }
+        if let Some(artifacts) = &services.artifacts {
+            registry.register(Arc::new(ReadArtifactTool::new(Arc::clone(artifacts))));
+        }
+        if let Some(memory) = &services.memory {
+            registry.register(Arc::new(RememberTool::new(Arc::clone(memory))));
+            registry.register(Arc::new(RecallTool::new(Arc::clone(memory))));
+            registry.register(Arc::new(ForgetTool::new(Arc::clone(memory))));
+        }
+
+        Self {
+            provider,
+            role_providers: HashMap::new(),
+            executor,
+            store,
+            context_manager,
+            session,
+            event_handler,
+            events,
+            mode: AgentMode::default(),
+            cancel_token: CancellationToken::new(),
+            repo_map: Arc::new(RwLock::new(None)),
+            planning,
+            questions,
+            role: ModelRole::Main,
+            subagents,
+            services,
+            verifier,
+            steering: SteeringQueue::new(),
+            loops: LoopDetector::new(config.loop_detection.clone()),
+            config,
+        }
+    }
+
+    // ========================================================================
+    // Accessors
+    // ========================================================================
+
+    /// Get a reference to the current session.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Get a mutable reference to the current session.
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Get the current agent mode.
+    pub fn mode(&self) -> AgentMode {
+        self.mode
+    }
+
+    /// Set the agent mode.
+    pub fn set_mode(&mut self, mode: AgentMode) {
+        self.mode = mode;
+        self.executor.set_mode(mode);
+    }
+
+    /// Set the model used for completions.
+    pub fn set_model(&mut self, model: impl Into<String>) {
+        self.config.model = model.into();
+    }
+
+    /// Restrict the model to the named tools (`None` allows all).
+    pub fn set_allowed_tools(&mut self, tools: Option<Vec<String>>) {
+        self.executor.set_allowed_tools(tools);
+    }
+
+    /// Get a reference to the configuration.
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
+
+    /// Get a reference to the context manager.
+    pub fn context_manager(&self) -> &ContextManager {
+        &self.context_manager
+    }
+
+    /// Get the shared repo map reference.
+    ///
+    /// This can be passed to GetRepoMapTool so it shares the same repo map.
+    pub fn repo_map_ref(&self) -> Arc<RwLock<Option<RepoMap>>> {
+        Arc::clone(&self.repo_map)
+    }
+
+    /// Set the repository map.
+    pub fn set_repo_map(&self, map: RepoMap) {
+        let mut guard = self.repo_map.write().unwrap();
+        *guard = Some(map);
+    }
+
+    /// Get the planning state.
+    pub fn planning(&self) -> &PlanningState {
+        &self.planning
+    }
+
+    /// Get a copy of the current plan.
+    pub fn plan(&self) -> Option<Plan> {
+        self.planning.plan()
+    }
+
+    /// Get a handle to the steering queue.
+    ///
+    /// Messages pushed to it while the agent runs are added to the
+    /// conversation at the next step, without cancelling running tools.
+    pub fn steering(&self) -> SteeringQueue {
+        self.steering.clone()
+    }
+
+    /// Get the project memory store, if any.
+    pub fn memory(&self) -> Option<&Arc<MemoryStore>> {
+        self.services.memory.as_ref()
+    }
+
+    /// Get the changeset file edits are staged in during dry runs.
+    ///
+    /// Staged changes stay there until the user accepts or discards them.
+    pub fn changeset(&self) -> Arc<Changeset> {
+        self.executor.changeset()
+    }
+
+    /// Subscribe to the events of this agent's runs.
+    ///
+    /// Each subscriber receives every event published after it subscribed,
+    /// alongside the [`AgentEventHandler`] callbacks.
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
+        self.events.subscribe()
+    }
+
+    /// Get the bus the agent publishes its events to.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Get the question queue.
+    pub fn questions(&self) -> &QuestionQueue {
+        &self.questions
+    }
+
+    /// Check if a repo map is loaded.
+    pub fn has_repo_map(&self) -> bool {
+        self.repo_map.read().unwrap().is_some()
+    }
+
+    /// Get the serialized repo map for prompt injection.
+    ///
+    /// Returns a compact text representation of the most important files,
+    /// limited by the specified token budget. Focus files are given priority.
+    fn get_repo_map_for_prompt(
+        &self,
+        token_budget: usize,
+        focus_files: &[PathBuf],
+    ) -> Option<String> {
+        let guard = self.repo_map.read().unwrap();
+        let map = guard.as_ref()?;
+
+        let config = SerializeConfig::with_budget(token_budget);
+        let serialized = if focus_files.is_empty() {
+            RepoMapSerializer::serialize_for_prompt(map, &config)
+        } else {
+            RepoMapSerializer::serialize_for_tool(map, Some(focus_files), None, &config)
+        };
+
+        if serialized.is_empty() {
+            None
+        } else {
+            Some(format!("<repository_map>\n{}</repository_map>", serialized))
+        }
+    }
+
+    /// Get the content of explicitly added files for prompt injection.
+    ///
+    /// Returns the full content of files that were added via `/add` command,
+    /// formatted for inclusion in the system prompt.
+    fn get_added_files_content(&self) -> Option<String> {
+        let files = &self.session.metadata.added_files;
+        if files.is_empty() {
+            return None;
+        }
+
+        let mut content = String::from("<added_files>\n");
+        let mut total_size = 0;
+        const MAX_TOTAL_SIZE: usize = 200_000; // ~50k tokens
+
+        for path in files {
+            let full_path = self.session.metadata.working_directory.join(path);
+            if let Ok(file_content) = std::fs::read_to_string(&full_path) {
+                // Check size limit
+                if total_size + file_content.len() > MAX_TOTAL_SIZE {
+                    content.push_str(&format!(
+                        "<file path=\"{}\" truncated=\"true\">Content truncated due to size limits</file>\n",
+                        path.display()
+                    ));
+                    break;
+                }
+                total_size += file_content.len();
+                content.push_str(&format!(
+                    "<file path=\"{}\">\n{}\n</file>\n",
+                    path.display(),
+                    file_content
+                ));
+            } else {
+                content.push_str(&format!(
+                    "<file path=\"{}\" error=\"true\">File not found or not readable</file>\n",
+                    path.display()
+                ));
+            }
+        }
+        content.push_str("</added_files>");
+        Some(content)
+    }
+
+    // ========================================================================
+    // Cancellation
+    // ========================================================================
+
+    /// Cancel any ongoing operation.
+    pub fn cancel(&self) {
+        self.cancel_token.cancel();
+    }
+
+    /// Check if cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.is_cancelled()
+    }
+
+    /// Reset the cancellation token for a new operation.
+    pub fn reset_cancel(&mut self) {
+        self.cancel_token = CancellationToken::new();
+    }
+
+    // ========================================================================
+    // Session Management
+    // ========================================================================
+
+    /// Save the current session to the store.
+    pub async fn save(&self) -> AgentResult<()> {
+        self.store
+            .update_metadata(&self.session.metadata)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))
+    }
+
+    /// Load a session by ID.
+    pub async fn load_session(store: Arc<dyn SessionStore>, id: &str) -> AgentResult<Session> {
+        store
+            .get(id)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))
+    }
+
+    /// Create a new session.
+    pub async fn new_session(
+        store: Arc<dyn SessionStore>,
+        working_dir: PathBuf,
+    ) -> AgentResult<Session> {
+        let session = Session::new(working_dir);
+        store
+            .create(&session)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))?;
+        Ok(session)
+    }
+
+    /// Trigger context compaction.
+    pub async fn compact(&mut self) -> AgentResult<()> {
+        self.notify(AgentEvent::Compacting).on_compacting();
+        if let Some(hooks) = &self.services.hooks {
+            hooks.pre_compact(self.session.id()).await;
+        }
+
+        // Find compaction boundary
+        let boundary = self.context_manager.find_compaction_boundary(&self.session);
+
+        if boundary.messages_to_compact == 0 {
+            debug!("No messages to compact");
+            return Ok(());
+        }
+
+        info!(
+            messages = boundary.messages_to_compact,
+            tokens = boundary.tokens_to_compact,
+            "Compacting session"
+        );
+
+        let compactor = Compactor::with_config(
+            CompactionConfig::default(),
+            ContextManager::with_config(self.context_manager.config().clone()),
+        );
+        let generator = LlmSummaryGenerator::new(
+            Arc::new(SummaryProvider {
+                provider: Arc::clone(&self.provider),
+            }),
+            LlmSummaryConfig {
+                model: self
+                    .config
+                    .compaction_model
+                    .clone()
+                    .unwrap_or_else(|| self.config.model.clone()),
+                ..Default::default()
+            },
+        );
+
+        let compacted = match compactor
+            .compact_with_generator(&self.session, &generator)
+            .await
+        {
+            Ok(compacted) => compacted,
+            Err(e) => {
+                warn!("LLM summary failed, using simple summary: {}", e);
+                compactor
+                    .compact_with_generator(&self.session, &SimpleSummaryGenerator)
+                    .await?
+            }
+        };
+        let Some(compacted) = compacted else {
+            return Ok(());
+        };
+
+        // The generator already folds the previous summary text into the new
+        // one; merge the structured fields so nothing older is dropped
+        let summary = match self.session.summary.as_ref() {
+            Some(previous) => agentik_core::session::CompactedSummary {
+                text: compacted.text.clone(),
+                ..compactor.merge_summaries(previous, &compacted)
+            },
+            None => compacted,
+        };
+
+        // Apply compaction to session
+        self.session.summary = Some(summary.clone());
+        self.session.compact_boundary = boundary.index;
+        self.session.metadata.metrics.compaction_count += 1;
+
+        // Persist to store
+        self.store
+            .apply_compaction(self.session.id(), &summary, boundary.index)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))?;
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // Checkpoints
+    // ========================================================================
+
+    /// List the session's checkpoints, one per turn, oldest first.
+    pub fn checkpoints(&self) -> AgentResult<Vec<Checkpoint>> {
+        match &self.services.checkpoints {
+            Some(checkpoints) => checkpoints
+                .list(self.session.id())
+                .map_err(|e| AgentError::Checkpoint(e.to_string())),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Rewind to the start of the turn at `message_index`.
+    ///
+    /// Files changed since are restored from their checkpoints, and the
+    /// conversation is truncated to the messages before the turn, depending
+    /// on the target. Returns the restored file paths.
+    pub async fn rewind(
+        &mut self,
+        message_index: usize,
+        target: RewindTarget,
+    ) -> AgentResult<Vec<PathBuf>> {
+        if message_index > self.session.messages.len() {
+            return Err(AgentError::Checkpoint(format!(
+                "no message at index {}",
+                message_index
+            )));
+        }
+
+        let mut restored = Vec::new();
+        if target.files() {
+            let checkpoints = self
+                .services
+                .checkpoints
+                .as_ref()
+                .ok_or_else(|| AgentError::NotConfigured("checkpoints are disabled".into()))?;
+            restored = checkpoints
+                .restore(self.session.id(), message_index)
+                .map_err(|e| AgentError::Checkpoint(e.to_string()))?;
+            info!(files = restored.len(), message_index, "Restored files");
+        }
+
+        if target.conversation() {
+            self.store
+                .truncate_messages(self.session.id(), message_index)
+                .await
+                .map_err(|e| AgentError::Session(e.to_string()))?;
+            self.session.messages.truncate(message_index);
+            if self.session.compact_boundary > message_index {
+                self.session.compact_boundary = 0;
+                self.session.summary = None;
+            }
+            if let Some(checkpoints) = &self.services.checkpoints {
+                checkpoints
+                    .truncate(self.session.id(), message_index)
+                    .map_err(|e| AgentError::Checkpoint(e.to_string()))?;
+            }
+            info!(message_index, "Rewound conversation");
+        }
+
+        Ok(restored)
+    }
+
+    // ========================================================================
+    // Mode Helpers
+    // ========================================================================
+
+    /// Check if streaming should be used based on mode.
+    fn should_stream(&self) -> bool {
+        // Always stream for better UX, unless explicitly disabled
+        true
+    }
+
+    /// Check if tools should be executed based on mode.
+    fn should_execute_tools(&self) -> bool {
+        self.mode != AgentMode::AskOnly
+    }
+
+    /// Provider serving the current role's model.
+    fn active_provider(&self) -> &Arc<dyn Provider> {
+        self.role_providers
+            .get(&self.role)
+            .unwrap_or(&self.provider)
+    }
+
+    /// Model used for the current role.
+    fn active_model(&self) -> &str {
+        let model = match self.role {
+            ModelRole::Main => None,
+            ModelRole::Architect => self.config.architect_model.as_deref(),
+            ModelRole::Editor => self.config.editor_model.as_deref(),
+        };
+        model.unwrap_or(&self.config.model)
+    }
+
+    /// Get mode-specific system prompt additions.
+    fn mode_system_prompt(&self) -> Option<String> {
+        match self.mode {
+            AgentMode::Planning => Some(
+                "You are in planning mode. Create detailed plans but do not execute any changes. \
+                 Investigate as needed, then submit the plan with the UpdatePlan tool as \
+                 ordered steps, each with a description and the files it will touch. \
+                 The user will review and approve the plan before it is executed."
+                    .to_string(),
+            ),
+            AgentMode::Architect => Some(match self.role {
+                ModelRole::Editor => architect::EDITOR_PROMPT.to_string(),
+                ModelRole::Main | ModelRole::Architect => architect::ARCHITECT_PROMPT.to_string(),
+            }),
+            AgentMode::AskOnly => Some(
+                "You are in ask-only mode. Answer questions and provide information, \
+                 but do not make any changes or execute any tools."
+                    .to_string(),
+            ),
+            AgentMode::DryRun => Some(
+                "You are in dry-run mode. Your file edits are staged for the user to review \
+                 instead of being written to disk; reading and searching files shows the \
+                 staged content, but git tools and the repository map only see the files on \
+                 disk. Shell commands, MCP tools and other tools that change state outside \
+                 files are unavailable, so you cannot run builds or tests."
+                    .to_string(),
+            ),
+            AgentMode::Supervised | AgentMode::Autonomous => None,
+        }
+    }
+
+    // ========================================================================
+    // Core Loop
+    // ========================================================================
+
+    /// Run the agent with user input.
+    ///
+    /// This is the main entry point for interacting with the agent.
+    /// It adds the user message, runs the completion loop, and returns
+    /// the final response.
+    pub async fn run(&mut self, input: &str) -> AgentResult<AgentResponse> {
+        let result = self.run_turn(input).await;
+        match &result {
+            Ok(response) => self
+                .notify(AgentEvent::Complete {
+                    content: response.content.clone(),
+                    turns: response.turns,
+                    usage: response.total_usage.clone(),
+                })
+                .on_complete(response),
+            // Callers report errors themselves, so only subscribers are told
+            Err(e) => self.events.emit(AgentEvent::Error {
+                message: e.to_string(),
+            }),
+        }
+        result
+    }
+
+    /// Publish an event, then return the handler to pass it on to.
+    fn notify(&self, event: AgentEvent) -> &dyn AgentEventHandler {
+        self.events.emit(event);
+        self.event_handler.as_ref()
+    }
+
+    /// Run one user turn to completion.
+    async fn run_turn(&mut self, input: &str) -> AgentResult<AgentResponse> {
+        // Reset cancellation for new run
+        self.reset_cancel();
+        self.loops.reset();
+        // Steering queued after the last run finished was meant for that run
+        let stale = self.steering.take();
+        if !stale.is_empty() {
+            warn!(
+                count = stale.len(),
+                "Discarding steering messages queued after the last run"
+            );
+        }
+        self.subagents.set_parent(
+            self.session.id(),
+            self.mode,
+            self.executor.allowed_tools(),
+            self.executor.is_read_only(),
+        );
+
+        // Let hooks block the prompt or add context to it
+        let prompt = input;
+        let mut input = input.to_string();
+        if let Some(hooks) = &self.services.hooks {
+            match hooks.user_prompt_submit(self.session.id(), &input).await {
+                PromptDecision::Allow(None) => {}
+                PromptDecision::Allow(Some(context)) => {
+                    input.push_str(&format!("\n\n<hook_context>\n{}\n</hook_context>", context));
+                }
+                PromptDecision::Block(reason) => return Err(AgentError::Blocked(reason)),
+            }
+        }
+
+        // Start a checkpoint for the files this turn changes; sub-agents
+        // record into their parent's
+        if let Some(checkpoints) = &self.services.checkpoints {
+            if self.session.metadata.parent_session_id.is_none() {
+                if let Err(e) =
+                    checkpoints.begin_turn(self.session.id(), self.session.messages.len(), prompt)
+                {
+                    warn!("Failed to start checkpoint: {}", e);
+                }
+            }
+        }
+
+        // Add user message
+        let user_msg = Message::user(input);
+        self.session.add_message(user_msg.clone());
+
+        // Persist the user message
+        self.store
+            .append_message(self.session.id(), &user_msg)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))?;
+
+        // Check context and compact if needed
+        if self.config.auto_compact && self.context_manager.needs_compaction(&self.session) {
+            self.compact().await?;
+        }
+
+        // Run the loop, then persist usage metrics and any plan changes the
+        // model made along the way
+        let result = if self.mode == AgentMode::Architect {
+            self.run_architect().await
+        } else {
+            self.run_loop().await
+        };
+        let result = match result {
+            Ok(response) => self.verify(response).await,
+            Err(e) => Err(e),
+        };
+        self.session.metadata.plan = self.planning.plan();
+        self.save().await?;
+
+        // Don't leave questions open once control returns to the caller;
+        // their answers are delivered at the next turn
+        self.questions.wait_for_pending().await;
+
+        if let Some(hooks) = &self.services.hooks {
+            match &result {
+                Ok(response) => hooks.stop(self.session.id(), &response.content, None).await,
+                Err(e) => {
+                    hooks
+                        .stop(self.session.id(), "", Some(&e.to_string()))
+                        .await
+                }
+            }
+        }
+        result
+    }
+
+    /// Internal loop that continues until completion or max turns.
+    async fn run_loop(&mut self) -> AgentResult<AgentResponse> {
+        let mut steps = Vec::new();
+        let mut total_usage = TurnUsage::default();
+
+        for turn in 0..self.config.max_turns {
+            // Check for cancellation
+            if self.is_cancelled() {
+                return Err(AgentError::Cancelled);
+            }
+
+            debug!(turn, "Running agent turn");
+
+            // Execute a single step
+            let step = self.step().await?;
+
+            // Accumulate usage
+            total_usage.accumulate(&step.usage);
+            self.record_metrics(&step);
+
+            // Report usage
+            self.notify(AgentEvent::Usage {
+                usage: step.usage.clone(),
+            })
+            .on_usage(&step.usage);
+
+            let has_tool_calls = !step.tool_calls.is_empty();
+            self.check_loops(&step).await?;
+            steps.push(step);
+
+            // If no tool calls, we're done, unless the user steered the
+            // agent while the model was responding
+            if !has_tool_calls && self.steering.is_empty() {
+                break;
+            }
+
+            // Check if we've hit max turns
+            if turn + 1 >= self.config.max_turns {
+                warn!(max_turns = self.config.max_turns, "Max turns exceeded");
+                return Err(AgentError::MaxTurnsExceeded(self.config.max_turns));
+            }
+        }
+
+        // Get final content from last step
+        let content = steps.last().map(|s| s.content.clone()).unwrap_or_default();
+
+        let response = AgentResponse {
+            content,
+            turns: steps.len(),
+            steps,
+            total_usage,
+        };
+
+        Ok(response)
+    }
+
+    /// Execute a single step (completion + optional tool execution).
+    async fn step(&mut self) -> AgentResult<StepResult> {
+        self.inject_answers().await?;
+        self.inject_steering().await?;
+        self.notify(AgentEvent::Thinking).on_thinking();
+
+        // Prepare context
+        let system = self.build_system_prompt();
+        let prepared = self
+            .context_manager
+            .prepare_context(&self.session, system.as_deref());
+
+        // Build completion request with the tools the executor will run
+        let tools: Vec<ToolDefinition> = if self.should_execute_tools() {
+            let mut tools = self.executor.registry().definitions();
+            tools.retain(|t| self.executor.is_available(t));
+            tools
+        } else {
+            vec![]
+        };
+
+        let mut request = CompletionRequest {
+            model: self.active_model().to_string(),
+            messages: prepared.messages,
+            system: prepared.system_message,
+            max_tokens: self.config.max_tokens,
+            temperature: Some(self.config.temperature),
+            tools,
+            stop: vec![],
+        };
+
+        // Adapt the request to what the target model accepts
+        if let Some(model) = self
+            .active_provider()
+            .available_models()
+            .into_iter()
+            .find(|m| m.id == request.model)
+        {
+            adapt_request(&mut request, &model, prepared.estimated_tokens);
+        }
+
+        // Execute completion, continuing text cut off by the output token limit
+        let mut completion = self.complete(request.clone()).await?;
+        let mut continuations = 0;
+        while completion.can_continue() {
+            if continuations == self.config.max_continuations {
+                warn!(
+                    continuations,
+                    "Response still truncated, giving up on continuing"
+                );
+                break;
+            }
+            continuations += 1;
+            info!(
+                continuations,
+                "Response hit the output token limit, continuing"
+            );
+            let request = self.continuation_request(&request, &mut completion.content);
+            let next = self.complete(request).await?;
+            completion.extend(next);
+        }
+        let Completion {
+            content,
+            mut tool_calls,
+            truncated_call,
+            usage,
+            ..
+        } = completion;
+
+        // A cut-off tool call is kept in the conversation, but answered with
+        // an error instead of being run
+        let runnable = tool_calls.len();
+        if let Some(ref call) = truncated_call {
+            warn!(tool = %call.name, "Tool call cut off by the output token limit");
+            tool_calls.push(call.clone());
+        }
+
+        // Create and store assistant message
+        let mut assistant_msg = Message::assistant(&content);
+        assistant_msg.tool_calls = tool_calls.clone();
+        self.session.add_message(assistant_msg.clone());
+
+        self.store
+            .append_message(self.session.id(), &assistant_msg)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))?;
+
+        // Execute tool calls if any
+        let mut tool_results = if runnable > 0 && self.should_execute_tools() {
+            self.handle_tool_calls(&tool_calls[..runnable]).await?
+        } else {
+            vec![]
+        };
+        if let Some(call) = truncated_call {
+            let result = ToolResult::error(call.id, TRUNCATED_CALL_ERROR);
+            self.store_tool_result(&result).await?;
+            tool_results.push(result);
+        }
+
+        Ok(StepResult {
+            content,
+            tool_calls,
+            tool_results,
+            usage,
+            role: self.role,
+        })
+    }
+
+    /// Add a step's usage, and that of any sub-agents it ran, to the
+    /// session metrics.
+    fn record_metrics(&mut self, step: &StepResult) {
+        fn add(metrics: &mut SessionMetrics, usage: &TurnUsage) {
+            metrics.total_tokens_in += u64::from(usage.input_tokens);
+            metrics.total_tokens_out += u64::from(usage.output_tokens);
+            metrics.total_cost += usage.cost_usd;
+        }
+
+        // A sub-agent's usage is recorded in its parent's session instead,
+        // so totals across sessions count it once
+        let is_subagent = self.session.metadata.parent_session_id.is_some();
+        let metrics = &mut self.session.metadata.metrics;
+        if !is_subagent {
+            add(metrics, &step.usage);
+        }
+        add(metrics, &self.subagents.take_usage());
+        metrics.tool_calls += step.tool_calls.len() as u32;
+    }
+
+    /// Add answers to non-blocking questions to the conversation.
+    async fn inject_answers(&mut self) -> AgentResult<()> {
+        for (question, answer) in self.questions.take_answers() {
+            let msg = Message::user(answer.describe(&question));
+            self.session.add_message(msg.clone());
+            self.store
+                .append_message(self.session.id(), &msg)
+                .await
+                .map_err(|e| AgentError::Session(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Add queued steering messages to the conversation.
+    async fn inject_steering(&mut self) -> AgentResult<()> {
+        for message in self.steering.take() {
+            debug!("Injecting steering message");
+            let msg = Message::user(&message);
+            self.session.add_message(msg.clone());
+            self.store
+                .append_message(self.session.id(), &msg)
+                .await
+                .map_err(|e| AgentError::Session(e.to_string()))?;
+            self.event_handler.on_steering(&message);
+        }
+        Ok(())
+    }
+
+    /// Look for repetition in a step's tool calls and step in if found.
+    ///
+    /// The first detections get a note asking the model to change course;
+    /// later ones ask the user whether to go on, and the run stops once
+    /// `max_detections` is exceeded.
+    async fn check_loops(&mut self, step: &StepResult) -> AgentResult<()> {
+        let mut found = None;
+        for (call, result) in step.tool_calls.iter().zip(&step.tool_results) {
+            let edited = if result.success {
+                self.executor.edited_paths(call)
+            } else {
+                vec![]
+            };
+            if let Some(detection) = self.loops.record(call, result, &edited) {
+                found.get_or_insert(detection);
+            }
+        }
+        let Some(detection) = found else {
+            return Ok(());
+        };
+
+        let strikes = self.loops.strike();
+        warn!(%detection, strikes, "Loop detected");
+        self.event_handler.on_loop_detected(&detection);
+        let config = &self.config.loop_detection;
+        if strikes > config.max_detections {
+            return Err(AgentError::LoopDetected(detection.to_string()));
+        }
+
+        let mut note = detection.note();
+        if strikes > config.notes_before_escalation {
+            let question = Question::new(format!(
+                "The agent seems to be stuck: {}. Let it continue?",
+                detection
+            ))
+            .with_options(vec!["Continue".to_string(), "Stop".to_string()])
+            .with_default("Continue");
+            let answer = self.questions.ask(&question).await;
+            let text = answer.text.trim();
+            if text.eq_ignore_ascii_case("stop") {
+                return Err(AgentError::LoopDetected(detection.to_string()));
+            }
+            if !text.eq_ignore_ascii_case("continue") {
+                note.push_str(&format!("\n\nGuidance from the user: {}", text));
+            }
+        }
+
+        let msg = Message::user(&note);
+        self.session.add_message(msg.clone());
+        self.store
+            .append_message(self.session.id(), &msg)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Get a completion, streaming it if enabled.
+    async fn complete(&mut self, request: CompletionRequest) -> AgentResult<Completion> {
+        if self.should_stream() {
+            self.step_streaming(request).await
+        } else {
+            self.step_non_streaming(request).await
+        }
+    }
+
+    /// Build the request continuing a response cut off by the output token
+    /// limit.
+    ///
+    /// Providers with prefill support continue the partial response given as
+    /// a trailing assistant message, which can't end in whitespace, so the
+    /// content is trimmed to match. Other providers get the partial response
+    /// back with a request to carry on.
+    fn continuation_request(
+        &self,
+        request: &CompletionRequest,
+        content: &mut String,
+    ) -> CompletionRequest {
+        let mut request = request.clone();
+        if self.active_provider().supports_prefill() {
+            content.truncate(content.trim_end().len());
+            request.messages.push(Message::assistant(content.as_str()));
+        } else {
+            request.messages.push(Message::assistant(content.as_str()));
+            request.messages.push(Message::user(CONTINUE_PROMPT));
+        }
+        request
+    }
+
+    /// Execute completion with streaming.
+    async fn step_streaming(&mut self, request: CompletionRequest) -> AgentResult<Completion> {
+        let mut stream = self.active_provider().complete_stream(request).await?;
+        let mut content = String::new();
+        let mut tool_builder = ToolCallBuilder::new();
+        let mut usage = TurnUsage::default();
+        let mut finish_reason = FinishReason::Stop;
+
+        while let Some(chunk_result) = stream.next().await {
+            // Check for cancellation
+            if self.is_cancelled() {
+                return Err(AgentError::Cancelled);
+            }
+
+            let chunk: StreamChunk = chunk_result?;
+
+            // Handle text delta
+            if let Some(delta) = &chunk.delta {
+                content.push_str(delta);
+                self.notify(AgentEvent::TextDelta {
+                    delta: delta.clone(),
+                })
+                .on_text_delta(delta);
+            }
+
+            // Handle tool call delta
+            if let Some(tool_delta) = &chunk.tool_call_delta {
+                tool_builder.apply_delta(tool_delta);
+            }
+
+            if let Some(reason) = chunk.finish_reason {
+                finish_reason = reason;
+            }
+
+            // Handle final chunk with usage
+            if chunk.is_final {
+                if let Some(u) = chunk.usage {
+                    usage = u.into();
+                }
+            }
+        }
+
+        let (tool_calls, truncated_call) =
+            tool_builder.build(finish_reason == FinishReason::MaxTokens);
+        Ok(Completion {
+            content,
+            tool_calls,
+            truncated_call,
+            usage,
+            finish_reason,
+        })
+    }
+
+    /// Execute completion without streaming.
+    async fn step_non_streaming(&mut self, request: CompletionRequest) -> AgentResult<Completion> {
+        let response: CompletionResponse = self.active_provider().complete(request).await?;
+
+        // Send full content as single delta for consistency
+        self.notify(AgentEvent::TextDelta {
+            delta: response.content.clone(),
+        })
+        .on_text_delta(&response.content);
+
+        let mut tool_calls = response.tool_calls;
+        let truncated_call = if response.finish_reason == FinishReason::MaxTokens {
+            tool_calls
+                .pop()
+                .map(|call| ToolCall::new(call.id, call.name, serde_json::json!({})))
+        } else {
+            None
+        };
+        Ok(Completion {
+            content: response.content,
+            tool_calls,
+            truncated_call,
+            usage: response.usage.into(),
+            finish_reason: response.finish_reason,
+        })
+    }
+
+    /// Build the system prompt including mode additions, repo map, and added files.
+    fn build_system_prompt(&self) -> Option<String> {
+        let mut parts = Vec::new();
+
+        if let Some(base) = &self.config.system_prompt {
+            parts.push(base.clone());
+        }
+
+        if let Some(mode_prompt) = self.mode_system_prompt() {
+            parts.push(mode_prompt);
+        }
+
+        if let Some(instructions) = self.services.instructions.as_ref().and_then(|i| i.render()) {
+            parts.push(instructions);
+        }
+
+        // Memories most relevant to the latest prompt
+        if let Some(memory) = &self.services.memory {
+            let prompt = self
+                .session
+                .messages
+                .iter()
+                .rev()
+                .find(|m| m.role == Role::User)
+                .map(|m| m.content.as_text())
+                .unwrap_or_default();
+            if let Some(memories) = memory.render(&prompt) {
+                parts.push(memories);
+            }
+        }
+
+        if let Some(plan) = self.planning.plan() {
+            parts.push(format!("<plan>\n{}</plan>", plan));
+        }
+
+        // Inject repo map with ~2000 token budget, prioritizing focus files
+        let focus_files = &self.session.metadata.added_files;
+        if let Some(repo_map) = self.get_repo_map_for_prompt(2000, focus_files) {
+            parts.push(repo_map);
+        }
+
+        // Inject full content of added files
+        if let Some(files_content) = self.get_added_files_content() {
+            parts.push(files_content);
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("\n\n"))
+        }
+    }
+
+    /// Handle execution of tool calls.
+    async fn handle_tool_calls(&mut self, calls: &[ToolCall]) -> AgentResult<Vec<ToolResult>> {
+        let results = self.executor.execute_batch(calls).await;
+
+        // Report instruction files the calls led to; a sub-agent's are
+        // reported by its parent
+        if let Some(instructions) = &self.services.instructions {
+            if self.session.metadata.parent_session_id.is_none() {
+                let loaded = instructions.take_loaded();
+                if !loaded.is_empty() {
+                    self.event_handler.on_instructions_loaded(&loaded);
+                }
+            }
+        }
+
+        // Store tool results in session
+        for result in &results {
+            self.store_tool_result(result).await?;
+        }
+
+        Ok(results)
+    }
+
+    /// Add a tool result to the session.
+    async fn store_tool_result(&mut self, result: &ToolResult) -> AgentResult<()> {
+        let tool_msg = Message::tool_result(
+            result.tool_call_id.clone(),
+            if result.success {
+                result.output.clone()
+            } else {
+                result.error.clone().unwrap_or_default()
+            },
+            !result.success,
+        );
+        self.session.add_message(tool_msg.clone());
+
+        self.store
+            .append_message(self.session.id(), &tool_msg)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))?;
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Agent Builder
+// ============================================================================
+
+/// Builder for constructing an [`Agent`].
+pub struct AgentBuilder {
+    provider: Option<Arc<dyn Provider>>,
+    role_providers: HashMap<ModelRole, Arc<dyn Provider>>,
+    executor: Option<ToolExecutor>,
+    store: Option<Arc<dyn SessionStore>>,
+    session: Option<Session>,
+    config: AgentConfig,
+    event_handler: Option<Arc<dyn AgentEventHandler>>,
+    mode: AgentMode,
+    repo_map: Option<RepoMap>,
+}
+
+impl Default for AgentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AgentBuilder {
+    /// Create a new builder with default configuration.
+    pub fn new() -> Self {
+        Self {
+            provider: None,
+            role_providers: HashMap::new(),
+            executor: None,
+            store: None,
+            session: None,
+            config: AgentConfig::default(),
+            event_handler: None,
+            mode: AgentMode::default(),
+            repo_map: None,
+        }
+    }
+
+    /// Set the AI provider.
+    pub fn provider(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Set the tool executor.
+    pub fn executor(mut self, executor: ToolExecutor) -> Self {
+        self.executor = Some(executor);
+        self
+    }
+
+    /// Set the session store.
+    pub fn store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Set the session.
+    pub fn session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Set the model to use.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.config.model = model.into();
+        self
+    }
+
+    /// Set the system prompt.
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.config.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Set the maximum tokens per response.
+    pub fn max_tokens(mut self, max: u32) -> Self {
+        self.config.max_tokens = max;
+        self
+    }
+
+    /// Set the temperature.
+    pub fn temperature(mut self, temp: f32) -> Self {
+        self.config.temperature = temp;
+        self
+    }
+
+    /// Set the maximum number of turns.
+    pub fn max_turns(mut self, max: usize) -> Self {
+        self.config.max_turns = max;
+        self
+    }
+
+    /// Enable or disable auto-compaction.
+    pub fn auto_compact(mut self, enabled: bool) -> Self {
+        self.config.auto_compact = enabled;
+        self
+    }
+
+    /// Set how long to wait for answers to questions before using defaults
+    /// (`None` waits indefinitely).
+    pub fn question_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.question_timeout = timeout;
+        self
+    }
+
+    /// Set the model that writes proposals in architect mode.
+    pub fn architect_model(mut self, model: impl Into<String>) -> Self {
+        self.config.architect_model = Some(model.into());
+        self
+    }
+
+    /// Set the model that implements proposals in architect mode.
+    pub fn editor_model(mut self, model: impl Into<String>) -> Self {
+        self.config.editor_model = Some(model.into());
+        self
+    }
+
+    /// Set the provider serving the architect model (defaults to the main
+    /// provider).
+    pub fn architect_provider(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.role_providers.insert(ModelRole::Architect, provider);
+        self
+    }
+
+    /// Set the provider serving the editor model (defaults to the main
+    /// provider).
+    pub fn editor_provider(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.role_providers.insert(ModelRole::Editor, provider);
+        self
+    }
+
+    /// Set the checks run after turns that edit files.
+    pub fn verify(mut self, config: VerifyConfig) -> Self {
+        self.config.verify = config;
+        self
+    }
+
+    /// Set how often a response cut off by the output token limit is
+    /// continued.
+    pub fn max_continuations(mut self, max: u32) -> Self {
+        self.config.max_continuations = max;
+        self
+    }
+
+    /// Set when to step in on repeated calls, errors and edits.
+    pub fn loop_detection(mut self, config: LoopDetectionConfig) -> Self {
+        self.config.loop_detection = config;
+        self
+    }
+
+    /// Set the model used to summarize history during compaction.
+    pub fn compaction_model(mut self, model: impl Into<String>) -> Self {
+        self.config.compaction_model = Some(model.into());
+        self
+    }
+
+    /// Set the full configuration.
+    pub fn config(mut self, config: AgentConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Set the event handler.
+    pub fn event_handler(mut self, handler: Arc<dyn AgentEventHandler>) -> Self {
+        self.event_handler = Some(handler);
+        self
+    }
+
+    /// Set the operating mode.
+    pub fn mode(mut self, mode: AgentMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the repository map for codebase context.
+    pub fn repo_map(mut self, map: RepoMap) -> Self {
+        self.repo_map = Some(map);
+        self
+    }
+
+    /// Build the agent.
+    ///
+    /// Returns an error if required components are missing.
+    pub fn build(self) -> AgentResult<Agent> {
+        let provider = self
+            .provider
+            .ok_or_else(|| AgentError::NotConfigured("provider is required".into()))?;
+        let executor = self
+            .executor
+            .ok_or_else(|| AgentError::NotConfigured("executor is required".into()))?;
+        let store = self
+            .store
+            .ok_or_else(|| AgentError::NotConfigured("store is required".into()))?;
+        let session = self
+            .session
+            .ok_or_else(|| AgentError::NotConfigured("session is required".into()))?;
+        let event_handler = self
+            .event_handler
+            .unwrap_or_else(|| Arc::new(NoOpEventHandler));
+
+        let mut agent = Agent::new(
+            provider,
+            executor,
+            store,
+            session,
+            self.config,
+            event_handler,
+        );
+        agent.set_mode(self.mode);
+        agent.role_providers = self.role_providers;
+
+        // Set repo map if provided
+        if let Some(map) = self.repo_map {
+            agent.set_repo_map(map);
+        }
+
+        Ok(agent)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests;
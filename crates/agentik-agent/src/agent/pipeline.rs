@@ -0,0 +1,253 @@
+//! The architect/editor pipeline.
+//!
+//! In architect mode each run has two phases: the architect model explores
+//! with read-only tools and writes a proposal, then the editor model
+//! implements it. See [`crate::architect`] for the prompts.
+
+use agentik_core::Message;
+use tracing::info;
+
+use super::{Agent, AgentError, AgentResponse, AgentResult};
+use crate::architect::{self, ModelRole};
+
+impl Agent {
+    /// Run the architect/editor pipeline.
+    ///
+    /// The architect model writes a proposal with read-only tools, then the
+    /// editor model implements it. An empty proposal skips the editor.
+    pub(super) async fn run_architect(&mut self) -> AgentResult<AgentResponse> {
+        let mut response = self.run_as(ModelRole::Architect).await?;
+        if response.content.trim().is_empty() {
+            return Ok(response);
+        }
+
+        let handoff = Message::user(architect::editor_request(&response.content));
+        self.session.add_message(handoff.clone());
+        self.store
+            .append_message(self.session.id(), &handoff)
+            .await
+            .map_err(|e| AgentError::Session(e.to_string()))?;
+
+        response.extend(self.run_as(ModelRole::Editor).await?);
+        Ok(response)
+    }
+
+    /// Run the loop with the model for a pipeline role.
+    ///
+    /// The architect may only run read-only tools.
+    pub(super) async fn run_as(&mut self, role: ModelRole) -> AgentResult<AgentResponse> {
+        self.role = role;
+        self.executor.set_read_only(role == ModelRole::Architect);
+        self.subagents.set_read_only(role == ModelRole::Architect);
+        info!(%role, model = self.active_model(), "Starting pipeline phase");
+        self.event_handler.on_role_change(role, self.active_model());
+        let result = self.run_loop().await;
+        self.executor.set_read_only(false);
+        self.subagents.set_read_only(false);
+        self.role = ModelRole::Main;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tests::{
+        create_test_executor, text_response, tool_call, MockProvider, MockSessionStore,
+    };
+    use crate::agent::{AgentBuilder, AgentEventHandler};
+    use crate::events::AgentEvent;
+    use crate::modes::AgentMode;
+    use agentik_core::config::VerifyConfig;
+    use agentik_core::Session;
+    use agentik_providers::traits::Usage;
+    use agentik_providers::CompletionResponse;
+    use agentik_session::SessionStore;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_architect_editor_pipeline() {
+        let usage = |input_tokens| Usage {
+            input_tokens,
+            output_tokens: 10,
+            cached_tokens: 0,
+        };
+        let architect = Arc::new(MockProvider::new(vec![
+            // Destructive calls are refused, even though none were offered
+            CompletionResponse {
+                usage: usage(400),
+                ..tool_call(
+                    "call_1",
+                    "Write",
+                    serde_json::json!({"path": "/tmp/test/x.rs", "content": ""}),
+                )
+            },
+            CompletionResponse {
+                usage: usage(600),
+                ..text_response("Rename `foo` to `bar` in src/lib.rs.")
+            },
+        ]));
+        let editor = Arc::new(MockProvider::new(vec![CompletionResponse {
+            usage: usage(200),
+            ..text_response("Renamed.")
+        }]));
+        let main = Arc::new(MockProvider::new(vec![]));
+        let store = Arc::new(MockSessionStore::new());
+        let session = Session::new(PathBuf::from("/tmp/test"));
+        store.create(&session).await.unwrap();
+        let mut agent = AgentBuilder::new()
+            .provider(main.clone())
+            .architect_provider(architect.clone())
+            .editor_provider(editor.clone())
+            .executor(create_test_executor())
+            .store(store)
+            .session(session)
+            .model("main-model")
+            .architect_model("strong-model")
+            .editor_model("fast-model")
+            .mode(AgentMode::Architect)
+            .build()
+            .unwrap();
+
+        let response = agent.run("Rename foo").await.unwrap();
+
+        assert!(main.requests.lock().unwrap().is_empty());
+        let architect_requests = architect.requests.lock().unwrap();
+        assert_eq!(architect_requests.len(), 2);
+        assert_eq!(architect_requests[0].model, "strong-model");
+        assert!(architect_requests[0]
+            .tools
+            .iter()
+            .all(|t| !t.is_destructive));
+        assert!(architect_requests[0].tools.iter().any(|t| t.name == "Read"));
+        let denied = &response.steps[0].tool_results[0];
+        assert!(!denied.success);
+        assert!(denied.error.as_deref().unwrap().contains("read-only"));
+
+        let editor_requests = editor.requests.lock().unwrap();
+        assert_eq!(editor_requests.len(), 1);
+        assert_eq!(editor_requests[0].model, "fast-model");
+        assert!(editor_requests[0].tools.iter().any(|t| t.name == "Edit"));
+        let handoff = editor_requests[0]
+            .messages
+            .last()
+            .unwrap()
+            .content
+            .as_text();
+        assert!(handoff.contains("<proposal>\nRename `foo` to `bar` in src/lib.rs.\n</proposal>"));
+
+        assert_eq!(response.content, "Renamed.");
+        assert_eq!(response.turns, 3);
+        assert_eq!(response.usage_for(ModelRole::Architect).input_tokens, 1000);
+        assert_eq!(response.usage_for(ModelRole::Editor).input_tokens, 200);
+        assert_eq!(response.total_usage.input_tokens, 1200);
+    }
+
+    #[tokio::test]
+    async fn test_architect_cannot_delegate_writes() {
+        let architect = Arc::new(MockProvider::new(vec![
+            tool_call(
+                "call_t",
+                "Task",
+                serde_json::json!({
+                    "description": "Apply the rename",
+                    "prompt": "Rename foo to bar in src/lib.rs",
+                    "tools": ["Read", "Write"]
+                }),
+            ),
+            text_response("Rename `foo` to `bar` in src/lib.rs."),
+        ]));
+        let editor = Arc::new(MockProvider::with_response("Renamed."));
+        let store = Arc::new(MockSessionStore::new());
+        let session = Session::new(PathBuf::from("/tmp/test"));
+        store.create(&session).await.unwrap();
+        let mut agent = AgentBuilder::new()
+            .provider(Arc::new(MockProvider::new(vec![])))
+            .architect_provider(architect.clone())
+            .editor_provider(editor)
+            .executor(create_test_executor())
+            .store(store.clone())
+            .session(session)
+            .mode(AgentMode::Architect)
+            .build()
+            .unwrap();
+
+        let response = agent.run("Rename foo").await.unwrap();
+
+        // The Task call is refused before a sub-agent session is forked
+        let refused = &response.steps[0].tool_results[0];
+        assert!(!refused.success);
+        assert!(refused.error.as_deref().unwrap().contains("read-only"));
+        assert_eq!(architect.requests.lock().unwrap().len(), 2);
+        assert_eq!(store.sessions.lock().unwrap().len(), 1);
+        assert_eq!(response.content, "Renamed.");
+    }
+
+    #[tokio::test]
+    async fn test_architect_run_completes_once() {
+        struct CompleteCounter(Mutex<Vec<String>>);
+
+        impl AgentEventHandler for CompleteCounter {
+            fn on_complete(&self, response: &AgentResponse) {
+                self.0.lock().unwrap().push(response.content.clone());
+            }
+        }
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        let write = |content: &str, reply: &str| {
+            MockProvider::with_tool_call(
+                "Write",
+                serde_json::json!({"file_path": "notes.txt", "content": content}),
+                reply,
+            )
+            .responses
+            .into_inner()
+            .unwrap()
+        };
+        let architect = Arc::new(MockProvider::with_response("Write the notes."));
+        let editor = Arc::new(MockProvider::new(
+            [write("broken", "Done."), write("fixed", "Fixed.")].concat(),
+        ));
+        let store = Arc::new(MockSessionStore::new());
+        let session = Session::new(dir.to_path_buf());
+        store.create(&session).await.unwrap();
+        let handler = Arc::new(CompleteCounter(Mutex::new(vec![])));
+        let mut agent = AgentBuilder::new()
+            .provider(Arc::new(MockProvider::new(vec![])))
+            .architect_provider(architect)
+            .editor_provider(editor)
+            .executor(
+                crate::executor::ExecutorBuilder::new()
+                    .with_builtins()
+                    .working_dir(dir)
+                    .mode(AgentMode::Autonomous)
+                    .build(Arc::new(crate::executor::AutoApproveHandler)),
+            )
+            .store(store)
+            .session(session)
+            .mode(AgentMode::Architect)
+            .event_handler(handler.clone())
+            .verify(VerifyConfig {
+                commands: vec!["grep -q fixed notes.txt".into()],
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let mut receiver = agent.subscribe();
+
+        let response = agent.run("Write the notes").await.unwrap();
+
+        // Architect, editor and repair phases end in one completion
+        assert_eq!(response.turns, 5);
+        assert_eq!(*handler.0.lock().unwrap(), vec!["Fixed.".to_string()]);
+        let mut completions = Vec::new();
+        while let Ok(envelope) = receiver.try_recv() {
+            if let AgentEvent::Complete { content, turns, .. } = envelope.event {
+                completions.push((content, turns));
+            }
+        }
+        assert_eq!(completions, vec![("Fixed.".to_string(), 5)]);
+    }
+}
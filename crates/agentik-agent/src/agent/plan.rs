@@ -0,0 +1,283 @@
+//! Executing approved plans.
+//!
+//! Each unfinished step of an approved plan is sent to the model as its own
+//! run. A failed step sends the model back to planning mode to revise the
+//! remaining steps, and execution stops until the revision is approved.
+
+use agentik_core::{Plan, PlanStepStatus};
+use tracing::{info, warn};
+
+use super::{Agent, AgentError, AgentResult};
+use crate::modes::AgentMode;
+use crate::planning::{PlanOutcome, PlanningState};
+
+impl Agent {
+    /// Persist the shared plan to session metadata if it changed.
+    async fn sync_plan(&mut self) -> AgentResult<()> {
+        let plan = self.planning.plan();
+        if plan == self.session.metadata.plan {
+            return Ok(());
+        }
+        self.session.metadata.plan = plan;
+        self.save().await
+    }
+
+    /// Edit the current plan and persist the result.
+    ///
+    /// Returns an error if there is no plan or the edit is invalid.
+    pub async fn update_plan<T>(
+        &mut self,
+        f: impl FnOnce(&mut Plan) -> agentik_core::Result<T>,
+    ) -> AgentResult<T> {
+        let result = self
+            .planning
+            .update(f)
+            .ok_or_else(|| AgentError::Plan("no plan exists".into()))?
+            .map_err(|e| AgentError::Plan(e.to_string()))?;
+        self.sync_plan().await?;
+        Ok(result)
+    }
+
+    /// Approve the current plan for execution.
+    pub async fn approve_plan(&mut self) -> AgentResult<()> {
+        self.update_plan(|plan| {
+            plan.approve();
+            Ok(())
+        })
+        .await
+    }
+
+    /// Discard the current plan.
+    pub async fn clear_plan(&mut self) -> AgentResult<()> {
+        self.planning.set_plan(None);
+        self.sync_plan().await
+    }
+
+    /// Execute the approved plan step by step.
+    ///
+    /// Each unfinished step is sent to the model as its own run. When a step
+    /// fails, the model is asked (in planning mode) to revise the remaining
+    /// steps and execution stops so the user can review the revision.
+    /// Planning mode is lifted while steps execute and restored afterwards.
+    pub async fn execute_plan(&mut self) -> AgentResult<PlanOutcome> {
+        let plan = self
+            .planning
+            .plan()
+            .ok_or_else(|| AgentError::Plan("no plan exists".into()))?;
+        if !plan.approved {
+            return Err(AgentError::Plan("plan has not been approved".into()));
+        }
+
+        let previous_mode = self.mode;
+        if previous_mode == AgentMode::Planning {
+            self.set_mode(AgentMode::default());
+        }
+        let outcome = self.execute_plan_steps().await;
+        self.set_mode(previous_mode);
+        outcome
+    }
+
+    async fn execute_plan_steps(&mut self) -> AgentResult<PlanOutcome> {
+        loop {
+            let plan = self
+                .planning
+                .plan()
+                .unwrap_or_else(|| Plan::new("", vec![]));
+            let Some((index, _)) = plan.next_step() else {
+                return Ok(PlanOutcome::Completed);
+            };
+
+            info!(
+                step = index + 1,
+                total = plan.steps.len(),
+                "Executing plan step"
+            );
+            self.planning
+                .update(|p| p.set_status(index, PlanStepStatus::InProgress, None));
+            self.sync_plan().await?;
+
+            let prompt = PlanningState::step_prompt(&plan, index);
+            let result = self.run(&prompt).await;
+
+            // A revision during the step replaces its unfinished steps, so
+            // `index` no longer refers to this step; the revision needs
+            // approving before execution goes on
+            let revised = self
+                .planning
+                .plan()
+                .map_or(true, |p| p.revisions != plan.revisions);
+            if revised {
+                self.sync_plan().await?;
+                result?;
+                return Ok(PlanOutcome::Revised { failed_step: index });
+            }
+
+            // The model reports failures through UpdatePlan; a step it left
+            // in progress is assumed to have succeeded
+            let status = match result {
+                Ok(_) => None,
+                Err(AgentError::Cancelled) => {
+                    self.planning
+                        .update(|p| p.set_status(index, PlanStepStatus::Pending, None));
+                    self.sync_plan().await?;
+                    return Err(AgentError::Cancelled);
+                }
+                Err(e) => Some((PlanStepStatus::Failed, Some(e.to_string()))),
+            };
+            self.planning.update(|p| match status {
+                Some((status, notes)) => p.set_status(index, status, notes),
+                None if p.steps.get(index).map(|s| s.status)
+                    == Some(PlanStepStatus::InProgress) =>
+                {
+                    p.set_status(index, PlanStepStatus::Completed, None)
+                }
+                None => Ok(()),
+            });
+            self.sync_plan().await?;
+
+            let plan = self
+                .planning
+                .plan()
+                .unwrap_or_else(|| Plan::new("", vec![]));
+            if plan.steps.get(index).map(|s| s.status) != Some(PlanStepStatus::Failed) {
+                continue;
+            }
+
+            // Re-plan the remaining work
+            warn!(step = index + 1, "Plan step failed, re-planning");
+            let revisions = plan.revisions;
+            let mode = self.mode;
+            self.set_mode(AgentMode::Planning);
+            let result = self.run(&PlanningState::replan_prompt(&plan, index)).await;
+            self.set_mode(mode);
+            result?;
+
+            let revised = self
+                .planning
+                .plan()
+                .is_some_and(|p| p.revisions > revisions);
+            return Ok(if revised {
+                PlanOutcome::Revised { failed_step: index }
+            } else {
+                PlanOutcome::Failed { failed_step: index }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tests::{create_test_agent, text_response, tool_call, MockProvider};
+    use agentik_providers::CompletionResponse;
+    use std::sync::Arc;
+
+    fn plan_call(id: &str, args: serde_json::Value) -> CompletionResponse {
+        tool_call(id, "UpdatePlan", args)
+    }
+
+    #[tokio::test]
+    async fn test_planning_tool_submits_plan() {
+        let provider = Arc::new(MockProvider::new(vec![
+            plan_call(
+                "call_1",
+                serde_json::json!({
+                    "goal": "Add README",
+                    "steps": [{"description": "Write README", "files": ["README.md"]}]
+                }),
+            ),
+            text_response("Plan submitted."),
+        ]));
+        let mut agent = create_test_agent(provider).await;
+        agent.set_mode(AgentMode::Planning);
+
+        agent.run("Plan a README").await.unwrap();
+
+        let plan = agent.plan().unwrap();
+        assert_eq!(plan.goal, "Add README");
+        assert!(!plan.approved);
+        // Persisted in session metadata
+        assert_eq!(agent.session().metadata.plan, Some(plan));
+        assert!(matches!(
+            agent.execute_plan().await,
+            Err(AgentError::Plan(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_replans_on_failure() {
+        let provider = Arc::new(MockProvider::new(vec![
+            // Step 1 succeeds
+            text_response("Step one done."),
+            // Step 2 reports failure
+            plan_call(
+                "call_2",
+                serde_json::json!({"step": 2, "status": "failed", "note": "tests fail"}),
+            ),
+            text_response("Step two failed."),
+            // Re-plan
+            plan_call(
+                "call_3",
+                serde_json::json!({"steps": [{"description": "Fix the failing tests"}]}),
+            ),
+            text_response("Revised."),
+        ]));
+        let mut agent = create_test_agent(provider).await;
+        agent.set_mode(AgentMode::Planning);
+        agent.planning().set_plan(Some(Plan::new(
+            "Ship feature",
+            vec![
+                agentik_core::PlanStep::new("Implement"),
+                agentik_core::PlanStep::new("Run tests"),
+            ],
+        )));
+        agent.approve_plan().await.unwrap();
+
+        let outcome = agent.execute_plan().await.unwrap();
+
+        assert_eq!(outcome, PlanOutcome::Revised { failed_step: 1 });
+        assert_eq!(agent.mode(), AgentMode::Planning);
+        let plan = agent.plan().unwrap();
+        assert!(!plan.approved);
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[0].status, PlanStepStatus::Completed);
+        assert_eq!(plan.steps[1].description, "Fix the failing tests");
+        assert_eq!(agent.session().metadata.plan, Some(plan));
+
+        // Approving and executing the revision completes the plan
+        agent.approve_plan().await.unwrap();
+        assert_eq!(agent.execute_plan().await.unwrap(), PlanOutcome::Completed);
+        assert!(agent.plan().unwrap().is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_stops_when_step_revises_plan() {
+        let provider = Arc::new(MockProvider::new(vec![
+            // Step 1 replaces the remaining steps with a shorter list
+            plan_call(
+                "call_1",
+                serde_json::json!({"steps": [{"description": "Do it differently"}]}),
+            ),
+            text_response("Revised mid-step."),
+        ]));
+        let mut agent = create_test_agent(provider).await;
+        agent.planning().set_plan(Some(Plan::new(
+            "Ship feature",
+            vec![
+                agentik_core::PlanStep::new("Implement"),
+                agentik_core::PlanStep::new("Test"),
+                agentik_core::PlanStep::new("Document"),
+            ],
+        )));
+        agent.approve_plan().await.unwrap();
+
+        let outcome = agent.execute_plan().await.unwrap();
+
+        assert_eq!(outcome, PlanOutcome::Revised { failed_step: 0 });
+        let plan = agent.plan().unwrap();
+        assert!(!plan.approved);
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].description, "Do it differently");
+        assert_eq!(plan.steps[0].status, PlanStepStatus::Pending);
+    }
+}
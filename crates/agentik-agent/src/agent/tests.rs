@@ -0,0 +1,1607 @@
+use super::*;
+use crate::checkpoint::CheckpointStore;
+use crate::hooks::HookRunner;
+use crate::instructions::Instructions;
+use agentik_providers::traits::{FinishReason, Usage};
+use agentik_providers::{CompletionResponse, StreamChunk};
+use agentik_session::store::StoreError;
+use futures::stream;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// ============================================================================
+// Mock Provider
+// ============================================================================
+
+pub(super) struct MockProvider {
+    pub(super) responses: Mutex<Vec<CompletionResponse>>,
+    call_count: AtomicUsize,
+    pub(super) requests: Mutex<Vec<CompletionRequest>>,
+    prefill: bool,
+}
+
+impl MockProvider {
+    pub(super) fn new(responses: Vec<CompletionResponse>) -> Self {
+        Self {
+            responses: Mutex::new(responses),
+            call_count: AtomicUsize::new(0),
+            requests: Mutex::new(Vec::new()),
+            prefill: false,
+        }
+    }
+
+    fn with_prefill(mut self) -> Self {
+        self.prefill = true;
+        self
+    }
+
+    pub(super) fn with_response(content: &str) -> Self {
+        Self::new(vec![CompletionResponse {
+            content: content.to_string(),
+            tool_calls: vec![],
+            finish_reason: FinishReason::Stop,
+            usage: Usage::default(),
+        }])
+    }
+
+    pub(super) fn with_tool_call(
+        tool_name: &str,
+        args: serde_json::Value,
+        final_response: &str,
+    ) -> Self {
+        Self::new(vec![
+            CompletionResponse {
+                content: String::new(),
+                tool_calls: vec![ToolCall::new("call_1", tool_name, args)],
+                finish_reason: FinishReason::ToolUse,
+                usage: Usage::default(),
+            },
+            CompletionResponse {
+                content: final_response.to_string(),
+                tool_calls: vec![],
+                finish_reason: FinishReason::Stop,
+                usage: Usage::default(),
+            },
+        ])
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    fn id(&self) -> &str {
+        "mock"
+    }
+
+    fn name(&self) -> &str {
+        "Mock Provider"
+    }
+
+    fn available_models(&self) -> Vec<agentik_providers::ModelInfo> {
+        vec![]
+    }
+
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    fn supports_prefill(&self) -> bool {
+        self.prefill
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> anyhow::Result<CompletionResponse> {
+        self.requests.lock().unwrap().push(request);
+        let idx = self.call_count.fetch_add(1, Ordering::SeqCst);
+        let responses = self.responses.lock().unwrap();
+        let response = responses
+            .get(idx)
+            .cloned()
+            .unwrap_or_else(|| CompletionResponse {
+                content: "No more responses".to_string(),
+                tool_calls: vec![],
+                finish_reason: FinishReason::Stop,
+                usage: Usage::default(),
+            });
+        Ok(response)
+    }
+
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> anyhow::Result<Pin<Box<dyn futures::Stream<Item = anyhow::Result<StreamChunk>> + Send>>>
+    {
+        // For simplicity, just convert complete to a single-chunk stream
+        let response = self.complete(request).await?;
+
+        // Create chunks for text and tool calls
+        let mut chunks = vec![];
+
+        if !response.content.is_empty() {
+            chunks.push(Ok(StreamChunk {
+                delta: Some(response.content),
+                tool_call_delta: None,
+                is_final: false,
+                usage: None,
+                finish_reason: None,
+            }));
+        }
+
+        for call in response.tool_calls {
+            chunks.push(Ok(StreamChunk {
+                delta: None,
+                tool_call_delta: Some(ToolCallDelta {
+                    id: Some(call.id),
+                    name: Some(call.name),
+                    // String arguments are streamed raw, e.g. to send
+                    // truncated JSON
+                    arguments: Some(match call.arguments {
+                        serde_json::Value::String(raw) => raw,
+                        args => args.to_string(),
+                    }),
+                }),
+                is_final: false,
+                usage: None,
+                finish_reason: None,
+            }));
+        }
+
+        // Final chunk with usage
+        chunks.push(Ok(StreamChunk {
+            delta: None,
+            tool_call_delta: None,
+            is_final: true,
+            usage: Some(response.usage),
+            finish_reason: Some(response.finish_reason),
+        }));
+
+        Ok(Box::pin(stream::iter(chunks)))
+    }
+}
+
+// ============================================================================
+// Mock Session Store
+// ============================================================================
+
+pub(super) struct MockSessionStore {
+    pub(super) sessions: Mutex<std::collections::HashMap<String, Session>>,
+}
+
+impl MockSessionStore {
+    pub(super) fn new() -> Self {
+        Self {
+            sessions: Mutex::new(std::collections::HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for MockSessionStore {
+    async fn create(&self, session: &Session) -> Result<(), StoreError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.insert(session.id().to_string(), session.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Session, StoreError> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(id)
+            .cloned()
+            .ok_or_else(|| StoreError::NotFound(id.to_string()))
+    }
+
+    async fn get_metadata(&self, id: &str) -> Result<agentik_core::SessionMetadata, StoreError> {
+        self.get(id).await.map(|s| s.metadata)
+    }
+
+    async fn update_metadata(
+        &self,
+        metadata: &agentik_core::SessionMetadata,
+    ) -> Result<(), StoreError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(&metadata.id) {
+            session.metadata = metadata.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), StoreError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.remove(id);
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        _query: &agentik_session::SessionQuery,
+    ) -> Result<Vec<agentik_session::SessionSummary>, StoreError> {
+        Ok(vec![])
+    }
+
+    async fn get_most_recent(&self) -> Result<Option<agentik_session::SessionSummary>, StoreError> {
+        Ok(None)
+    }
+
+    async fn append_message(
+        &self,
+        session_id: &str,
+        message: &Message,
+    ) -> Result<agentik_session::AppendResult, StoreError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.messages.push(message.clone());
+        }
+        Ok(agentik_session::AppendResult {
+            file_offset: 0,
+            byte_length: 0,
+            message_index: 0,
+        })
+    }
+
+    async fn get_messages(
+        &self,
+        session_id: &str,
+        _from: Option<i64>,
+        _limit: Option<usize>,
+    ) -> Result<Vec<Message>, StoreError> {
+        let sessions = self.sessions.lock().unwrap();
+        Ok(sessions
+            .get(session_id)
+            .map(|s| s.messages.clone())
+            .unwrap_or_default())
+    }
+
+    async fn truncate_messages(&self, session_id: &str, count: usize) -> Result<(), StoreError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.messages.truncate(count);
+        }
+        Ok(())
+    }
+
+    async fn apply_compaction(
+        &self,
+        session_id: &str,
+        summary: &agentik_core::session::CompactedSummary,
+        boundary: usize,
+    ) -> Result<(), StoreError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.summary = Some(summary.clone());
+            session.compact_boundary = boundary;
+        }
+        Ok(())
+    }
+
+    async fn set_state(
+        &self,
+        _id: &str,
+        _state: agentik_core::SessionState,
+    ) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    async fn touch(&self, _id: &str) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    async fn find_by_prefix(
+        &self,
+        _prefix: &str,
+    ) -> Result<Vec<agentik_session::SessionSummary>, StoreError> {
+        Ok(vec![])
+    }
+
+    async fn get_aggregated_stats(
+        &self,
+        _since: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<agentik_session::AggregatedStats, StoreError> {
+        Ok(agentik_session::AggregatedStats {
+            session_count: 0,
+            total_tokens_in: 0,
+            total_tokens_out: 0,
+            total_cost: 0.0,
+            total_turns: 0,
+            total_tool_calls: 0,
+        })
+    }
+}
+
+// ============================================================================
+// Test Event Handler
+// ============================================================================
+
+struct TestEventHandler {
+    text_deltas: Mutex<Vec<String>>,
+    tool_starts: AtomicUsize,
+    tool_completes: AtomicUsize,
+}
+
+impl TestEventHandler {
+    fn new() -> Self {
+        Self {
+            text_deltas: Mutex::new(vec![]),
+            tool_starts: AtomicUsize::new(0),
+            tool_completes: AtomicUsize::new(0),
+        }
+    }
+
+    fn collected_text(&self) -> String {
+        self.text_deltas.lock().unwrap().join("")
+    }
+}
+
+impl AgentEventHandler for TestEventHandler {
+    fn on_text_delta(&self, delta: &str) {
+        self.text_deltas.lock().unwrap().push(delta.to_string());
+    }
+
+    fn on_tool_start(&self, _call: &ToolCall) {
+        self.tool_starts.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_tool_complete(&self, _call: &ToolCall, _result: &ToolResult) {
+        self.tool_completes.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+pub(super) fn create_test_executor() -> ToolExecutor {
+    use crate::executor::{AutoApproveHandler, ExecutorBuilder};
+
+    ExecutorBuilder::new()
+        .with_builtins()
+        .mode(AgentMode::Autonomous)
+        .build(Arc::new(AutoApproveHandler))
+}
+
+pub(super) async fn create_test_agent(provider: Arc<dyn Provider>) -> Agent {
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+
+    let executor = create_test_executor();
+
+    AgentBuilder::new()
+        .provider(provider)
+        .executor(executor)
+        .store(store)
+        .session(session)
+        .max_turns(10)
+        .build()
+        .unwrap()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[test]
+fn test_agent_config_default() {
+    let config = AgentConfig::default();
+    assert_eq!(config.max_tokens, 8192);
+    assert_eq!(config.temperature, 0.7);
+    assert_eq!(config.max_turns, 100);
+    assert!(config.auto_compact);
+}
+
+#[test]
+fn test_turn_usage_from_provider_usage() {
+    let provider_usage = Usage {
+        input_tokens: 100,
+        output_tokens: 50,
+        cached_tokens: 25,
+    };
+    let turn_usage: TurnUsage = provider_usage.into();
+    assert_eq!(turn_usage.input_tokens, 100);
+    assert_eq!(turn_usage.output_tokens, 50);
+    assert_eq!(turn_usage.cached_tokens, 25);
+}
+
+#[test]
+fn test_tool_call_builder() {
+    let mut builder = ToolCallBuilder::new();
+
+    // First tool call
+    builder.apply_delta(&ToolCallDelta {
+        id: Some("call_1".to_string()),
+        name: Some("Read".to_string()),
+        arguments: Some("{\"file".to_string()),
+    });
+    builder.apply_delta(&ToolCallDelta {
+        id: None,
+        name: None,
+        arguments: Some("_path\": \"/tmp/test\"}".to_string()),
+    });
+
+    let (calls, truncated) = builder.build(false);
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].id, "call_1");
+    assert_eq!(calls[0].name, "Read");
+    assert_eq!(calls[0].arguments["file_path"], "/tmp/test");
+    assert!(truncated.is_none());
+}
+
+#[test]
+fn test_tool_call_builder_truncated() {
+    let mut builder = ToolCallBuilder::new();
+    builder.apply_delta(&ToolCallDelta {
+        id: Some("call_1".to_string()),
+        name: Some("Read".to_string()),
+        arguments: Some("{\"file_path\": \"a.rs\"".to_string()),
+    });
+    builder.apply_delta(&ToolCallDelta {
+        id: Some("call_2".to_string()),
+        name: Some("Write".to_string()),
+        arguments: Some("{\"file_path\": \"b.rs\", \"content\": \"fn ma".to_string()),
+    });
+
+    // The first call only lacks its closing brace and is repaired; the
+    // last one was cut off and is set aside
+    let (calls, truncated) = builder.build(true);
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].arguments["file_path"], "a.rs");
+    let truncated = truncated.unwrap();
+    assert_eq!(truncated.id, "call_2");
+    assert_eq!(truncated.arguments, serde_json::json!({}));
+}
+
+#[test]
+fn test_repair_json() {
+    assert_eq!(
+        repair_json(r#"{"a": [1, 2"#),
+        Some(serde_json::json!({"a": [1, 2]}))
+    );
+    assert_eq!(
+        repair_json(r#"{"a": "say \"hi"#),
+        Some(serde_json::json!({"a": "say \"hi"}))
+    );
+    assert_eq!(
+        repair_json(r#"{"a": 1, "#),
+        Some(serde_json::json!({"a": 1}))
+    );
+    assert_eq!(repair_json(r#"{"a": "#), None);
+    assert_eq!(repair_json(r#"{"a": 1]"#), None);
+    assert_eq!(repair_json("[1, 2"), None);
+}
+
+#[tokio::test]
+async fn test_basic_run() {
+    let provider = Arc::new(MockProvider::with_response("Hello, how can I help you?"));
+    let mut agent = create_test_agent(provider).await;
+
+    let response = agent.run("Hi there!").await.unwrap();
+
+    assert_eq!(response.content, "Hello, how can I help you?");
+    assert_eq!(response.turns, 1);
+    assert!(response.steps[0].tool_calls.is_empty());
+}
+
+#[tokio::test]
+async fn test_event_handler_receives_deltas() {
+    let provider = Arc::new(MockProvider::with_response("Test response"));
+    let handler = Arc::new(TestEventHandler::new());
+
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+
+    let executor = create_test_executor();
+
+    let mut agent = AgentBuilder::new()
+        .provider(provider)
+        .executor(executor)
+        .store(store)
+        .session(session)
+        .event_handler(handler.clone())
+        .build()
+        .unwrap();
+
+    agent.run("Test").await.unwrap();
+
+    assert_eq!(handler.collected_text(), "Test response");
+}
+
+#[tokio::test]
+async fn test_subscribers_receive_run_events() {
+    let provider = Arc::new(MockProvider::with_tool_call(
+        "Glob",
+        serde_json::json!({"pattern": "*.rs"}),
+        "Done.",
+    ));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+
+    let mut agent = AgentBuilder::new()
+        .provider(provider)
+        .executor(create_test_executor())
+        .store(store)
+        .session(session)
+        .mode(AgentMode::Autonomous)
+        .build()
+        .unwrap();
+    let mut receiver = agent.subscribe();
+
+    agent.run("Find the Rust files").await.unwrap();
+
+    let mut events = Vec::new();
+    while let Ok(envelope) = receiver.try_recv() {
+        assert_eq!(envelope.seq, events.len() as u64);
+        events.push(envelope.event);
+    }
+    let kinds: Vec<_> = events
+        .iter()
+        .map(|e| match e {
+            AgentEvent::Thinking => "thinking",
+            AgentEvent::TextDelta { .. } => "text",
+            AgentEvent::ToolStart { .. } => "tool_start",
+            AgentEvent::ToolComplete { .. } => "tool_complete",
+            AgentEvent::Usage { .. } => "usage",
+            AgentEvent::Complete { .. } => "complete",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "thinking",
+            "tool_start",
+            "tool_complete",
+            "usage",
+            "thinking",
+            "text",
+            "usage",
+            "complete"
+        ]
+    );
+    assert!(matches!(
+        events.last(),
+        Some(AgentEvent::Complete { content, turns: 2, .. }) if content == "Done."
+    ));
+}
+
+#[test]
+fn test_cancellation() {
+    // Test the cancellation API directly
+    let provider = Arc::new(MockProvider::with_response("test"));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    let executor = create_test_executor();
+
+    let agent = Agent::new(
+        provider,
+        executor,
+        store,
+        session,
+        AgentConfig::default(),
+        Arc::new(NoOpEventHandler),
+    );
+
+    // Initially not cancelled
+    assert!(!agent.is_cancelled());
+
+    // Cancel
+    agent.cancel();
+    assert!(agent.is_cancelled());
+}
+
+#[tokio::test]
+async fn test_cancellation_during_run() {
+    use std::sync::Arc as StdArc;
+    use tokio::sync::Notify;
+
+    // Create a provider that waits for cancellation signal
+    struct SlowProvider {
+        notify: StdArc<Notify>,
+    }
+
+    #[async_trait]
+    impl Provider for SlowProvider {
+        fn id(&self) -> &str {
+            "slow"
+        }
+        fn name(&self) -> &str {
+            "Slow Provider"
+        }
+        fn available_models(&self) -> Vec<agentik_providers::ModelInfo> {
+            vec![]
+        }
+        fn is_configured(&self) -> bool {
+            true
+        }
+        async fn complete(
+            &self,
+            _request: CompletionRequest,
+        ) -> anyhow::Result<CompletionResponse> {
+            // Wait for notification (simulating slow response)
+            self.notify.notified().await;
+            Ok(CompletionResponse {
+                content: "test".to_string(),
+                tool_calls: vec![],
+                finish_reason: FinishReason::Stop,
+                usage: Usage::default(),
+            })
+        }
+        async fn complete_stream(
+            &self,
+            request: CompletionRequest,
+        ) -> anyhow::Result<Pin<Box<dyn futures::Stream<Item = anyhow::Result<StreamChunk>> + Send>>>
+        {
+            // Use non-streaming for simplicity in test
+            let response = self.complete(request).await?;
+            Ok(Box::pin(stream::iter(vec![Ok(StreamChunk {
+                delta: Some(response.content),
+                tool_call_delta: None,
+                is_final: true,
+                usage: Some(response.usage),
+                finish_reason: None,
+            })])))
+        }
+    }
+
+    let notify = StdArc::new(Notify::new());
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+
+    let provider = Arc::new(SlowProvider {
+        notify: notify.clone(),
+    });
+    let executor = create_test_executor();
+
+    let mut agent = AgentBuilder::new()
+        .provider(provider)
+        .executor(executor)
+        .store(store)
+        .session(session)
+        .build()
+        .unwrap();
+
+    // Get a handle to the cancel token before running
+    let cancel_token = agent.cancel_token.clone();
+
+    // Spawn the agent run in a separate task
+    let handle = tokio::spawn(async move { agent.run("Test").await });
+
+    // Give the agent time to start
+    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+    // Cancel the operation
+    cancel_token.cancel();
+
+    // Notify the provider to continue (it will check cancellation)
+    notify.notify_one();
+
+    // The run should return a cancellation error
+    let result = handle.await.unwrap();
+    // Note: Due to how the test is structured, the provider completes before
+    // cancellation is checked, so we just verify the test completes
+    // In real usage, cancellation happens during streaming
+    assert!(result.is_ok() || matches!(result, Err(AgentError::Cancelled)));
+}
+
+#[tokio::test]
+async fn test_mode_changes() {
+    let provider = Arc::new(MockProvider::with_response("OK"));
+    let mut agent = create_test_agent(provider).await;
+
+    assert_eq!(agent.mode(), AgentMode::Autonomous);
+
+    agent.set_mode(AgentMode::Planning);
+    assert_eq!(agent.mode(), AgentMode::Planning);
+
+    agent.set_mode(AgentMode::AskOnly);
+    assert_eq!(agent.mode(), AgentMode::AskOnly);
+    assert!(!agent.should_execute_tools());
+}
+
+#[tokio::test]
+async fn test_builder_missing_provider() {
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    let executor = create_test_executor();
+
+    let result = AgentBuilder::new()
+        .executor(executor)
+        .store(store)
+        .session(session)
+        .build();
+
+    assert!(matches!(result, Err(AgentError::NotConfigured(_))));
+}
+
+#[tokio::test]
+async fn test_max_turns_exceeded() {
+    // Create provider that always returns tool calls
+    let responses: Vec<CompletionResponse> = (0..15)
+        .map(|i| CompletionResponse {
+            content: String::new(),
+            tool_calls: vec![ToolCall::new(
+                format!("call_{}", i),
+                "nonexistent_tool",
+                serde_json::json!({}),
+            )],
+            finish_reason: FinishReason::ToolUse,
+            usage: Usage::default(),
+        })
+        .collect();
+
+    let provider = Arc::new(MockProvider::new(responses));
+    let mut agent = create_test_agent(provider).await;
+
+    // Set low max turns to trigger the error quickly
+    agent.config.max_turns = 3;
+
+    let result = agent.run("Test").await;
+    assert!(matches!(result, Err(AgentError::MaxTurnsExceeded(3))));
+}
+
+#[test]
+fn test_mode_system_prompts() {
+    let provider = Arc::new(MockProvider::with_response("OK"));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    let executor = create_test_executor();
+
+    // Create agent synchronously using the builder pattern
+    let mut agent = Agent::new(
+        provider,
+        executor,
+        store,
+        session,
+        AgentConfig::default(),
+        Arc::new(NoOpEventHandler),
+    );
+
+    // Test different modes
+    agent.set_mode(AgentMode::Autonomous);
+    assert!(agent.mode_system_prompt().is_none());
+
+    agent.set_mode(AgentMode::Planning);
+    assert!(agent.mode_system_prompt().is_some());
+    assert!(agent
+        .mode_system_prompt()
+        .unwrap()
+        .contains("planning mode"));
+
+    agent.set_mode(AgentMode::Architect);
+    assert!(agent
+        .mode_system_prompt()
+        .unwrap()
+        .contains("architect mode"));
+
+    agent.set_mode(AgentMode::AskOnly);
+    assert!(agent
+        .mode_system_prompt()
+        .unwrap()
+        .contains("ask-only mode"));
+}
+
+/// Fill the session with enough history for compaction to kick in.
+fn add_compactable_history(agent: &mut Agent) {
+    let mut write = Message::assistant("Writing the entry point");
+    write.tool_calls = vec![ToolCall::new(
+        "call_w",
+        "Write",
+        serde_json::json!({"file_path": "src/main.rs", "content": "fn main() {}"}),
+    )];
+    write.token_count = Some(5_000);
+    agent.session_mut().add_message(write);
+
+    for i in 0..13 {
+        let mut msg = if i % 2 == 0 {
+            Message::user(format!("Please handle request {}", i))
+        } else {
+            Message::assistant(format!("Reply {}", i))
+        };
+        msg.token_count = Some(5_000);
+        agent.session_mut().add_message(msg);
+    }
+}
+
+#[tokio::test]
+async fn test_compact_uses_llm_summary() {
+    let provider = Arc::new(MockProvider::with_response("Built the entry point."));
+    let mut agent = create_test_agent(provider).await;
+    add_compactable_history(&mut agent);
+
+    agent.compact().await.unwrap();
+
+    let session = agent.session();
+    let summary = session.summary.as_ref().unwrap();
+    assert_eq!(summary.text, "Built the entry point.");
+    assert_eq!(summary.modified_files, vec![PathBuf::from("src/main.rs")]);
+    assert_eq!(summary.messages_compacted, 4);
+    assert_eq!(session.compact_boundary, 4);
+    assert_eq!(session.metadata.metrics.compaction_count, 1);
+}
+
+#[tokio::test]
+async fn test_compact_merges_previous_summary() {
+    let provider = Arc::new(MockProvider::with_response("Second summary."));
+    let mut agent = create_test_agent(provider).await;
+    add_compactable_history(&mut agent);
+    agent.session_mut().summary = Some(agentik_core::session::CompactedSummary {
+        text: "First summary.".to_string(),
+        key_decisions: vec!["Use tokio".to_string()],
+        modified_files: vec![PathBuf::from("Cargo.toml")],
+        created_at: chrono::Utc::now(),
+        messages_compacted: 6,
+    });
+
+    agent.compact().await.unwrap();
+
+    let summary = agent.session().summary.clone().unwrap();
+    assert_eq!(summary.text, "Second summary.");
+    assert_eq!(summary.key_decisions, vec!["Use tokio".to_string()]);
+    assert_eq!(summary.modified_files.len(), 2);
+    assert_eq!(summary.messages_compacted, 10);
+}
+
+#[tokio::test]
+async fn test_compact_falls_back_on_provider_failure() {
+    let provider = Arc::new(MockProvider::with_response(""));
+    let mut agent = create_test_agent(provider).await;
+    add_compactable_history(&mut agent);
+
+    agent.compact().await.unwrap();
+
+    let summary = agent.session().summary.clone().unwrap();
+    assert!(summary.text.contains("Please handle request 0"));
+    assert_eq!(summary.modified_files, vec![PathBuf::from("src/main.rs")]);
+    assert_eq!(agent.session().metadata.metrics.compaction_count, 1);
+}
+
+pub(super) fn text_response(content: &str) -> CompletionResponse {
+    CompletionResponse {
+        content: content.to_string(),
+        tool_calls: vec![],
+        finish_reason: FinishReason::Stop,
+        usage: Usage::default(),
+    }
+}
+
+pub(super) fn tool_call(id: &str, name: &str, args: serde_json::Value) -> CompletionResponse {
+    CompletionResponse {
+        content: String::new(),
+        tool_calls: vec![ToolCall::new(id, name, args)],
+        finish_reason: FinishReason::ToolUse,
+        usage: Usage::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_instructions_in_system_prompt() {
+    use crate::executor::{AutoApproveHandler, ExecutorBuilder};
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let dir = temp.path();
+    std::fs::create_dir_all(dir.join(".git")).unwrap();
+    std::fs::create_dir_all(dir.join("db")).unwrap();
+    std::fs::write(dir.join("AGENTS.md"), "Run cargo fmt").unwrap();
+    std::fs::write(dir.join("db/AGENTS.md"), "Never edit migrations").unwrap();
+    let instructions = Arc::new(Instructions::discover(dir, None));
+    instructions.take_loaded();
+
+    let provider = Arc::new(MockProvider::new(vec![
+        CompletionResponse {
+            content: String::new(),
+            tool_calls: vec![ToolCall::new(
+                "call_1",
+                "Read",
+                serde_json::json!({"file_path": dir.join("db/schema.sql")}),
+            )],
+            finish_reason: FinishReason::ToolUse,
+            usage: Usage::default(),
+        },
+        text_response("Done"),
+    ]));
+    let executor = ExecutorBuilder::new()
+        .with_builtins()
+        .working_dir(dir)
+        .mode(AgentMode::Autonomous)
+        .instructions(Arc::clone(&instructions))
+        .build(Arc::new(AutoApproveHandler));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(dir.to_path_buf());
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(executor)
+        .store(store)
+        .session(session)
+        .build()
+        .unwrap();
+
+    agent.run("Look at the schema").await.unwrap();
+
+    // The nested file applies from the step after the call touching it
+    let requests = provider.requests.lock().unwrap();
+    let first = requests[0].system.clone().unwrap();
+    assert!(first.contains("Run cargo fmt"));
+    assert!(!first.contains("Never edit migrations"));
+    assert!(requests[1]
+        .system
+        .as_ref()
+        .unwrap()
+        .contains("Never edit migrations"));
+}
+
+#[tokio::test]
+async fn test_loop_detection_notes_then_escalates() {
+    struct StoppingHandler;
+
+    #[async_trait]
+    impl AgentEventHandler for StoppingHandler {
+        async fn on_question(&self, _question: &Question) -> Option<String> {
+            Some("Stop".to_string())
+        }
+    }
+
+    let read = |id: &str| CompletionResponse {
+        content: String::new(),
+        tool_calls: vec![ToolCall::new(
+            id,
+            "Read",
+            serde_json::json!({"file_path": "/nonexistent/agentik-loop.rs"}),
+        )],
+        finish_reason: FinishReason::ToolUse,
+        usage: Usage::default(),
+    };
+    let mut responses: Vec<_> = (1..=6).map(|i| read(&format!("call_{}", i))).collect();
+    responses.push(text_response("never"));
+    let provider = Arc::new(MockProvider::new(responses));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(create_test_executor())
+        .store(store)
+        .session(session)
+        .event_handler(Arc::new(StoppingHandler))
+        .build()
+        .unwrap();
+
+    let result = agent.run("Read the file").await;
+
+    // The third identical call gets a note, the sixth asks the user
+    assert!(matches!(result, Err(AgentError::LoopDetected(_))));
+    assert_eq!(provider.requests.lock().unwrap().len(), 6);
+    let notes: Vec<_> = agent
+        .session()
+        .messages
+        .iter()
+        .filter(|m| m.content.as_text().starts_with("<loop_detected>"))
+        .collect();
+    assert_eq!(notes.len(), 1);
+}
+
+#[tokio::test]
+async fn test_non_blocking_answer_injected_next_turn() {
+    struct AnsweringHandler;
+
+    #[async_trait]
+    impl AgentEventHandler for AnsweringHandler {
+        async fn on_question(&self, _question: &Question) -> Option<String> {
+            Some("blue".to_string())
+        }
+    }
+
+    let provider = Arc::new(MockProvider::new(vec![
+        CompletionResponse {
+            content: String::new(),
+            tool_calls: vec![ToolCall::new(
+                "call_q",
+                "AskUser",
+                serde_json::json!({"question": "Favourite colour?", "blocking": false}),
+            )],
+            finish_reason: FinishReason::ToolUse,
+            usage: Usage::default(),
+        },
+        text_response("Carrying on."),
+        text_response("Using blue."),
+    ]));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(provider)
+        .executor(create_test_executor())
+        .store(store)
+        .session(session)
+        .event_handler(Arc::new(AnsweringHandler))
+        .build()
+        .unwrap();
+
+    agent.run("Style the page").await.unwrap();
+    assert!(agent.questions().pending().is_empty());
+
+    agent.run("Go on").await.unwrap();
+    let injected = agent
+        .session()
+        .messages
+        .iter()
+        .any(|m| m.content.as_text() == "User answered \"Favourite colour?\": blue");
+    assert!(injected);
+}
+
+#[tokio::test]
+async fn test_task_runs_subagent_in_forked_session() {
+    let usage = |input_tokens| Usage {
+        input_tokens,
+        output_tokens: 5,
+        cached_tokens: 0,
+    };
+    let provider = Arc::new(MockProvider::new(vec![
+        CompletionResponse {
+            usage: usage(100),
+            ..tool_call(
+                "call_t",
+                "Task",
+                serde_json::json!({
+                    "description": "Find parse callers",
+                    "prompt": "Find every caller of parse",
+                    "model": "fast-model"
+                }),
+            )
+        },
+        // Sub-agent
+        CompletionResponse {
+            usage: usage(40),
+            ..text_response("`parse` is called from src/cli.rs:12.")
+        },
+        CompletionResponse {
+            usage: usage(120),
+            ..text_response("Done.")
+        },
+    ]));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    let parent_id = session.id().to_string();
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(create_test_executor())
+        .store(store.clone())
+        .session(session)
+        .mode(AgentMode::Autonomous)
+        .build()
+        .unwrap();
+
+    agent.run("Who calls parse?").await.unwrap();
+
+    // Only the sub-agent's answer reaches the parent
+    let requests = provider.requests.lock().unwrap();
+    assert_eq!(requests[1].model, "fast-model");
+    assert!(requests[1].tools.iter().all(|t| !t.is_destructive));
+    assert!(requests[1].tools.iter().all(|t| t.name != "Task"));
+    let result = serde_json::to_string(&requests[2].messages.last().unwrap().content).unwrap();
+    assert!(result.contains("`parse` is called from src/cli.rs:12."));
+    assert_eq!(requests[2].messages.len(), 3);
+
+    let sessions = store.sessions.lock().unwrap();
+    let child = sessions
+        .values()
+        .find(|s| s.metadata.parent_session_id.as_deref() == Some(parent_id.as_str()))
+        .unwrap();
+    assert_eq!(child.metadata.title.as_deref(), Some("Find parse callers"));
+
+    // The sub-agent's usage is recorded once, in the parent
+    let metrics = &agent.session().metadata.metrics;
+    assert_eq!(metrics.total_tokens_in, 260);
+    assert_eq!(metrics.tool_calls, 1);
+    assert_eq!(sessions[&parent_id].metadata.metrics.total_tokens_in, 260);
+    assert_eq!(child.metadata.metrics.total_tokens_in, 0);
+    assert_eq!(child.metadata.metrics.total_cost, 0.0);
+}
+
+#[tokio::test]
+async fn test_task_keeps_parent_tool_restriction() {
+    let provider = Arc::new(MockProvider::new(vec![
+        tool_call(
+            "call_t",
+            "Task",
+            serde_json::json!({
+                "description": "Look around",
+                "prompt": "Summarize the repository"
+            }),
+        ),
+        // Sub-agent
+        text_response("A small crate."),
+        text_response("Done."),
+    ]));
+    let mut agent = create_test_agent(provider.clone()).await;
+    agent.set_allowed_tools(Some(vec!["Task".to_string(), "Read".to_string()]));
+
+    agent.run("What is this?").await.unwrap();
+
+    let requests = provider.requests.lock().unwrap();
+    let names: Vec<_> = requests[1].tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["Read"]);
+}
+
+#[tokio::test]
+async fn test_task_respects_require_confirm() {
+    use agentik_core::config::PermissionsConfig;
+
+    let provider = Arc::new(MockProvider::new(vec![
+        tool_call(
+            "call_t",
+            "Task",
+            serde_json::json!({
+                "description": "Look around",
+                "prompt": "Summarize the repository"
+            }),
+        ),
+        text_response("Done."),
+    ]));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+    let executor = crate::executor::ExecutorBuilder::new()
+        .with_builtins()
+        .permissions(PermissionsConfig {
+            require_confirm: vec!["Task".to_string()],
+            ..Default::default()
+        })
+        .mode(AgentMode::Planning)
+        .build(Arc::new(crate::executor::DenyAllHandler));
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(executor)
+        .store(store.clone())
+        .session(session)
+        .mode(AgentMode::Planning)
+        .build()
+        .unwrap();
+
+    let response = agent.run("What is this?").await.unwrap();
+
+    // The handler was asked and declined, so no sub-agent ran
+    let result = &response.steps[0].tool_results[0];
+    assert!(!result.success);
+    assert!(result.error.as_ref().unwrap().contains("declined"));
+    assert_eq!(provider.requests.lock().unwrap().len(), 2);
+    assert_eq!(store.sessions.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_prompt_hooks_add_context_or_block() {
+    use agentik_core::config::{HookConfig, HooksConfig};
+
+    let build = |command: &str| {
+        let hooks = HooksConfig {
+            user_prompt_submit: vec![HookConfig {
+                command: command.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        crate::executor::ExecutorBuilder::new()
+            .with_builtins()
+            .hooks(Arc::new(HookRunner::new(hooks, std::env::temp_dir())))
+            .build(Arc::new(crate::executor::AutoApproveHandler))
+    };
+    let new_agent = |executor| async {
+        let store = Arc::new(MockSessionStore::new());
+        let session = Session::new(PathBuf::from("/tmp/test"));
+        store.create(&session).await.unwrap();
+        AgentBuilder::new()
+            .provider(Arc::new(MockProvider::with_response("OK")))
+            .executor(executor)
+            .store(store)
+            .session(session)
+            .build()
+            .unwrap()
+    };
+
+    let mut agent = new_agent(build("echo 'Branch: main'")).await;
+    agent.run("Fix the build").await.unwrap();
+    assert_eq!(
+        agent.session().messages[0].content.as_text(),
+        "Fix the build\n\n<hook_context>\nBranch: main\n</hook_context>"
+    );
+
+    let mut agent = new_agent(build("echo 'no deploys on Friday' >&2; exit 2")).await;
+    let err = agent.run("Deploy").await.unwrap_err();
+    assert!(matches!(err, AgentError::Blocked(ref r) if r == "no deploys on Friday"));
+    assert!(agent.session().messages.is_empty());
+}
+
+#[tokio::test]
+async fn test_stop_hooks_run_when_the_run_fails() {
+    use agentik_core::config::{HookConfig, HooksConfig};
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let hooks = HooksConfig {
+        stop: vec![HookConfig {
+            command: "cat > stop.json".to_string(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let executor = crate::executor::ExecutorBuilder::new()
+        .with_builtins()
+        .working_dir(temp.path())
+        .hooks(Arc::new(HookRunner::new(hooks, temp.path())))
+        .build(Arc::new(crate::executor::AutoApproveHandler));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(temp.path().to_path_buf());
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(Arc::new(MockProvider::with_tool_call(
+            "Glob",
+            serde_json::json!({"pattern": "*"}),
+            "Done.",
+        )))
+        .executor(executor)
+        .store(store)
+        .session(session)
+        .max_turns(1)
+        .build()
+        .unwrap();
+
+    let err = agent.run("List files").await.unwrap_err();
+    assert!(matches!(err, AgentError::MaxTurnsExceeded(1)));
+    let payload: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(temp.path().join("stop.json")).unwrap())
+            .unwrap();
+    assert_eq!(payload["hook_event"], "stop");
+    assert_eq!(payload["session_id"], agent.session().id());
+    assert_eq!(payload["error"], err.to_string());
+}
+
+#[tokio::test]
+async fn test_rewind_restores_files_and_conversation() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let dir = temp.path();
+    let file = dir.join("notes.txt");
+    std::fs::write(&file, "v1").unwrap();
+
+    let write = |content: &str| {
+        MockProvider::with_tool_call(
+            "Write",
+            serde_json::json!({"file_path": file.to_str().unwrap(), "content": content}),
+            "Done.",
+        )
+        .responses
+        .into_inner()
+        .unwrap()
+    };
+    let provider = Arc::new(MockProvider::new([write("v2"), write("v3")].concat()));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(dir.to_path_buf());
+    store.create(&session).await.unwrap();
+    let executor = crate::executor::ExecutorBuilder::new()
+        .with_builtins()
+        .working_dir(dir)
+        .mode(AgentMode::Autonomous)
+        .checkpoints(Arc::new(CheckpointStore::new(dir.join("checkpoints"))))
+        .build(Arc::new(crate::executor::AutoApproveHandler));
+    let mut agent = AgentBuilder::new()
+        .provider(provider)
+        .executor(executor)
+        .store(store.clone())
+        .session(session)
+        .mode(AgentMode::Autonomous)
+        .build()
+        .unwrap();
+
+    agent.run("Write v2").await.unwrap();
+    agent.run("Write v3").await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "v3");
+    let checkpoints = agent.checkpoints().unwrap();
+    assert_eq!(checkpoints.len(), 2);
+    assert_eq!(checkpoints[1].message_index, 4);
+    assert_eq!(checkpoints[1].prompt, "Write v3");
+
+    let restored = agent.rewind(4, RewindTarget::Both).await.unwrap();
+    assert_eq!(restored, vec![file.clone()]);
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "v2");
+    assert_eq!(agent.session().messages.len(), 4);
+    let id = agent.session().id().to_string();
+    assert_eq!(store.sessions.lock().unwrap()[&id].messages.len(), 4);
+    assert_eq!(agent.checkpoints().unwrap().len(), 1);
+
+    agent.rewind(0, RewindTarget::Files).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "v1");
+    assert_eq!(agent.session().messages.len(), 4);
+}
+
+#[tokio::test]
+async fn test_dry_run_stages_writes() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let dir = temp.path().to_path_buf();
+    let file = dir.join("notes.txt");
+    std::fs::write(&file, "v1\n").unwrap();
+
+    let mut responses = MockProvider::with_tool_call(
+        "Write",
+        serde_json::json!({"file_path": file.to_str().unwrap(), "content": "v2\n"}),
+        "",
+    )
+    .responses
+    .into_inner()
+    .unwrap();
+    responses.pop();
+    responses.extend(
+        MockProvider::with_tool_call(
+            "Read",
+            serde_json::json!({"file_path": file.to_str().unwrap()}),
+            "Done.",
+        )
+        .responses
+        .into_inner()
+        .unwrap(),
+    );
+    let provider = Arc::new(MockProvider::new(responses));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(dir.clone());
+    store.create(&session).await.unwrap();
+    let executor = crate::executor::ExecutorBuilder::new()
+        .with_builtins()
+        .working_dir(&dir)
+        .build(Arc::new(crate::executor::DenyAllHandler));
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(executor)
+        .store(store)
+        .session(session)
+        .mode(AgentMode::DryRun)
+        .build()
+        .unwrap();
+
+    let response = agent.run("Update the notes").await.unwrap();
+
+    // The write was staged without asking, and the read saw it
+    assert!(response.steps[0].tool_results[0].success);
+    assert!(response.steps[1].tool_results[0].output.contains("v2"));
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "v1\n");
+    let tools = &provider.requests.lock().unwrap()[0].tools;
+    assert!(tools.iter().any(|t| t.name == "Write"));
+    assert!(!tools.iter().any(|t| t.name == "Bash"));
+
+    let changeset = agent.changeset();
+    assert!(changeset.diff(&dir).contains("-v1\n+v2\n"));
+    assert!(changeset.accept(&file).unwrap());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "v2\n");
+}
+
+#[tokio::test]
+async fn test_steering_injected_at_next_step() {
+    #[derive(Default)]
+    struct Steerer(Mutex<Option<SteeringQueue>>);
+
+    impl Steerer {
+        fn push(&self, message: &str) {
+            if let Some(queue) = self.0.lock().unwrap().as_ref() {
+                queue.push(message);
+            }
+        }
+    }
+
+    #[async_trait]
+    impl crate::executor::PermissionHandler for Steerer {
+        async fn request_approval(&self, _call: &ToolCall, _tool: &ToolDefinition) -> bool {
+            true
+        }
+
+        fn on_execute(&self, _call: &ToolCall) {
+            self.push("Only look at src/");
+        }
+    }
+
+    impl AgentEventHandler for Steerer {
+        fn on_text_delta(&self, delta: &str) {
+            if delta == "Done." {
+                self.push("Also check tests/");
+            }
+        }
+    }
+
+    let mut responses =
+        MockProvider::with_tool_call("Glob", serde_json::json!({"pattern": "*.rs"}), "Done.")
+            .responses
+            .into_inner()
+            .unwrap();
+    responses.push(text_response("Checked tests/ too."));
+    let provider = Arc::new(MockProvider::new(responses));
+    let handler = Arc::new(Steerer::default());
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(
+            crate::executor::ExecutorBuilder::new()
+                .with_builtins()
+                .mode(AgentMode::Autonomous)
+                .build(handler.clone()),
+        )
+        .store(store)
+        .session(session)
+        .event_handler(handler.clone())
+        .mode(AgentMode::Autonomous)
+        .build()
+        .unwrap();
+    *handler.0.lock().unwrap() = Some(agent.steering());
+
+    let response = agent.run("Find the Rust files").await.unwrap();
+
+    {
+        // The message sent during the tool call follows its result
+        let requests = provider.requests.lock().unwrap();
+        let last = |i: usize| requests[i].messages.last().unwrap().content.as_text();
+        assert_eq!(last(1), "Only look at src/");
+        assert_eq!(requests[1].messages.len(), 4);
+        // The message sent as the model finished gets another turn
+        assert_eq!(last(2), "Also check tests/");
+    }
+    assert_eq!(response.content, "Checked tests/ too.");
+    assert!(agent.steering().is_empty());
+
+    // A message queued once the run is over isn't carried into the next
+    agent.steering().push("Too late");
+    agent.run("Now the docs").await.unwrap();
+    let requests = provider.requests.lock().unwrap();
+    let messages = &requests.last().unwrap().messages;
+    assert_eq!(messages.last().unwrap().content.as_text(), "Now the docs");
+    assert!(messages.iter().all(|m| m.content.as_text() != "Too late"));
+}
+
+fn truncated_response(content: &str, tool_calls: Vec<ToolCall>) -> CompletionResponse {
+    CompletionResponse {
+        content: content.to_string(),
+        tool_calls,
+        finish_reason: FinishReason::MaxTokens,
+        usage: Usage::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_max_tokens_continuation() {
+    let provider = Arc::new(MockProvider::new(vec![
+        truncated_response("fn main() {\n", vec![]),
+        truncated_response("    println!(\"hi\");\n", vec![]),
+        text_response("}"),
+    ]));
+    let mut agent = create_test_agent(provider.clone()).await;
+
+    let response = agent.run("Write a program").await.unwrap();
+
+    // The pieces make up one assistant message in one step
+    assert_eq!(response.content, "fn main() {\n    println!(\"hi\");\n}");
+    assert_eq!(response.steps.len(), 1);
+    assert_eq!(agent.session().messages.len(), 2);
+
+    // Without prefill support, each continuation sends the partial
+    // response back with a request to go on
+    let requests = provider.requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    let messages = &requests[2].messages;
+    assert_eq!(
+        messages[messages.len() - 2].content.as_text(),
+        "fn main() {\n    println!(\"hi\");\n"
+    );
+    assert_eq!(
+        messages[messages.len() - 1].content.as_text(),
+        CONTINUE_PROMPT
+    );
+}
+
+#[tokio::test]
+async fn test_max_tokens_continuation_with_prefill() {
+    let provider = Arc::new(
+        MockProvider::new(vec![
+            truncated_response("fn main() {\n", vec![]),
+            text_response("\n}"),
+        ])
+        .with_prefill(),
+    );
+    let mut agent = create_test_agent(provider.clone()).await;
+
+    let response = agent.run("Write a program").await.unwrap();
+    assert_eq!(response.content, "fn main() {\n}");
+
+    // The partial response is sent as the start of the assistant's
+    // answer, without trailing whitespace or a request to go on
+    let requests = provider.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let last = requests[1].messages.last().unwrap();
+    assert_eq!(last.role, agentik_core::message::Role::Assistant);
+    assert_eq!(last.content.as_text(), "fn main() {");
+    assert!(requests[1]
+        .messages
+        .iter()
+        .all(|m| m.content.as_text() != CONTINUE_PROMPT));
+}
+
+#[tokio::test]
+async fn test_max_continuations_cap() {
+    let provider = Arc::new(MockProvider::new(vec![
+        truncated_response("one ", vec![]),
+        truncated_response("two ", vec![]),
+        text_response("never"),
+    ]));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(PathBuf::from("/tmp/test"));
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(create_test_executor())
+        .store(store)
+        .session(session)
+        .max_continuations(1)
+        .build()
+        .unwrap();
+
+    let response = agent.run("Count").await.unwrap();
+
+    assert_eq!(response.content, "one two ");
+    assert_eq!(provider.requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_truncated_tool_call_not_run() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let dir = temp.path();
+    let path = dir.join("big.rs");
+    let raw = format!(
+        r#"{{"file_path": "{}", "content": "fn main() {{ prin"#,
+        path.display()
+    );
+
+    let provider = Arc::new(MockProvider::new(vec![
+        truncated_response(
+            "Writing the file.",
+            vec![ToolCall::new(
+                "call_1",
+                "Write",
+                serde_json::Value::String(raw),
+            )],
+        ),
+        text_response("I'll write it in smaller pieces."),
+    ]));
+    let store = Arc::new(MockSessionStore::new());
+    let session = Session::new(dir.to_path_buf());
+    store.create(&session).await.unwrap();
+    let mut agent = AgentBuilder::new()
+        .provider(provider.clone())
+        .executor(
+            crate::executor::ExecutorBuilder::new()
+                .with_builtins()
+                .mode(AgentMode::Autonomous)
+                .build(Arc::new(crate::executor::AutoApproveHandler)),
+        )
+        .store(store)
+        .session(session)
+        .build()
+        .unwrap();
+
+    let response = agent.run("Write big.rs").await.unwrap();
+
+    // The call was answered with an error and never run
+    assert!(!path.exists());
+    let result = &response.steps[0].tool_results[0];
+    assert_eq!(result.tool_call_id, "call_1");
+    assert!(!result.success);
+    assert_eq!(result.error.as_deref(), Some(TRUNCATED_CALL_ERROR));
+    assert_eq!(response.content, "I'll write it in smaller pieces.");
+
+    // The model saw the error on the next request
+    let requests = provider.requests.lock().unwrap();
+    let last = requests[1].messages.last().unwrap();
+    assert!(matches!(
+        &last.content,
+        agentik_core::message::Content::Parts(parts) if matches!(
+            &parts[0],
+            agentik_core::message::ContentPart::ToolResult { tool_use_id, is_error: true, .. }
+                if tool_use_id == "call_1"
+        )
+    ));
+}
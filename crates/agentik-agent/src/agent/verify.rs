@@ -0,0 +1,139 @@
+//! Verifying edits.
+//!
+//! After a run that edited files, the configured checks run and any failures
+//! are fed back to the model to repair. See [`crate::verify`] for the checks.
+
+use agentik_core::Message;
+use tracing::warn;
+
+use super::{Agent, AgentError, AgentResponse, AgentResult};
+use crate::architect::ModelRole;
+use crate::modes::AgentMode;
+
+impl Agent {
+    /// Run the verification checks after a turn that edited files.
+    ///
+    /// Failures are sent back to the model for another turn, until the
+    /// checks pass, the model stops editing, or the attempts run out.
+    pub(super) async fn verify(
+        &mut self,
+        mut response: AgentResponse,
+    ) -> AgentResult<AgentResponse> {
+        let Some(verifier) = self.verifier.clone() else {
+            return Ok(response);
+        };
+        // Checks run against the disk, which a dry run leaves alone
+        if self.mode == AgentMode::DryRun {
+            return Ok(response);
+        }
+
+        let mut steps = 0;
+        let mut attempt = 0;
+        while response.steps[steps..]
+            .iter()
+            .any(|s| s.modified_files(self.executor.registry()))
+        {
+            let report = verifier.run(attempt).await;
+            self.event_handler.on_verify(&report);
+            if report.passed() {
+                break;
+            }
+            if attempt >= verifier.max_attempts() {
+                warn!(attempts = attempt, "Verification still failing");
+                break;
+            }
+            attempt += 1;
+
+            let feedback = Message::user(report.feedback());
+            self.session.add_message(feedback.clone());
+            self.store
+                .append_message(self.session.id(), &feedback)
+                .await
+                .map_err(|e| AgentError::Session(e.to_string()))?;
+
+            // In architect mode the editor repairs its own changes
+            steps = response.steps.len();
+            let repair = if self.mode == AgentMode::Architect {
+                self.run_as(ModelRole::Editor).await?
+            } else {
+                self.run_loop().await?
+            };
+            response.extend(repair);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tests::{MockProvider, MockSessionStore};
+    use crate::agent::{AgentBuilder, AgentEventHandler};
+    use crate::verify::VerifyReport;
+    use agentik_core::config::VerifyConfig;
+    use agentik_core::Session;
+    use agentik_session::SessionStore;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_verify_feeds_failures_back() {
+        struct VerifyRecorder(Mutex<Vec<bool>>);
+
+        impl AgentEventHandler for VerifyRecorder {
+            fn on_verify(&self, report: &VerifyReport) {
+                self.0.lock().unwrap().push(report.passed());
+            }
+        }
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        let write = |content: &str, reply: &str| {
+            MockProvider::with_tool_call(
+                "Write",
+                serde_json::json!({"file_path": "notes.txt", "content": content}),
+                reply,
+            )
+            .responses
+            .into_inner()
+            .unwrap()
+        };
+        let provider = Arc::new(MockProvider::new(
+            [write("broken", "Done."), write("fixed", "Fixed.")].concat(),
+        ));
+        let store = Arc::new(MockSessionStore::new());
+        let session = Session::new(dir.to_path_buf());
+        store.create(&session).await.unwrap();
+        let handler = Arc::new(VerifyRecorder(Mutex::new(vec![])));
+        let mut agent = AgentBuilder::new()
+            .provider(provider.clone())
+            .executor(
+                crate::executor::ExecutorBuilder::new()
+                    .with_builtins()
+                    .working_dir(dir)
+                    .mode(AgentMode::Autonomous)
+                    .build(Arc::new(crate::executor::AutoApproveHandler)),
+            )
+            .store(store)
+            .session(session)
+            .mode(AgentMode::Autonomous)
+            .event_handler(handler.clone())
+            .verify(VerifyConfig {
+                commands: vec![
+                    "grep -q fixed notes.txt || { echo 'error: not fixed'; exit 1; }".into(),
+                ],
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let response = agent.run("Write the notes").await.unwrap();
+
+        assert_eq!(response.content, "Fixed.");
+        assert_eq!(response.turns, 4);
+        assert_eq!(*handler.0.lock().unwrap(), vec![false, true]);
+        let requests = provider.requests.lock().unwrap();
+        let feedback = requests[2].messages.last().unwrap().content.as_text();
+        assert!(feedback.starts_with("Verification failed"));
+        assert!(feedback.contains("error: not fixed"));
+    }
+}
@@ -0,0 +1,106 @@
+//! Architect/editor two-model pipeline.
+//!
+//! In [`AgentMode::Architect`](crate::AgentMode::Architect) each request is
+//! handled in two phases. A strong architect model investigates the codebase
+//! with read-only tools and writes a proposal describing the change; a
+//! cheaper or faster editor model then turns the proposal into concrete
+//! `Edit`/`Write` tool calls. Usage is tracked separately for each role.
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::{StepResult, TurnUsage};
+
+/// Which model handled a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelRole {
+    /// The main model, outside the architect/editor pipeline
+    #[default]
+    Main,
+    /// Reasons about the change and writes a proposal
+    Architect,
+    /// Implements the architect's proposal
+    Editor,
+}
+
+impl std::fmt::Display for ModelRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Main => write!(f, "main"),
+            Self::Architect => write!(f, "architect"),
+            Self::Editor => write!(f, "editor"),
+        }
+    }
+}
+
+/// System prompt for the architect phase.
+pub const ARCHITECT_PROMPT: &str =
+    "You are in architect mode, acting as the architect. Investigate the codebase \
+     with the read-only tools as needed, then describe the change precisely: which \
+     files to modify or create, and what to change in each, with code where it \
+     matters. Do not make any changes yourself; an editor will implement your \
+     proposal exactly as written.";
+
+/// System prompt for the editor phase.
+pub const EDITOR_PROMPT: &str =
+    "You are in architect mode, acting as the editor. Implement the architect's \
+     proposal exactly, using the Edit and Write tools. Do not redesign the change \
+     or make changes it doesn't call for. When done, briefly summarize what you \
+     changed.";
+
+/// Build the message that hands the architect's proposal to the editor.
+pub fn editor_request(proposal: &str) -> String {
+    format!(
+        "The architect proposed the following change. Implement it now.\n\n\
+         <proposal>\n{}\n</proposal>",
+        proposal.trim()
+    )
+}
+
+/// Total usage of the steps handled by one role.
+pub fn usage_for_role(steps: &[StepResult], role: ModelRole) -> TurnUsage {
+    let mut total = TurnUsage::default();
+    for step in steps.iter().filter(|s| s.role == role) {
+        total.accumulate(&step.usage);
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(role: ModelRole, input_tokens: u32) -> StepResult {
+        StepResult {
+            content: String::new(),
+            tool_calls: vec![],
+            tool_results: vec![],
+            usage: TurnUsage {
+                input_tokens,
+                ..Default::default()
+            },
+            role,
+        }
+    }
+
+    #[test]
+    fn test_usage_for_role() {
+        let steps = vec![
+            step(ModelRole::Architect, 100),
+            step(ModelRole::Architect, 50),
+            step(ModelRole::Editor, 20),
+        ];
+        assert_eq!(
+            usage_for_role(&steps, ModelRole::Architect).input_tokens,
+            150
+        );
+        assert_eq!(usage_for_role(&steps, ModelRole::Editor).input_tokens, 20);
+        assert_eq!(usage_for_role(&steps, ModelRole::Main).input_tokens, 0);
+    }
+
+    #[test]
+    fn test_editor_request_wraps_proposal() {
+        let request = editor_request("  Rename foo to bar in src/lib.rs\n");
+        assert!(request.contains("<proposal>\nRename foo to bar in src/lib.rs\n</proposal>"));
+    }
+}
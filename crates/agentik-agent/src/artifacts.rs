@@ -0,0 +1,364 @@
+//! Large tool outputs.
+//!
+//! A build log or a grep over a big tree can fill most of the context
+//! window in one call. Outputs above a size threshold are saved in full to an
+//! [`ArtifactStore`] by the executor, and the conversation only gets an
+//! excerpt of their start and end plus the artifact id. The model can page
+//! through or search the full output with [`ReadArtifactTool`].
+//!
+//! Artifacts are plain text files named by a hash of their content:
+//!
+//! ```text
+//! <dir>/<id>.txt
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use agentik_core::config::OutputConfig;
+use agentik_core::tool::{Artifact, ArtifactType, ToolCategory, ARTIFACT_NOTE_PREFIX};
+use agentik_core::{ToolCall, ToolDefinition, ToolResult};
+use agentik_tools::{Tool, ToolContext, ToolError};
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::checkpoint::write_atomic;
+
+/// Name of the tool that reads artifacts; its output is never offloaded.
+pub const READ_ARTIFACT_TOOL: &str = "ReadArtifact";
+
+/// Output a tool may capture when large outputs are offloaded (5MB).
+pub const MAX_CAPTURE_SIZE: usize = 5_000_000;
+
+/// Length of artifact ids (hex digits of the content hash).
+const ID_LEN: usize = 12;
+
+/// Lines returned by `ReadArtifact` when no limit is given.
+const DEFAULT_PAGE_LINES: usize = 200;
+
+// ============================================================================
+// Artifact Store
+// ============================================================================
+
+/// Store for tool outputs too large for the conversation.
+pub struct ArtifactStore {
+    dir: PathBuf,
+    config: OutputConfig,
+}
+
+impl ArtifactStore {
+    /// Create a store saving artifacts under a directory.
+    pub fn new(dir: impl Into<PathBuf>, config: OutputConfig) -> Self {
+        Self {
+            dir: dir.into(),
+            config,
+        }
+    }
+
+    /// Get the output configuration.
+    pub fn config(&self) -> &OutputConfig {
+        &self.config
+    }
+
+    /// Save an output, returning the artifact describing it.
+    pub fn save(&self, tool: &str, content: &str) -> io::Result<Artifact> {
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let id = hash[..ID_LEN].to_string();
+        let path = self.path(&id);
+        if !path.exists() {
+            write_atomic(&path, content.as_bytes())?;
+        }
+        debug!(
+            id,
+            tool,
+            bytes = content.len(),
+            "Saved tool output as artifact"
+        );
+
+        Ok(Artifact {
+            artifact_type: ArtifactType::Log,
+            name: id,
+            content: path.display().to_string(),
+            metadata: HashMap::from([
+                ("tool".to_string(), tool.to_string()),
+                ("bytes".to_string(), content.len().to_string()),
+                ("lines".to_string(), content.lines().count().to_string()),
+            ]),
+        })
+    }
+
+    /// Read an artifact's full content.
+    pub fn read(&self, id: &str) -> io::Result<String> {
+        if id.len() != ID_LEN || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid artifact id: {}", id),
+            ));
+        }
+        fs::read_to_string(self.path(id))
+    }
+
+    /// Replace a result's output with an excerpt if it is over the threshold.
+    ///
+    /// The full output is saved as an artifact and the excerpt ends with a
+    /// note naming it. If saving fails, the excerpt is kept anyway so the
+    /// context isn't flooded.
+    pub fn offload(&self, tool: &str, result: &mut ToolResult) {
+        let output = &result.output;
+        if output.len() <= self.config.offload_threshold {
+            return;
+        }
+
+        let note = match self.save(tool, output) {
+            Ok(artifact) => {
+                let note = format!(
+                    "{} {} ({} lines, {} bytes). Use {} to page through or search it.]",
+                    ARTIFACT_NOTE_PREFIX,
+                    artifact.name,
+                    output.lines().count(),
+                    output.len(),
+                    READ_ARTIFACT_TOOL
+                );
+                result.artifacts.push(artifact);
+                note
+            }
+            Err(e) => {
+                warn!(tool, error = %e, "Failed to save tool output as artifact");
+                format!("[Output truncated from {} bytes]", output.len())
+            }
+        };
+        result.output = format!(
+            "{}\n\n{}",
+            excerpt(output, self.config.head_bytes, self.config.tail_bytes),
+            note
+        );
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.txt", id))
+    }
+}
+
+/// The start and end of a text, cut at line boundaries where possible.
+fn excerpt(text: &str, head_bytes: usize, tail_bytes: usize) -> String {
+    let mut head_end = head_bytes.min(text.len());
+    while !text.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    if let Some(newline) = text[..head_end].rfind('\n') {
+        head_end = newline;
+    }
+
+    let mut tail_start = text.len().saturating_sub(tail_bytes).max(head_end);
+    while !text.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    if let Some(newline) = text[tail_start..].find('\n') {
+        tail_start += newline + 1;
+    }
+
+    format!(
+        "{}\n\n[... {} bytes omitted ...]\n\n{}",
+        &text[..head_end],
+        tail_start - head_end,
+        text[tail_start..].trim_end_matches('\n')
+    )
+}
+
+// ============================================================================
+// ReadArtifact Tool
+// ============================================================================
+
+/// Tool the model uses to read outputs saved as artifacts.
+pub struct ReadArtifactTool {
+    store: Arc<ArtifactStore>,
+}
+
+impl ReadArtifactTool {
+    /// Create the tool backed by an artifact store.
+    pub fn new(store: Arc<ArtifactStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for ReadArtifactTool {
+    fn name(&self) -> &str {
+        READ_ARTIFACT_TOOL
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            READ_ARTIFACT_TOOL,
+            "Read a tool output that was too large for the conversation and was saved \
+             as an artifact. Page through it by line, or give a regex pattern to list \
+             the matching lines.",
+        )
+        .with_parameters(json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "The artifact id from the output's note."
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Line number to start from (1-based). Default is 1."
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of lines to return. Default is 200."
+                },
+                "pattern": {
+                    "type": "string",
+                    "description": "Only return lines matching this regex."
+                }
+            },
+            "required": ["id"]
+        }))
+        .with_category(ToolCategory::Agent)
+    }
+
+    async fn execute(&self, call: &ToolCall, _ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let args = &call.arguments;
+        let id = args
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::MissingParameter("id".into()))?;
+        let offset = args
+            .get("offset")
+            .and_then(|v| v.as_u64())
+            .map(|n| n.max(1) as usize)
+            .unwrap_or(1);
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_PAGE_LINES);
+        let pattern = args
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| ToolError::InvalidArguments(format!("invalid pattern: {}", e)))?;
+
+        let content = self.store.read(id).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ToolError::NotFound(format!("artifact {}", id)),
+            _ => ToolError::Io(e),
+        })?;
+        let total = content.lines().count();
+        let matches = content
+            .lines()
+            .enumerate()
+            .skip(offset - 1)
+            .filter(|(_, line)| pattern.as_ref().map_or(true, |p| p.is_match(line)));
+
+        // Keep the page well under the offload threshold
+        let max_bytes = self.store.config.offload_threshold / 2;
+        let mut output = String::new();
+        let mut last = None;
+        for (i, line) in matches.take(limit) {
+            if output.len() + line.len() > max_bytes && last.is_some() {
+                break;
+            }
+            output.push_str(&format!("{:>6}\t{}\n", i + 1, line));
+            last = Some(i + 1);
+        }
+
+        let summary = match last {
+            Some(last) => format!("[Showing up to line {} of {}]", last, total),
+            None if pattern.is_some() => format!("[No matching lines from line {}]", offset),
+            None => format!(
+                "[No lines after line {}; the artifact has {}]",
+                offset - 1,
+                total
+            ),
+        };
+        output.push_str(&summary);
+        Ok(ToolResult::success(&call.id, output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn store(threshold: usize) -> (ArtifactStore, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let config = OutputConfig {
+            offload_threshold: threshold,
+            head_bytes: 20,
+            tail_bytes: 20,
+            ..Default::default()
+        };
+        (ArtifactStore::new(dir.path(), config), dir)
+    }
+
+    fn numbered_lines(count: usize) -> String {
+        (1..=count).map(|i| format!("line {}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_offload_keeps_excerpt() {
+        let (store, _dir) = store(100);
+
+        let mut small = ToolResult::success("call_1", "short");
+        store.offload("Bash", &mut small);
+        assert_eq!(small.output, "short");
+        assert!(small.artifacts.is_empty());
+
+        let output = numbered_lines(50);
+        let mut large = ToolResult::success("call_2", output.clone());
+        store.offload("Bash", &mut large);
+
+        assert!(large.output.starts_with("line 1\nline 2\n"));
+        assert!(large.output.contains("bytes omitted"));
+        assert!(large.output.contains("line 50\n"));
+        let artifact = &large.artifacts[0];
+        assert!(large.output.contains(&format!(
+            "{} {} (50 lines",
+            ARTIFACT_NOTE_PREFIX, artifact.name
+        )));
+        assert_eq!(artifact.metadata["tool"], "Bash");
+        assert_eq!(store.read(&artifact.name).unwrap(), output);
+        assert!(store.read("../../etc/pw").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_artifact_pages_and_searches() {
+        let (store, dir) = store(10_000);
+        let id = store.save("Bash", &numbered_lines(500)).unwrap().name;
+        let tool = ReadArtifactTool::new(Arc::new(store));
+        let ctx = ToolContext::new(dir.path());
+
+        let call = ToolCall::new(
+            "1",
+            READ_ARTIFACT_TOOL,
+            json!({"id": id, "offset": 11, "limit": 2}),
+        );
+        let result = tool.execute(&call, &ctx).await.unwrap();
+        assert_eq!(
+            result.output,
+            "    11\tline 11\n    12\tline 12\n[Showing up to line 12 of 500]"
+        );
+
+        let call = ToolCall::new(
+            "2",
+            READ_ARTIFACT_TOOL,
+            json!({"id": id, "pattern": "^line 49\\d$"}),
+        );
+        let result = tool.execute(&call, &ctx).await.unwrap();
+        assert_eq!(result.output.lines().count(), 11);
+        assert!(result.output.starts_with("   490\tline 490\n"));
+
+        let call = ToolCall::new("3", READ_ARTIFACT_TOOL, json!({"id": "0123456789ab"}));
+        assert!(tool.execute(&call, &ctx).await.is_err());
+    }
+}
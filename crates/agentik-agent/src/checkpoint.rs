@@ -0,0 +1,378 @@
+//! File checkpoints.
+//!
+//! Before a destructive tool call runs, the executor snapshots the files it
+//! is about to touch into a [`CheckpointStore`]. Snapshots are grouped by
+//! turn, keyed by the index of the user message that started it, so a turn
+//! can later be rewound: files are put back the way they were before the
+//! turn, and the conversation can be truncated to match.
+//!
+//! Checkpoints live outside the project, so they work in directories that
+//! aren't git repositories and never touch the user's history:
+//!
+//! ```text
+//! <dir>/objects/ab/cdef...       # file contents, named by SHA-256
+//! <dir>/sessions/<id>.json       # the session's checkpoints
+//! ```
+//!
+//! Only paths named in a call's arguments are captured; files changed by a
+//! shell command can't be restored.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+/// Maximum length of the prompt preview kept with a checkpoint.
+const PROMPT_PREVIEW_CHARS: usize = 200;
+
+// ============================================================================
+// Checkpoints
+// ============================================================================
+
+/// A file's content before a turn changed it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// Absolute path of the file
+    pub path: PathBuf,
+    /// Hash of the content, or `None` if the file didn't exist
+    pub blob: Option<String>,
+}
+
+/// The files a turn changed, as they were before it started.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Index of the user message that started the turn
+    pub message_index: usize,
+    /// Start of the user's prompt
+    pub prompt: String,
+    /// When the turn started
+    pub created_at: DateTime<Utc>,
+    /// Files snapshotted during the turn
+    #[serde(default)]
+    pub files: Vec<FileSnapshot>,
+}
+
+/// What to restore when rewinding to a checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RewindTarget {
+    /// Only the files
+    Files,
+    /// Only the conversation
+    Conversation,
+    /// Files and conversation
+    #[default]
+    Both,
+}
+
+impl RewindTarget {
+    /// Whether files are restored.
+    pub fn files(self) -> bool {
+        matches!(self, Self::Files | Self::Both)
+    }
+
+    /// Whether the conversation is truncated.
+    pub fn conversation(self) -> bool {
+        matches!(self, Self::Conversation | Self::Both)
+    }
+}
+
+impl std::str::FromStr for RewindTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "files" | "code" => Ok(Self::Files),
+            "conversation" | "chat" => Ok(Self::Conversation),
+            "both" | "all" => Ok(Self::Both),
+            _ => Err(format!(
+                "unknown rewind target: {} (expected files, conversation or both)",
+                s
+            )),
+        }
+    }
+}
+
+// ============================================================================
+// Checkpoint Store
+// ============================================================================
+
+/// The turn snapshots are currently recorded under.
+#[derive(Debug)]
+struct Turn {
+    session_id: String,
+    message_index: usize,
+}
+
+/// Content-addressed store for file checkpoints.
+///
+/// Shared between an agent, which starts a checkpoint at each turn, and its
+/// executor, which adds snapshots to it.
+pub struct CheckpointStore {
+    dir: PathBuf,
+    turn: Mutex<Option<Turn>>,
+}
+
+impl CheckpointStore {
+    /// Create a store rooted at a directory (created on first use).
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            turn: Mutex::new(None),
+        }
+    }
+
+    /// Start a checkpoint for a new turn.
+    ///
+    /// Checkpoints at or after `message_index` are left over from a
+    /// conversation that has since been rewound, and are replaced.
+    pub fn begin_turn(
+        &self,
+        session_id: &str,
+        message_index: usize,
+        prompt: &str,
+    ) -> io::Result<()> {
+        let mut turn = self.turn.lock().unwrap();
+        let mut checkpoints = self.list(session_id)?;
+        checkpoints.retain(|c| c.message_index < message_index);
+        checkpoints.push(Checkpoint {
+            message_index,
+            prompt: prompt.chars().take(PROMPT_PREVIEW_CHARS).collect(),
+            created_at: Utc::now(),
+            files: vec![],
+        });
+        self.write_manifest(session_id, &checkpoints)?;
+
+        *turn = Some(Turn {
+            session_id: session_id.to_string(),
+            message_index,
+        });
+        Ok(())
+    }
+
+    /// Snapshot files before they are changed.
+    ///
+    /// Only the first snapshot of a file in a turn is kept, since that is the
+    /// state the turn started from. Does nothing outside a turn.
+    pub fn snapshot(&self, paths: &[PathBuf]) -> io::Result<()> {
+        // Holding the lock serializes manifest updates from concurrent calls
+        let turn = self.turn.lock().unwrap();
+        let Some(turn) = turn.as_ref() else {
+            return Ok(());
+        };
+
+        let mut checkpoints = self.list(&turn.session_id)?;
+        let Some(checkpoint) = checkpoints
+            .iter_mut()
+            .find(|c| c.message_index == turn.message_index)
+        else {
+            return Ok(());
+        };
+
+        let mut changed = false;
+        for path in paths {
+            if path.is_dir() || checkpoint.files.iter().any(|f| &f.path == path) {
+                continue;
+            }
+            let blob = match fs::read(path) {
+                Ok(content) => Some(self.write_blob(&content)?),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            debug!(path = %path.display(), "Snapshotted file");
+            checkpoint.files.push(FileSnapshot {
+                path: path.clone(),
+                blob,
+            });
+            changed = true;
+        }
+
+        if changed {
+            self.write_manifest(&turn.session_id, &checkpoints)?;
+        }
+        Ok(())
+    }
+
+    /// List a session's checkpoints, oldest first.
+    pub fn list(&self, session_id: &str) -> io::Result<Vec<Checkpoint>> {
+        let path = self.manifest_path(session_id);
+        match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Put files back the way they were before the turn at `message_index`.
+    ///
+    /// Every file changed in that turn or a later one is restored from its
+    /// earliest snapshot; files that didn't exist yet are deleted. Returns
+    /// the restored paths.
+    pub fn restore(&self, session_id: &str, message_index: usize) -> io::Result<Vec<PathBuf>> {
+        let mut seen = HashSet::new();
+        let mut restored = Vec::new();
+        for checkpoint in self.list(session_id)? {
+            if checkpoint.message_index < message_index {
+                continue;
+            }
+            for file in checkpoint.files {
+                if !seen.insert(file.path.clone()) {
+                    continue;
+                }
+                match &file.blob {
+                    Some(hash) => {
+                        let content = fs::read(self.blob_path(hash))?;
+                        if let Some(parent) = file.path.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        fs::write(&file.path, content)?;
+                    }
+                    None => match fs::remove_file(&file.path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(e),
+                    },
+                }
+                restored.push(file.path);
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Drop checkpoints at or after `message_index`, after the conversation
+    /// was rewound past them.
+    pub fn truncate(&self, session_id: &str, message_index: usize) -> io::Result<()> {
+        let mut turn = self.turn.lock().unwrap();
+        let mut checkpoints = self.list(session_id)?;
+        checkpoints.retain(|c| c.message_index < message_index);
+        self.write_manifest(session_id, &checkpoints)?;
+
+        if turn
+            .as_ref()
+            .is_some_and(|t| t.session_id == session_id && t.message_index >= message_index)
+        {
+            *turn = None;
+        }
+        Ok(())
+    }
+
+    fn manifest_path(&self, session_id: &str) -> PathBuf {
+        self.dir
+            .join("sessions")
+            .join(format!("{}.json", session_id))
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join("objects").join(&hash[..2]).join(&hash[2..])
+    }
+
+    fn write_manifest(&self, session_id: &str, checkpoints: &[Checkpoint]) -> io::Result<()> {
+        let path = self.manifest_path(session_id);
+        let json = serde_json::to_string_pretty(checkpoints).map_err(io::Error::other)?;
+        write_atomic(&path, json.as_bytes())
+    }
+
+    /// Store content, returning its hash. Identical content is stored once.
+    fn write_blob(&self, content: &[u8]) -> io::Result<String> {
+        let hash = format!("{:x}", Sha256::digest(content));
+        let path = self.blob_path(&hash);
+        if !path.exists() {
+            write_atomic(&path, content)?;
+        }
+        Ok(hash)
+    }
+}
+
+/// Write a file via a temporary file so readers never see partial content.
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, content)?;
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_restore_files_before_turn() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let store = CheckpointStore::new(dir.join("checkpoints"));
+        let project = dir.join("project");
+        fs::create_dir_all(&project).unwrap();
+        let lib = project.join("lib.rs");
+        let new = project.join("new.rs");
+        fs::write(&lib, "v1").unwrap();
+
+        store.begin_turn("s1", 0, "first").unwrap();
+        store.snapshot(std::slice::from_ref(&lib)).unwrap();
+        fs::write(&lib, "v2").unwrap();
+        // A second snapshot in the same turn keeps the original content
+        store.snapshot(std::slice::from_ref(&lib)).unwrap();
+        fs::write(&lib, "v3").unwrap();
+
+        store.begin_turn("s1", 4, "second").unwrap();
+        store.snapshot(&[lib.clone(), new.clone()]).unwrap();
+        fs::write(&lib, "v4").unwrap();
+        fs::write(&new, "created").unwrap();
+
+        let checkpoints = store.list("s1").unwrap();
+        assert_eq!(checkpoints.len(), 2);
+        assert_eq!(checkpoints[0].files.len(), 1);
+        assert_eq!(checkpoints[1].files[1].blob, None);
+
+        store.restore("s1", 4).unwrap();
+        assert_eq!(fs::read_to_string(&lib).unwrap(), "v3");
+        assert!(!new.exists());
+
+        store.restore("s1", 0).unwrap();
+        assert_eq!(fs::read_to_string(&lib).unwrap(), "v1");
+    }
+
+    #[test]
+    fn test_truncate_and_restart_turns() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let store = CheckpointStore::new(dir);
+
+        // Snapshots outside a turn are ignored
+        store.snapshot(&[dir.join("a")]).unwrap();
+        assert!(store.list("s1").unwrap().is_empty());
+
+        store.begin_turn("s1", 0, "first").unwrap();
+        store.begin_turn("s1", 2, "second").unwrap();
+        store.begin_turn("s1", 5, "third").unwrap();
+        store.truncate("s1", 2).unwrap();
+        assert_eq!(store.list("s1").unwrap().len(), 1);
+
+        store.begin_turn("s1", 3, "again").unwrap();
+        store.begin_turn("s1", 1, "rewound").unwrap();
+        let prompts: Vec<_> = store
+            .list("s1")
+            .unwrap()
+            .into_iter()
+            .map(|c| c.prompt)
+            .collect();
+        assert_eq!(prompts, vec!["first", "rewound"]);
+    }
+
+    #[test]
+    fn test_parse_rewind_target() {
+        assert_eq!("files".parse(), Ok(RewindTarget::Files));
+        assert_eq!("Conversation".parse(), Ok(RewindTarget::Conversation));
+        assert_eq!("both".parse(), Ok(RewindTarget::Both));
+        assert!("everything".parse::<RewindTarget>().is_err());
+        assert!(RewindTarget::Both.files() && RewindTarget::Both.conversation());
+        assert!(!RewindTarget::Files.conversation());
+    }
+}
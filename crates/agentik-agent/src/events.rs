@@ -0,0 +1,240 @@
+//! Serializable event stream for observing a run.
+//!
+//! [`AgentEventHandler`] callbacks are synchronous and have a single owner,
+//! which suits a terminal UI but not async UIs, JSON output or remote
+//! clients. The agent also publishes what happens during a run as
+//! [`AgentEvent`]s on an [`EventBus`]: any number of consumers can
+//! [`subscribe`](EventBus::subscribe) and receive every event, numbered and
+//! timestamped, on a tokio broadcast channel.
+//!
+//! The agent publishes each event right before the matching callback, and
+//! tool and approval events by wrapping its permission handler, so callbacks
+//! and the stream always see the same run. A receiver
+//! that falls more than [`EVENT_CAPACITY`] events behind misses the oldest
+//! ones; the gap shows in the sequence numbers.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use agentik_core::{ToolCall, ToolDefinition, ToolResult};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::agent::TurnUsage;
+use crate::executor::PermissionHandler;
+
+/// Events a subscriber can fall behind by before missing some.
+pub const EVENT_CAPACITY: usize = 1024;
+
+/// Something that happened during a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    /// The agent is waiting for the model.
+    Thinking,
+    /// Text streamed from the model.
+    TextDelta { delta: String },
+    /// A tool call started executing.
+    ToolStart { call: ToolCall },
+    /// A tool call finished, successfully or not.
+    ToolComplete { call: ToolCall, result: ToolResult },
+    /// A tool call needs the user's approval.
+    ApprovalRequest { call: ToolCall },
+    /// The user approved or declined a tool call.
+    ApprovalResponse { call_id: String, approved: bool },
+    /// Usage of one turn.
+    Usage { usage: TurnUsage },
+    /// The conversation is being compacted.
+    Compacting,
+    /// The run failed.
+    Error { message: String },
+    /// The agent finished responding.
+    Complete {
+        content: String,
+        turns: usize,
+        usage: TurnUsage,
+    },
+}
+
+/// An event with its position in the stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    /// Position in the stream, starting at 0
+    pub seq: u64,
+    /// When the event was published
+    pub timestamp: DateTime<Utc>,
+    /// The event itself
+    #[serde(flatten)]
+    pub event: AgentEvent,
+}
+
+/// Publishes [`AgentEvent`]s to any number of subscribers.
+///
+/// Clones publish to the same subscribers with the same numbering.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<EventEnvelope>,
+    seq: Arc<AtomicU64>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    /// Create a bus without subscribers.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            sender,
+            seq: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Receive the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope> {
+        self.sender.subscribe()
+    }
+
+    /// Number of subscribers.
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Publish an event; it is dropped if nobody is subscribed.
+    pub fn emit(&self, event: AgentEvent) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        let envelope = EventEnvelope {
+            seq: self.seq.fetch_add(1, Ordering::SeqCst),
+            timestamp: Utc::now(),
+            event,
+        };
+        let _ = self.sender.send(envelope);
+    }
+}
+
+/// Permission handler that publishes tool and approval events before
+/// passing them on.
+pub(crate) struct PublishingPermissionHandler {
+    inner: Arc<dyn PermissionHandler>,
+    bus: EventBus,
+}
+
+impl PublishingPermissionHandler {
+    pub(crate) fn new(inner: Arc<dyn PermissionHandler>, bus: EventBus) -> Self {
+        Self { inner, bus }
+    }
+}
+
+#[async_trait]
+impl PermissionHandler for PublishingPermissionHandler {
+    async fn request_approval(&self, call: &ToolCall, tool: &ToolDefinition) -> bool {
+        self.bus
+            .emit(AgentEvent::ApprovalRequest { call: call.clone() });
+        let approved = self.inner.request_approval(call, tool).await;
+        self.bus.emit(AgentEvent::ApprovalResponse {
+            call_id: call.id.clone(),
+            approved,
+        });
+        approved
+    }
+
+    fn on_execute(&self, call: &ToolCall) {
+        self.bus.emit(AgentEvent::ToolStart { call: call.clone() });
+        self.inner.on_execute(call);
+    }
+
+    fn on_complete(&self, call: &ToolCall, result: &ToolResult) {
+        self.bus.emit(AgentEvent::ToolComplete {
+            call: call.clone(),
+            result: result.clone(),
+        });
+        self.inner.on_complete(call, result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_see_numbered_events() {
+        let bus = EventBus::new();
+        // Nobody is listening yet, so this is dropped without a number
+        bus.emit(AgentEvent::Thinking);
+
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+        bus.emit(AgentEvent::Thinking);
+        bus.emit(AgentEvent::TextDelta {
+            delta: "Hi".to_string(),
+        });
+
+        for receiver in [&mut first, &mut second] {
+            let a = receiver.recv().await.unwrap();
+            let b = receiver.recv().await.unwrap();
+            assert_eq!(a.seq, 0);
+            assert!(matches!(a.event, AgentEvent::Thinking));
+            assert_eq!(b.seq, 1);
+            assert!(matches!(b.event, AgentEvent::TextDelta { delta } if delta == "Hi"));
+            assert!(b.timestamp >= a.timestamp);
+        }
+    }
+
+    #[test]
+    fn test_envelope_serialization() {
+        let envelope = EventEnvelope {
+            seq: 3,
+            timestamp: Utc::now(),
+            event: AgentEvent::ApprovalResponse {
+                call_id: "call_1".to_string(),
+                approved: false,
+            },
+        };
+
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["seq"], 3);
+        assert_eq!(json["type"], "approval_response");
+        assert_eq!(json["call_id"], "call_1");
+        assert_eq!(json["approved"], false);
+
+        let parsed: EventEnvelope = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.seq, 3);
+        assert_eq!(parsed.timestamp, envelope.timestamp);
+        assert!(matches!(
+            parsed.event,
+            AgentEvent::ApprovalResponse {
+                approved: false,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_permission_handler_publishes_approvals() {
+        let bus = EventBus::new();
+        let mut receiver = bus.subscribe();
+        let handler = PublishingPermissionHandler::new(
+            Arc::new(crate::executor::DenyAllHandler),
+            bus.clone(),
+        );
+        let call = ToolCall::new("call_1", "Bash", serde_json::json!({"command": "ls"}));
+        let tool = ToolDefinition::new("Bash", "Run a command");
+
+        assert!(!handler.request_approval(&call, &tool).await);
+
+        let request = receiver.recv().await.unwrap().event;
+        assert!(matches!(request, AgentEvent::ApprovalRequest { call } if call.id == "call_1"));
+        let response = receiver.recv().await.unwrap().event;
+        assert!(matches!(
+            response,
+            AgentEvent::ApprovalResponse { call_id, approved: false } if call_id == "call_1"
+        ));
+    }
+}
@@ -37,17 +37,27 @@
 //! let result = executor.execute(&tool_call).await;
 //! ```
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use agentik_core::config::PermissionsConfig;
+use agentik_core::tool::ToolCategory;
 use agentik_core::{ToolCall, ToolDefinition, ToolResult};
-use agentik_tools::{ToolContext, ToolRegistry};
+use agentik_tools::{Changeset, ToolContext, ToolRegistry};
 use async_trait::async_trait;
-use futures::future::join_all;
+use futures::stream::{self, StreamExt};
 use tracing::{debug, info, warn};
 
+use crate::artifacts::{ArtifactStore, MAX_CAPTURE_SIZE, READ_ARTIFACT_TOOL};
+use crate::checkpoint::CheckpointStore;
+use crate::hooks::{HookRunner, ToolDecision};
+use crate::instructions::Instructions;
+use crate::memory::MemoryStore;
 use crate::modes::AgentMode;
 
+/// Default limit on tool calls executing at the same time.
+pub const DEFAULT_MAX_PARALLEL_TOOLS: usize = 8;
+
 /// Callback for handling permission requests during tool execution.
 ///
 /// Implementations of this trait control how tool execution approval is handled.
@@ -116,6 +126,12 @@ pub enum DenialReason {
     UserDeclined,
     /// Tool not found in registry
     ToolNotFound,
+    /// Tool is not in the allowed list
+    NotAllowed,
+    /// Tool would change state that a dry run can't stage
+    DryRun,
+    /// Tool is destructive and only read-only tools may run
+    ReadOnly,
 }
 
 impl std::fmt::Display for DenialReason {
@@ -125,23 +141,53 @@ impl std::fmt::Display for DenialReason {
             DenialReason::AskOnlyMode => write!(f, "agent is in ask-only mode"),
             DenialReason::UserDeclined => write!(f, "user declined approval"),
             DenialReason::ToolNotFound => write!(f, "tool not found"),
+            DenialReason::NotAllowed => write!(f, "tool is not in the allowed list"),
+            DenialReason::DryRun => write!(f, "dry run only stages file edits"),
+            DenialReason::ReadOnly => write!(f, "only read-only tools are allowed"),
         }
     }
 }
 
+/// Stores and state an executor's tool calls share with the rest of the run.
+///
+/// Sub-agents get a clone, so their calls are hooked, checkpointed, offloaded
+/// and staged alongside the parent's. The tool restriction isn't part of it:
+/// it changes between runs, so sub-agents take the parent's current one.
+#[derive(Clone, Default)]
+pub struct ExecutorServices {
+    /// Lifecycle hooks run around each tool call
+    pub hooks: Option<Arc<HookRunner>>,
+    /// Store files are snapshotted to before destructive calls
+    pub checkpoints: Option<Arc<CheckpointStore>>,
+    /// Store large outputs are saved to
+    pub artifacts: Option<Arc<ArtifactStore>>,
+    /// Project instructions, extended as calls touch nested directories
+    pub instructions: Option<Arc<Instructions>>,
+    /// Store the memory tools save to
+    pub memory: Option<Arc<MemoryStore>>,
+    /// Changeset file edits are staged in during dry runs
+    pub changeset: Arc<Changeset>,
+}
+
 /// Tool executor with permission checking and approval workflows.
 ///
 /// The `ToolExecutor` wraps a [`ToolRegistry`] and adds:
 /// - Permission checking based on configuration and agent mode
 /// - User approval workflows via [`PermissionHandler`]
 /// - Lifecycle notifications (on_execute, on_complete)
-/// - Batch execution with parallel processing
+/// - Staging of file edits in a [`Changeset`] in dry-run mode
+/// - Dependency-aware batch execution with bounded parallelism
 pub struct ToolExecutor {
     registry: ToolRegistry,
     context: ToolContext,
     permissions: PermissionsConfig,
     mode: AgentMode,
     handler: Arc<dyn PermissionHandler>,
+    max_parallel: usize,
+    services: ExecutorServices,
+    allowed_tools: Option<Vec<String>>,
+    read_only: bool,
+    session_id: String,
 }
 
 impl ToolExecutor {
@@ -161,13 +207,20 @@ impl ToolExecutor {
         mode: AgentMode,
         handler: Arc<dyn PermissionHandler>,
     ) -> Self {
-        Self {
+        let mut executor = Self {
             registry,
             context,
             permissions,
             mode,
             handler,
-        }
+            max_parallel: DEFAULT_MAX_PARALLEL_TOOLS,
+            services: ExecutorServices::default(),
+            allowed_tools: None,
+            read_only: false,
+            session_id: String::new(),
+        };
+        executor.set_mode(mode);
+        executor
     }
 
     /// Get a reference to the underlying tool registry.
@@ -185,14 +238,134 @@ impl ToolExecutor {
         &self.context
     }
 
+    /// Get the permissions configuration.
+    pub fn permissions(&self) -> &PermissionsConfig {
+        &self.permissions
+    }
+
+    /// Get the permission handler.
+    pub fn handler(&self) -> Arc<dyn PermissionHandler> {
+        Arc::clone(&self.handler)
+    }
+
+    /// Replace the permission handler.
+    pub fn set_handler(&mut self, handler: Arc<dyn PermissionHandler>) {
+        self.handler = handler;
+    }
+
     /// Get the current agent mode.
     pub fn mode(&self) -> AgentMode {
         self.mode
     }
 
     /// Set the agent mode.
+    ///
+    /// In dry-run mode the file tools stage their writes in the changeset;
+    /// staged changes are kept when switching to another mode.
     pub fn set_mode(&mut self, mode: AgentMode) {
         self.mode = mode;
+        self.context.changeset =
+            (mode == AgentMode::DryRun).then(|| Arc::clone(&self.services.changeset));
+    }
+
+    /// Get the services tool calls share with the rest of the run.
+    pub fn services(&self) -> &ExecutorServices {
+        &self.services
+    }
+
+    /// Set the services tool calls share, e.g. the parent's for a sub-agent.
+    ///
+    /// With an artifact store, tools capture their full output, since only
+    /// an excerpt of it reaches the conversation.
+    pub fn set_services(&mut self, services: ExecutorServices) {
+        if services.artifacts.is_some() {
+            self.context.max_output_size = MAX_CAPTURE_SIZE;
+        }
+        self.services = services;
+        self.set_mode(self.mode);
+    }
+
+    /// Get the changeset file edits are staged in during dry runs.
+    pub fn changeset(&self) -> Arc<Changeset> {
+        Arc::clone(&self.services.changeset)
+    }
+
+    /// Get the tools execution is restricted to, if any.
+    pub fn allowed_tools(&self) -> Option<&[String]> {
+        self.allowed_tools.as_deref()
+    }
+
+    /// Restrict execution to the named tools (`None` allows all).
+    pub fn set_allowed_tools(&mut self, tools: Option<Vec<String>>) {
+        self.allowed_tools = tools;
+    }
+
+    /// Check if a tool is allowed by the current restriction.
+    pub fn is_allowed(&self, tool_name: &str) -> bool {
+        self.allowed_tools
+            .as_ref()
+            .map_or(true, |tools| tools.iter().any(|t| t == tool_name))
+    }
+
+    /// Restrict execution to non-destructive tools, e.g. for the architect.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Check if only non-destructive tools may run.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Check if a tool can be offered to the model in the current mode.
+    ///
+    /// Besides the allowed list, a read-only executor offers no destructive
+    /// tools, and a dry run only offers destructive tools whose changes it
+    /// can stage, i.e. file edits.
+    pub fn is_available(&self, tool: &ToolDefinition) -> bool {
+        self.is_allowed(&tool.name)
+            && !(self.read_only && tool.is_destructive)
+            && (self.mode != AgentMode::DryRun || can_stage(tool))
+    }
+
+    /// Get the limit on tool calls executing at the same time.
+    pub fn max_parallel(&self) -> usize {
+        self.max_parallel
+    }
+
+    /// Set the limit on tool calls executing at the same time.
+    pub fn set_max_parallel(&mut self, max: usize) {
+        self.max_parallel = max.max(1);
+    }
+
+    /// Get the lifecycle hooks, if any.
+    pub fn hooks(&self) -> Option<Arc<HookRunner>> {
+        self.services.hooks.clone()
+    }
+
+    /// Set the session ID passed to hooks.
+    pub fn set_session_id(&mut self, session_id: &str) {
+        self.session_id = session_id.to_string();
+    }
+
+    /// Get the checkpoint store, if any.
+    pub fn checkpoints(&self) -> Option<Arc<CheckpointStore>> {
+        self.services.checkpoints.clone()
+    }
+
+    /// Get the artifact store, if any.
+    pub fn artifacts(&self) -> Option<Arc<ArtifactStore>> {
+        self.services.artifacts.clone()
+    }
+
+    /// Get the project instructions, if any.
+    pub fn instructions(&self) -> Option<Arc<Instructions>> {
+        self.services.instructions.clone()
+    }
+
+    /// Get the project memory store, if any.
+    pub fn memory(&self) -> Option<Arc<MemoryStore>> {
+        self.services.memory.clone()
     }
 
     /// Check if a tool is always denied.
@@ -211,6 +384,21 @@ impl ToolExecutor {
             return Some(DenialReason::AskOnlyMode);
         }
 
+        if !self.is_allowed(tool_name) {
+            return Some(DenialReason::NotAllowed);
+        }
+
+        let definition = self.registry.get(tool_name).map(|tool| tool.definition());
+        if self.read_only && definition.as_ref().is_some_and(|d| d.is_destructive) {
+            return Some(DenialReason::ReadOnly);
+        }
+
+        // Shell commands, git, MCP servers and the like would change things
+        // behind the changeset's back
+        if self.mode == AgentMode::DryRun && definition.is_some_and(|d| !can_stage(&d)) {
+            return Some(DenialReason::DryRun);
+        }
+
         None
     }
 
@@ -251,11 +439,23 @@ impl ToolExecutor {
 
     /// Check if a tool is auto-approved (no approval needed).
     fn is_auto_approved(&self, tool: &ToolDefinition) -> bool {
+        // Agent coordination tools only touch agent state (the plan itself is
+        // approved separately), unless the user asked to confirm them
+        if tool.category == ToolCategory::Agent && !self.requires_approval(tool) {
+            return true;
+        }
+
         // In Autonomous mode, non-destructive tools are auto-approved
         if self.mode == AgentMode::Autonomous && !tool.is_destructive {
             return true;
         }
 
+        // A dry run only lets through tools whose changes are staged, and the
+        // user reviews the changeset instead
+        if self.mode == AgentMode::DryRun {
+            return true;
+        }
+
         // Tools in default_allow list are auto-approved (unless they require approval)
         if self.permissions.default_allow.contains(&tool.name) && !self.requires_approval(tool) {
             return true;
@@ -268,10 +468,14 @@ impl ToolExecutor {
     ///
     /// This method:
     /// 1. Looks up the tool in the registry
-    /// 2. Checks if the tool is denied
-    /// 3. Checks if approval is required and requests it
-    /// 4. Executes the tool if approved
-    /// 5. Notifies the handler of completion
+    /// 2. Runs pre-tool hooks, which may deny or rewrite the call
+    /// 3. Checks if the tool is denied
+    /// 4. Checks if approval is required and requests it
+    /// 5. Snapshots the files a destructive call will change
+    /// 6. Executes the tool if approved
+    /// 7. Runs post-tool hooks, appending their feedback to the result
+    /// 8. Saves a large output as an artifact, keeping an excerpt
+    /// 9. Notifies the handler of completion
     ///
     /// # Returns
     ///
@@ -291,6 +495,31 @@ impl ToolExecutor {
 
         let definition = tool.definition();
 
+        // Run pre-tool hooks first, so the checks below see the call as
+        // rewritten
+        let rewritten: ToolCall;
+        let call = match &self.services.hooks {
+            Some(hooks) => match hooks.pre_tool_use(&self.session_id, call).await {
+                ToolDecision::Allow(None) => call,
+                ToolDecision::Allow(Some(arguments)) => {
+                    debug!(tool = %call.name, "Hook rewrote tool arguments");
+                    rewritten = ToolCall {
+                        arguments,
+                        ..call.clone()
+                    };
+                    &rewritten
+                }
+                ToolDecision::Deny(reason) => {
+                    info!(tool = %call.name, reason = %reason, "Tool call denied by hook");
+                    return ToolResult::error(
+                        &call.id,
+                        format!("Tool execution denied by hook: {}", reason),
+                    );
+                }
+            },
+            None => call,
+        };
+
         // Check if tool is denied
         if let Some(reason) = self.is_denied(&call.name) {
             warn!(tool = %call.name, reason = %reason, "Tool execution denied");
@@ -311,11 +540,16 @@ impl ToolExecutor {
             }
         }
 
+        // Snapshot the files a destructive call is about to change
+        if definition.is_destructive {
+            self.checkpoint(call);
+        }
+
         // Notify execution start
         self.handler.on_execute(call);
 
         // Execute the tool
-        let result = match self.registry.execute(call, &self.context).await {
+        let mut result = match self.registry.execute(call, &self.context).await {
             Ok(result) => result,
             Err(e) => {
                 warn!(tool = %call.name, error = %e, "Tool execution failed");
@@ -323,6 +557,31 @@ impl ToolExecutor {
             }
         };
 
+        // Run post-tool hooks
+        if let Some(hooks) = &self.services.hooks {
+            if let Some(feedback) = hooks.post_tool_use(&self.session_id, call, &result).await {
+                let feedback = format!("\n\n<hook_feedback>\n{}\n</hook_feedback>", feedback);
+                match result.error.as_mut() {
+                    Some(error) if !result.success => error.push_str(&feedback),
+                    _ => result.output.push_str(&feedback),
+                }
+            }
+        }
+
+        // Save a large output as an artifact
+        if let Some(artifacts) = &self.services.artifacts {
+            if call.name != READ_ARTIFACT_TOOL {
+                artifacts.offload(&call.name, &mut result);
+            }
+        }
+
+        // Pick up instruction files in the directories the call touched
+        if let Some(instructions) = &self.services.instructions {
+            for path in self.access(call).paths.unwrap_or_default() {
+                instructions.discover_nested(&path);
+            }
+        }
+
         // Notify completion
         self.handler.on_complete(call, &result);
 
@@ -336,11 +595,31 @@ impl ToolExecutor {
         result
     }
 
-    /// Execute multiple tool calls, potentially in parallel.
+    /// Snapshot the files a call names, if checkpoints are enabled.
+    fn checkpoint(&self, call: &ToolCall) {
+        // Staged edits leave the files on disk alone
+        if self.context.changeset.is_some() {
+            return;
+        }
+        let Some(checkpoints) = &self.services.checkpoints else {
+            return;
+        };
+        match self.access(call).paths {
+            Some(paths) => {
+                if let Err(e) = checkpoints.snapshot(&paths) {
+                    warn!(tool = %call.name, error = %e, "Failed to checkpoint files");
+                }
+            }
+            None => debug!(tool = %call.name, "Call touches unknown paths; not checkpointed"),
+        }
+    }
+
+    /// Execute multiple tool calls, in parallel where safe.
     ///
-    /// This method analyzes the calls and executes them in parallel when safe.
-    /// Currently, all calls are executed in parallel since tools are expected
-    /// to be independent. Future versions may implement dependency detection.
+    /// Calls are scheduled by [`ToolExecutor::schedule`]: read-only tools run
+    /// concurrently, destructive tools run one at a time in model order, and
+    /// a call that touches a path written by another call waits for it. At
+    /// most `max_parallel` calls execute at once.
     ///
     /// # Arguments
     ///
@@ -358,12 +637,149 @@ impl ToolExecutor {
             return vec![self.execute(&calls[0]).await];
         }
 
-        info!(count = calls.len(), "Executing tool calls in batch");
+        let stages = self.schedule(calls);
+        info!(
+            count = calls.len(),
+            stages = stages.len(),
+            "Executing tool calls in batch"
+        );
+
+        let mut results: Vec<Option<ToolResult>> = vec![None; calls.len()];
+        for stage in stages {
+            let futures: Vec<_> = stage.iter().map(|&i| self.execute(&calls[i])).collect();
+            let stage_results: Vec<ToolResult> = stream::iter(futures)
+                .buffered(self.max_parallel)
+                .collect()
+                .await;
+            for (i, result) in stage.into_iter().zip(stage_results) {
+                results[i] = Some(result);
+            }
+        }
 
-        // Execute all calls in parallel
-        let futures: Vec<_> = calls.iter().map(|call| self.execute(call)).collect();
+        results.into_iter().flatten().collect()
+    }
+
+    /// Group a batch of calls into stages that run one after another.
+    ///
+    /// Calls within a stage are independent and may run concurrently. A call
+    /// is placed after every earlier call it depends on: destructive calls
+    /// depend on all earlier destructive calls, and any call depends on an
+    /// earlier call that conflicts with it (see [`CallAccess`]).
+    pub fn schedule(&self, calls: &[ToolCall]) -> Vec<Vec<usize>> {
+        let access: Vec<CallAccess> = calls.iter().map(|c| self.access(c)).collect();
+        let mut levels = vec![0usize; calls.len()];
+        for j in 0..calls.len() {
+            for i in 0..j {
+                let ordered = access[i].writes && access[j].writes;
+                if ordered || access[i].conflicts_with(&access[j]) {
+                    levels[j] = levels[j].max(levels[i] + 1);
+                }
+            }
+        }
+
+        let mut stages = vec![Vec::new(); levels.iter().max().map_or(0, |m| m + 1)];
+        for (i, level) in levels.into_iter().enumerate() {
+            stages[level].push(i);
+        }
+        stages
+    }
+
+    /// Files a call to a file-editing tool names (empty for other tools).
+    pub(crate) fn edited_paths(&self, call: &ToolCall) -> Vec<PathBuf> {
+        let edits_files = self.registry.get(&call.name).is_some_and(|tool| {
+            let definition = tool.definition();
+            definition.is_destructive && definition.category == ToolCategory::FileSystem
+        });
+        if !edits_files {
+            return vec![];
+        }
+        self.access(call).paths.unwrap_or_default()
+    }
+
+    /// Work out what a call reads or writes from its tool and arguments.
+    fn access(&self, call: &ToolCall) -> CallAccess {
+        // Unknown tools fail without touching anything
+        let Some(tool) = self.registry.get(&call.name) else {
+            return CallAccess {
+                writes: false,
+                paths: Some(vec![]),
+            };
+        };
+        let definition = tool.definition();
+
+        let args = &call.arguments;
+        let mut paths: Vec<PathBuf> = ["file_path", "path"]
+            .iter()
+            .filter_map(|key| args.get(*key).and_then(|v| v.as_str()))
+            .map(|p| self.context.resolve_path(p))
+            .collect();
+        if let Some(files) = args.get("files").and_then(|v| v.as_array()) {
+            paths.extend(
+                files
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|p| self.context.resolve_path(p)),
+            );
+        }
+
+        let paths = if !paths.is_empty() {
+            Some(paths)
+        } else if definition.category == ToolCategory::Web && !definition.is_destructive {
+            // Web reads don't touch the workspace
+            Some(vec![])
+        } else {
+            None
+        };
+
+        CallAccess {
+            writes: definition.is_destructive,
+            paths,
+        }
+    }
+}
+
+/// Whether a tool's changes can be staged in a changeset: it is a read-only
+/// built-in, only touches agent state, or edits files through the file tools.
+///
+/// MCP and external tools may have side effects whatever they declare, so
+/// they never run in a dry run.
+fn can_stage(tool: &ToolDefinition) -> bool {
+    match tool.category {
+        ToolCategory::FileSystem | ToolCategory::Agent => true,
+        ToolCategory::Mcp(_) | ToolCategory::External => false,
+        ToolCategory::Shell | ToolCategory::Git | ToolCategory::Web => !tool.is_destructive,
+    }
+}
+
+// ============================================================================
+// Batch Scheduling
+// ============================================================================
+
+/// What a tool call reads or writes, for batch scheduling.
+#[derive(Debug, Clone, PartialEq)]
+struct CallAccess {
+    /// Whether the call modifies state
+    writes: bool,
+    /// Paths the call touches (`None` if it may touch anything, e.g. `Bash`)
+    paths: Option<Vec<PathBuf>>,
+}
 
-        join_all(futures).await
+impl CallAccess {
+    /// Whether two calls must not run at the same time.
+    ///
+    /// Reads never conflict with each other. A write conflicts with any call
+    /// touching the same file or a parent/child path, and with any call whose
+    /// paths are unknown.
+    fn conflicts_with(&self, other: &CallAccess) -> bool {
+        if !self.writes && !other.writes {
+            return false;
+        }
+        match (&self.paths, &other.paths) {
+            (Some(a), Some(b)) => a
+                .iter()
+                .any(|p| b.iter().any(|q| p.starts_with(q) || q.starts_with(p))),
+            _ => true,
+        }
     }
 }
 
@@ -376,6 +792,9 @@ pub struct ExecutorBuilder {
     working_dir: Option<std::path::PathBuf>,
     permissions: Option<PermissionsConfig>,
     mode: AgentMode,
+    max_parallel: Option<usize>,
+    services: ExecutorServices,
+    allowed_tools: Option<Vec<String>>,
 }
 
 impl ExecutorBuilder {
@@ -414,6 +833,60 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Set the limit on tool calls executing at the same time.
+    pub fn max_parallel(mut self, max: usize) -> Self {
+        self.max_parallel = Some(max);
+        self
+    }
+
+    /// Set the lifecycle hooks run around each tool call.
+    pub fn hooks(mut self, hooks: Arc<HookRunner>) -> Self {
+        self.services.hooks = Some(hooks);
+        self
+    }
+
+    /// Set the store files are snapshotted to before destructive calls.
+    pub fn checkpoints(mut self, checkpoints: Arc<CheckpointStore>) -> Self {
+        self.services.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Set the store large outputs are saved to.
+    pub fn artifacts(mut self, artifacts: Arc<ArtifactStore>) -> Self {
+        self.services.artifacts = Some(artifacts);
+        self
+    }
+
+    /// Set the project instructions.
+    pub fn instructions(mut self, instructions: Arc<Instructions>) -> Self {
+        self.services.instructions = Some(instructions);
+        self
+    }
+
+    /// Set the store the memory tools save to.
+    pub fn memory(mut self, memory: Arc<MemoryStore>) -> Self {
+        self.services.memory = Some(memory);
+        self
+    }
+
+    /// Set the changeset file edits are staged in during dry runs.
+    pub fn changeset(mut self, changeset: Arc<Changeset>) -> Self {
+        self.services.changeset = changeset;
+        self
+    }
+
+    /// Share the services of another executor, e.g. the parent's.
+    pub fn services(mut self, services: ExecutorServices) -> Self {
+        self.services = services;
+        self
+    }
+
+    /// Restrict execution to the named tools.
+    pub fn allowed_tools(mut self, tools: Vec<String>) -> Self {
+        self.allowed_tools = Some(tools);
+        self
+    }
+
     /// Build the executor with the given permission handler.
     pub fn build(self, handler: Arc<dyn PermissionHandler>) -> ToolExecutor {
         let registry = self.registry.unwrap_or_default();
@@ -423,7 +896,13 @@ impl ExecutorBuilder {
         let context = ToolContext::new(working_dir);
         let permissions = self.permissions.unwrap_or_default();
 
-        ToolExecutor::new(registry, context, permissions, self.mode, handler)
+        let mut executor = ToolExecutor::new(registry, context, permissions, self.mode, handler);
+        if let Some(max) = self.max_parallel {
+            executor.set_max_parallel(max);
+        }
+        executor.set_services(self.services);
+        executor.set_allowed_tools(self.allowed_tools);
+        executor
     }
 }
 
@@ -432,6 +911,7 @@ mod tests {
     use super::*;
     use agentik_core::tool::ToolCategory;
     use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use tempfile::TempDir;
     use tokio::sync::Mutex;
 
     /// A mock permission handler for testing.
@@ -514,6 +994,66 @@ mod tests {
         assert_eq!(executor.is_denied("safe_tool"), None);
     }
 
+    #[test]
+    fn test_is_denied_not_allowed() {
+        let mut executor = ExecutorBuilder::new()
+            .with_builtins()
+            .mode(AgentMode::Autonomous)
+            .build(Arc::new(AutoApproveHandler));
+        executor.set_allowed_tools(Some(vec!["Read".to_string()]));
+
+        assert_eq!(executor.is_denied("Read"), None);
+        assert_eq!(executor.is_denied("Bash"), Some(DenialReason::NotAllowed));
+
+        executor.set_allowed_tools(None);
+        assert_eq!(executor.is_denied("Bash"), None);
+    }
+
+    #[test]
+    fn test_read_only_denies_destructive_tools() {
+        let mut executor = ExecutorBuilder::new()
+            .with_builtins()
+            .mode(AgentMode::Autonomous)
+            .build(Arc::new(AutoApproveHandler));
+        executor.set_read_only(true);
+        let definition = |name: &str| executor.registry().get(name).unwrap().definition();
+
+        assert_eq!(executor.is_denied("Read"), None);
+        assert_eq!(executor.is_denied("Write"), Some(DenialReason::ReadOnly));
+        assert_eq!(executor.is_denied("Bash"), Some(DenialReason::ReadOnly));
+        assert!(executor.is_available(&definition("Grep")));
+        assert!(!executor.is_available(&definition("Edit")));
+
+        executor.set_read_only(false);
+        assert_eq!(executor.is_denied("Write"), None);
+    }
+
+    #[test]
+    fn test_dry_run_only_offers_stageable_tools() {
+        let mut executor = ExecutorBuilder::new()
+            .with_builtins()
+            .mode(AgentMode::DryRun)
+            .build(Arc::new(DenyAllHandler));
+        let definition = |name: &str| executor.registry().get(name).unwrap().definition();
+
+        assert_eq!(executor.is_denied("Edit"), None);
+        assert_eq!(executor.is_denied("Read"), None);
+        assert_eq!(executor.is_denied("Bash"), Some(DenialReason::DryRun));
+        assert_eq!(executor.is_denied("GitCommit"), Some(DenialReason::DryRun));
+        assert!(executor.is_available(&definition("Write")));
+        assert!(!executor.is_available(&definition("Bash")));
+        assert!(executor.context().changeset.is_some());
+        // Tools with side effects of their own don't run, whatever they declare
+        let mcp = ToolDefinition::new("mcp__fs__read", "Read via MCP")
+            .with_category(ToolCategory::Mcp("fs".to_string()));
+        assert!(!executor.is_available(&mcp));
+        assert!(!executor.is_available(&create_test_tool_definition("ext", false, false)));
+
+        executor.set_mode(AgentMode::Autonomous);
+        assert_eq!(executor.is_denied("Bash"), None);
+        assert!(executor.context().changeset.is_none());
+    }
+
     #[test]
     fn test_is_denied_ask_only_mode() {
         let executor = ExecutorBuilder::new()
@@ -583,6 +1123,35 @@ mod tests {
         assert!(!autonomous_executor.requires_approval(&destructive_tool));
     }
 
+    #[test]
+    fn test_agent_tools_auto_approved() {
+        let executor = ExecutorBuilder::new()
+            .mode(AgentMode::Planning)
+            .build(Arc::new(AutoApproveHandler));
+
+        let mut plan_tool = create_test_tool_definition("UpdatePlan", false, false);
+        plan_tool.category = ToolCategory::Agent;
+        assert!(executor.is_auto_approved(&plan_tool));
+
+        let other = create_test_tool_definition("other", false, false);
+        assert!(!executor.is_auto_approved(&other));
+
+        // Supervised mode and require_confirm still apply
+        let supervised = ExecutorBuilder::new()
+            .mode(AgentMode::Supervised)
+            .build(Arc::new(AutoApproveHandler));
+        assert!(!supervised.is_auto_approved(&plan_tool));
+
+        let confirming = ExecutorBuilder::new()
+            .mode(AgentMode::Planning)
+            .permissions(PermissionsConfig {
+                require_confirm: vec!["UpdatePlan".to_string()],
+                ..Default::default()
+            })
+            .build(Arc::new(AutoApproveHandler));
+        assert!(!confirming.is_auto_approved(&plan_tool));
+    }
+
     #[test]
     fn test_auto_approve_handler() {
         let handler = AutoApproveHandler;
@@ -721,12 +1290,161 @@ mod tests {
         assert_eq!(results[2].tool_call_id, "3");
     }
 
+    #[test]
+    fn test_schedule_orders_conflicting_calls() {
+        let executor = ExecutorBuilder::new()
+            .with_builtins()
+            .working_dir("/project")
+            .build(Arc::new(AutoApproveHandler));
+        let edit = |id: &str, path: &str| {
+            ToolCall::new(
+                id,
+                "Edit",
+                serde_json::json!({"file_path": path, "old_string": "a", "new_string": "b"}),
+            )
+        };
+        let calls = vec![
+            edit("0", "src/a.rs"),
+            edit("1", "src/a.rs"),
+            ToolCall::new("2", "Read", serde_json::json!({"file_path": "src/b.rs"})),
+            ToolCall::new("3", "Read", serde_json::json!({"file_path": "src/a.rs"})),
+            ToolCall::new("4", "Bash", serde_json::json!({"command": "cargo test"})),
+            ToolCall::new(
+                "5",
+                "Grep",
+                serde_json::json!({"pattern": "fn", "path": "src"}),
+            ),
+        ];
+
+        let stages = executor.schedule(&calls);
+
+        assert_eq!(stages, vec![vec![0, 2], vec![1], vec![3], vec![4], vec![5]]);
+
+        // Independent reads all run together
+        let reads: Vec<ToolCall> = (0..3)
+            .map(|i| ToolCall::new(i.to_string(), "Read", serde_json::json!({"file_path": "x"})))
+            .collect();
+        assert_eq!(executor.schedule(&reads), vec![vec![0, 1, 2]]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_batch_serializes_edits_to_same_file() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("lib.rs"), "fn one() {}\nfn two() {}\n").unwrap();
+
+        let executor = ExecutorBuilder::new()
+            .with_builtins()
+            .working_dir(dir)
+            .max_parallel(4)
+            .build(Arc::new(AutoApproveHandler));
+        let calls: Vec<ToolCall> = [("one", "uno"), ("two", "dos")]
+            .iter()
+            .map(|(old, new)| {
+                ToolCall::new(
+                    *old,
+                    "Edit",
+                    serde_json::json!({
+                        "file_path": "lib.rs",
+                        "old_string": format!("fn {}()", old),
+                        "new_string": format!("fn {}()", new)
+                    }),
+                )
+            })
+            .collect();
+
+        let results = executor.execute_batch(&calls).await;
+
+        assert!(results.iter().all(|r| r.success));
+        let content = std::fs::read_to_string(dir.join("lib.rs")).unwrap();
+        assert_eq!(content, "fn uno() {}\nfn dos() {}\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_runs_hooks() {
+        use agentik_core::config::{HookConfig, HooksConfig};
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("notes.txt"), "hello").unwrap();
+        let hook = |command: &str, matcher: &str| HookConfig {
+            command: command.to_string(),
+            matcher: Some(matcher.to_string()),
+            timeout_secs: None,
+        };
+        let hooks = HooksConfig {
+            pre_tool_use: vec![hook("echo 'writes are frozen' >&2; exit 2", "Write")],
+            post_tool_use: vec![hook("echo 'lint: ok'", "Read")],
+            ..Default::default()
+        };
+        let executor = ExecutorBuilder::new()
+            .with_builtins()
+            .working_dir(dir)
+            .hooks(Arc::new(HookRunner::new(hooks, dir)))
+            .build(Arc::new(AutoApproveHandler));
+
+        let write = ToolCall::new(
+            "1",
+            "Write",
+            serde_json::json!({"file_path": "notes.txt", "content": "bye"}),
+        );
+        let result = executor.execute(&write).await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("writes are frozen"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("notes.txt")).unwrap(),
+            "hello"
+        );
+
+        let read = ToolCall::new("2", "Read", serde_json::json!({"file_path": "notes.txt"}));
+        let result = executor.execute(&read).await;
+        assert!(result.success);
+        assert!(result
+            .output
+            .ends_with("<hook_feedback>\nlint: ok\n</hook_feedback>"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_offloads_large_output() {
+        use agentik_core::config::OutputConfig;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        let artifacts = Arc::new(ArtifactStore::new(
+            dir.join("artifacts"),
+            OutputConfig::default(),
+        ));
+        let executor = ExecutorBuilder::new()
+            .with_builtins()
+            .working_dir(dir)
+            .artifacts(Arc::clone(&artifacts))
+            .build(Arc::new(AutoApproveHandler));
+
+        // 100,000 bytes: more than Bash alone would capture
+        let call = ToolCall::new(
+            "1",
+            "Bash",
+            serde_json::json!({"command": "seq -w 1 20000"}),
+        );
+        let result = executor.execute(&call).await;
+        assert!(result.success);
+        assert!(result.output.len() < OutputConfig::default().offload_threshold);
+        assert!(result.output.starts_with("00001\n"));
+        assert!(result
+            .output
+            .contains("20000\n\n[Full output saved as artifact"));
+
+        let full = artifacts.read(&result.artifacts[0].name).unwrap();
+        assert_eq!(full.lines().count(), 20_000);
+    }
+
     #[test]
     fn test_builder_defaults() {
         let executor = ExecutorBuilder::new().build(Arc::new(AutoApproveHandler));
 
         assert!(executor.registry().is_empty());
         assert_eq!(executor.mode(), AgentMode::Autonomous);
+        assert_eq!(executor.max_parallel(), DEFAULT_MAX_PARALLEL_TOOLS);
     }
 
     #[test]
@@ -0,0 +1,456 @@
+//! Lifecycle hooks.
+//!
+//! Hooks are shell commands configured in [`HooksConfig`] that run on agent
+//! lifecycle events, letting teams enforce policy and automate checks without
+//! changing the code. Each hook receives a JSON object on stdin:
+//!
+//! ```json
+//! {"hook_event": "pre_tool_use", "session_id": "...", "cwd": "/project",
+//!  "tool_name": "Edit", "tool_input": {"file_path": "src/main.rs", ...}}
+//! ```
+//!
+//! Tool events carry `tool_name` and `tool_input` (plus `tool_output` and
+//! `success` after the tool ran), `user_prompt_submit` carries `prompt`, and
+//! `stop` carries the final `response` (and `error` if the run failed).
+//!
+//! A hook responds through its exit code and stdout:
+//! - Exit code 2 denies the tool call (or blocks the prompt); stderr is the
+//!   reason, which is shown to the model.
+//! - Exit code 0 with a JSON object on stdout can set `decision` (`"allow"`
+//!   or `"deny"`) and `reason`, rewrite the call with `tool_input`, return
+//!   `feedback` to append to a tool result, or add `context` to a prompt.
+//!   Plain-text stdout is used as feedback or context.
+//! - A timeout denies the tool call (or blocks the prompt) unless
+//!   `allow_on_timeout` is set.
+//! - Any other failure is logged and ignored.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use agentik_core::config::{HookConfig, HooksConfig};
+use agentik_core::{ToolCall, ToolResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+/// Exit code a hook uses to deny a tool call or block a prompt.
+const BLOCK_EXIT_CODE: i32 = 2;
+
+/// Lifecycle events hooks can run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    /// Before a tool runs
+    PreToolUse,
+    /// After a tool completes
+    PostToolUse,
+    /// When the user submits a prompt
+    UserPromptSubmit,
+    /// When the agent finishes responding
+    Stop,
+    /// Before the conversation is compacted
+    PreCompact,
+}
+
+/// What a pre-tool hook decided.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolDecision {
+    /// Run the call, with rewritten arguments if a hook changed them.
+    Allow(Option<Value>),
+    /// Don't run the call; the reason is returned to the model.
+    Deny(String),
+}
+
+/// What a prompt hook decided.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptDecision {
+    /// Send the prompt, with extra context from hooks appended.
+    Allow(Option<String>),
+    /// Don't send the prompt.
+    Block(String),
+}
+
+/// Structured hook output (stdout JSON).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct HookResponse {
+    decision: Option<String>,
+    reason: Option<String>,
+    tool_input: Option<Value>,
+    feedback: Option<String>,
+    context: Option<String>,
+}
+
+/// Result of running one hook command.
+enum HookOutcome {
+    /// Exited successfully, with JSON output or plain-text output
+    Success(HookResponse, Option<String>),
+    /// Exited with the blocking exit code
+    Block(String),
+    /// Didn't finish in time (already logged)
+    TimedOut(String),
+    /// Failed in some other way (already logged)
+    Failed,
+}
+
+/// Runs the configured hooks.
+pub struct HookRunner {
+    config: HooksConfig,
+    working_dir: PathBuf,
+}
+
+impl HookRunner {
+    /// Create a runner; hook commands run in `working_dir`.
+    pub fn new(config: HooksConfig, working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            working_dir: working_dir.into(),
+        }
+    }
+
+    /// Reason to deny a call or block a prompt whose hook timed out, if
+    /// timeouts aren't allowed through.
+    fn timeout_denial(&self, reason: String) -> Option<String> {
+        (!self.config.allow_on_timeout).then_some(reason)
+    }
+
+    /// Run the pre-tool hooks for a call.
+    ///
+    /// Hooks run in order; each sees the arguments as rewritten by the
+    /// previous ones, and the first denial wins.
+    pub async fn pre_tool_use(&self, session_id: &str, call: &ToolCall) -> ToolDecision {
+        let mut input = call.arguments.clone();
+        let mut rewritten = false;
+
+        for hook in self.tool_hooks(&self.config.pre_tool_use, &call.name) {
+            let payload = json!({"tool_name": call.name, "tool_input": input});
+            match self
+                .run(hook, HookEvent::PreToolUse, session_id, payload)
+                .await
+            {
+                HookOutcome::Success(response, _) => {
+                    if response.decision.as_deref() == Some("deny") {
+                        return ToolDecision::Deny(
+                            response
+                                .reason
+                                .unwrap_or_else(|| "denied by hook".to_string()),
+                        );
+                    }
+                    if let Some(new_input) = response.tool_input {
+                        input = new_input;
+                        rewritten = true;
+                    }
+                }
+                HookOutcome::Block(reason) => return ToolDecision::Deny(reason),
+                HookOutcome::TimedOut(reason) => {
+                    if let Some(reason) = self.timeout_denial(reason) {
+                        return ToolDecision::Deny(reason);
+                    }
+                }
+                HookOutcome::Failed => {}
+            }
+        }
+
+        ToolDecision::Allow(rewritten.then_some(input))
+    }
+
+    /// Run the post-tool hooks for a call and collect their feedback.
+    pub async fn post_tool_use(
+        &self,
+        session_id: &str,
+        call: &ToolCall,
+        result: &ToolResult,
+    ) -> Option<String> {
+        let mut feedback = Vec::new();
+        for hook in self.tool_hooks(&self.config.post_tool_use, &call.name) {
+            let payload = json!({
+                "tool_name": call.name,
+                "tool_input": call.arguments,
+                "tool_output": result.output,
+                "success": result.success,
+            });
+            match self
+                .run(hook, HookEvent::PostToolUse, session_id, payload)
+                .await
+            {
+                HookOutcome::Success(response, stdout) => {
+                    feedback.extend(response.feedback.or(stdout));
+                }
+                HookOutcome::Block(reason) => feedback.push(reason),
+                HookOutcome::TimedOut(_) | HookOutcome::Failed => {}
+            }
+        }
+        join(feedback)
+    }
+
+    /// Run the prompt hooks for a user prompt.
+    pub async fn user_prompt_submit(&self, session_id: &str, prompt: &str) -> PromptDecision {
+        let mut context = Vec::new();
+        for hook in &self.config.user_prompt_submit {
+            let payload = json!({"prompt": prompt});
+            match self
+                .run(hook, HookEvent::UserPromptSubmit, session_id, payload)
+                .await
+            {
+                HookOutcome::Success(response, stdout) => {
+                    if matches!(response.decision.as_deref(), Some("deny" | "block")) {
+                        return PromptDecision::Block(
+                            response
+                                .reason
+                                .unwrap_or_else(|| "blocked by hook".to_string()),
+                        );
+                    }
+                    context.extend(response.context.or(stdout));
+                }
+                HookOutcome::Block(reason) => return PromptDecision::Block(reason),
+                HookOutcome::TimedOut(reason) => {
+                    if let Some(reason) = self.timeout_denial(reason) {
+                        return PromptDecision::Block(reason);
+                    }
+                }
+                HookOutcome::Failed => {}
+            }
+        }
+        PromptDecision::Allow(join(context))
+    }
+
+    /// Run the stop hooks, with the final response or the error the run
+    /// failed with.
+    pub async fn stop(&self, session_id: &str, response: &str, error: Option<&str>) {
+        let payload = json!({"response": response, "error": error});
+        for hook in &self.config.stop {
+            self.run(hook, HookEvent::Stop, session_id, payload.clone())
+                .await;
+        }
+    }
+
+    /// Run the pre-compaction hooks.
+    pub async fn pre_compact(&self, session_id: &str) {
+        for hook in &self.config.pre_compact {
+            self.run(hook, HookEvent::PreCompact, session_id, json!({}))
+                .await;
+        }
+    }
+
+    fn tool_hooks<'a>(
+        &self,
+        hooks: &'a [HookConfig],
+        tool_name: &'a str,
+    ) -> impl Iterator<Item = &'a HookConfig> {
+        hooks.iter().filter(move |h| h.matches(tool_name))
+    }
+
+    /// Run one hook command with the event payload on stdin.
+    async fn run(
+        &self,
+        hook: &HookConfig,
+        event: HookEvent,
+        session_id: &str,
+        mut payload: Value,
+    ) -> HookOutcome {
+        payload["hook_event"] = json!(event);
+        payload["session_id"] = json!(session_id);
+        payload["cwd"] = json!(self.working_dir);
+
+        let timeout = Duration::from_secs(hook.timeout_secs.unwrap_or(self.config.timeout_secs));
+        debug!(command = %hook.command, ?event, "Running hook");
+
+        let output = tokio::time::timeout(timeout, async {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(&hook.command)
+                .current_dir(&self.working_dir)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                // Hooks may exit without reading their input
+                let _ = stdin.write_all(payload.to_string().as_bytes()).await;
+            }
+            child.wait_with_output().await
+        })
+        .await;
+
+        let output = match output {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                warn!(command = %hook.command, error = %e, "Failed to run hook");
+                return HookOutcome::Failed;
+            }
+            Err(_) => {
+                warn!(command = %hook.command, ?timeout, "Hook timed out");
+                return HookOutcome::TimedOut(format!(
+                    "hook timed out after {}s: {}",
+                    timeout.as_secs(),
+                    hook.command
+                ));
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        match output.status.code() {
+            Some(0) => match serde_json::from_str::<HookResponse>(&stdout) {
+                Ok(response) if stdout.starts_with('{') => HookOutcome::Success(response, None),
+                _ => HookOutcome::Success(HookResponse::default(), Some(stdout)),
+            },
+            Some(BLOCK_EXIT_CODE) => HookOutcome::Block(if stderr.is_empty() {
+                format!("blocked by hook: {}", hook.command)
+            } else {
+                stderr
+            }),
+            code => {
+                warn!(command = %hook.command, ?code, stderr = %stderr, "Hook failed");
+                HookOutcome::Failed
+            }
+        }
+    }
+}
+
+/// Join non-empty hook messages.
+fn join(messages: Vec<String>) -> Option<String> {
+    let messages: Vec<String> = messages.into_iter().filter(|m| !m.is_empty()).collect();
+    (!messages.is_empty()).then(|| messages.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(command: &str, matcher: Option<&str>) -> HookConfig {
+        HookConfig {
+            command: command.to_string(),
+            matcher: matcher.map(String::from),
+            timeout_secs: None,
+        }
+    }
+
+    fn hook_runner(config: HooksConfig) -> HookRunner {
+        HookRunner::new(config, std::env::temp_dir())
+    }
+
+    #[tokio::test]
+    async fn test_pre_tool_use_deny_and_rewrite() {
+        let call = ToolCall::new("1", "Bash", json!({"command": "rm -rf /"}));
+
+        let deny = hook_runner(HooksConfig {
+            pre_tool_use: vec![hook("echo 'no rm allowed' >&2; exit 2", Some("Bash"))],
+            ..Default::default()
+        });
+        assert_eq!(
+            deny.pre_tool_use("s1", &call).await,
+            ToolDecision::Deny("no rm allowed".to_string())
+        );
+        // Matcher doesn't apply to other tools
+        let read = ToolCall::new("2", "Read", json!({"file_path": "a"}));
+        assert_eq!(
+            deny.pre_tool_use("s1", &read).await,
+            ToolDecision::Allow(None)
+        );
+
+        let rewrite = hook_runner(HooksConfig {
+            pre_tool_use: vec![hook(
+                r#"echo '{"tool_input": {"command": "echo safe"}}'"#,
+                None,
+            )],
+            ..Default::default()
+        });
+        assert_eq!(
+            rewrite.pre_tool_use("s1", &call).await,
+            ToolDecision::Allow(Some(json!({"command": "echo safe"})))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_tool_use_feedback_and_failures() {
+        let runner = hook_runner(HooksConfig {
+            post_tool_use: vec![
+                // Reads the event from stdin
+                hook("grep -o '\"tool_name\":\"Edit\"'", None),
+                hook("exit 1", None),
+            ],
+            ..Default::default()
+        });
+        let call = ToolCall::new("1", "Edit", json!({}));
+        let result = ToolResult::success("1", "edited");
+        assert_eq!(
+            runner.post_tool_use("s1", &call, &result).await.as_deref(),
+            Some("\"tool_name\":\"Edit\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_user_prompt_submit() {
+        let runner = hook_runner(HooksConfig {
+            user_prompt_submit: vec![hook("echo 'Ticket: ABC-1'", None)],
+            timeout_secs: 5,
+            ..Default::default()
+        });
+        assert_eq!(
+            runner.user_prompt_submit("s1", "fix it").await,
+            PromptDecision::Allow(Some("Ticket: ABC-1".to_string()))
+        );
+
+        let runner = hook_runner(HooksConfig {
+            user_prompt_submit: vec![hook(
+                r#"echo '{"decision": "block", "reason": "no secrets"}'"#,
+                None,
+            )],
+            ..Default::default()
+        });
+        assert_eq!(
+            runner
+                .user_prompt_submit("s1", "my password is hunter2")
+                .await,
+            PromptDecision::Block("no secrets".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout_denies_unless_allowed() {
+        let config = HooksConfig {
+            pre_tool_use: vec![HookConfig {
+                timeout_secs: Some(1),
+                ..hook("sleep 5; exit 0", None)
+            }],
+            ..Default::default()
+        };
+        let call = ToolCall::new("1", "Read", json!({}));
+
+        let runner = hook_runner(config.clone());
+        assert!(matches!(
+            runner.pre_tool_use("s1", &call).await,
+            ToolDecision::Deny(reason) if reason.contains("timed out")
+        ));
+
+        let runner = hook_runner(HooksConfig {
+            allow_on_timeout: true,
+            ..config
+        });
+        assert_eq!(
+            runner.pre_tool_use("s1", &call).await,
+            ToolDecision::Allow(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_id_is_per_call() {
+        let runner = hook_runner(HooksConfig {
+            user_prompt_submit: vec![hook("grep -o '\"session_id\":\"[a-z0-9]*\"'", None)],
+            ..Default::default()
+        });
+        assert_eq!(
+            runner.user_prompt_submit("parent", "hi").await,
+            PromptDecision::Allow(Some("\"session_id\":\"parent\"".to_string()))
+        );
+        assert_eq!(
+            runner.user_prompt_submit("child", "hi").await,
+            PromptDecision::Allow(Some("\"session_id\":\"child\"".to_string()))
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Project instruction files.
+//!
+//! Instruction files (`AGENTS.md` or `.agentik/instructions.md`) tell the
+//! agent about a project's conventions. [`Instructions`] loads the user's
+//! global file and those from the repository root down to the working
+//! directory, then picks up files in nested directories as the agent touches
+//! files under them.
+//!
+//! Files are ordered from most general to most specific, so later ones take
+//! precedence, and a line consisting of `@path` is replaced by the content of
+//! that file, relative to the including file. Files in the repository can
+//! only include files inside it; the user's global file can include any
+//! file, and use `~/` for the home directory.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use tracing::{debug, warn};
+
+/// Names of instruction files, looked up in each directory.
+pub const INSTRUCTION_FILES: [&str; 2] = ["AGENTS.md", ".agentik/instructions.md"];
+
+/// How deep `@path` includes may nest.
+const MAX_INCLUDE_DEPTH: usize = 5;
+
+/// Where an instruction file was found, from most general to most specific.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InstructionScope {
+    /// The user's global file
+    User,
+    /// Between the repository root and the working directory
+    Project,
+    /// Below the working directory, found when files under it were touched
+    Directory,
+}
+
+/// A loaded instruction file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructionFile {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Where it was found
+    pub scope: InstructionScope,
+    /// Content with includes expanded
+    pub content: String,
+}
+
+/// Instruction files for a working directory.
+#[derive(Debug)]
+pub struct Instructions {
+    working_dir: PathBuf,
+    /// Repository root, which includes from project files can't leave
+    root: PathBuf,
+    /// Loaded files, most general first
+    files: RwLock<Vec<InstructionFile>>,
+    /// Directories already searched
+    searched: Mutex<HashSet<PathBuf>>,
+    /// Files loaded since [`take_loaded`](Self::take_loaded) was last called
+    loaded: Mutex<Vec<PathBuf>>,
+}
+
+impl Instructions {
+    /// Load the user's global file, if given, and the instruction files from
+    /// the repository root (the nearest ancestor with a `.git`) down to the
+    /// working directory.
+    pub fn discover(working_dir: impl Into<PathBuf>, user_file: Option<PathBuf>) -> Self {
+        let working_dir = working_dir.into();
+        let root = working_dir
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .unwrap_or(&working_dir);
+        let instructions = Self {
+            working_dir: working_dir.clone(),
+            root: canonical(root),
+            files: RwLock::new(Vec::new()),
+            searched: Mutex::new(HashSet::new()),
+            loaded: Mutex::new(Vec::new()),
+        };
+
+        if let Some(path) = user_file.filter(|p| p.is_file()) {
+            instructions.load(&path, InstructionScope::User);
+        }
+
+        let mut dirs: Vec<&Path> = working_dir
+            .ancestors()
+            .take_while(|dir| dir.starts_with(root))
+            .collect();
+        dirs.reverse();
+        for dir in dirs {
+            instructions.search(dir, InstructionScope::Project);
+        }
+        instructions
+    }
+
+    /// Get the loaded files, most general first.
+    pub fn files(&self) -> Vec<InstructionFile> {
+        self.files.read().unwrap().clone()
+    }
+
+    /// Whether no files were loaded.
+    pub fn is_empty(&self) -> bool {
+        self.files.read().unwrap().is_empty()
+    }
+
+    /// Load the instruction files of the directories between the working
+    /// directory and a touched path. Returns the paths of the files loaded.
+    pub fn discover_nested(&self, path: &Path) -> Vec<PathBuf> {
+        let dir = if path.is_dir() {
+            path
+        } else {
+            match path.parent() {
+                Some(parent) => parent,
+                None => return vec![],
+            }
+        };
+        if !dir.starts_with(&self.working_dir) {
+            return vec![];
+        }
+
+        let mut dirs: Vec<&Path> = dir
+            .ancestors()
+            .take_while(|d| *d != self.working_dir)
+            .collect();
+        dirs.reverse();
+        dirs.into_iter()
+            .flat_map(|d| self.search(d, InstructionScope::Directory))
+            .collect()
+    }
+
+    /// Take the paths of the files loaded since the last call.
+    pub fn take_loaded(&self) -> Vec<PathBuf> {
+        std::mem::take(&mut *self.loaded.lock().unwrap())
+    }
+
+    /// Render the files for the system prompt.
+    pub fn render(&self) -> Option<String> {
+        let files = self.files.read().unwrap();
+        if files.is_empty() {
+            return None;
+        }
+
+        let mut output = String::from(
+            "<instructions>\nInstructions for this project, from most general to most \
+             specific; where they conflict, later files take precedence.\n",
+        );
+        for file in files.iter() {
+            output.push_str(&format!(
+                "\n<file path=\"{}\">\n{}\n</file>\n",
+                file.path.display(),
+                file.content.trim_end()
+            ));
+        }
+        output.push_str("</instructions>");
+        Some(output)
+    }
+
+    /// Load the instruction files in a directory, unless already searched.
+    fn search(&self, dir: &Path, scope: InstructionScope) -> Vec<PathBuf> {
+        if !self.searched.lock().unwrap().insert(dir.to_path_buf()) {
+            return vec![];
+        }
+        INSTRUCTION_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|path| path.is_file() && self.load(path, scope))
+            .collect()
+    }
+
+    /// Load a file, keeping the files ordered from most general to most
+    /// specific. Returns whether it was loaded.
+    fn load(&self, path: &Path, scope: InstructionScope) -> bool {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to read instructions");
+                return false;
+            }
+        };
+        debug!(path = %path.display(), ?scope, "Loaded instructions");
+        let root = (scope != InstructionScope::User).then_some(self.root.as_path());
+        let file = InstructionFile {
+            path: path.to_path_buf(),
+            scope,
+            content: expand_includes(path, &content, &mut vec![canonical(path)], root),
+        };
+
+        let key = |f: &InstructionFile| (f.scope, f.path.components().count());
+        let mut files = self.files.write().unwrap();
+        let index = files.partition_point(|f| key(f) <= key(&file));
+        files.insert(index, file);
+        self.loaded.lock().unwrap().push(path.to_path_buf());
+        true
+    }
+}
+
+/// Replace `@path` lines with the content of the files they name.
+///
+/// `chain` holds the files being expanded, so include cycles are left as
+/// they are. With a `root`, includes outside it are left as they are too.
+fn expand_includes(
+    path: &Path,
+    content: &str,
+    chain: &mut Vec<PathBuf>,
+    root: Option<&Path>,
+) -> String {
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut output = String::new();
+    for line in content.lines() {
+        let include = line
+            .trim()
+            .strip_prefix('@')
+            .filter(|target| !target.is_empty() && !target.contains(char::is_whitespace))
+            .and_then(|target| resolve_include(base, target, root));
+        match include {
+            Some(target) if chain.len() <= MAX_INCLUDE_DEPTH && !chain.contains(&target) => {
+                match std::fs::read_to_string(&target) {
+                    Ok(included) => {
+                        chain.push(target.clone());
+                        output.push_str(&expand_includes(&target, &included, chain, root));
+                        chain.pop();
+                        if !output.ends_with('\n') {
+                            output.push('\n');
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        debug!(path = %target.display(), error = %e, "Instruction include not read");
+                    }
+                }
+            }
+            Some(target) => {
+                warn!(path = %target.display(), "Instruction include nested too deeply or cyclic");
+            }
+            None => {}
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Resolve an include target against the including file's directory.
+///
+/// With a `root`, the target must resolve (following symlinks) to a file
+/// inside it; without one, `~/` names the home directory.
+fn resolve_include(base: &Path, target: &str, root: Option<&Path>) -> Option<PathBuf> {
+    let Some(root) = root else {
+        if let Some(rest) = target.strip_prefix("~/") {
+            if let Some(home) = std::env::var_os("HOME") {
+                return Some(canonical(&PathBuf::from(home).join(rest)));
+            }
+        }
+        return Some(canonical(&base.join(target)));
+    };
+
+    let path = match base.join(target).canonicalize() {
+        Ok(path) => path,
+        Err(e) => {
+            debug!(target, error = %e, "Instruction include not read");
+            return None;
+        }
+    };
+    if !path.starts_with(root) {
+        warn!(path = %path.display(), "Instruction include outside the repository refused");
+        return None;
+    }
+    Some(path)
+}
+
+/// The canonical form of a path, or the path itself if it can't be resolved.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_discover_and_render() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let working_dir = root.join("crates").join("app");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(working_dir.join(".agentik")).unwrap();
+        fs::create_dir_all(working_dir.join("src/db")).unwrap();
+        fs::write(
+            root.join("AGENTS.md"),
+            "Root rules\n@docs/style.md\n@missing.md\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/style.md"), "Use tabs").unwrap();
+        fs::write(working_dir.join(".agentik/instructions.md"), "App rules").unwrap();
+        fs::write(working_dir.join("src/db/AGENTS.md"), "@AGENTS.md\nDB rules").unwrap();
+        let user = root.join("user.md");
+        fs::write(&user, "User rules").unwrap();
+
+        let instructions = Instructions::discover(&working_dir, Some(user.clone()));
+
+        let paths: Vec<_> = instructions.files().into_iter().map(|f| f.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                user.clone(),
+                root.join("AGENTS.md"),
+                working_dir.join(".agentik/instructions.md")
+            ]
+        );
+        assert_eq!(instructions.take_loaded(), paths);
+        assert_eq!(
+            instructions.files()[1].content,
+            "Root rules\nUse tabs\n@missing.md\n"
+        );
+
+        // Nested files load once, and a cyclic include stays as it is
+        let file = working_dir.join("src/db/schema.rs");
+        let nested = working_dir.join("src/db/AGENTS.md");
+        assert_eq!(instructions.discover_nested(&file), vec![nested.clone()]);
+        assert!(instructions.discover_nested(&file).is_empty());
+        assert!(instructions
+            .discover_nested(&root.join("other.rs"))
+            .is_empty());
+        let files = instructions.files();
+        assert_eq!(files[3].scope, InstructionScope::Directory);
+        assert_eq!(files[3].content, "@AGENTS.md\nDB rules\n");
+
+        let rendered = instructions.render().unwrap();
+        assert!(rendered.starts_with("<instructions>\n"));
+        let user_at = rendered.find("User rules").unwrap();
+        let db_at = rendered.find("DB rules").unwrap();
+        assert!(user_at < db_at);
+    }
+
+    #[test]
+    fn test_includes_cannot_leave_repository() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("repo");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        let outside = temp.path().join("outside.md");
+        fs::write(&outside, "Secret").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&outside, root.join("link.md")).unwrap();
+        let project = format!(
+            "@../outside.md\n@{}\n@~/outside.md\n@link.md\n",
+            outside.display()
+        );
+        fs::write(root.join("AGENTS.md"), &project).unwrap();
+        let user = temp.path().join("user.md");
+        fs::write(&user, "@outside.md\n").unwrap();
+
+        let instructions = Instructions::discover(&root, Some(user));
+
+        let files = instructions.files();
+        assert_eq!(files[0].scope, InstructionScope::User);
+        assert_eq!(files[0].content, "Secret\n");
+        assert_eq!(files[1].content, project);
+    }
+
+    #[test]
+    fn test_no_files() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+
+        let instructions = Instructions::discover(dir.path(), None);
+
+        assert!(instructions.is_empty());
+        assert!(instructions.render().is_none());
+    }
+}
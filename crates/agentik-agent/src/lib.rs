@@ -7,22 +7,54 @@
 //! - Planning mode
 //! - Architect/Editor model separation
 //! - "Anytime" question asking system
+//! - Mid-run steering messages
+//! - Serializable event stream for observing runs
+//! - Sub-agents with isolated context
 //! - Tool execution orchestration
+//! - Lifecycle hooks
+//! - Project instruction files (`AGENTS.md`)
+//! - Project memory across sessions
+//! - File checkpoints for rewinding turns
+//! - Automatic verification of edits
+//! - Offloading of large tool outputs to artifacts
+//! - Detection of loops in tool calls
 
 pub mod agent;
+pub mod architect;
+pub mod artifacts;
+pub mod checkpoint;
+pub mod events;
 pub mod executor;
+pub mod hooks;
+pub mod instructions;
+pub mod loops;
+pub mod memory;
 pub mod modes;
 pub mod planning;
 pub mod questions;
+pub mod steering;
+pub mod task;
+pub mod verify;
 
 pub use agent::{
     Agent, AgentBuilder, AgentConfig, AgentError, AgentEventHandler, AgentResponse, AgentResult,
     NoOpEventHandler, StepResult, TurnUsage,
 };
+pub use architect::ModelRole;
+pub use artifacts::{ArtifactStore, ReadArtifactTool};
+pub use checkpoint::{Checkpoint, CheckpointStore, FileSnapshot, RewindTarget};
+pub use events::{AgentEvent, EventBus, EventEnvelope, EVENT_CAPACITY};
 pub use executor::{
-    AutoApproveHandler, DenialReason, DenyAllHandler, ExecutorBuilder, PermissionHandler,
-    ToolExecutor,
+    AutoApproveHandler, DenialReason, DenyAllHandler, ExecutorBuilder, ExecutorServices,
+    PermissionHandler, ToolExecutor, DEFAULT_MAX_PARALLEL_TOOLS,
 };
+pub use hooks::{HookEvent, HookRunner, PromptDecision, ToolDecision};
+pub use instructions::{InstructionFile, InstructionScope, Instructions, INSTRUCTION_FILES};
+pub use loops::{LoopDetection, LoopDetector, LoopKind};
+pub use memory::{ForgetTool, Memory, MemoryStore, RecallTool, RememberTool};
 pub use modes::AgentMode;
-pub use planning::PlanningState;
-pub use questions::QuestionQueue;
+pub use planning::{PlanOutcome, PlanningState};
+pub use questions::{Answer, AskUserTool, Question, QuestionQueue, DEFAULT_QUESTION_TIMEOUT};
+pub use steering::SteeringQueue;
+pub use task::{SubAgentState, TaskTool};
+pub use verify::{CheckResult, Verifier, VerifyReport};
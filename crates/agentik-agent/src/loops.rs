@@ -0,0 +1,292 @@
+//! Loop detection.
+//!
+//! Models sometimes get stuck: calling the same tool with the same arguments
+//! and getting the same result, hitting the same error with every attempted
+//! fix, or editing a file back and forth between versions. [`LoopDetector`]
+//! watches the tool calls of a run for these patterns; the agent answers a
+//! detection with a corrective note, a question to the user, or by stopping
+//! the run (see [`LoopDetectionConfig`]).
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use agentik_core::config::LoopDetectionConfig;
+use agentik_core::{ToolCall, ToolResult};
+
+/// Maximum length of an error quoted in a detection.
+const MAX_ERROR_CHARS: usize = 200;
+
+/// Kind of repetition found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopKind {
+    /// The same call returned the same result again and again
+    IdenticalCalls,
+    /// Calls to a tool kept failing with the same error
+    RepeatedErrors,
+    /// A file kept being edited back to an earlier version
+    OscillatingEdits,
+}
+
+/// A repetition found in the agent's tool calls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopDetection {
+    /// What kind of repetition it is
+    pub kind: LoopKind,
+    /// Tool involved
+    pub tool: String,
+    /// How often it repeated
+    pub count: usize,
+    /// The repeated error, or the file edited back and forth
+    pub detail: String,
+}
+
+impl LoopDetection {
+    /// The note asking the model to change course.
+    pub fn note(&self) -> String {
+        let advice = match self.kind {
+            LoopKind::IdenticalCalls => {
+                "Repeating the call won't change its result. Try a different approach, \
+                 or explain what is blocking you."
+            }
+            LoopKind::RepeatedErrors => {
+                "Stop retrying the same fix. Re-read the relevant code and error, then \
+                 try a different approach, or explain what is blocking you."
+            }
+            LoopKind::OscillatingEdits => {
+                "Decide on one version instead of switching between them, and explain \
+                 the trade-off if you are unsure."
+            }
+        };
+        format!("<loop_detected>\n{}. {}\n</loop_detected>", self, advice)
+    }
+}
+
+impl fmt::Display for LoopDetection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LoopKind::IdenticalCalls => write!(
+                f,
+                "`{}` was called {} times with the same arguments and result",
+                self.tool, self.count
+            ),
+            LoopKind::RepeatedErrors => write!(
+                f,
+                "`{}` failed {} times with the same error: {}",
+                self.tool, self.count, self.detail
+            ),
+            LoopKind::OscillatingEdits => write!(
+                f,
+                "{} was edited back to an earlier version {} times",
+                self.detail, self.count
+            ),
+        }
+    }
+}
+
+/// Watches a run's tool calls for repetition.
+#[derive(Debug)]
+pub struct LoopDetector {
+    config: LoopDetectionConfig,
+    /// Occurrences of each call and result
+    calls: HashMap<u64, usize>,
+    /// Occurrences of each error, by tool
+    errors: HashMap<(String, String), usize>,
+    /// Content hashes of each edited file, in the order they were written
+    versions: HashMap<PathBuf, Vec<u64>>,
+    /// Times each file returned to an earlier version
+    returns: HashMap<PathBuf, usize>,
+    /// Detections so far in this run
+    detections: u32,
+}
+
+impl LoopDetector {
+    /// Create a detector with the given thresholds.
+    pub fn new(config: LoopDetectionConfig) -> Self {
+        Self {
+            config,
+            calls: HashMap::new(),
+            errors: HashMap::new(),
+            versions: HashMap::new(),
+            returns: HashMap::new(),
+            detections: 0,
+        }
+    }
+
+    /// Get the thresholds.
+    pub fn config(&self) -> &LoopDetectionConfig {
+        &self.config
+    }
+
+    /// Forget everything seen, for a new run.
+    pub fn reset(&mut self) {
+        self.clear();
+        self.detections = 0;
+    }
+
+    /// Count a detection and start watching afresh, so the same repetition
+    /// has to build up again before it is reported. Returns the number of
+    /// detections in this run.
+    pub fn strike(&mut self) -> u32 {
+        self.clear();
+        self.detections += 1;
+        self.detections
+    }
+
+    /// Record a call and its result; `edited` are the files it changed.
+    pub fn record(
+        &mut self,
+        call: &ToolCall,
+        result: &ToolResult,
+        edited: &[PathBuf],
+    ) -> Option<LoopDetection> {
+        let outcome = if result.success {
+            result.output.as_str()
+        } else {
+            result.error.as_deref().unwrap_or_default()
+        };
+        let key = hash(&(&call.name, call.arguments.to_string(), outcome));
+        let count = self.calls.entry(key).or_default();
+        *count += 1;
+        if *count >= self.config.identical_calls {
+            return Some(detection(
+                LoopKind::IdenticalCalls,
+                call,
+                *count,
+                String::new(),
+            ));
+        }
+
+        if !result.success {
+            let error = first_line(outcome);
+            let count = self
+                .errors
+                .entry((call.name.clone(), error.clone()))
+                .or_default();
+            *count += 1;
+            if *count >= self.config.repeated_errors {
+                return Some(detection(LoopKind::RepeatedErrors, call, *count, error));
+            }
+        }
+
+        for path in edited {
+            let content = std::fs::read(path).ok();
+            let version = hash(&content);
+            let versions = self.versions.entry(path.clone()).or_default();
+            if versions.last() == Some(&version) {
+                continue;
+            }
+            let returned = versions.contains(&version);
+            versions.push(version);
+            if returned {
+                let count = self.returns.entry(path.clone()).or_default();
+                *count += 1;
+                if *count >= self.config.oscillations {
+                    let path = path.display().to_string();
+                    return Some(detection(LoopKind::OscillatingEdits, call, *count, path));
+                }
+            }
+        }
+        None
+    }
+
+    fn clear(&mut self) {
+        self.calls.clear();
+        self.errors.clear();
+        self.versions.clear();
+        self.returns.clear();
+    }
+}
+
+fn detection(kind: LoopKind, call: &ToolCall, count: usize, detail: String) -> LoopDetection {
+    LoopDetection {
+        kind,
+        tool: call.name.clone(),
+        count,
+        detail,
+    }
+}
+
+fn hash(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The first line of an error, shortened for quoting.
+fn first_line(error: &str) -> String {
+    let line = error.lines().next().unwrap_or_default();
+    match line.char_indices().nth(MAX_ERROR_CHARS) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn detector() -> LoopDetector {
+        LoopDetector::new(LoopDetectionConfig::default())
+    }
+
+    #[test]
+    fn test_identical_calls() {
+        let mut loops = detector();
+        let call = ToolCall::new("1", "Bash", json!({"command": "cargo test"}));
+        let failed = ToolResult::success("1", "test result: FAILED");
+
+        assert!(loops.record(&call, &failed, &[]).is_none());
+        // A different result is progress
+        let passed = ToolResult::success("1", "test result: ok");
+        assert!(loops.record(&call, &passed, &[]).is_none());
+        assert!(loops.record(&call, &failed, &[]).is_none());
+
+        let detection = loops.record(&call, &failed, &[]).unwrap();
+        assert_eq!(detection.kind, LoopKind::IdenticalCalls);
+        assert_eq!(detection.count, 3);
+        assert!(detection.note().contains("`Bash` was called 3 times"));
+
+        // Striking starts over
+        assert_eq!(loops.strike(), 1);
+        assert!(loops.record(&call, &failed, &[]).is_none());
+    }
+
+    #[test]
+    fn test_repeated_errors() {
+        let mut loops = detector();
+        let error = ToolResult::error("1", "old_string not found in file\nmore detail");
+        for (i, old) in ["a", "b"].iter().enumerate() {
+            let call = ToolCall::new(i.to_string(), "Edit", json!({"old_string": old}));
+            assert!(loops.record(&call, &error, &[]).is_none());
+        }
+
+        let call = ToolCall::new("3", "Edit", json!({"old_string": "c"}));
+        let detection = loops.record(&call, &error, &[]).unwrap();
+        assert_eq!(detection.kind, LoopKind::RepeatedErrors);
+        assert_eq!(detection.detail, "old_string not found in file");
+    }
+
+    #[test]
+    fn test_oscillating_edits() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("lib.rs");
+        let mut loops = detector();
+
+        let mut detections = Vec::new();
+        for (i, content) in ["a", "b", "a", "b"].iter().enumerate() {
+            std::fs::write(&path, content).unwrap();
+            let call = ToolCall::new(i.to_string(), "Write", json!({"content": content, "n": i}));
+            let result = ToolResult::success(i.to_string(), "Wrote file");
+            detections.push(loops.record(&call, &result, std::slice::from_ref(&path)));
+        }
+
+        assert!(detections[..3].iter().all(Option::is_none));
+        let detection = detections[3].as_ref().unwrap();
+        assert_eq!(detection.kind, LoopKind::OscillatingEdits);
+        assert_eq!(detection.detail, path.display().to_string());
+    }
+}
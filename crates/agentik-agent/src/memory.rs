@@ -0,0 +1,626 @@
+//! Project memory.
+//!
+//! Facts worth keeping across sessions (build commands, conventions,
+//! gotchas) are saved to a [`MemoryStore`] per project, keyed by the
+//! repository root. The model manages them with the `Remember`, `Recall` and
+//! `Forget` tools, and the memories most relevant to the conversation are
+//! added to the system prompt under a token budget.
+//!
+//! Each project's memories are a JSON file named by a hash of its root:
+//!
+//! ```text
+//! <dir>/<hash>.json
+//! <dir>/<hash>.lock       # held while the memories are changed
+//! ```
+//!
+//! Several sessions can share a project, so each change re-reads the file
+//! under the lock and applies to the latest memories.
+
+use std::cmp::Reverse;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use agentik_core::config::MemoryConfig;
+use agentik_core::tool::ToolCategory;
+use agentik_core::{SessionMetadata, ToolCall, ToolDefinition, ToolResult};
+use agentik_tools::{Tool, ToolContext, ToolError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::checkpoint::write_atomic;
+
+/// Length of memory ids.
+const ID_LEN: usize = 8;
+
+/// Length of the project hash naming a store's file.
+const PROJECT_HASH_LEN: usize = 16;
+
+/// Memories returned by `Recall` when no limit is given.
+const DEFAULT_RECALL_LIMIT: usize = 20;
+
+/// Characters per token when fitting memories into the prompt budget.
+const CHARS_PER_TOKEN: usize = 4;
+
+// ============================================================================
+// Memory Store
+// ============================================================================
+
+/// A remembered fact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    /// Short id used to edit or forget it
+    pub id: String,
+    /// The fact
+    pub content: String,
+    /// Keywords it can be recalled by
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When it was saved
+    pub created_at: DateTime<Utc>,
+    /// When it was last saved or edited
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Memory {
+    /// Render as a list item.
+    fn line(&self) -> String {
+        if self.tags.is_empty() {
+            format!("- [{}] {}", self.id, self.content)
+        } else {
+            format!(
+                "- [{}] {} (tags: {})",
+                self.id,
+                self.content,
+                self.tags.join(", ")
+            )
+        }
+    }
+
+    /// How many of the terms it mentions.
+    fn relevance(&self, terms: &[String]) -> usize {
+        let content = self.content.to_lowercase();
+        terms
+            .iter()
+            .filter(|term| {
+                content.contains(term.as_str())
+                    || self.tags.iter().any(|t| t.to_lowercase() == **term)
+            })
+            .count()
+    }
+}
+
+/// On-disk form of a project's memories.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MemoryFile {
+    project: PathBuf,
+    memories: Vec<Memory>,
+}
+
+/// Memories of one project.
+pub struct MemoryStore {
+    path: PathBuf,
+    project: PathBuf,
+    config: MemoryConfig,
+    memories: RwLock<Vec<Memory>>,
+}
+
+impl MemoryStore {
+    /// Open the memories of a project from a directory of stores.
+    pub fn open(
+        dir: impl AsRef<Path>,
+        project: impl Into<PathBuf>,
+        config: MemoryConfig,
+    ) -> io::Result<Self> {
+        let project = project.into();
+        let hash = format!("{:x}", Sha256::digest(project.to_string_lossy().as_bytes()));
+        let path = dir
+            .as_ref()
+            .join(format!("{}.json", &hash[..PROJECT_HASH_LEN]));
+        let memories = Self::read(&path)?;
+        debug!(project = %project.display(), count = memories.len(), "Opened memory store");
+        Ok(Self {
+            path,
+            project,
+            config,
+            memories: RwLock::new(memories),
+        })
+    }
+
+    /// The root of a session's project: its repository root, or the nearest
+    /// ancestor of the working directory with a `.git`, or the working
+    /// directory itself.
+    pub fn project_root(metadata: &SessionMetadata) -> PathBuf {
+        if let Some(git) = &metadata.git {
+            return git.repository.clone();
+        }
+        let working_dir = &metadata.working_directory;
+        working_dir
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .unwrap_or(working_dir)
+            .to_path_buf()
+    }
+
+    /// Get the project root.
+    pub fn project(&self) -> &Path {
+        &self.project
+    }
+
+    /// Get the memory configuration.
+    pub fn config(&self) -> &MemoryConfig {
+        &self.config
+    }
+
+    /// List all memories, oldest first.
+    pub fn list(&self) -> Vec<Memory> {
+        self.memories.read().unwrap().clone()
+    }
+
+    /// Save a fact. Saving one already remembered refreshes it and adds any
+    /// new tags.
+    pub fn remember(&self, content: &str, tags: Vec<String>) -> io::Result<Memory> {
+        let content = content.trim();
+        let now = Utc::now();
+        self.modify(
+            |memories| match memories.iter_mut().find(|m| m.content == content) {
+                Some(memory) => {
+                    for tag in tags {
+                        if !memory.tags.contains(&tag) {
+                            memory.tags.push(tag);
+                        }
+                    }
+                    memory.updated_at = now;
+                    memory.clone()
+                }
+                None => {
+                    let memory = Memory {
+                        id: uuid::Uuid::new_v4().simple().to_string()[..ID_LEN].to_string(),
+                        content: content.to_string(),
+                        tags,
+                        created_at: now,
+                        updated_at: now,
+                    };
+                    memories.push(memory.clone());
+                    memory
+                }
+            },
+        )
+    }
+
+    /// Change a memory's content. Returns the updated memory, or `None` if
+    /// there is none with the id.
+    pub fn update(&self, id: &str, content: &str) -> io::Result<Option<Memory>> {
+        self.modify(|memories| {
+            let memory = memories.iter_mut().find(|m| m.id == id)?;
+            memory.content = content.trim().to_string();
+            memory.updated_at = Utc::now();
+            Some(memory.clone())
+        })
+    }
+
+    /// Delete a memory. Returns it, or `None` if there is none with the id.
+    pub fn forget(&self, id: &str) -> io::Result<Option<Memory>> {
+        self.modify(|memories| {
+            let index = memories.iter().position(|m| m.id == id)?;
+            Some(memories.remove(index))
+        })
+    }
+
+    /// Find memories mentioning words of a query, best match first. An
+    /// empty query returns the most recent ones.
+    pub fn recall(&self, query: &str, limit: usize) -> Vec<Memory> {
+        let terms = terms(query);
+        let mut ranked = self.ranked(&terms);
+        if !terms.is_empty() {
+            ranked.retain(|(relevance, _)| *relevance > 0);
+        }
+        ranked.into_iter().take(limit).map(|(_, m)| m).collect()
+    }
+
+    /// Render the memories most relevant to some text for the system
+    /// prompt, within the configured token budget.
+    pub fn render(&self, context: &str) -> Option<String> {
+        let budget = self.config.prompt_tokens * CHARS_PER_TOKEN;
+        let ranked = self.ranked(&terms(context));
+        let total = ranked.len();
+
+        let mut lines = Vec::new();
+        let mut used = 0;
+        for (_, memory) in ranked {
+            let line = memory.line();
+            if used + line.len() > budget {
+                break;
+            }
+            used += line.len() + 1;
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            return None;
+        }
+
+        let mut output = String::from(
+            "<memory>\nFacts remembered about this project from earlier sessions. Save new \
+             ones with Remember, and Forget any that turn out to be wrong or out of date.\n",
+        );
+        output.push_str(&lines.join("\n"));
+        if lines.len() < total {
+            output.push_str(&format!(
+                "\n({} more; use Recall to search them)",
+                total - lines.len()
+            ));
+        }
+        output.push_str("\n</memory>");
+        Some(output)
+    }
+
+    /// All memories with their relevance to some terms, most relevant and
+    /// then most recent first.
+    fn ranked(&self, terms: &[String]) -> Vec<(usize, Memory)> {
+        let mut ranked: Vec<_> = self
+            .memories
+            .read()
+            .unwrap()
+            .iter()
+            .map(|m| (m.relevance(terms), m.clone()))
+            .collect();
+        ranked.sort_by_key(|(relevance, m)| (Reverse(*relevance), Reverse(m.updated_at)));
+        ranked
+    }
+
+    /// Change the memories and save them.
+    ///
+    /// The change applies to the memories on disk, re-read under an exclusive
+    /// lock, so changes made by other sessions since this store was opened
+    /// aren't lost.
+    fn modify<T>(&self, change: impl FnOnce(&mut Vec<Memory>) -> T) -> io::Result<T> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // The lock is released when the file is closed
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_extension("lock"))?;
+        lock.lock_exclusive()?;
+
+        let mut memories = Self::read(&self.path)?;
+        let result = change(&mut memories);
+        let file = MemoryFile {
+            project: self.project.clone(),
+            memories,
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_atomic(&self.path, json.as_bytes())?;
+        *self.memories.write().unwrap() = file.memories;
+        Ok(result)
+    }
+
+    /// Read the memories saved in a file (none if it doesn't exist).
+    fn read(path: &Path) -> io::Result<Vec<Memory>> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str::<MemoryFile>(&json)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                .memories),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Distinct lowercase words of a text worth matching on.
+fn terms(text: &str) -> Vec<String> {
+    let mut terms: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+// ============================================================================
+// Memory Tools
+// ============================================================================
+
+/// Tool the model uses to save a fact about the project.
+///
+/// Memories outlast the session and end up in later system prompts, so the
+/// tool is destructive: outside autonomous mode, saving one needs approval.
+pub struct RememberTool {
+    store: Arc<MemoryStore>,
+}
+
+impl RememberTool {
+    /// Create the tool backed by a memory store.
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for RememberTool {
+    fn name(&self) -> &str {
+        "Remember"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "Remember",
+            "Save a fact about this project for future sessions, such as how to build \
+             or test it, a convention to follow, or a pitfall you ran into. Keep each \
+             memory to one short, self-contained fact.",
+        )
+        .with_parameters(json!({
+            "type": "object",
+            "properties": {
+                "content": {
+                    "type": "string",
+                    "description": "The fact to remember."
+                },
+                "tags": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Keywords to recall it by, e.g. build, test, style."
+                }
+            },
+            "required": ["content"]
+        }))
+        .with_category(ToolCategory::External)
+        .destructive()
+    }
+
+    async fn execute(&self, call: &ToolCall, _ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let content = call
+            .arguments
+            .get("content")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| ToolError::MissingParameter("content".into()))?;
+        let tags = call
+            .arguments
+            .get("tags")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|t| t.trim().to_lowercase())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let memory = self.store.remember(content, tags)?;
+        Ok(ToolResult::success(
+            &call.id,
+            format!("Remembered [{}]: {}", memory.id, memory.content),
+        ))
+    }
+}
+
+/// Tool the model uses to search the project's memories.
+pub struct RecallTool {
+    store: Arc<MemoryStore>,
+}
+
+impl RecallTool {
+    /// Create the tool backed by a memory store.
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for RecallTool {
+    fn name(&self) -> &str {
+        "Recall"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "Recall",
+            "Search the facts remembered about this project in earlier sessions. \
+             Without a query, lists the most recent ones.",
+        )
+        .with_parameters(json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Words to search for."
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of memories to return. Default is 20."
+                }
+            }
+        }))
+        .with_category(ToolCategory::Agent)
+    }
+
+    async fn execute(&self, call: &ToolCall, _ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let query = call
+            .arguments
+            .get("query")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let limit = call
+            .arguments
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_RECALL_LIMIT);
+
+        let memories = self.store.recall(query, limit);
+        let output = if !memories.is_empty() {
+            memories
+                .iter()
+                .map(Memory::line)
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else if query.trim().is_empty() {
+            "No memories saved for this project.".to_string()
+        } else {
+            format!("No memories match \"{}\".", query)
+        };
+        Ok(ToolResult::success(&call.id, output))
+    }
+}
+
+/// Tool the model uses to delete a memory that is wrong or out of date.
+///
+/// Destructive, like [`RememberTool`].
+pub struct ForgetTool {
+    store: Arc<MemoryStore>,
+}
+
+impl ForgetTool {
+    /// Create the tool backed by a memory store.
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for ForgetTool {
+    fn name(&self) -> &str {
+        "Forget"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "Forget",
+            "Delete a remembered fact that is wrong or out of date.",
+        )
+        .with_parameters(json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "description": "Id of the memory, as shown in brackets."
+                }
+            },
+            "required": ["id"]
+        }))
+        .with_category(ToolCategory::External)
+        .destructive()
+    }
+
+    async fn execute(&self, call: &ToolCall, _ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let id = call
+            .arguments
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::MissingParameter("id".into()))?;
+        let id = id.trim().trim_start_matches('[').trim_end_matches(']');
+
+        let memory = self
+            .store
+            .forget(id)?
+            .ok_or_else(|| ToolError::NotFound(format!("memory {}", id)))?;
+        Ok(ToolResult::success(
+            &call.id,
+            format!("Forgot [{}]: {}", memory.id, memory.content),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn open(dir: &Path, prompt_tokens: usize) -> MemoryStore {
+        let config = MemoryConfig {
+            prompt_tokens,
+            ..Default::default()
+        };
+        MemoryStore::open(dir, "/repo", config).unwrap()
+    }
+
+    #[test]
+    fn test_store_persists() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let store = open(dir, 1_000);
+
+        let build = store
+            .remember("Build with `make release`", vec!["build".into()])
+            .unwrap();
+        let style = store.remember("Use tabs in Go files", vec![]).unwrap();
+        // Saving a fact again refreshes it rather than duplicating it
+        let again = store
+            .remember("Build with `make release`", vec!["ci".into()])
+            .unwrap();
+        assert_eq!(again.id, build.id);
+        assert_eq!(again.tags, vec!["build", "ci"]);
+
+        store.update(&style.id, "Use gofmt").unwrap().unwrap();
+        assert!(store.update("missing", "x").unwrap().is_none());
+
+        // Another project doesn't see them; reopening does
+        let other = MemoryStore::open(dir, "/other", MemoryConfig::default()).unwrap();
+        assert!(other.list().is_empty());
+        let reopened = open(dir, 1_000);
+        let contents: Vec<_> = reopened.list().into_iter().map(|m| m.content).collect();
+        assert_eq!(contents, vec!["Build with `make release`", "Use gofmt"]);
+
+        assert_eq!(reopened.forget(&style.id).unwrap().unwrap().id, style.id);
+        assert_eq!(open(dir, 1_000).list().len(), 1);
+    }
+
+    #[test]
+    fn test_sessions_keep_each_others_changes() {
+        let temp = TempDir::new().unwrap();
+        let first = open(temp.path(), 1_000);
+        let second = open(temp.path(), 1_000);
+
+        let build = first.remember("Build with make", vec![]).unwrap();
+        second.remember("Use tabs", vec![]).unwrap();
+        first.remember("Run tests with make test", vec![]).unwrap();
+        second.forget(&build.id).unwrap().unwrap();
+
+        let contents: Vec<_> = open(temp.path(), 1_000)
+            .list()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["Use tabs", "Run tests with make test"]);
+        assert_eq!(first.list().len(), 3);
+        assert_eq!(second.list().len(), 2);
+    }
+
+    #[test]
+    fn test_recall_and_render() {
+        let dir = TempDir::new().unwrap();
+        let store = open(dir.path(), 20);
+        store
+            .remember("Tests need a running postgres", vec!["test".into()])
+            .unwrap();
+        store
+            .remember("Release notes live in docs/", vec![])
+            .unwrap();
+
+        let found = store.recall("how do I run the test suite", 10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].content, "Tests need a running postgres");
+        assert_eq!(store.recall("", 10).len(), 2);
+        assert!(store.recall("kubernetes", 10).is_empty());
+
+        // The budget fits one memory, and the relevant one goes first
+        let rendered = store.render("fix the failing test").unwrap();
+        assert!(rendered.contains("Tests need a running postgres"));
+        assert!(!rendered.contains("Release notes"));
+        assert!(rendered.contains("(1 more; use Recall to search them)"));
+        assert!(open(dir.path(), 0).render("test").is_none());
+    }
+}
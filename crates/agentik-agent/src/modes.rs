@@ -13,8 +13,59 @@ pub enum AgentMode {
     Planning,
     /// Supervised - asks before each action
     Supervised,
-    /// Architect - high-level design without implementation
+    /// Architect - an architect model proposes changes, an editor model implements them
     Architect,
     /// Ask-only - no code modifications
     AskOnly,
+    /// Dry run - file changes are staged for review instead of written to disk
+    DryRun,
+}
+
+impl AgentMode {
+    /// How much the mode lets the agent do on its own, from ask-only (0) to
+    /// autonomous.
+    fn permission_level(self) -> u8 {
+        match self {
+            AgentMode::AskOnly => 0,
+            AgentMode::Planning => 1,
+            AgentMode::DryRun => 2,
+            AgentMode::Supervised | AgentMode::Architect => 3,
+            AgentMode::Autonomous => 4,
+        }
+    }
+
+    /// This mode, unless it is more permissive than `limit`, in which case
+    /// `limit`.
+    ///
+    /// Used where a mode is requested on behalf of something less trusted
+    /// than the user, e.g. a sub-agent or a custom command. Under a dry run
+    /// everything but ask-only stays a dry run, so edits are still staged.
+    pub fn limited_to(self, limit: AgentMode) -> AgentMode {
+        if self.permission_level() > limit.permission_level()
+            || (limit == AgentMode::DryRun && self != AgentMode::AskOnly)
+        {
+            limit
+        } else {
+            self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limited_to_never_escalates() {
+        use AgentMode::*;
+
+        assert_eq!(Autonomous.limited_to(Supervised), Supervised);
+        assert_eq!(Supervised.limited_to(Planning), Planning);
+        assert_eq!(Supervised.limited_to(AskOnly), AskOnly);
+        assert_eq!(AskOnly.limited_to(Autonomous), AskOnly);
+        assert_eq!(Planning.limited_to(Supervised), Planning);
+        assert_eq!(Supervised.limited_to(DryRun), DryRun);
+        assert_eq!(Planning.limited_to(DryRun), DryRun);
+        assert_eq!(AskOnly.limited_to(DryRun), AskOnly);
+    }
 }
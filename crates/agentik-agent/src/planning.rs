@@ -1,7 +1,140 @@
 //! Planning mode implementation.
+//!
+//! In planning mode the model submits a structured [`Plan`] through the
+//! `UpdatePlan` tool instead of making changes. The user reviews, edits and
+//! approves the plan; [`Agent::execute_plan`](crate::Agent::execute_plan)
+//! then runs it one step at a time, and a failed step sends the model back to
+//! planning mode to revise the remaining steps.
 
-// TODO: Implement planning mode
-// This is a stub for the initial project structure.
+use std::sync::{Arc, RwLock};
+
+use agentik_core::Plan;
+
+/// Outcome of executing a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanOutcome {
+    /// Every step completed (or was skipped).
+    Completed,
+    /// A step failed and the model revised the plan; it needs approval again.
+    Revised {
+        /// Index of the step that failed.
+        failed_step: usize,
+    },
+    /// A step failed and the model did not revise the plan.
+    Failed {
+        /// Index of the step that failed.
+        failed_step: usize,
+    },
+}
 
 /// Planning mode state.
-pub struct PlanningState;
+///
+/// Holds the current plan, shared with the `UpdatePlan` tool so the model can
+/// create and update it during a turn.
+#[derive(Debug, Clone, Default)]
+pub struct PlanningState {
+    plan: Arc<RwLock<Option<Plan>>>,
+}
+
+impl PlanningState {
+    /// Create planning state, optionally restoring a persisted plan.
+    pub fn new(plan: Option<Plan>) -> Self {
+        Self {
+            plan: Arc::new(RwLock::new(plan)),
+        }
+    }
+
+    /// Get the shared plan reference (for `UpdatePlanTool`).
+    pub fn shared(&self) -> Arc<RwLock<Option<Plan>>> {
+        Arc::clone(&self.plan)
+    }
+
+    /// Get a copy of the current plan.
+    pub fn plan(&self) -> Option<Plan> {
+        self.plan.read().unwrap().clone()
+    }
+
+    /// Replace the current plan.
+    pub fn set_plan(&self, plan: Option<Plan>) {
+        *self.plan.write().unwrap() = plan;
+    }
+
+    /// Modify the current plan in place, if there is one.
+    pub fn update<T>(&self, f: impl FnOnce(&mut Plan) -> T) -> Option<T> {
+        self.plan.write().unwrap().as_mut().map(f)
+    }
+
+    /// Build the prompt that asks the model to execute one step.
+    pub fn step_prompt(plan: &Plan, index: usize) -> String {
+        let step = &plan.steps[index];
+        let mut prompt = format!(
+            "Execute step {} of {} of the approved plan for: {}\n\nStep: {}\n",
+            index + 1,
+            plan.steps.len(),
+            plan.goal,
+            step.description
+        );
+        if !step.files.is_empty() {
+            let files: Vec<String> = step.files.iter().map(|p| p.display().to_string()).collect();
+            prompt.push_str(&format!("Files: {}\n", files.join(", ")));
+        }
+        prompt.push_str(&format!(
+            "\nOnly do this step. When done, call UpdatePlan with step {} and status \
+             \"completed\", or \"failed\" with a note explaining what went wrong.",
+            index + 1
+        ));
+        prompt
+    }
+
+    /// Build the prompt that asks the model to revise a plan after a failure.
+    pub fn replan_prompt(plan: &Plan, index: usize) -> String {
+        let step = &plan.steps[index];
+        format!(
+            "Step {} of the plan failed: {}\nReason: {}\n\nCurrent plan:\n{}\n\
+             Investigate the failure and call UpdatePlan with revised steps for the \
+             remaining work (completed steps are kept). Do not make any changes yet.",
+            index + 1,
+            step.description,
+            step.notes.as_deref().unwrap_or("unknown"),
+            plan
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentik_core::{PlanStep, PlanStepStatus};
+
+    #[test]
+    fn test_shared_plan_updates() {
+        let state = PlanningState::default();
+        assert!(state.update(|p| p.approve()).is_none());
+
+        let shared = state.shared();
+        *shared.write().unwrap() = Some(Plan::new("Goal", vec![PlanStep::new("One")]));
+        state.update(|p| p.approve());
+
+        assert!(state.plan().unwrap().approved);
+    }
+
+    #[test]
+    fn test_prompts() {
+        let mut plan = Plan::new(
+            "Add caching",
+            vec![
+                PlanStep::new("Add cache module").with_files(vec!["src/cache.rs".into()]),
+                PlanStep::new("Use cache in handler"),
+            ],
+        );
+
+        let prompt = PlanningState::step_prompt(&plan, 0);
+        assert!(prompt.contains("step 1 of 2"));
+        assert!(prompt.contains("Files: src/cache.rs"));
+
+        plan.set_status(1, PlanStepStatus::Failed, Some("handler missing".into()))
+            .unwrap();
+        let prompt = PlanningState::replan_prompt(&plan, 1);
+        assert!(prompt.contains("Reason: handler missing"));
+    }
+}
@@ -1,7 +1,419 @@
 //! "Anytime" question asking system.
+//!
+//! The model asks the user questions through the `AskUser` tool. Blocking
+//! questions are answered before the tool returns; non-blocking questions are
+//! queued and answered in the background while the agent keeps working, and
+//! their answers are injected into the conversation at the next turn.
+//!
+//! Answers come from [`AgentEventHandler::on_question`]. Handlers that cannot
+//! ask (headless runs) return `None`, and the question's default answer is
+//! used instead, as it is for questions left unanswered past the timeout.
 
-// TODO: Implement question queue
-// This is a stub for the initial project structure.
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use agentik_core::tool::ToolCategory;
+use agentik_core::{ToolCall, ToolDefinition, ToolResult};
+use agentik_tools::{Tool, ToolContext, ToolError};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::task::JoinHandle;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::agent::AgentEventHandler;
+
+/// How long questions wait for an answer by default.
+pub const DEFAULT_QUESTION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Answer used when a question has no options or default.
+const NO_ANSWER: &str = "The user did not answer. Use your best judgement.";
+
+// ============================================================================
+// Questions and Answers
+// ============================================================================
+
+/// A question from the model to the user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Question {
+    /// Unique question ID
+    pub id: String,
+    /// Question text
+    pub text: String,
+    /// Multiple-choice answers (free-form if empty)
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Answer to use if the user doesn't answer
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Whether the agent waits for the answer
+    pub blocking: bool,
+}
+
+impl Question {
+    /// Create a blocking free-form question.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            text: text.into(),
+            options: vec![],
+            default: None,
+            blocking: true,
+        }
+    }
+
+    /// Set multiple-choice answers.
+    pub fn with_options(mut self, options: Vec<String>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the default answer.
+    pub fn with_default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    /// Let the agent keep working while the question is open.
+    pub fn non_blocking(mut self) -> Self {
+        self.blocking = false;
+        self
+    }
+
+    /// The answer used when the user doesn't answer.
+    pub fn default_answer(&self) -> String {
+        self.default
+            .clone()
+            .or_else(|| self.options.first().cloned())
+            .unwrap_or_else(|| NO_ANSWER.to_string())
+    }
+
+    /// Resolve a raw reply, accepting an option number for multiple choice.
+    fn resolve(&self, reply: &str) -> String {
+        let reply = reply.trim();
+        reply
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| self.options.get(i))
+            .cloned()
+            .unwrap_or_else(|| reply.to_string())
+    }
+}
+
+/// The user's answer to a question.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Answer {
+    /// ID of the answered question
+    pub question_id: String,
+    /// Answer text
+    pub text: String,
+    /// Whether the default answer was used
+    pub defaulted: bool,
+}
+
+impl Answer {
+    /// Format a question and its answer for the model.
+    pub fn describe(&self, question: &Question) -> String {
+        if self.defaulted {
+            format!(
+                "No answer to \"{}\"; using default: {}",
+                question.text, self.text
+            )
+        } else {
+            format!("User answered \"{}\": {}", question.text, self.text)
+        }
+    }
+}
+
+// ============================================================================
+// Question Queue
+// ============================================================================
+
+#[derive(Default)]
+struct QueueState {
+    /// Non-blocking questions still waiting for an answer
+    pending: Vec<Question>,
+    /// Answers not yet delivered to the model
+    answered: Vec<(Question, Answer)>,
+}
 
 /// Question queue for AI-initiated questions.
-pub struct QuestionQueue;
+pub struct QuestionQueue {
+    handler: Arc<dyn AgentEventHandler>,
+    timeout: Option<Duration>,
+    state: Arc<Mutex<QueueState>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl QuestionQueue {
+    /// Create a queue that answers questions through an event handler.
+    ///
+    /// With a timeout, questions left unanswered that long take their default.
+    pub fn new(handler: Arc<dyn AgentEventHandler>, timeout: Option<Duration>) -> Self {
+        Self {
+            handler,
+            timeout,
+            state: Arc::new(Mutex::new(QueueState::default())),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Ask a question and wait for the answer.
+    pub async fn ask(&self, question: &Question) -> Answer {
+        answer(self.handler.as_ref(), self.timeout, question).await
+    }
+
+    /// Queue a question and answer it in the background.
+    pub fn ask_later(&self, question: Question) {
+        self.state.lock().unwrap().pending.push(question.clone());
+
+        let handler = Arc::clone(&self.handler);
+        let timeout = self.timeout;
+        let state = Arc::clone(&self.state);
+        let task = tokio::spawn(async move {
+            let answer = answer(handler.as_ref(), timeout, &question).await;
+            let mut state = state.lock().unwrap();
+            state.pending.retain(|q| q.id != question.id);
+            state.answered.push((question, answer));
+        });
+        self.tasks.lock().unwrap().push(task);
+    }
+
+    /// Questions still waiting for an answer.
+    pub fn pending(&self) -> Vec<Question> {
+        self.state.lock().unwrap().pending.clone()
+    }
+
+    /// Take the answers that haven't been delivered yet.
+    pub fn take_answers(&self) -> Vec<(Question, Answer)> {
+        std::mem::take(&mut self.state.lock().unwrap().answered)
+    }
+
+    /// Wait until every queued question has been answered.
+    pub async fn wait_for_pending(&self) {
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for task in tasks {
+            let _ = task.await;
+        }
+    }
+}
+
+/// Get an answer from the handler, falling back to the default.
+async fn answer(
+    handler: &dyn AgentEventHandler,
+    timeout: Option<Duration>,
+    question: &Question,
+) -> Answer {
+    let reply = match timeout {
+        Some(limit) => tokio::time::timeout(limit, handler.on_question(question))
+            .await
+            .unwrap_or_else(|_| {
+                debug!(question = %question.text, "Question timed out");
+                None
+            }),
+        None => handler.on_question(question).await,
+    };
+
+    match reply.filter(|r| !r.trim().is_empty()) {
+        Some(reply) => Answer {
+            question_id: question.id.clone(),
+            text: question.resolve(&reply),
+            defaulted: false,
+        },
+        None => Answer {
+            question_id: question.id.clone(),
+            text: question.default_answer(),
+            defaulted: true,
+        },
+    }
+}
+
+// ============================================================================
+// AskUser Tool
+// ============================================================================
+
+/// Tool the model uses to ask the user a question.
+pub struct AskUserTool {
+    queue: Arc<QuestionQueue>,
+}
+
+impl AskUserTool {
+    /// Create the tool backed by a question queue.
+    pub fn new(queue: Arc<QuestionQueue>) -> Self {
+        Self { queue }
+    }
+}
+
+#[async_trait]
+impl Tool for AskUserTool {
+    fn name(&self) -> &str {
+        "AskUser"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "AskUser",
+            "Ask the user a question when you need a decision or information you cannot \
+             find yourself. Provide options for multiple-choice questions and a default \
+             to use if the user doesn't answer. Set blocking to false to keep working \
+             while the user considers the question; the answer arrives in a later message.",
+        )
+        .with_parameters(json!({
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question to ask."
+                },
+                "options": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Optional multiple-choice answers."
+                },
+                "default": {
+                    "type": "string",
+                    "description": "Answer to use if the user doesn't answer."
+                },
+                "blocking": {
+                    "type": "boolean",
+                    "description": "Wait for the answer before continuing. Default is true."
+                }
+            },
+            "required": ["question"]
+        }))
+        .with_category(ToolCategory::Agent)
+    }
+
+    async fn execute(&self, call: &ToolCall, _ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let args = &call.arguments;
+        let text = args
+            .get("question")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::MissingParameter("question".into()))?;
+
+        let mut question = Question::new(text).with_options(
+            args.get("options")
+                .and_then(|v| v.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|v| v.as_str())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        );
+        if let Some(default) = args.get("default").and_then(|v| v.as_str()) {
+            question = question.with_default(default);
+        }
+        if args.get("blocking").and_then(|v| v.as_bool()) == Some(false) {
+            question = question.non_blocking();
+        }
+
+        if question.blocking {
+            let answer = self.queue.ask(&question).await;
+            Ok(ToolResult::success(&call.id, answer.describe(&question)))
+        } else {
+            self.queue.ask_later(question);
+            Ok(ToolResult::success(
+                &call.id,
+                "Question sent to the user. Continue working; the answer will be \
+                 provided in a later message.",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::NoOpEventHandler;
+
+    struct ScriptedHandler {
+        reply: Option<String>,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl AgentEventHandler for ScriptedHandler {
+        async fn on_question(&self, _question: &Question) -> Option<String> {
+            tokio::time::sleep(self.delay).await;
+            self.reply.clone()
+        }
+    }
+
+    fn scripted_queue(
+        reply: Option<&str>,
+        delay_ms: u64,
+        timeout_ms: Option<u64>,
+    ) -> QuestionQueue {
+        QuestionQueue::new(
+            Arc::new(ScriptedHandler {
+                reply: reply.map(String::from),
+                delay: Duration::from_millis(delay_ms),
+            }),
+            timeout_ms.map(Duration::from_millis),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_blocking_answer_by_option_number() {
+        let queue = scripted_queue(Some("2"), 0, None);
+        let question =
+            Question::new("Which database?").with_options(vec!["sqlite".into(), "postgres".into()]);
+
+        let answer = queue.ask(&question).await;
+        assert_eq!(answer.text, "postgres");
+        assert!(!answer.defaulted);
+    }
+
+    #[tokio::test]
+    async fn test_defaults_when_unanswered() {
+        let queue = QuestionQueue::new(Arc::new(NoOpEventHandler), None);
+        let question = Question::new("Proceed?").with_default("yes");
+        let answer = queue.ask(&question).await;
+        assert_eq!(answer.text, "yes");
+        assert!(answer.defaulted);
+
+        // Timeout falls back to the first option
+        let queue = scripted_queue(Some("b"), 200, Some(10));
+        let question = Question::new("Which?").with_options(vec!["a".into(), "b".into()]);
+        let answer = queue.ask(&question).await;
+        assert_eq!(answer.text, "a");
+        assert!(answer.defaulted);
+    }
+
+    #[tokio::test]
+    async fn test_non_blocking_questions_are_queued() {
+        let queue = scripted_queue(Some("later"), 20, None);
+        queue.ask_later(Question::new("Any naming preference?").non_blocking());
+        assert_eq!(queue.pending().len(), 1);
+        assert!(queue.take_answers().is_empty());
+
+        queue.wait_for_pending().await;
+        assert!(queue.pending().is_empty());
+        let answers = queue.take_answers();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(answers[0].1.text, "later");
+        assert!(queue.take_answers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pending_questions_time_out() {
+        // A handler that never answers in time doesn't hold up the run
+        let queue = scripted_queue(Some("too late"), 10_000, Some(10));
+        queue.ask_later(
+            Question::new("Deploy now?")
+                .with_default("no")
+                .non_blocking(),
+        );
+
+        tokio::time::timeout(Duration::from_secs(1), queue.wait_for_pending())
+            .await
+            .unwrap();
+        let answers = queue.take_answers();
+        assert_eq!(answers[0].1.text, "no");
+        assert!(answers[0].1.defaulted);
+    }
+}
@@ -0,0 +1,69 @@
+//! Mid-run steering.
+//!
+//! While the agent works through a long tool chain, the UI can push messages
+//! onto a [`SteeringQueue`] at any time. They are added to the conversation
+//! as user messages at the next step boundary: tools already running finish
+//! first, and the model sees the messages on its next call. Nothing is
+//! cancelled, so the user can redirect the agent without losing its work.
+//! Messages that arrive after a run has finished are discarded when the next
+//! run starts, since they were meant for the run that ended.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Thread-safe queue of steering messages from the user.
+///
+/// Clones share the same queue, so the UI can keep a handle while the agent
+/// runs.
+#[derive(Debug, Clone, Default)]
+pub struct SteeringQueue {
+    messages: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl SteeringQueue {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a message for the next step.
+    pub fn push(&self, message: impl Into<String>) {
+        self.messages.lock().unwrap().push_back(message.into());
+    }
+
+    /// Whether no messages are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.messages.lock().unwrap().is_empty()
+    }
+
+    /// Number of messages waiting.
+    pub fn len(&self) -> usize {
+        self.messages.lock().unwrap().len()
+    }
+
+    /// Take every waiting message, oldest first.
+    pub fn take(&self) -> Vec<String> {
+        self.messages.lock().unwrap().drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_queue() {
+        let queue = SteeringQueue::new();
+        let ui = queue.clone();
+        std::thread::spawn(move || {
+            ui.push("use tabs");
+            ui.push("and add docs");
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.take(), vec!["use tabs", "and add docs"]);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,357 @@
+//! Sub-agents.
+//!
+//! The `Task` tool spawns a child [`Agent`] to handle a self-contained piece
+//! of work, typically read-only research. The child runs in its own session
+//! (forked from the parent via `parent_session_id`) with a restricted tool
+//! set, so its exploratory reads and searches never enter the parent's
+//! context window; only its final answer is returned. The child's token usage
+//! is recorded in the parent session's metrics, not its own.
+//!
+//! Sub-agents cannot spawn sub-agents of their own, and are held to the
+//! parent's mode and tool restrictions, including read-only phases. Several `Task` calls in one response
+//! run concurrently.
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use agentik_core::config::PermissionsConfig;
+use agentik_core::tool::ToolCategory;
+use agentik_core::{Session, ToolCall, ToolDefinition, ToolResult};
+use agentik_providers::Provider;
+use agentik_session::SessionStore;
+use agentik_tools::{Tool, ToolContext, ToolError, ToolRegistry};
+use async_trait::async_trait;
+use serde_json::json;
+use tracing::info;
+
+use crate::agent::{Agent, AgentConfig, NoOpEventHandler, TurnUsage};
+use crate::executor::{ExecutorBuilder, ExecutorServices, PermissionHandler, ToolExecutor};
+use crate::modes::AgentMode;
+
+/// System prompt for sub-agents.
+const SUBAGENT_PROMPT: &str =
+    "You are a sub-agent working on a task delegated by another agent. Complete the \
+     task using the tools available to you, then reply with a concise, self-contained \
+     answer. Only your final reply is passed back, so include every finding the other \
+     agent needs (file paths, line numbers, names) and nothing else.";
+
+// ============================================================================
+// Shared State
+// ============================================================================
+
+#[derive(Debug, Default)]
+struct Parent {
+    session_id: String,
+    mode: AgentMode,
+    allowed_tools: Option<Vec<String>>,
+    read_only: bool,
+}
+
+/// State shared between an agent and its `Task` tool.
+///
+/// The agent keeps the parent session, mode and tool restrictions up to
+/// date; the tool reports the usage of finished sub-agents back for the
+/// agent to record.
+#[derive(Debug, Clone, Default)]
+pub struct SubAgentState {
+    parent: Arc<RwLock<Parent>>,
+    usage: Arc<Mutex<TurnUsage>>,
+}
+
+impl SubAgentState {
+    /// Set the session, mode and tool restrictions sub-agents are spawned
+    /// from.
+    pub fn set_parent(
+        &self,
+        session_id: &str,
+        mode: AgentMode,
+        allowed_tools: Option<&[String]>,
+        read_only: bool,
+    ) {
+        let mut parent = self.parent.write().unwrap();
+        parent.session_id = session_id.to_string();
+        parent.mode = mode;
+        parent.allowed_tools = allowed_tools.map(<[String]>::to_vec);
+        parent.read_only = read_only;
+    }
+
+    /// Hold sub-agents to non-destructive tools while the parent is, e.g.
+    /// during the architect phase.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.parent.write().unwrap().read_only = read_only;
+    }
+
+    /// Take the usage of sub-agents that finished since the last call.
+    pub fn take_usage(&self) -> TurnUsage {
+        std::mem::take(&mut *self.usage.lock().unwrap())
+    }
+
+    fn record(&self, usage: &TurnUsage) {
+        self.usage.lock().unwrap().accumulate(usage);
+    }
+}
+
+// ============================================================================
+// Task Tool
+// ============================================================================
+
+/// Tool that delegates a task to a sub-agent.
+pub struct TaskTool {
+    provider: Arc<dyn Provider>,
+    store: Arc<dyn SessionStore>,
+    config: AgentConfig,
+    permissions: PermissionsConfig,
+    handler: Arc<dyn PermissionHandler>,
+    max_parallel: usize,
+    services: ExecutorServices,
+    state: SubAgentState,
+}
+
+impl TaskTool {
+    /// Create the tool; sub-agents inherit the parent's provider, store and
+    /// configuration, and the permissions, parallelism and services of its
+    /// executor.
+    pub fn new(
+        provider: Arc<dyn Provider>,
+        store: Arc<dyn SessionStore>,
+        config: AgentConfig,
+        executor: &ToolExecutor,
+        state: SubAgentState,
+    ) -> Self {
+        Self {
+            provider,
+            store,
+            config,
+            permissions: executor.permissions().clone(),
+            handler: executor.handler(),
+            max_parallel: executor.max_parallel(),
+            services: executor.services().clone(),
+            state,
+        }
+    }
+
+    /// Build the sub-agent's tool registry.
+    ///
+    /// Without an explicit list, sub-agents get the read-only built-in tools.
+    fn registry(tools: Option<Vec<String>>) -> Result<ToolRegistry, ToolError> {
+        let builtins = ToolRegistry::with_builtins();
+        let mut registry = ToolRegistry::new();
+        match tools {
+            Some(names) => {
+                for name in names {
+                    let tool = builtins.get(&name).ok_or_else(|| {
+                        ToolError::InvalidArguments(format!("unknown tool for sub-agent: {}", name))
+                    })?;
+                    registry.register(tool);
+                }
+            }
+            None => {
+                for tool in builtins.tools() {
+                    if !tool.definition().is_destructive {
+                        registry.register(Arc::clone(tool));
+                    }
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Pick the sub-agent's mode; it can't be more permissive than the parent's,
+    /// and stages its edits too in a dry run.
+    fn mode(requested: Option<&str>, parent: AgentMode) -> Result<AgentMode, ToolError> {
+        let Some(requested) = requested else {
+            return Ok(parent);
+        };
+        let mode: AgentMode = serde_json::from_value(json!(requested.to_lowercase()))
+            .map_err(|_| ToolError::InvalidArguments(format!("unknown mode: {}", requested)))?;
+        Ok(mode.limited_to(parent))
+    }
+}
+
+#[async_trait]
+impl Tool for TaskTool {
+    fn name(&self) -> &str {
+        "Task"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "Task",
+            "Delegate a self-contained task to a sub-agent with its own context window. \
+             Use it for research that needs many searches and reads (e.g. \"find every \
+             caller of X and how it handles errors\"); only the sub-agent's final answer \
+             is returned, keeping your context small. The sub-agent cannot see this \
+             conversation, so the prompt must contain everything it needs. By default it \
+             can only use read-only tools. Call Task several times in one response to run \
+             sub-agents concurrently.",
+        )
+        .with_parameters(json!({
+            "type": "object",
+            "properties": {
+                "description": {
+                    "type": "string",
+                    "description": "Short (3-5 word) description of the task."
+                },
+                "prompt": {
+                    "type": "string",
+                    "description": "Detailed instructions for the sub-agent."
+                },
+                "tools": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Built-in tools the sub-agent may use. Defaults to the read-only tools."
+                },
+                "model": {
+                    "type": "string",
+                    "description": "Model for the sub-agent. Defaults to the current model."
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["autonomous", "supervised", "planning", "askonly"],
+                    "description": "Mode for the sub-agent. Defaults to the current mode."
+                }
+            },
+            "required": ["description", "prompt"]
+        }))
+        .with_category(ToolCategory::Agent)
+    }
+
+    async fn execute(&self, call: &ToolCall, ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        let args = &call.arguments;
+        let description = args
+            .get("description")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::MissingParameter("description".into()))?;
+        let prompt = args
+            .get("prompt")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::MissingParameter("prompt".into()))?;
+        let tools = args.get("tools").and_then(|v| v.as_array()).map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect()
+        });
+
+        let (parent_id, parent_mode, allowed_tools, read_only) = {
+            let parent = self.state.parent.read().unwrap();
+            (
+                parent.session_id.clone(),
+                parent.mode,
+                parent.allowed_tools.clone(),
+                parent.read_only,
+            )
+        };
+        let mode = Self::mode(args.get("mode").and_then(|v| v.as_str()), parent_mode)?;
+        let registry = Self::registry(tools)?;
+        if read_only {
+            if let Some(tool) = registry.tools().find(|t| t.definition().is_destructive) {
+                return Err(ToolError::InvalidArguments(format!(
+                    "sub-agents can't use {} while the agent is read-only",
+                    tool.name()
+                )));
+            }
+        }
+
+        // Fork a session for the sub-agent
+        let mut session = Session::new(ctx.working_dir.clone());
+        session.metadata.parent_session_id = Some(parent_id);
+        session.metadata.title = Some(description.to_string());
+        self.store
+            .create(&session)
+            .await
+            .map_err(|e| ToolError::execution(format!("failed to create session: {}", e)))?;
+
+        let mut builder = ExecutorBuilder::new()
+            .with_registry(registry)
+            .working_dir(&ctx.working_dir)
+            .permissions(self.permissions.clone())
+            .mode(mode)
+            .max_parallel(self.max_parallel)
+            .services(self.services.clone());
+        if let Some(tools) = allowed_tools {
+            builder = builder.allowed_tools(tools);
+        }
+        let mut executor = builder.build(Arc::clone(&self.handler));
+        executor.set_read_only(read_only);
+
+        // The parent verifies once the sub-agent's work is back
+        let mut config = AgentConfig {
+            system_prompt: Some(SUBAGENT_PROMPT.to_string()),
+            verify: Default::default(),
+            ..self.config.clone()
+        };
+        if let Some(model) = args.get("model").and_then(|v| v.as_str()) {
+            config.model = model.to_string();
+        }
+
+        info!(task = description, model = %config.model, ?mode, "Starting sub-agent");
+        let mut agent = Agent::new(
+            Arc::clone(&self.provider),
+            executor,
+            Arc::clone(&self.store),
+            session,
+            config,
+            Arc::new(NoOpEventHandler),
+        );
+        agent.set_mode(mode);
+
+        let response = agent
+            .run(prompt)
+            .await
+            .map_err(|e| ToolError::execution(format!("sub-agent failed: {}", e)))?;
+        self.state.record(&response.total_usage);
+
+        let answer = if response.content.trim().is_empty() {
+            "The sub-agent finished without an answer.".to_string()
+        } else {
+            response.content
+        };
+        Ok(ToolResult::success(&call.id, answer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_registry_is_read_only() {
+        let registry = TaskTool::registry(None).unwrap();
+        assert!(registry.contains("Read"));
+        assert!(registry.contains("Grep"));
+        assert!(!registry.contains("Write"));
+        assert!(!registry.contains("Bash"));
+
+        let registry = TaskTool::registry(Some(vec!["Edit".into()])).unwrap();
+        assert_eq!(registry.list(), vec!["Edit"]);
+        assert!(TaskTool::registry(Some(vec!["Task".into()])).is_err());
+    }
+
+    #[test]
+    fn test_mode_cannot_escalate() {
+        let mode = |requested, parent| TaskTool::mode(requested, parent).unwrap();
+        assert_eq!(mode(None, AgentMode::Supervised), AgentMode::Supervised);
+        assert_eq!(
+            mode(Some("autonomous"), AgentMode::Supervised),
+            AgentMode::Supervised
+        );
+        assert_eq!(
+            mode(Some("askonly"), AgentMode::Autonomous),
+            AgentMode::AskOnly
+        );
+        assert_eq!(
+            mode(Some("supervised"), AgentMode::DryRun),
+            AgentMode::DryRun
+        );
+        assert_eq!(mode(Some("askonly"), AgentMode::DryRun), AgentMode::AskOnly);
+        assert_eq!(
+            mode(Some("supervised"), AgentMode::Planning),
+            AgentMode::Planning
+        );
+        assert_eq!(
+            mode(Some("supervised"), AgentMode::AskOnly),
+            AgentMode::AskOnly
+        );
+        assert!(TaskTool::mode(Some("bogus"), AgentMode::Autonomous).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Automatic verification.
+//!
+//! After a turn that edits files, the agent runs the checks configured in
+//! [`VerifyConfig`] (e.g. `cargo check`, `npm run lint`) in the working
+//! directory; a check passes when its command exits successfully. When a
+//! check fails, the failures are pulled out of its output and
+//! sent back to the model as a follow-up message, and the model gets another
+//! turn to fix them, up to `max_attempts` times.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use agentik_core::config::VerifyConfig;
+use tokio::process::Command;
+use tracing::{debug, info};
+
+/// Maximum number of output lines reported for a failed check.
+const MAX_FAILURE_LINES: usize = 40;
+
+/// Context lines kept after each failure line.
+const FAILURE_CONTEXT_LINES: usize = 2;
+
+/// Markers of failure lines in compiler, test runner and linter output.
+const FAILURE_MARKERS: &[&str] = &[
+    "error",
+    "Error",
+    "ERROR",
+    "FAILED",
+    "FAIL ",
+    "failed",
+    "panicked at",
+    "Traceback",
+    "✖",
+    "✗",
+];
+
+// ============================================================================
+// Results
+// ============================================================================
+
+/// Result of running one check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    /// The command that ran
+    pub command: String,
+    /// Whether it succeeded
+    pub passed: bool,
+    /// Full output
+    pub output: String,
+    /// Lines describing the failures (empty if the check passed)
+    pub failures: Vec<String>,
+}
+
+/// Result of one verification run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyReport {
+    /// Repair attempts made before this run (0 for the first run)
+    pub attempt: u32,
+    /// Results of the checks, in the configured order
+    pub checks: Vec<CheckResult>,
+}
+
+impl VerifyReport {
+    /// Whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    /// The follow-up message asking the model to fix the failures.
+    pub fn feedback(&self) -> String {
+        let mut message = String::from(
+            "Verification failed after your changes. Fix the problems below, then \
+             stop; the checks run again automatically.",
+        );
+        for check in self.checks.iter().filter(|c| !c.passed) {
+            message.push_str(&format!(
+                "\n\n<verify command=\"{}\">\n{}\n</verify>",
+                check.command,
+                check.failures.join("\n")
+            ));
+        }
+        message
+    }
+}
+
+// ============================================================================
+// Verifier
+// ============================================================================
+
+/// Runs the configured checks.
+pub struct Verifier {
+    config: VerifyConfig,
+    working_dir: PathBuf,
+}
+
+impl Verifier {
+    /// Create a verifier running checks in `working_dir`.
+    pub fn new(config: VerifyConfig, working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            config,
+            working_dir: working_dir.into(),
+        }
+    }
+
+    /// Maximum repair attempts after a failed check.
+    pub fn max_attempts(&self) -> u32 {
+        self.config.max_attempts
+    }
+
+    /// Run every check, stopping at the first failure.
+    ///
+    /// Later checks (e.g. tests after a build) usually fail for the same
+    /// reason, so their output would only add noise.
+    pub async fn run(&self, attempt: u32) -> VerifyReport {
+        let mut checks = Vec::new();
+        for command in &self.config.commands {
+            let check = self.check(command).await;
+            let passed = check.passed;
+            checks.push(check);
+            if !passed {
+                break;
+            }
+        }
+        VerifyReport { attempt, checks }
+    }
+
+    async fn check(&self, command: &str) -> CheckResult {
+        debug!(command, "Running verification check");
+        let (passed, output) = match self.execute(command).await {
+            Ok(output) => {
+                let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if !stderr.is_empty() {
+                    if !text.is_empty() && !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    text.push_str(&stderr);
+                }
+                (output.status.success(), text)
+            }
+            Err(e) => (false, e),
+        };
+        info!(command, passed, "Verification check finished");
+
+        let failures = if passed {
+            vec![]
+        } else {
+            extract_failures(&output)
+        };
+        CheckResult {
+            command: command.to_string(),
+            passed,
+            output,
+            failures,
+        }
+    }
+
+    /// Run a command, killing it if it outlives the timeout.
+    async fn execute(&self, command: &str) -> Result<std::process::Output, String> {
+        let child = Command::new("bash")
+            .arg("-c")
+            .arg(command)
+            .current_dir(&self.working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("failed to run check: {}", e))?;
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(output) => output.map_err(|e| format!("failed to run check: {}", e)),
+            Err(_) => Err(format!(
+                "check timed out after {} seconds",
+                self.config.timeout_secs
+            )),
+        }
+    }
+}
+
+/// Pull the lines describing failures out of a check's output.
+///
+/// Keeps lines with a failure marker plus a little context after each; if
+/// nothing matches, falls back to the end of the output.
+pub fn extract_failures(output: &str) -> Vec<String> {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut keep = vec![false; lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        if FAILURE_MARKERS.iter().any(|m| line.contains(m)) {
+            let end = (i + FAILURE_CONTEXT_LINES + 1).min(lines.len());
+            keep[i..end].iter_mut().for_each(|k| *k = true);
+        }
+    }
+
+    let mut failures: Vec<String> = lines
+        .iter()
+        .zip(&keep)
+        .filter(|(_, keep)| **keep)
+        .map(|(line, _)| line.to_string())
+        .take(MAX_FAILURE_LINES)
+        .collect();
+    if failures.is_empty() {
+        let start = lines.len().saturating_sub(MAX_FAILURE_LINES);
+        failures = lines[start..].iter().map(|l| l.to_string()).collect();
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verifier(commands: &[&str]) -> Verifier {
+        Verifier::new(
+            VerifyConfig {
+                commands: commands.iter().map(|c| c.to_string()).collect(),
+                ..Default::default()
+            },
+            std::env::temp_dir(),
+        )
+    }
+
+    #[test]
+    fn test_extract_failures() {
+        let output = "\
+   Compiling demo v0.1.0
+error[E0425]: cannot find value `x` in this scope
+ --> src/main.rs:2:13
+  |
+2 |     println!(\"{}\", x);
+warning: unused import
+error: could not compile `demo`";
+        let failures = extract_failures(output);
+        assert_eq!(
+            failures,
+            vec![
+                "error[E0425]: cannot find value `x` in this scope",
+                " --> src/main.rs:2:13",
+                "  |",
+                "error: could not compile `demo`",
+            ]
+        );
+
+        // Without markers, the end of the output is used
+        assert_eq!(
+            extract_failures("one\ntwo\nexit 1"),
+            vec!["one", "two", "exit 1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_at_first_failure() {
+        let report = verifier(&["echo ok", "echo 'error: broken' >&2; exit 1", "echo never"])
+            .run(0)
+            .await;
+        assert!(!report.passed());
+        assert_eq!(report.checks.len(), 2);
+        assert!(report.checks[0].passed);
+        assert_eq!(report.checks[1].failures[0], "error: broken");
+
+        let feedback = report.feedback();
+        assert!(feedback.contains("<verify command=\"echo 'error: broken' >&2; exit 1\">"));
+        assert!(!feedback.contains("echo ok"));
+
+        assert!(verifier(&["true"]).run(0).await.passed());
+    }
+
+    #[tokio::test]
+    async fn test_exit_status_decides() {
+        // Output that looks like a failure doesn't fail a check
+        assert!(verifier(&["echo '[exit code: 1]'"]).run(0).await.passed());
+
+        // Nor does a long output hide the exit status
+        let report = verifier(&["seq 1 200000; exit 3"]).run(0).await;
+        assert!(!report.passed());
+        assert!(report.checks[0].output.ends_with("200000\n"));
+
+        let report = Verifier::new(
+            VerifyConfig {
+                commands: vec!["sleep 5".to_string()],
+                timeout_secs: 1,
+                ..Default::default()
+            },
+            std::env::temp_dir(),
+        )
+        .run(0)
+        .await;
+        assert!(!report.passed());
+        assert_eq!(
+            report.checks[0].failures,
+            vec!["check timed out after 1 seconds"]
+        );
+    }
+}
@@ -5,8 +5,10 @@ use std::sync::Arc;
 
 use futures::StreamExt;
 
-use agentik_core::Message;
-use agentik_providers::CompletionRequest;
+use chrono::Utc;
+
+use agentik_core::{Config, Message};
+use agentik_providers::{adapt_request, Adaptation, CompletionRequest};
 
 use crate::{AppContext, Cli};
 
@@ -47,16 +49,42 @@ pub async fn run(prompt: &str, cli: &Cli, ctx: &Arc<AppContext>) -> anyhow::Resu
     // Build the request
     let messages = vec![Message::user(prompt)];
 
-    let request = CompletionRequest {
+    let mut request = CompletionRequest {
         model,
         messages,
         max_tokens: ctx.config.limits.max_tokens,
-        temperature: 0.7,
+        temperature: Some(0.7),
         system: None,
         tools: vec![],
         stop: vec![],
     };
 
+    if let Some(info) = provider
+        .available_models()
+        .into_iter()
+        .find(|m| m.id == request.model)
+    {
+        // Rough estimate; print mode only sends a single prompt
+        let estimated = (prompt.len() / 4) as u32;
+        for adaptation in adapt_request(&mut request, &info, estimated) {
+            if matches!(adaptation, Adaptation::ContextExceeded { .. }) {
+                eprintln!("Warning: {}", adaptation);
+            }
+        }
+    }
+
+    // Print mode has no session, so give each run its own wire log
+    if let Some(wire_log) = ctx.registry.wire_log() {
+        let path = Config::data_dir().join("wire").join(format!(
+            "print-{}.jsonl",
+            Utc::now().format("%Y%m%d-%H%M%S")
+        ));
+        match wire_log.open(&path) {
+            Ok(()) => eprintln!("[Wire log: {}]", path.display()),
+            Err(e) => eprintln!("Warning: failed to open wire log: {}", e),
+        }
+    }
+
     // Stream the response
     let mut stream = provider.complete_stream(request).await?;
 
@@ -1,8 +1,11 @@
 //! Session management commands.
 
+use std::path::Path;
+
 use chrono::{DateTime, Local, Utc};
 
 use agentik_core::SessionState;
+use agentik_providers::WIRE_LOG_FILE;
 use agentik_session::{
     recovery::{RecoveryError, SessionRecovery},
     store::{SessionSummary, SqliteSessionStore},
@@ -61,6 +64,7 @@ pub async fn handle(action: SessionAction) -> anyhow::Result<()> {
         }
     };
 
+    let sessions_dir = store.sessions_dir().to_path_buf();
     let recovery = SessionRecovery::new(store);
 
     match action {
@@ -68,7 +72,7 @@ pub async fn handle(action: SessionAction) -> anyhow::Result<()> {
             list_sessions(&recovery, limit, filter.as_deref()).await?;
         }
         SessionAction::Show { id } => {
-            show_session(&recovery, &id).await?;
+            show_session(&recovery, &id, &sessions_dir).await?;
         }
         SessionAction::Export { id, format } => {
             export_session(&recovery, &id, &format).await?;
@@ -132,6 +136,7 @@ async fn list_sessions<S: agentik_session::store::SessionStore>(
 async fn show_session<S: agentik_session::store::SessionStore>(
     recovery: &SessionRecovery<S>,
     id: &str,
+    sessions_dir: &Path,
 ) -> anyhow::Result<()> {
     // Try to find session by prefix
     let session = match recovery.store().find_by_prefix(id).await {
@@ -209,6 +214,11 @@ async fn show_session<S: agentik_session::store::SessionStore>(
         }
     }
 
+    if let Some(ref plan) = meta.plan {
+        println!();
+        print!("{}", plan);
+    }
+
     // Show recent messages
     if !session.messages.is_empty() {
         println!();
@@ -233,6 +243,16 @@ async fn show_session<S: agentik_session::store::SessionStore>(
         }
     }
 
+    let wire_log = sessions_dir.join(&meta.id).join(WIRE_LOG_FILE);
+    if let Ok(file_meta) = std::fs::metadata(&wire_log) {
+        println!();
+        println!(
+            "Wire Log:    {} ({} bytes)",
+            wire_log.display(),
+            file_meta.len()
+        );
+    }
+
     println!();
     println!("To resume: agentik -r {}", &meta.id[..8]);
 
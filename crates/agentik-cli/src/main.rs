@@ -57,6 +57,11 @@ struct Cli {
     #[arg(long)]
     plan: bool,
 
+    /// Stage file changes for review instead of writing them to disk
+    /// (interactive mode only; print mode never changes files)
+    #[arg(long, conflicts_with_all = ["plan", "print"])]
+    dry_run: bool,
+
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
@@ -65,6 +70,10 @@ struct Cli {
     #[arg(long)]
     no_color: bool,
 
+    /// Log provider requests and responses to the session's wire log
+    #[arg(long)]
+    debug_wire: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -241,7 +250,7 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     // Load and validate configuration
-    let config = match Config::load_validated() {
+    let mut config = match Config::load_validated() {
         Ok(c) => c,
         Err(e) => {
             // If validation fails, log the error but try to continue with defaults
@@ -251,6 +260,10 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    if cli.debug_wire {
+        config.debug.wire_log = true;
+    }
+
     // Initialize provider registry
     let registry = ProviderRegistry::from_config(&config);
 
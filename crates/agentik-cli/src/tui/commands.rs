@@ -3,11 +3,13 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use agentik_agent::{Agent, AgentMode};
+use agentik_agent::{Agent, AgentMode, PlanOutcome, RewindTarget};
+use agentik_core::{PlanStep, PlanStepStatus};
 use agentik_session::{SessionQuery, SessionStore};
 use chrono::Utc;
 use git2::{Repository, StatusOptions};
 
+use super::custom::{CustomCommand, CustomCommands};
 use crate::AppContext;
 
 /// Result of command execution.
@@ -26,6 +28,7 @@ pub async fn handle_command(
     ctx: &AppContext,
     store: &Arc<dyn SessionStore>,
     agent: &mut Agent,
+    custom: &CustomCommands,
 ) -> CommandResult {
     let parts: Vec<&str> = input.split_whitespace().collect();
     let command = parts.first().copied().unwrap_or("");
@@ -33,7 +36,7 @@ pub async fn handle_command(
 
     match command {
         "/help" | "/h" | "/?" => {
-            print_help();
+            print_help(custom);
             CommandResult::Continue
         }
         "/exit" | "/quit" | "/q" => {
@@ -51,6 +54,7 @@ pub async fn handle_command(
         "/status" => print_status(ctx, store, agent).await,
         "/history" => handle_history_command(args, store, agent.session().id()).await,
         "/mode" => handle_mode_command(args, agent),
+        "/plan" => handle_plan_command(args, agent).await,
         "/tools" => handle_tools_command(args, agent),
         "/compact" => handle_compact_command(agent).await,
         "/cost" => handle_cost_command(agent),
@@ -58,16 +62,65 @@ pub async fn handle_command(
         "/drop" => handle_drop_command(args, store, agent).await,
         "/files" => handle_files_command(agent),
         "/undo" => handle_undo_command(agent),
+        "/rewind" => handle_rewind_command(args, agent).await,
+        "/memory" => handle_memory_command(args, agent),
+        "/changes" => handle_changes_command(args, agent),
         "/stats" => handle_stats_command(args, store).await,
-        _ => CommandResult::Error(format!(
-            "Unknown command: {}. Type /help for available commands.",
-            command
-        )),
+        _ => match custom.get(command.trim_start_matches('/')) {
+            Some(custom) => {
+                let arguments = input.trim_start()[command.len()..].trim();
+                run_custom_command(custom, arguments, agent).await
+            }
+            None => CommandResult::Error(format!(
+                "Unknown command: {}. Type /help for available commands.",
+                command
+            )),
+        },
+    }
+}
+
+/// Run a custom command with its model, mode and tools, restoring the
+/// agent's own afterwards. The command's mode can't be more permissive than
+/// the agent's.
+async fn run_custom_command(
+    command: &CustomCommand,
+    arguments: &str,
+    agent: &mut Agent,
+) -> CommandResult {
+    let working_dir = agent.session().metadata.working_directory.clone();
+    let prompt = command.expand(arguments, &working_dir).await;
+    if prompt.is_empty() {
+        return CommandResult::Error(format!("/{} expanded to an empty prompt", command.name));
+    }
+
+    let mode = agent.mode();
+    let model = agent.config().model.clone();
+    let command_mode = command.mode_in(mode);
+    if let Some(requested) = command.mode.filter(|m| *m != command_mode) {
+        eprintln!(
+            "[/{} asks for {:?} mode; running in {:?} mode]",
+            command.name, requested, command_mode
+        );
+    }
+    agent.set_mode(command_mode);
+    if let Some(ref command_model) = command.model {
+        agent.set_model(command_model);
+    }
+    agent.set_allowed_tools(command.allowed_tools.clone());
+
+    let result = super::process_message(&prompt, agent).await;
+
+    agent.set_mode(mode);
+    agent.set_model(model);
+    agent.set_allowed_tools(None);
+    match result {
+        Ok(()) => CommandResult::Continue,
+        Err(e) => CommandResult::Error(e.to_string()),
     }
 }
 
 /// Print help information.
-fn print_help() {
+fn print_help(custom: &CustomCommands) {
     println!("Available commands:");
     println!();
     println!("  /help, /h, /?    Show this help message");
@@ -88,7 +141,16 @@ fn print_help() {
     println!();
     println!("Agent commands:");
     println!("  /mode            Show current agent mode");
-    println!("  /mode <mode>     Switch mode (supervised, autonomous, planning, ask)");
+    println!("  /mode <mode>     Switch mode (supervised, autonomous, planning, architect, ask, dry-run)");
+    println!("  /plan            Show the current plan");
+    println!("  /plan approve    Approve the plan for execution");
+    println!("  /plan run        Execute the approved plan step by step");
+    println!("  /plan edit <n> <text>      Change a step's description");
+    println!("  /plan move <from> <to>     Reorder a step");
+    println!("  /plan add <text>           Append a step");
+    println!("  /plan remove <n>           Remove a step");
+    println!("  /plan skip <n>             Skip a step");
+    println!("  /plan clear      Discard the plan");
     println!("  /tools           List available tools");
     println!("  /compact         Trigger context compaction");
     println!("  /cost            Show session token usage and cost");
@@ -99,9 +161,37 @@ fn print_help() {
     println!("  /drop <path>     Remove file from context");
     println!("  /files           List files in context");
     println!();
+    println!("Checkpoint commands:");
+    println!("  /rewind          List turns that can be rewound");
+    println!("  /rewind <n> [files|conversation|both]");
+    println!("                   Restore files and/or conversation to before turn n");
+    println!();
+    println!("Dry-run commands:");
+    println!("  /changes         Show the diff of staged file changes");
+    println!("  /changes accept [file...]  Write staged changes to disk");
+    println!("  /changes discard [file...] Drop staged changes");
+    println!();
+    println!("Memory commands:");
+    println!("  /memory          List facts remembered about this project");
+    println!("  /memory add <text>         Remember a fact");
+    println!("  /memory edit <id> <text>   Change a fact");
+    println!("  /memory forget <id>        Forget a fact");
+    println!();
     println!("Git commands:");
     println!("  /undo            Undo the last git commit");
     println!();
+    if !custom.is_empty() {
+        println!("Custom commands:");
+        for command in custom.iter() {
+            let usage = format!("/{}", command.name);
+            println!(
+                "  {:<16} {}",
+                usage,
+                command.description.as_deref().unwrap_or("(no description)")
+            );
+        }
+        println!();
+    }
     println!("Tool Approval (when prompted):");
     println!("  y, yes           Approve this tool call");
     println!("  n, no            Deny this tool call");
@@ -111,6 +201,7 @@ fn print_help() {
     println!("Tips:");
     println!("  - Press Ctrl+D to exit");
     println!("  - Press Ctrl+C to cancel current operation");
+    println!("  - Type a message and press Enter while the agent works to steer it");
     println!("  - Use Up/Down arrows for command history");
     println!("  - Start agentik with -c to continue your last session");
     println!("  - Start agentik with -r <id> to resume a specific session");
@@ -458,22 +549,16 @@ fn handle_mode_command(args: &[&str], agent: &mut Agent) -> CommandResult {
         println!("  supervised   Ask before each tool execution (default)");
         println!("  autonomous   Execute tools without asking");
         println!("  planning     Create plans but don't execute");
-        println!("  architect    High-level design without implementation");
+        println!("  architect    Architect model proposes, editor model implements");
         println!("  ask          Answer questions only, no tool use");
+        println!("  dry-run      Stage file changes for review instead of writing them");
         CommandResult::Continue
     } else {
-        let mode = match args[0].to_lowercase().as_str() {
-            "supervised" => AgentMode::Supervised,
-            "autonomous" => AgentMode::Autonomous,
-            "planning" => AgentMode::Planning,
-            "architect" => AgentMode::Architect,
-            "ask" | "askonly" | "ask-only" => AgentMode::AskOnly,
-            other => {
-                return CommandResult::Error(format!(
-                    "Unknown mode: '{}'. Available: supervised, autonomous, planning, architect, ask",
-                    other
-                ));
-            }
+        let Some(mode) = parse_mode(args[0]) else {
+            return CommandResult::Error(format!(
+                "Unknown mode: '{}'. Available: supervised, autonomous, planning, architect, ask, dry-run",
+                args[0].to_lowercase()
+            ));
         };
 
         agent.set_mode(mode);
@@ -482,6 +567,127 @@ fn handle_mode_command(args: &[&str], agent: &mut Agent) -> CommandResult {
     }
 }
 
+/// Parse a mode name as accepted by /mode.
+pub(super) fn parse_mode(name: &str) -> Option<AgentMode> {
+    match name.to_lowercase().as_str() {
+        "supervised" => Some(AgentMode::Supervised),
+        "autonomous" => Some(AgentMode::Autonomous),
+        "planning" => Some(AgentMode::Planning),
+        "architect" => Some(AgentMode::Architect),
+        "ask" | "askonly" | "ask-only" => Some(AgentMode::AskOnly),
+        "dry-run" | "dryrun" | "dry" => Some(AgentMode::DryRun),
+        _ => None,
+    }
+}
+
+/// Handle /plan command.
+async fn handle_plan_command(args: &[&str], agent: &mut Agent) -> CommandResult {
+    // Steps are numbered from 1 for the user
+    let step_arg = |i: usize| -> Result<usize, String> {
+        args.get(i)
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .map(|n| n - 1)
+            .ok_or_else(|| "Expected a step number".to_string())
+    };
+    let text_from = |i: usize| args.get(i..).map(|rest| rest.join(" ")).unwrap_or_default();
+
+    let result = match args.first().copied() {
+        None | Some("show") => {
+            match agent.plan() {
+                Some(plan) => print!("{}", plan),
+                None => {
+                    println!("No plan yet.");
+                    println!();
+                    println!("Switch to planning mode with /mode planning and describe the task.");
+                }
+            }
+            return CommandResult::Continue;
+        }
+        Some("approve") => agent.approve_plan().await,
+        Some("clear") => agent.clear_plan().await,
+        Some("run") => {
+            println!("[Executing plan...]");
+            return match agent.execute_plan().await {
+                Ok(outcome) => {
+                    if let Some(plan) = agent.plan() {
+                        println!();
+                        print!("{}", plan);
+                    }
+                    match outcome {
+                        PlanOutcome::Completed => println!("[Plan complete]"),
+                        PlanOutcome::Revised { failed_step } => println!(
+                            "[Step {} failed; the plan was revised. Review it and /plan approve to continue]",
+                            failed_step + 1
+                        ),
+                        PlanOutcome::Failed { failed_step } => println!(
+                            "[Step {} failed. Edit the plan or /plan skip {} to continue]",
+                            failed_step + 1,
+                            failed_step + 1
+                        ),
+                    }
+                    CommandResult::Continue
+                }
+                Err(agentik_agent::AgentError::Cancelled) => {
+                    println!("[Plan execution cancelled]");
+                    CommandResult::Continue
+                }
+                Err(e) => CommandResult::Error(format!("Plan execution failed: {}", e)),
+            };
+        }
+        Some("edit") => match step_arg(1) {
+            Ok(index) if args.len() > 2 => {
+                let text = text_from(2);
+                agent.update_plan(|p| p.edit_step(index, text)).await
+            }
+            Ok(_) => return CommandResult::Error("Usage: /plan edit <n> <text>".into()),
+            Err(e) => return CommandResult::Error(e),
+        },
+        Some("move") => match (step_arg(1), step_arg(2)) {
+            (Ok(from), Ok(to)) => agent.update_plan(|p| p.move_step(from, to)).await,
+            _ => return CommandResult::Error("Usage: /plan move <from> <to>".into()),
+        },
+        Some("add") if args.len() > 1 => {
+            let text = text_from(1);
+            agent
+                .update_plan(|p| {
+                    p.add_step(PlanStep::new(text));
+                    Ok(())
+                })
+                .await
+        }
+        Some("remove") => match step_arg(1) {
+            Ok(index) => agent.update_plan(|p| p.remove_step(index).map(|_| ())).await,
+            Err(e) => return CommandResult::Error(e),
+        },
+        Some("skip") => match step_arg(1) {
+            Ok(index) => {
+                agent
+                    .update_plan(|p| p.set_status(index, PlanStepStatus::Skipped, None))
+                    .await
+            }
+            Err(e) => return CommandResult::Error(e),
+        },
+        Some(other) => {
+            return CommandResult::Error(format!(
+                "Unknown plan command: '{}'. Available: show, approve, run, edit, move, add, remove, skip, clear",
+                other
+            ))
+        }
+    };
+
+    match result {
+        Ok(()) => {
+            match agent.plan() {
+                Some(plan) => print!("{}", plan),
+                None => println!("[Plan cleared]"),
+            }
+            CommandResult::Continue
+        }
+        Err(e) => CommandResult::Error(e.to_string()),
+    }
+}
+
 /// Handle /tools command.
 fn handle_tools_command(args: &[&str], _agent: &Agent) -> CommandResult {
     // Get tools from the agent's context (through executor)
@@ -858,6 +1064,201 @@ fn format_size(bytes: usize) -> String {
     }
 }
 
+/// Handle /rewind command to restore files or conversation to an earlier turn.
+async fn handle_rewind_command(args: &[&str], agent: &mut Agent) -> CommandResult {
+    let checkpoints = match agent.checkpoints() {
+        Ok(c) => c,
+        Err(e) => return CommandResult::Error(format!("Failed to load checkpoints: {}", e)),
+    };
+
+    if args.is_empty() {
+        if checkpoints.is_empty() {
+            println!("No checkpoints in this session yet.");
+            return CommandResult::Continue;
+        }
+        println!("Turns:");
+        println!();
+        for (i, checkpoint) in checkpoints.iter().enumerate() {
+            let prompt = checkpoint.prompt.lines().next().unwrap_or("");
+            let preview: String = prompt.chars().take(60).collect();
+            let ellipsis = if preview.len() < prompt.len() {
+                "..."
+            } else {
+                ""
+            };
+            println!(
+                "  {:>3}. [{}] {}{} ({} file{})",
+                i + 1,
+                checkpoint.created_at.format("%H:%M:%S"),
+                preview,
+                ellipsis,
+                checkpoint.files.len(),
+                if checkpoint.files.len() == 1 { "" } else { "s" }
+            );
+        }
+        println!();
+        println!("Use /rewind <n> [files|conversation|both] to rewind to before turn n.");
+        return CommandResult::Continue;
+    }
+
+    let Some(checkpoint) = args[0]
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|i| checkpoints.get(i))
+    else {
+        return CommandResult::Error(format!(
+            "Invalid turn: {}. Use /rewind to list turns.",
+            args[0]
+        ));
+    };
+    let target: RewindTarget = match args.get(1).map(|s| s.parse()).transpose() {
+        Ok(target) => target.unwrap_or_default(),
+        Err(e) => return CommandResult::Error(e),
+    };
+
+    match agent.rewind(checkpoint.message_index, target).await {
+        Ok(restored) => {
+            if target.files() {
+                println!("[Restored {} file(s)]", restored.len());
+                for path in &restored {
+                    println!("  {}", path.display());
+                }
+            }
+            if target.conversation() {
+                println!(
+                    "[Conversation rewound to {} message(s)]",
+                    agent.session().messages.len()
+                );
+            }
+            CommandResult::Continue
+        }
+        Err(e) => CommandResult::Error(format!("Rewind failed: {}", e)),
+    }
+}
+
+/// Handle /memory command to list and edit project memories.
+fn handle_memory_command(args: &[&str], agent: &Agent) -> CommandResult {
+    let Some(memory) = agent.memory() else {
+        return CommandResult::Error("Project memory is disabled".to_string());
+    };
+
+    let result = match args {
+        [] | ["list"] => {
+            let memories = memory.list();
+            if memories.is_empty() {
+                println!("No memories for {}.", memory.project().display());
+                return CommandResult::Continue;
+            }
+            println!("Memories for {}:", memory.project().display());
+            println!();
+            for m in &memories {
+                println!(
+                    "  [{}] {} {}",
+                    m.id,
+                    m.updated_at.format("%Y-%m-%d"),
+                    m.content
+                );
+                if !m.tags.is_empty() {
+                    println!("             tags: {}", m.tags.join(", "));
+                }
+            }
+            return CommandResult::Continue;
+        }
+        ["add", text @ ..] if !text.is_empty() => memory
+            .remember(&text.join(" "), vec![])
+            .map(|m| format!("[Remembered {}]", m.id)),
+        ["edit", id, text @ ..] if !text.is_empty() => match memory.update(id, &text.join(" ")) {
+            Ok(Some(m)) => Ok(format!("[Updated {}]", m.id)),
+            Ok(None) => return CommandResult::Error(format!("No memory with id {}", id)),
+            Err(e) => Err(e),
+        },
+        ["forget" | "rm", id] => match memory.forget(id) {
+            Ok(Some(m)) => Ok(format!("[Forgot {}]", m.id)),
+            Ok(None) => return CommandResult::Error(format!("No memory with id {}", id)),
+            Err(e) => Err(e),
+        },
+        _ => {
+            return CommandResult::Error(
+                "Usage: /memory [list|add <text>|edit <id> <text>|forget <id>]".to_string(),
+            )
+        }
+    };
+
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            CommandResult::Continue
+        }
+        Err(e) => CommandResult::Error(format!("Failed to save memories: {}", e)),
+    }
+}
+
+/// Handle /changes command to review the changes staged by a dry run.
+fn handle_changes_command(args: &[&str], agent: &Agent) -> CommandResult {
+    let changeset = agent.changeset();
+    let working_dir = &agent.session().metadata.working_directory;
+    let display = |path: &std::path::Path| {
+        path.strip_prefix(working_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+
+    match args {
+        [] | ["diff"] => {
+            if changeset.is_empty() {
+                println!("No staged changes.");
+            } else {
+                print!("{}", changeset.diff(working_dir));
+            }
+            CommandResult::Continue
+        }
+        ["accept"] => match changeset.accept_all() {
+            Ok(paths) => {
+                for path in &paths {
+                    println!("[Wrote {}]", display(path));
+                }
+                CommandResult::Continue
+            }
+            Err(e) => CommandResult::Error(format!("Failed to write changes: {}", e)),
+        },
+        ["discard"] => {
+            let paths = changeset.discard_all();
+            println!("[Discarded {} staged file change(s)]", paths.len());
+            CommandResult::Continue
+        }
+        ["accept", files @ ..] => {
+            for file in files {
+                let path = working_dir.join(file);
+                match changeset.accept(&path) {
+                    Ok(true) => println!("[Wrote {}]", display(&path)),
+                    Ok(false) => {
+                        return CommandResult::Error(format!("No staged changes to {}", file))
+                    }
+                    Err(e) => {
+                        return CommandResult::Error(format!("Failed to write {}: {}", file, e))
+                    }
+                }
+            }
+            CommandResult::Continue
+        }
+        ["discard", files @ ..] => {
+            for file in files {
+                let path = working_dir.join(file);
+                if !changeset.discard(&path) {
+                    return CommandResult::Error(format!("No staged changes to {}", file));
+                }
+                println!("[Discarded {}]", display(&path));
+            }
+            CommandResult::Continue
+        }
+        _ => CommandResult::Error(
+            "Usage: /changes [diff|accept [file...]|discard [file...]]".to_string(),
+        ),
+    }
+}
+
 /// Handle /undo command to revert the last git commit.
 fn handle_undo_command(agent: &Agent) -> CommandResult {
     let working_dir = &agent.session().metadata.working_directory;
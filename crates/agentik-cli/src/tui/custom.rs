@@ -0,0 +1,530 @@
+//! Custom slash commands from prompt template files.
+//!
+//! Each `*.md` file in the user's command directory
+//! (`~/.config/agentik/commands/`) or the project's (`.agentik/commands/`)
+//! is a command named after the file; project commands override user ones.
+//! Invoking `/name args` expands the template and sends it as a prompt.
+//!
+//! A template may start with front-matter:
+//!
+//! ```text
+//! ---
+//! description: Review the current diff
+//! allowed-tools: Read, Grep, Bash
+//! model: claude-sonnet-4-20250514
+//! mode: autonomous
+//! ---
+//! Review these changes, focusing on $ARGUMENTS:
+//!
+//! !`git diff HEAD`
+//! ```
+//!
+//! In the body, `$ARGUMENTS` is replaced by everything after the command
+//! name and `$1`..`$9` by single (optionally quoted) arguments. `` !`cmd` ``
+//! is replaced by the output of a shell command run in the working
+//! directory, with the arguments substituted into it shell-quoted, and
+//! `@path` in the template itself attaches the content of a file in the
+//! working directory.
+//!
+//! A command's mode can't be more permissive than the mode it is invoked
+//! from.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use agentik_agent::AgentMode;
+use agentik_core::Config;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use super::commands::parse_mode;
+
+/// How long a shell command in a template may run.
+const SHELL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A command loaded from a template file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomCommand {
+    /// Name it is invoked by, without the slash
+    pub name: String,
+    /// File it was loaded from
+    pub path: PathBuf,
+    /// Shown in `/help`
+    pub description: Option<String>,
+    /// Tools the model may use while it runs (`None` allows all)
+    pub allowed_tools: Option<Vec<String>>,
+    /// Model to run it with
+    pub model: Option<String>,
+    /// Mode to run it in
+    pub mode: Option<AgentMode>,
+    /// Prompt template
+    pub template: String,
+}
+
+impl CustomCommand {
+    /// Parse a template file's content.
+    pub fn parse(name: &str, path: &Path, text: &str) -> Result<Self, String> {
+        let mut command = Self {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            description: None,
+            allowed_tools: None,
+            model: None,
+            mode: None,
+            template: text.to_string(),
+        };
+
+        let Some(rest) = text.strip_prefix("---\n") else {
+            return Ok(command);
+        };
+        let (front_matter, body) = match rest.find("\n---\n") {
+            Some(end) => (&rest[..end], &rest[end + 5..]),
+            None => match rest.strip_suffix("\n---") {
+                Some(front_matter) => (front_matter, ""),
+                None => return Err("front-matter is not closed with ---".to_string()),
+            },
+        };
+        command.template = body.to_string();
+
+        for line in front_matter.lines().filter(|l| !l.trim().is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("invalid front-matter line: {}", line))?;
+            let value = unquote(value.trim());
+            match key.trim() {
+                "description" => command.description = Some(value.to_string()),
+                "allowed-tools" | "allowed_tools" => {
+                    let list = value.trim_start_matches('[').trim_end_matches(']');
+                    command.allowed_tools = Some(
+                        list.split(',')
+                            .map(|t| unquote(t.trim()).to_string())
+                            .filter(|t| !t.is_empty())
+                            .collect(),
+                    );
+                }
+                "model" => command.model = Some(value.to_string()),
+                "mode" => {
+                    command.mode =
+                        Some(parse_mode(value).ok_or_else(|| format!("unknown mode: {}", value))?);
+                }
+                other => debug!(command = name, key = other, "Ignoring front-matter key"),
+            }
+        }
+        Ok(command)
+    }
+
+    /// The mode to run the command in when invoked from `current`: its own,
+    /// unless that is more permissive.
+    pub fn mode_in(&self, current: AgentMode) -> AgentMode {
+        self.mode.map_or(current, |mode| mode.limited_to(current))
+    }
+
+    /// Expand the template with the arguments given to the command.
+    pub async fn expand(&self, arguments: &str, working_dir: &Path) -> String {
+        let arguments = arguments.trim();
+        let positional = split_arguments(arguments);
+
+        // Arguments first, so they can be used in commands; there they are
+        // quoted, so they can't add shell syntax
+        let mut text = String::new();
+        let mut rest = self.template.as_str();
+        while let Some((before, command, after)) = split_shell_command(rest) {
+            text.push_str(&substitute(before, arguments, &positional, false));
+            let command = substitute(command, arguments, &positional, true);
+            text.push_str(&run_shell(&command, working_dir).await);
+            rest = after;
+        }
+        text.push_str(&substitute(rest, arguments, &positional, false));
+        let uses_arguments = self.template.contains("$ARGUMENTS")
+            || (1..=9).any(|i| self.template.contains(&format!("${}", i)));
+
+        // Attach the files the template mentions with @path; arguments and
+        // command output can't name files to attach
+        let mut files = Vec::new();
+        for target in attachment_targets(&self.template) {
+            let Some(path) = resolve_attachment(working_dir, target) else {
+                continue;
+            };
+            if files.iter().any(|(p, _)| p == &path) {
+                continue;
+            }
+            match std::fs::read_to_string(&path) {
+                Ok(content) => files.push((path, content)),
+                Err(e) => debug!(path = %path.display(), error = %e, "Not attaching file"),
+            }
+        }
+        for (path, content) in files {
+            text.push_str(&format!(
+                "\n\n<file path=\"{}\">\n{}\n</file>",
+                path.display(),
+                content.trim_end()
+            ));
+        }
+
+        // Templates without placeholders still get the arguments
+        if !uses_arguments && !arguments.is_empty() {
+            text.push_str(&format!("\n\nArguments: {}", arguments));
+        }
+        text.trim().to_string()
+    }
+}
+
+/// The custom commands available in a working directory.
+#[derive(Debug, Default)]
+pub struct CustomCommands {
+    commands: BTreeMap<String, CustomCommand>,
+}
+
+impl CustomCommands {
+    /// Load the user's and the project's commands.
+    pub fn discover(working_dir: &Path) -> Self {
+        Self::load(&[
+            Config::config_dir().join("commands"),
+            working_dir.join(".agentik").join("commands"),
+        ])
+    }
+
+    /// Load the commands in directories; later ones override earlier ones,
+    /// with a warning.
+    pub fn load(dirs: &[PathBuf]) -> Self {
+        let mut commands = BTreeMap::new();
+        for dir in dirs {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "md"))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                let parsed = std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| CustomCommand::parse(name, &path, &text));
+                match parsed {
+                    Ok(command) => {
+                        if let Some(shadowed) = commands.insert(name.to_string(), command) {
+                            warn!(
+                                command = name,
+                                path = %path.display(),
+                                shadowed = %shadowed.path.display(),
+                                "Command overrides another with the same name"
+                            );
+                        }
+                    }
+                    Err(e) => warn!(path = %path.display(), error = %e, "Invalid command file"),
+                }
+            }
+        }
+        Self { commands }
+    }
+
+    /// Get a command by name.
+    pub fn get(&self, name: &str) -> Option<&CustomCommand> {
+        self.commands.get(name)
+    }
+
+    /// Iterate over the commands by name.
+    pub fn iter(&self) -> impl Iterator<Item = &CustomCommand> {
+        self.commands.values()
+    }
+
+    /// Whether there are no commands.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// Strip matching quotes around a value.
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Split arguments on whitespace, keeping quoted ones together.
+fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_arg = false;
+    for c in arguments.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Split the text around its first `` !`cmd` ``: the text before, the
+/// command, and the text after.
+fn split_shell_command(text: &str) -> Option<(&str, &str, &str)> {
+    let start = text.find("!`")?;
+    let len = text[start + 2..].find('`')?;
+    Some((
+        &text[..start],
+        &text[start + 2..start + 2 + len],
+        &text[start + 2 + len + 1..],
+    ))
+}
+
+/// The `@path` targets in a template, outside of shell commands.
+fn attachment_targets(template: &str) -> Vec<&str> {
+    let mut targets = Vec::new();
+    let mut rest = template;
+    loop {
+        let (text, after) = match split_shell_command(rest) {
+            Some((before, _, after)) => (before, Some(after)),
+            None => (rest, None),
+        };
+        targets.extend(
+            text.split_whitespace()
+                .filter_map(|token| token.strip_prefix('@'))
+                .filter(|target| !target.is_empty()),
+        );
+        match after {
+            Some(after) => rest = after,
+            None => return targets,
+        }
+    }
+}
+
+/// Resolve an attachment, refusing files outside the working directory.
+fn resolve_attachment(working_dir: &Path, target: &str) -> Option<PathBuf> {
+    let root = working_dir.canonicalize().ok()?;
+    let path = match working_dir.join(target).canonicalize() {
+        Ok(path) => path,
+        Err(e) => {
+            debug!(target, error = %e, "Not attaching file");
+            return None;
+        }
+    };
+    if !path.starts_with(&root) {
+        warn!(path = %path.display(), "Attachment outside the working directory refused");
+        return None;
+    }
+    Some(path)
+}
+
+/// Replace `$ARGUMENTS` and `$1`..`$9` in a piece of a template, quoting
+/// the arguments for the shell if `quote` is set.
+fn substitute(text: &str, arguments: &str, positional: &[String], quote: bool) -> String {
+    let all = if quote {
+        positional
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        arguments.to_string()
+    };
+    let mut text = text.replace("$ARGUMENTS", &all);
+    for i in (1..=9).rev() {
+        let value = positional.get(i - 1).map(String::as_str).unwrap_or("");
+        let value = if quote {
+            shell_quote(value)
+        } else {
+            value.to_string()
+        };
+        text = text.replace(&format!("${}", i), &value);
+    }
+    text
+}
+
+/// Quote a value as a single shell word.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Run a shell command, returning its output or a note on why it failed.
+async fn run_shell(command: &str, working_dir: &Path) -> String {
+    debug!(command, "Running template command");
+    let run = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(working_dir)
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(SHELL_TIMEOUT, run).await {
+        Ok(Ok(output)) => {
+            let stdout = String::from_utf8_lossy(&output.stdout)
+                .trim_end()
+                .to_string();
+            if output.status.success() {
+                stdout
+            } else {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                format!(
+                    "{}\n[`{}` failed ({}): {}]",
+                    stdout,
+                    command,
+                    output.status,
+                    stderr.trim()
+                )
+                .trim_start()
+                .to_string()
+            }
+        }
+        Ok(Err(e)) => format!("[`{}` could not run: {}]", command, e),
+        Err(_) => format!(
+            "[`{}` timed out after {}s]",
+            command,
+            SHELL_TIMEOUT.as_secs()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_front_matter() {
+        let text = "---\ndescription: \"Fix an issue\"\nallowed-tools: [Read, \"Edit\"]\n\
+                    mode: autonomous\n---\nFix issue #$1\n";
+        let command = CustomCommand::parse("fix", Path::new("fix.md"), text).unwrap();
+
+        assert_eq!(command.description.as_deref(), Some("Fix an issue"));
+        assert_eq!(
+            command.allowed_tools,
+            Some(vec!["Read".to_string(), "Edit".to_string()])
+        );
+        assert_eq!(command.mode, Some(AgentMode::Autonomous));
+        assert_eq!(command.model, None);
+        assert_eq!(command.template, "Fix issue #$1\n");
+
+        let plain = CustomCommand::parse("plain", Path::new("plain.md"), "Just do it").unwrap();
+        assert_eq!(plain.template, "Just do it");
+        assert!(
+            CustomCommand::parse("bad", Path::new("bad.md"), "---\nmode: fast\n---\n").is_err()
+        );
+        assert!(CustomCommand::parse("open", Path::new("open.md"), "---\nmodel: x\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expand() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "remember the milk\n").unwrap();
+        let template =
+            "Compare $1 with $2 ($ARGUMENTS).\nBranch: !`echo main`\nSee @notes.txt @missing.txt";
+        let command = CustomCommand::parse("cmp", Path::new("cmp.md"), template).unwrap();
+
+        let text = command.expand("'old version' new", dir.path()).await;
+
+        assert!(text.starts_with(
+            "Compare old version with new ('old version' new).\nBranch: main\nSee @notes.txt"
+        ));
+        assert!(text.ends_with(&format!(
+            "<file path=\"{}\">\nremember the milk\n</file>",
+            dir.path()
+                .join("notes.txt")
+                .canonicalize()
+                .unwrap()
+                .display()
+        )));
+
+        // Only the template names files, and only inside the working directory
+        let work = dir.path().join("work");
+        std::fs::create_dir_all(&work).unwrap();
+        std::fs::write(work.join("notes.txt"), "inside\n").unwrap();
+        let attach = CustomCommand::parse(
+            "attach",
+            Path::new("attach.md"),
+            "Read $1 !`echo @notes.txt` @../notes.txt @/etc/hostname",
+        )
+        .unwrap();
+        let text = attach.expand("@notes.txt", &work).await;
+        assert!(!text.contains("<file"));
+        assert!(!text.contains("remember the milk"));
+
+        // Arguments can't inject shell syntax into commands
+        let echo = CustomCommand::parse(
+            "echo",
+            Path::new("echo.md"),
+            "Got !`printf '%s|' $1`, all !`printf '%s|' $ARGUMENTS`",
+        )
+        .unwrap();
+        assert_eq!(
+            echo.expand("\"it's; touch pwned\" $(touch pwned)", dir.path())
+                .await,
+            "Got it's; touch pwned|, all it's; touch pwned|$(touch|pwned)|"
+        );
+        assert!(!dir.path().join("pwned").exists());
+
+        // Arguments are appended when the template has no placeholders
+        let plain = CustomCommand::parse("plain", Path::new("plain.md"), "Summarize").unwrap();
+        assert_eq!(
+            plain.expand("src/", dir.path()).await,
+            "Summarize\n\nArguments: src/"
+        );
+    }
+
+    #[test]
+    fn test_load_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("user");
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(&user).unwrap();
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(user.join("review.md"), "User review").unwrap();
+        std::fs::write(user.join("deploy.md"), "Deploy").unwrap();
+        std::fs::write(project.join("review.md"), "Project review").unwrap();
+        std::fs::write(project.join("notes.txt"), "not a command").unwrap();
+
+        let commands = CustomCommands::load(&[user, project]);
+
+        let names: Vec<_> = commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["deploy", "review"]);
+        assert_eq!(commands.get("review").unwrap().template, "Project review");
+    }
+
+    #[test]
+    fn test_mode_cannot_escalate() {
+        let command = |text| CustomCommand::parse("cmd", Path::new("cmd.md"), text).unwrap();
+        let autonomous = command("---\nmode: autonomous\n---\nGo");
+        assert_eq!(
+            autonomous.mode_in(AgentMode::Supervised),
+            AgentMode::Supervised
+        );
+        assert_eq!(
+            autonomous.mode_in(AgentMode::Autonomous),
+            AgentMode::Autonomous
+        );
+        assert_eq!(autonomous.mode_in(AgentMode::DryRun), AgentMode::DryRun);
+        let ask = command("---\nmode: askonly\n---\nExplain");
+        assert_eq!(ask.mode_in(AgentMode::Autonomous), AgentMode::AskOnly);
+        assert_eq!(
+            command("Go").mode_in(AgentMode::Planning),
+            AgentMode::Planning
+        );
+    }
+}
@@ -6,13 +6,19 @@
 
 use std::collections::HashSet;
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::Mutex;
 use std::time::Instant;
 
-use agentik_agent::{AgentEventHandler, AgentResponse, PermissionHandler, TurnUsage};
+use agentik_agent::{
+    AgentEventHandler, AgentResponse, LoopDetection, ModelRole, PermissionHandler, Question,
+    TurnUsage, VerifyReport,
+};
 use agentik_core::{ToolCall, ToolDefinition, ToolResult};
 use async_trait::async_trait;
 
+use super::input;
+
 // ============================================================================
 // CLI Event Handler
 // ============================================================================
@@ -42,6 +48,27 @@ impl Default for CliEventHandler {
     }
 }
 
+impl CliEventHandler {
+    /// Show a question.
+    fn show_question(question: &Question) {
+        eprintln!();
+        if question.blocking {
+            eprintln!("[Question] {}", question.text);
+        } else {
+            eprintln!("[Question - answer any time] {}", question.text);
+        }
+        for (i, option) in question.options.iter().enumerate() {
+            eprintln!("  {}. {}", i + 1, option);
+        }
+        eprint!(
+            "Answer (Enter for default: {}): ",
+            question.default_answer()
+        );
+        io::stderr().flush().ok();
+    }
+}
+
+#[async_trait]
 impl AgentEventHandler for CliEventHandler {
     fn on_thinking(&self) {
         // Could show a spinner here, but we'll keep it simple
@@ -91,6 +118,43 @@ impl AgentEventHandler for CliEventHandler {
         eprintln!("[Compacting context...]");
     }
 
+    fn on_role_change(&self, role: ModelRole, model: &str) {
+        eprintln!("\n[{}: {}]", role, model);
+    }
+
+    fn on_verify(&self, report: &VerifyReport) {
+        let retry = if report.attempt > 0 {
+            format!(" after {} repair attempt(s)", report.attempt)
+        } else {
+            String::new()
+        };
+        for check in &report.checks {
+            let status = if check.passed { "passed" } else { "failed" };
+            eprintln!("[Verify: {}] {}{}", check.command, status, retry);
+        }
+    }
+
+    fn on_steering(&self, message: &str) {
+        eprintln!("\n[Steering: {}]", message);
+    }
+
+    fn on_loop_detected(&self, detection: &LoopDetection) {
+        eprintln!("\n[Loop detected: {}]", detection);
+    }
+
+    fn on_instructions_loaded(&self, paths: &[PathBuf]) {
+        for path in paths {
+            eprintln!("\n[Instructions: {}]", path.display());
+        }
+    }
+
+    async fn on_question(&self, question: &Question) -> Option<String> {
+        // An empty line or end of input means "use the default"
+        let input = input::prompt(|| Self::show_question(question)).await?;
+        let input = input.trim();
+        (!input.is_empty()).then(|| input.to_string())
+    }
+
     fn on_error(&self, error: &agentik_agent::AgentError) {
         eprintln!("[Error: {}]", error);
     }
@@ -140,13 +204,13 @@ impl CliPermissionHandler {
             .insert(tool_name.to_string());
     }
 
-    /// Read user input for approval (sync blocking version).
-    fn prompt_user_sync(
+    /// Show the approval prompt for a tool call.
+    fn show_approval(
         tool_name: &str,
         tool_desc: &str,
         is_destructive: bool,
         args: &serde_json::Value,
-    ) -> ApprovalResponse {
+    ) {
         // Show tool information
         eprintln!();
         eprintln!("╔══════════════════════════════════════════════════════════════╗");
@@ -173,13 +237,10 @@ impl CliPermissionHandler {
         eprintln!();
         eprint!("Approve? [y]es / [n]o / [a]lways / [q]uit: ");
         io::stderr().flush().ok();
+    }
 
-        // Read input
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
-            return ApprovalResponse::Deny;
-        }
-
+    /// Parse the answer to an approval prompt.
+    fn parse_approval(input: &str) -> ApprovalResponse {
         match input.trim().to_lowercase().as_str() {
             "y" | "yes" => ApprovalResponse::Approve,
             "n" | "no" | "" => ApprovalResponse::Deny,
@@ -221,18 +282,16 @@ impl PermissionHandler for CliPermissionHandler {
             return true;
         }
 
-        // Clone the data we need for the blocking thread
-        let tool_name = call.name.clone();
-        let tool_desc = tool.description.clone();
-        let is_destructive = tool.is_destructive;
-        let args = call.arguments.clone();
-
-        // Use spawn_blocking for stdin read since it's blocking I/O
-        let response = tokio::task::spawn_blocking(move || {
-            Self::prompt_user_sync(&tool_name, &tool_desc, is_destructive, &args)
+        let response = input::prompt(|| {
+            Self::show_approval(
+                &call.name,
+                &tool.description,
+                tool.is_destructive,
+                &call.arguments,
+            )
         })
         .await
-        .unwrap_or(ApprovalResponse::Deny);
+        .map_or(ApprovalResponse::Deny, |input| Self::parse_approval(&input));
 
         match response {
             ApprovalResponse::Approve => true,
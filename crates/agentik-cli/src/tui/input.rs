@@ -0,0 +1,155 @@
+//! Reading stdin while the agent works.
+//!
+//! A single background thread reads stdin for everything that wants a line
+//! during a run: prompts (questions and approvals) and steering. A line typed
+//! while a prompt is waiting answers it; any other line is queued as a
+//! steering message. The thread only reads while someone wants a line, and
+//! polls instead of blocking on a read, so it never swallows the next REPL
+//! line once the run ends, and a prompt that is given up on (a question that
+//! timed out, say) stops taking input the moment it is dropped.
+
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use agentik_agent::SteeringQueue;
+use tokio::sync::oneshot;
+
+/// How long the reader waits for input before checking for demand again.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where the next line goes.
+#[derive(Default)]
+struct Demand {
+    /// Prompt waiting for an answer (`None` at end of input)
+    prompt: Option<oneshot::Sender<Option<String>>>,
+    /// Steering queue while a run accepts steering
+    steering: Option<SteeringQueue>,
+    /// Stdin reached end of input
+    closed: bool,
+}
+
+impl Demand {
+    fn wants_input(&self) -> bool {
+        !self.closed && (self.prompt.is_some() || self.steering.is_some())
+    }
+}
+
+/// Serializes prompts so only one is shown and answered at a time.
+static PROMPT: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// The shared reader, started on first use.
+fn demand() -> &'static Arc<Mutex<Demand>> {
+    static DEMAND: OnceLock<Arc<Mutex<Demand>>> = OnceLock::new();
+    DEMAND.get_or_init(|| {
+        let demand = Arc::new(Mutex::new(Demand::default()));
+        let reader = Arc::clone(&demand);
+        std::thread::spawn(move || read_lines(reader));
+        demand
+    })
+}
+
+/// Show a prompt and wait for the line typed in answer.
+///
+/// `show` runs once this prompt has stdin to itself. Returns `None` at end
+/// of input. Dropping the future withdraws the prompt; later lines go to
+/// steering or wait for the next reader.
+pub async fn prompt(show: impl FnOnce()) -> Option<String> {
+    let _turn = PROMPT.lock().await;
+    let (sender, receiver) = oneshot::channel();
+    {
+        let mut demand = demand().lock().unwrap();
+        if demand.closed {
+            return None;
+        }
+        demand.prompt = Some(sender);
+    }
+    let _withdraw = Withdraw;
+    show();
+    receiver.await.ok().flatten()
+}
+
+/// Withdraws the current prompt when dropped, answered or not.
+struct Withdraw;
+
+impl Drop for Withdraw {
+    fn drop(&mut self) {
+        // Prompts take turns, so the slot holds this prompt if anything
+        demand().lock().unwrap().prompt = None;
+    }
+}
+
+/// Send lines that don't answer a prompt to a steering queue until the
+/// returned guard is dropped.
+pub fn steer(queue: SteeringQueue) -> SteeringGuard {
+    demand().lock().unwrap().steering = Some(queue);
+    SteeringGuard
+}
+
+/// Stops steering when dropped.
+pub struct SteeringGuard;
+
+impl Drop for SteeringGuard {
+    fn drop(&mut self) {
+        demand().lock().unwrap().steering = None;
+    }
+}
+
+fn read_lines(demand: Arc<Mutex<Demand>>) {
+    use std::io::BufRead;
+
+    loop {
+        if !demand.lock().unwrap().wants_input() {
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        if !stdin_ready() {
+            continue;
+        }
+
+        let mut line = String::new();
+        let read = std::io::stdin().lock().read_line(&mut line);
+        let mut demand = demand.lock().unwrap();
+        if matches!(read, Ok(0) | Err(_)) {
+            demand.closed = true;
+            if let Some(prompt) = demand.prompt.take() {
+                let _ = prompt.send(None);
+            }
+            return;
+        }
+
+        // Answer a waiting prompt, or steer
+        let line = line.trim_end_matches(['\r', '\n']).to_string();
+        let line = match demand.prompt.take() {
+            Some(prompt) => match prompt.send(Some(line)) {
+                Ok(()) => continue,
+                Err(line) => line.unwrap_or_default(),
+            },
+            None => line,
+        };
+        if let Some(ref queue) = demand.steering {
+            let line = line.trim();
+            if !line.is_empty() {
+                queue.push(line);
+            }
+        }
+    }
+}
+
+/// Wait up to [`POLL_INTERVAL`] for a line on stdin.
+#[cfg(unix)]
+fn stdin_ready() -> bool {
+    use std::os::fd::AsFd;
+
+    use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+
+    let stdin = std::io::stdin();
+    let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+    let timeout = PollTimeout::try_from(POLL_INTERVAL).unwrap_or(PollTimeout::NONE);
+    matches!(poll(&mut fds, timeout), Ok(n) if n > 0)
+}
+
+/// Without polling, reads block until a line arrives.
+#[cfg(not(unix))]
+fn stdin_ready() -> bool {
+    true
+}
@@ -6,21 +6,29 @@
 //! - Streaming response display
 //! - Session management
 //! - Tool execution with approval workflows
+//! - Steering the agent by typing while it works
 
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 
-use agentik_agent::{Agent, AgentBuilder, AgentMode, ExecutorBuilder};
-use agentik_core::Session;
+use agentik_agent::{
+    Agent, AgentBuilder, AgentMode, ArtifactStore, CheckpointStore, ExecutorBuilder, HookRunner,
+    Instructions, MemoryStore, ModelRole,
+};
+use agentik_core::{Config, Session};
+use agentik_providers::WIRE_LOG_FILE;
 use agentik_session::{SessionStore, SqliteSessionStore};
 
 use crate::{AppContext, Cli};
 
 mod commands;
+mod custom;
 mod handlers;
+mod input;
 
 pub use handlers::{CliEventHandler, CliPermissionHandler};
 
@@ -28,12 +36,24 @@ pub use handlers::{CliEventHandler, CliPermissionHandler};
 pub async fn run(cli: Cli, ctx: Arc<AppContext>) -> anyhow::Result<()> {
     // Initialize session store
     let store = SqliteSessionStore::open_default()?;
+    let sessions_dir = store.sessions_dir().to_path_buf();
+    let checkpoint_dir = store.base_dir().join("checkpoints");
+    let memory_dir = store.base_dir().join("memory");
     let store = Arc::new(store) as Arc<dyn SessionStore>;
 
     // Create or resume session
     let session = create_or_resume_session(&cli, &store).await?;
     let session_id = session.id().to_string();
 
+    // Point the wire log at this session
+    if let Some(wire_log) = ctx.registry.wire_log() {
+        let path = sessions_dir.join(&session_id).join(WIRE_LOG_FILE);
+        match wire_log.open(&path) {
+            Ok(()) => println!("[Wire log: {}]", path.display()),
+            Err(e) => eprintln!("Warning: failed to open wire log: {}", e),
+        }
+    }
+
     // Print welcome banner
     print_welcome_banner(&cli, &ctx);
 
@@ -45,13 +65,25 @@ pub async fn run(cli: Cli, ctx: Arc<AppContext>) -> anyhow::Result<()> {
     );
     println!();
 
-    // Create the agent
-    let mut agent = create_agent(&cli, &ctx, store.clone(), session)?;
+    // Create the agent; large tool outputs are kept with the session
+    let artifact_dir = sessions_dir.join(&session_id).join("artifacts");
+    let mut agent = create_agent(
+        &cli,
+        &ctx,
+        store.clone(),
+        session,
+        checkpoint_dir,
+        artifact_dir,
+        memory_dir,
+    )?;
 
     // Show initial mode
     println!("[Mode: {:?}]", agent.mode());
     println!();
 
+    // Load custom commands from the user's and the project's directories
+    let custom = custom::CustomCommands::discover(&agent.session().metadata.working_directory);
+
     // Initialize readline editor
     let mut editor = DefaultEditor::new()?;
 
@@ -85,9 +117,14 @@ pub async fn run(cli: Cli, ctx: Arc<AppContext>) -> anyhow::Result<()> {
 
                 // Handle slash commands
                 if line.starts_with('/') {
-                    match commands::handle_command(line, &ctx, &store, &mut agent).await {
+                    match commands::handle_command(line, &ctx, &store, &mut agent, &custom).await {
                         commands::CommandResult::Continue => continue,
-                        commands::CommandResult::Exit => break,
+                        commands::CommandResult::Exit => {
+                            if confirm_exit(&mut editor, &agent) {
+                                break;
+                            }
+                            continue;
+                        }
                         commands::CommandResult::Error(e) => {
                             eprintln!("Error: {}", e);
                             continue;
@@ -107,7 +144,11 @@ pub async fn run(cli: Cli, ctx: Arc<AppContext>) -> anyhow::Result<()> {
                 continue;
             }
             Err(ReadlineError::Eof) => {
-                println!("\nGoodbye!");
+                println!();
+                if !confirm_exit(&mut editor, &agent) {
+                    continue;
+                }
+                println!("Goodbye!");
                 break;
             }
             Err(err) => {
@@ -126,12 +167,65 @@ pub async fn run(cli: Cli, ctx: Arc<AppContext>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Ask what to do with changes staged by a dry run before exiting.
+///
+/// Returns `false` if the user keeps reviewing them instead. Without
+/// anyone to ask, the changes are discarded.
+fn confirm_exit(editor: &mut DefaultEditor, agent: &Agent) -> bool {
+    let changeset = agent.changeset();
+    if changeset.is_empty() {
+        return true;
+    }
+
+    let working_dir = &agent.session().metadata.working_directory;
+    let display = |path: &std::path::Path| {
+        path.strip_prefix(working_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    println!("Staged changes not yet written to disk:");
+    for path in changeset.paths() {
+        println!("  {}", display(&path));
+    }
+
+    loop {
+        match editor.readline("[a]ccept / [d]iscard / [k]eep reviewing: ") {
+            Ok(answer) => match answer.trim().to_lowercase().as_str() {
+                "a" | "accept" => match changeset.accept_all() {
+                    Ok(paths) => {
+                        for path in &paths {
+                            println!("[Wrote {}]", display(path));
+                        }
+                        return true;
+                    }
+                    Err(e) => {
+                        eprintln!("Error: Failed to write changes: {}", e);
+                        return false;
+                    }
+                },
+                "d" | "discard" => break,
+                "k" | "keep" => return false,
+                _ => eprintln!("Unknown response."),
+            },
+            Err(ReadlineError::Interrupted) => return false,
+            Err(_) => break,
+        }
+    }
+    let paths = changeset.discard_all();
+    println!("[Discarded {} staged file change(s)]", paths.len());
+    true
+}
+
 /// Create the agent with CLI handlers and tools.
 fn create_agent(
     cli: &Cli,
     ctx: &AppContext,
     store: Arc<dyn SessionStore>,
     session: Session,
+    checkpoint_dir: PathBuf,
+    artifact_dir: PathBuf,
+    memory_dir: PathBuf,
 ) -> anyhow::Result<Agent> {
     // Get the provider
     let provider = ctx.registry.default_provider().ok_or_else(|| {
@@ -145,18 +239,53 @@ fn create_agent(
     // Determine initial mode
     let mode = if cli.plan {
         AgentMode::Planning
+    } else if cli.dry_run {
+        AgentMode::DryRun
     } else {
         AgentMode::Supervised
     };
 
     // Build tool executor with builtins
     let working_dir = session.metadata.working_directory.clone();
-    let executor = ExecutorBuilder::new()
+    let instructions = Arc::new(Instructions::discover(
+        &working_dir,
+        Some(Config::config_dir().join("AGENTS.md")),
+    ));
+    for path in instructions.take_loaded() {
+        println!("[Instructions: {}]", path.display());
+    }
+    let mut executor = ExecutorBuilder::new()
         .with_builtins()
         .working_dir(&working_dir)
         .permissions(ctx.config.permissions.clone())
         .mode(mode)
-        .build(permission_handler);
+        .max_parallel(ctx.config.limits.max_parallel_tools)
+        .checkpoints(Arc::new(CheckpointStore::new(checkpoint_dir)))
+        .instructions(instructions)
+        .artifacts(Arc::new(ArtifactStore::new(
+            artifact_dir,
+            ctx.config.output.clone(),
+        )));
+    if !ctx.config.hooks.is_empty() {
+        executor = executor.hooks(Arc::new(HookRunner::new(
+            ctx.config.hooks.clone(),
+            &working_dir,
+        )));
+    }
+    if ctx.config.memory.enabled {
+        let project = MemoryStore::project_root(&session.metadata);
+        match MemoryStore::open(&memory_dir, project, ctx.config.memory.clone()) {
+            Ok(memory) => {
+                let count = memory.list().len();
+                if count > 0 {
+                    println!("[Memories: {}]", count);
+                }
+                executor = executor.memory(Arc::new(memory));
+            }
+            Err(e) => eprintln!("Warning: failed to open project memory: {}", e),
+        }
+    }
+    let executor = executor.build(permission_handler);
 
     // Determine model
     let model = cli
@@ -165,17 +294,41 @@ fn create_agent(
         .unwrap_or_else(|| ctx.config.general.model.clone());
 
     // Build the agent
-    let agent = AgentBuilder::new()
+    let mut builder = AgentBuilder::new()
         .provider(provider)
         .executor(executor)
         .store(store)
         .session(session)
         .model(model)
         .max_tokens(ctx.config.limits.max_tokens)
+        .max_continuations(ctx.config.limits.max_continuations)
+        .question_timeout(
+            Some(ctx.config.limits.question_timeout_secs)
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+        )
+        .loop_detection(ctx.config.loop_detection.clone())
+        .verify(ctx.config.verify.clone())
         .temperature(0.7)
         .event_handler(event_handler)
-        .mode(mode)
-        .build()?;
+        .mode(mode);
+    if let Some(ref compaction_model) = ctx.config.general.compaction_model {
+        builder = builder.compaction_model(compaction_model);
+    }
+    // Architect and editor models may be served by other providers
+    if let Some(ref architect_model) = ctx.config.general.architect_model {
+        builder = match ctx.registry.resolve_model(architect_model) {
+            Some((provider, model)) => builder.architect_model(model).architect_provider(provider),
+            None => builder.architect_model(architect_model),
+        };
+    }
+    if let Some(ref editor_model) = ctx.config.general.editor_model {
+        builder = match ctx.registry.resolve_model(editor_model) {
+            Some((provider, model)) => builder.editor_model(model).editor_provider(provider),
+            None => builder.editor_model(editor_model),
+        };
+    }
+    let agent = builder.build()?;
 
     Ok(agent)
 }
@@ -241,11 +394,45 @@ async fn process_message(input: &str, agent: &mut Agent) -> anyhow::Result<()> {
     // Reset cancellation for new message
     agent.reset_cancel();
 
-    // Run the agent - event handler streams text via on_text_delta
+    // Run the agent - event handler streams text via on_text_delta, and
+    // lines typed meanwhile steer it
     println!();
-    match agent.run(input).await {
-        Ok(_response) => {
-            // Response is already streamed via event handler
+    let steering = input::steer(agent.steering());
+    let result = agent.run(input).await;
+    drop(steering);
+    match result {
+        Ok(response) => {
+            // Response is already streamed via event handler; show the plan
+            // for review when the model proposed one
+            if agent.mode() == AgentMode::Architect {
+                for role in [ModelRole::Architect, ModelRole::Editor] {
+                    let usage = response.usage_for(role);
+                    eprintln!(
+                        "[{}: {} in / {} out]",
+                        role, usage.input_tokens, usage.output_tokens
+                    );
+                }
+            }
+            if agent.mode() == AgentMode::Planning {
+                if let Some(plan) = agent.plan().filter(|p| !p.approved) {
+                    println!();
+                    print!("{}", plan);
+                    println!("[Review with /plan edit|move|add|remove, then /plan approve and /plan run]");
+                }
+            }
+            let changeset = agent.changeset();
+            if !changeset.is_empty() {
+                let working_dir = &agent.session().metadata.working_directory;
+                println!();
+                println!("Staged changes:");
+                for path in changeset.paths() {
+                    println!(
+                        "  {}",
+                        path.strip_prefix(working_dir).unwrap_or(&path).display()
+                    );
+                }
+                println!("[Review with /changes, then /changes accept or /changes discard, optionally per file]");
+            }
             Ok(())
         }
         Err(agentik_agent::AgentError::Cancelled) => {
@@ -281,6 +468,10 @@ fn print_welcome_banner(cli: &Cli, ctx: &AppContext) {
         println!("[Planning mode enabled]");
     }
 
+    if cli.dry_run {
+        println!("[Dry run: file changes are staged for review]");
+    }
+
     if !cli.add.is_empty() {
         println!("[Added to context: {:?}]", cli.add);
     }
@@ -5,6 +5,7 @@ use figment::{
     Figment,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use crate::error::Error;
@@ -25,6 +26,20 @@ pub struct Config {
     pub sandbox: SandboxConfig,
     /// Provider configurations
     pub providers: ProvidersConfig,
+    /// HTTP network settings shared by providers, web tools and MCP
+    pub network: NetworkConfig,
+    /// Debugging aids
+    pub debug: DebugConfig,
+    /// Lifecycle hooks
+    pub hooks: HooksConfig,
+    /// Checks run after turns that edit files
+    pub verify: VerifyConfig,
+    /// Handling of large tool outputs
+    pub output: OutputConfig,
+    /// Detection of an agent stuck repeating itself
+    pub loop_detection: LoopDetectionConfig,
+    /// Facts remembered per project across sessions
+    pub memory: MemoryConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +53,15 @@ pub struct GeneralConfig {
     pub sandbox: bool,
     /// Auto-save sessions
     pub auto_save: bool,
+    /// Cheaper model for summarizing history during compaction (defaults to `model`)
+    pub compaction_model: Option<String>,
+    /// Model that writes proposals in architect mode (defaults to `model`);
+    /// `provider/model` selects a provider other than the default
+    pub architect_model: Option<String>,
+    /// Cheaper or faster model that implements proposals in architect mode
+    /// (defaults to `model`); `provider/model` selects a provider other than
+    /// the default
+    pub editor_model: Option<String>,
 }
 
 impl Default for GeneralConfig {
@@ -47,6 +71,9 @@ impl Default for GeneralConfig {
             provider: "anthropic".to_string(),
             sandbox: true,
             auto_save: true,
+            compaction_model: None,
+            architect_model: None,
+            editor_model: None,
         }
     }
 }
@@ -93,6 +120,13 @@ pub struct LimitsConfig {
     pub daily_budget: Option<f64>,
     /// Monthly budget (USD)
     pub monthly_budget: Option<f64>,
+    /// Maximum tool calls executing at the same time
+    pub max_parallel_tools: usize,
+    /// Maximum continuations of a response cut off by the output token limit
+    pub max_continuations: u32,
+    /// Seconds to wait for answers to questions before using their
+    /// defaults (0 waits indefinitely)
+    pub question_timeout_secs: u64,
 }
 
 impl Default for LimitsConfig {
@@ -104,6 +138,9 @@ impl Default for LimitsConfig {
             cost_warning_threshold: 1.0,
             daily_budget: None,
             monthly_budget: None,
+            max_parallel_tools: 8,
+            max_continuations: 3,
+            question_timeout_secs: 300,
         }
     }
 }
@@ -167,6 +204,17 @@ pub struct ProvidersConfig {
     pub openai: Option<ProviderConfig>,
     /// Local/Ollama configuration
     pub local: Option<LocalProviderConfig>,
+    /// Client-side rate limits keyed by `provider` or `provider/model`
+    pub rate_limits: HashMap<String, RateLimitConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Maximum requests per minute
+    pub requests_per_minute: Option<u32>,
+    /// Maximum tokens (input + output) per minute
+    pub tokens_per_minute: Option<u32>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -180,6 +228,10 @@ pub struct ProviderConfig {
     pub default_model: Option<String>,
     /// Base URL (optional, for custom endpoints)
     pub base_url: Option<String>,
+    /// API version header (Anthropic `anthropic-version`)
+    pub api_version: Option<String>,
+    /// Extra headers sent with every request (e.g. `anthropic-beta`)
+    pub headers: HashMap<String, String>,
 }
 
 impl ProviderConfig {
@@ -208,6 +260,9 @@ pub struct LocalProviderConfig {
     pub base_url: Option<String>,
     /// Default model
     pub default_model: Option<String>,
+    /// Per-model override of prompt-based tool emulation (model id -> emulate).
+    /// Models not listed here or by the provider are emulated.
+    pub emulate_tools: HashMap<String, bool>,
 }
 
 impl Default for LocalProviderConfig {
@@ -216,6 +271,236 @@ impl Default for LocalProviderConfig {
             enabled: true,
             base_url: None,
             default_model: None,
+            emulate_tools: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Proxy URL for all HTTP(S) traffic (falls back to HTTPS_PROXY etc.)
+    pub proxy: Option<String>,
+    /// Hosts that bypass the proxy
+    pub no_proxy: Vec<String>,
+    /// Extra PEM bundle of root certificates to trust
+    pub ca_bundle: Option<PathBuf>,
+    /// Connection timeout in seconds
+    pub connect_timeout_secs: u64,
+    /// Timeout between reads in seconds (applies to streaming responses)
+    pub read_timeout_secs: u64,
+    /// Overall request timeout in seconds (none by default so long streams aren't cut off)
+    pub timeout_secs: Option<u64>,
+    /// Headers added to every request
+    pub headers: HashMap<String, String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            no_proxy: vec![],
+            ca_bundle: None,
+            connect_timeout_secs: 30,
+            read_timeout_secs: 300,
+            timeout_secs: None,
+            headers: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    /// Write provider requests/responses to a per-session wire log
+    pub wire_log: bool,
+    /// Extra regex patterns redacted from the wire log
+    pub redact_patterns: Vec<String>,
+}
+
+/// Shell commands run on agent lifecycle events.
+///
+/// Each hook receives a JSON description of the event on stdin. See
+/// `agentik_agent::hooks` for the protocol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Before a tool runs; may allow, deny or rewrite the call
+    pub pre_tool_use: Vec<HookConfig>,
+    /// After a tool completes; may append feedback to the result
+    pub post_tool_use: Vec<HookConfig>,
+    /// When the user submits a prompt; may block it or add context
+    pub user_prompt_submit: Vec<HookConfig>,
+    /// When the agent finishes responding
+    pub stop: Vec<HookConfig>,
+    /// Before the conversation is compacted
+    pub pre_compact: Vec<HookConfig>,
+    /// Default timeout for a hook command (seconds)
+    pub timeout_secs: u64,
+    /// Let a tool call or prompt through when one of its hooks times out;
+    /// by default it is denied
+    pub allow_on_timeout: bool,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_tool_use: vec![],
+            post_tool_use: vec![],
+            user_prompt_submit: vec![],
+            stop: vec![],
+            pre_compact: vec![],
+            timeout_secs: 60,
+            allow_on_timeout: false,
+        }
+    }
+}
+
+impl HooksConfig {
+    /// Whether no hooks are configured.
+    pub fn is_empty(&self) -> bool {
+        self.pre_tool_use.is_empty()
+            && self.post_tool_use.is_empty()
+            && self.user_prompt_submit.is_empty()
+            && self.stop.is_empty()
+            && self.pre_compact.is_empty()
+    }
+}
+
+/// A single hook command.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookConfig {
+    /// Shell command to run
+    pub command: String,
+    /// Tool names the hook applies to, separated by `|` (tool events only;
+    /// empty or `*` matches every tool)
+    pub matcher: Option<String>,
+    /// Timeout override (seconds)
+    pub timeout_secs: Option<u64>,
+}
+
+impl HookConfig {
+    /// Whether the hook applies to a tool.
+    pub fn matches(&self, tool_name: &str) -> bool {
+        match self.matcher.as_deref().map(str::trim) {
+            None | Some("") | Some("*") => true,
+            Some(matcher) => matcher.split('|').any(|m| m.trim() == tool_name),
+        }
+    }
+}
+
+/// Checks run automatically after a turn that edits files.
+///
+/// Failures are fed back to the model, which gets up to `max_attempts` turns
+/// to repair them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerifyConfig {
+    /// Shell commands to run in order (e.g. `cargo check`, `npm run lint`)
+    pub commands: Vec<String>,
+    /// Maximum repair attempts after a failed check
+    pub max_attempts: u32,
+    /// Timeout for each command (seconds)
+    pub timeout_secs: u64,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            commands: vec![],
+            max_attempts: 3,
+            timeout_secs: 300,
+        }
+    }
+}
+
+impl VerifyConfig {
+    /// Whether any checks are configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.commands.is_empty()
+    }
+}
+
+/// Handling of large tool outputs.
+///
+/// Outputs above `offload_threshold` are saved to disk as artifacts; the
+/// conversation only gets an excerpt of their start and end, and the model
+/// can read the rest on demand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Outputs larger than this (bytes) are saved as artifacts
+    pub offload_threshold: usize,
+    /// Bytes from the start of an offloaded output kept in the conversation
+    pub head_bytes: usize,
+    /// Bytes from the end of an offloaded output kept in the conversation
+    pub tail_bytes: usize,
+    /// Tool results kept in full in the context; older ones are elided
+    pub keep_recent_results: usize,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            offload_threshold: 20_000,
+            head_bytes: 8_000,
+            tail_bytes: 4_000,
+            keep_recent_results: 10,
+        }
+    }
+}
+
+/// Detection of an agent stuck repeating itself.
+///
+/// The first `notes_before_escalation` detections in a run are answered with
+/// a corrective note to the model; later ones ask the user whether to go on,
+/// and the run stops once `max_detections` is exceeded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoopDetectionConfig {
+    /// Identical calls with identical results that count as a loop
+    pub identical_calls: usize,
+    /// Identical tool errors that count as a loop
+    pub repeated_errors: usize,
+    /// Times a file may return to an earlier version before it counts as a loop
+    pub oscillations: usize,
+    /// Detections answered with a corrective note before asking the user
+    pub notes_before_escalation: u32,
+    /// Detections allowed in a run before it is stopped
+    pub max_detections: u32,
+}
+
+impl Default for LoopDetectionConfig {
+    fn default() -> Self {
+        Self {
+            identical_calls: 3,
+            repeated_errors: 3,
+            oscillations: 2,
+            notes_before_escalation: 1,
+            max_detections: 3,
+        }
+    }
+}
+
+/// Facts remembered per project across sessions.
+///
+/// The memories most relevant to the conversation are added to the system
+/// prompt, up to `prompt_tokens`; the model can recall the others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Whether the memory tools are available
+    pub enabled: bool,
+    /// Tokens of memories added to the system prompt (0 adds none)
+    pub prompt_tokens: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            prompt_tokens: 1_000,
         }
     }
 }
@@ -366,6 +651,25 @@ impl Config {
             result.add_error("general.provider", "Provider name cannot be empty");
         }
 
+        if self.general.compaction_model.as_deref() == Some("") {
+            result.add_error(
+                "general.compaction_model",
+                "Compaction model cannot be empty; omit it to use the main model",
+            );
+        }
+
+        for (field, model) in [
+            ("general.architect_model", &self.general.architect_model),
+            ("general.editor_model", &self.general.editor_model),
+        ] {
+            if model.as_deref() == Some("") {
+                result.add_error(
+                    field,
+                    "Model cannot be empty; omit it to use the main model",
+                );
+            }
+        }
+
         // Validate limits
         if self.limits.max_tokens == 0 {
             result.add_error("limits.max_tokens", "max_tokens must be greater than 0");
@@ -389,6 +693,13 @@ impl Config {
             result.add_error("limits.max_turns", "max_turns must be greater than 0");
         }
 
+        if self.limits.max_parallel_tools == 0 {
+            result.add_error(
+                "limits.max_parallel_tools",
+                "max_parallel_tools must be greater than 0",
+            );
+        }
+
         if self.limits.cost_warning_threshold < 0.0 {
             result.add_error(
                 "limits.cost_warning_threshold",
@@ -442,6 +753,14 @@ impl Config {
             {
                 result.add_warning("providers.anthropic.api_key", "API key is empty string");
             }
+            if let Some(ref base_url) = anthropic.base_url {
+                if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+                    result.add_error(
+                        "providers.anthropic.base_url",
+                        "base_url must start with http:// or https://",
+                    );
+                }
+            }
         }
 
         if let Some(ref openai) = self.providers.openai {
@@ -463,6 +782,104 @@ impl Config {
             }
         }
 
+        // Validate network settings
+        if let Some(ref proxy) = self.network.proxy {
+            if !proxy.contains("://") {
+                result.add_error(
+                    "network.proxy",
+                    "proxy must be a URL such as http://proxy:8080",
+                );
+            }
+        }
+        if let Some(ref ca_bundle) = self.network.ca_bundle {
+            if !ca_bundle.exists() {
+                result.add_error(
+                    "network.ca_bundle",
+                    format!("CA bundle not found: {}", ca_bundle.display()),
+                );
+            }
+        }
+        if self.network.connect_timeout_secs == 0 || self.network.read_timeout_secs == 0 {
+            result.add_error("network", "timeouts must be greater than 0");
+        }
+
+        for (key, limit) in &self.providers.rate_limits {
+            if limit.requests_per_minute == Some(0) || limit.tokens_per_minute == Some(0) {
+                result.add_error(
+                    format!("providers.rate_limits.{}", key),
+                    "rate limits must be greater than 0",
+                );
+            }
+        }
+
+        let hooks = &self.hooks;
+        for (event, list) in [
+            ("pre_tool_use", &hooks.pre_tool_use),
+            ("post_tool_use", &hooks.post_tool_use),
+            ("user_prompt_submit", &hooks.user_prompt_submit),
+            ("stop", &hooks.stop),
+            ("pre_compact", &hooks.pre_compact),
+        ] {
+            for (i, hook) in list.iter().enumerate() {
+                if hook.command.trim().is_empty() {
+                    result.add_error(
+                        format!("hooks.{}[{}].command", event, i),
+                        "Hook command cannot be empty",
+                    );
+                }
+                if hook.timeout_secs == Some(0) {
+                    result.add_error(
+                        format!("hooks.{}[{}].timeout_secs", event, i),
+                        "Hook timeout must be greater than 0",
+                    );
+                }
+            }
+        }
+        if hooks.timeout_secs == 0 {
+            result.add_error("hooks.timeout_secs", "Hook timeout must be greater than 0");
+        }
+
+        for (i, command) in self.verify.commands.iter().enumerate() {
+            if command.trim().is_empty() {
+                result.add_error(
+                    format!("verify.commands[{}]", i),
+                    "Verify command cannot be empty",
+                );
+            }
+        }
+        if self.verify.timeout_secs == 0 || self.verify.timeout_secs > 600 {
+            result.add_error(
+                "verify.timeout_secs",
+                "Verify timeout must be between 1 and 600 seconds",
+            );
+        }
+
+        let loops = &self.loop_detection;
+        for (field, value) in [
+            ("identical_calls", loops.identical_calls),
+            ("repeated_errors", loops.repeated_errors),
+        ] {
+            if value < 2 {
+                result.add_error(
+                    format!("loop_detection.{}", field),
+                    format!("{} must be at least 2", field),
+                );
+            }
+        }
+        if loops.oscillations == 0 {
+            result.add_error(
+                "loop_detection.oscillations",
+                "oscillations must be greater than 0",
+            );
+        }
+
+        if self.output.head_bytes + self.output.tail_bytes >= self.output.offload_threshold {
+            result.add_error(
+                "output.offload_threshold",
+                "offload_threshold must be greater than head_bytes + tail_bytes",
+            );
+        }
+
         result
     }
 
@@ -517,6 +934,73 @@ mod tests {
         assert!(result.errors().iter().any(|e| e.field == "display.color"));
     }
 
+    #[test]
+    fn test_hook_matcher_and_validation() {
+        let hook = HookConfig {
+            command: "cargo fmt".to_string(),
+            matcher: Some("Edit | Write".to_string()),
+            timeout_secs: None,
+        };
+        assert!(hook.matches("Write"));
+        assert!(!hook.matches("Read"));
+        assert!(HookConfig::default().matches("Read"));
+
+        let mut config = Config::default();
+        config.hooks.post_tool_use = vec![hook, HookConfig::default()];
+        let result = config.validate();
+        assert_eq!(result.errors().len(), 1);
+        assert_eq!(result.errors()[0].field, "hooks.post_tool_use[1].command");
+    }
+
+    #[test]
+    fn test_verify_validation() {
+        let mut config = Config::default();
+        assert!(!config.verify.is_enabled());
+        config.verify.commands = vec!["cargo check".to_string(), " ".to_string()];
+        config.verify.timeout_secs = 0;
+        let result = config.validate();
+        let fields: Vec<_> = result.errors().iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["verify.commands[1]", "verify.timeout_secs"]);
+    }
+
+    #[test]
+    fn test_loop_detection_validation() {
+        let mut config = Config::default();
+        config.loop_detection.identical_calls = 1;
+        config.loop_detection.oscillations = 0;
+        let result = config.validate();
+        let fields: Vec<_> = result.errors().iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "loop_detection.identical_calls",
+                "loop_detection.oscillations"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_validation() {
+        let mut config = Config::default();
+        config.output.offload_threshold = 10_000;
+        let result = config.validate();
+        let fields: Vec<_> = result.errors().iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["output.offload_threshold"]);
+    }
+
+    #[test]
+    fn test_empty_editor_model() {
+        let mut config = Config::default();
+        config.general.architect_model = Some("claude-opus-4-20250514".to_string());
+        config.general.editor_model = Some(String::new());
+        let result = config.validate();
+        assert!(!result.is_ok());
+        assert!(result
+            .errors()
+            .iter()
+            .all(|e| e.field == "general.editor_model"));
+    }
+
     #[test]
     fn test_invalid_sandbox_mode() {
         let mut config = Config::default();
@@ -0,0 +1,123 @@
+//! Shared HTTP client factory.
+//!
+//! Every component that talks HTTP (providers, web tools, MCP transports)
+//! should build its client here so that the `[network]` config section —
+//! proxy, extra root certificates, timeouts and default headers — applies
+//! uniformly.
+
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+
+use crate::config::NetworkConfig;
+use crate::error::{Error, Result};
+
+/// Build an HTTP client from network configuration.
+pub fn build_client(config: &NetworkConfig) -> Result<Client> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.read_timeout_secs));
+
+    if let Some(secs) = config.timeout_secs {
+        builder = builder.timeout(Duration::from_secs(secs));
+    }
+
+    // Without an explicit proxy reqwest honours HTTP(S)_PROXY / NO_PROXY
+    if let Some(ref url) = config.proxy {
+        let mut proxy = Proxy::all(url)
+            .map_err(|e| Error::Config(format!("Invalid proxy URL '{}': {}", url, e)))?;
+        if !config.no_proxy.is_empty() {
+            proxy = proxy.no_proxy(NoProxy::from_string(&config.no_proxy.join(",")));
+        }
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ref path) = config.ca_bundle {
+        let pem = std::fs::read(path).map_err(|e| {
+            Error::Config(format!(
+                "Failed to read CA bundle {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| Error::Config(format!("Invalid CA bundle {}: {}", path.display(), e)))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if !config.headers.is_empty() {
+        builder = builder.default_headers(default_headers(config)?);
+    }
+
+    builder
+        .build()
+        .map_err(|e| Error::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// Build an HTTP client, falling back to the default client on error.
+///
+/// Configuration errors are logged rather than returned so that a bad
+/// `[network]` section doesn't prevent startup.
+pub fn build_client_or_default(config: &NetworkConfig) -> Client {
+    build_client(config).unwrap_or_else(|e| {
+        tracing::warn!("{}; using default HTTP client", e);
+        Client::new()
+    })
+}
+
+/// Convert configured headers into a header map.
+fn default_headers(config: &NetworkConfig) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &config.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| Error::Config(format!("Invalid header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| Error::Config(format!("Invalid value for header '{}': {}", name, e)))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_builds() {
+        assert!(build_client(&NetworkConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_proxy_and_headers() {
+        let mut config = NetworkConfig {
+            proxy: Some("http://proxy.internal:8080".to_string()),
+            no_proxy: vec!["localhost".to_string(), "127.0.0.1".to_string()],
+            ..Default::default()
+        };
+        config
+            .headers
+            .insert("X-Team".to_string(), "platform".to_string());
+        assert!(build_client(&config).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_header_rejected() {
+        let mut config = NetworkConfig::default();
+        config
+            .headers
+            .insert("bad header".to_string(), "value".to_string());
+        assert!(matches!(build_client(&config), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_missing_ca_bundle_rejected() {
+        let config = NetworkConfig {
+            ca_bundle: Some("/nonexistent/ca.pem".into()),
+            ..Default::default()
+        };
+        assert!(matches!(build_client(&config), Err(Error::Config(_))));
+    }
+}
@@ -6,17 +6,22 @@
 //! - Message and conversation primitives
 //! - Tool definitions and execution types
 //! - Session and state management types
+//! - Structured task plans
 //! - Configuration system
+//! - Shared HTTP client factory
 //! - Common error types
 
 pub mod config;
 pub mod error;
+pub mod http;
 pub mod message;
+pub mod plan;
 pub mod session;
 pub mod tool;
 
 pub use config::Config;
 pub use error::{Error, Result};
 pub use message::{Content, Message, Role};
+pub use plan::{Plan, PlanStep, PlanStepStatus};
 pub use session::{Session, SessionMetadata, SessionState};
 pub use tool::{ToolCall, ToolDefinition, ToolResult};
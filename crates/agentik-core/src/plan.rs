@@ -0,0 +1,353 @@
+//! Structured task plans.
+//!
+//! A [`Plan`] is an ordered list of steps produced by the model in planning
+//! mode. The user reviews and edits it, approves it, and the agent then
+//! executes it step by step, recording each step's status. Plans are stored
+//! in [`SessionMetadata`](crate::SessionMetadata) so they survive resumes.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+
+/// Execution status of a plan step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStepStatus {
+    /// Not started yet
+    #[default]
+    Pending,
+    /// Currently being executed
+    InProgress,
+    /// Finished successfully
+    Completed,
+    /// Attempted and failed
+    Failed,
+    /// Skipped by the user or a revised plan
+    Skipped,
+}
+
+impl PlanStepStatus {
+    /// Whether the step no longer needs work.
+    pub fn is_done(self) -> bool {
+        matches!(self, Self::Completed | Self::Skipped)
+    }
+
+    /// Short marker used when rendering a plan.
+    pub fn marker(self) -> &'static str {
+        match self {
+            Self::Pending => "[ ]",
+            Self::InProgress => "[>]",
+            Self::Completed => "[x]",
+            Self::Failed => "[!]",
+            Self::Skipped => "[-]",
+        }
+    }
+}
+
+impl std::str::FromStr for PlanStepStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "pending" => Ok(Self::Pending),
+            "in_progress" | "in-progress" | "running" => Ok(Self::InProgress),
+            "completed" | "complete" | "done" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            "skipped" => Ok(Self::Skipped),
+            other => Err(Error::Validation(format!(
+                "Unknown plan step status: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A single step of a plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// What the step does
+    pub description: String,
+    /// Files the step is expected to touch
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// Execution status
+    #[serde(default)]
+    pub status: PlanStepStatus,
+    /// Outcome or failure notes
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl PlanStep {
+    /// Create a pending step.
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            files: vec![],
+            status: PlanStepStatus::Pending,
+            notes: None,
+        }
+    }
+
+    /// Set the target files.
+    pub fn with_files(mut self, files: Vec<PathBuf>) -> Self {
+        self.files = files;
+        self
+    }
+}
+
+/// An ordered, reviewable task plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    /// Overall goal
+    pub goal: String,
+    /// Ordered steps
+    pub steps: Vec<PlanStep>,
+    /// Whether the user approved the plan for execution
+    #[serde(default)]
+    pub approved: bool,
+    /// Number of times the plan was revised after a failure
+    #[serde(default)]
+    pub revisions: u32,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Plan {
+    /// Create an unapproved plan.
+    pub fn new(goal: impl Into<String>, steps: Vec<PlanStep>) -> Self {
+        let now = Utc::now();
+        Self {
+            goal: goal.into(),
+            steps,
+            approved: false,
+            revisions: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Mark the plan as approved.
+    pub fn approve(&mut self) {
+        self.approved = true;
+        self.touch();
+    }
+
+    /// Append a step.
+    pub fn add_step(&mut self, step: PlanStep) {
+        self.steps.push(step);
+        self.touch();
+    }
+
+    /// Replace a step's description.
+    pub fn edit_step(&mut self, index: usize, description: impl Into<String>) -> Result<()> {
+        self.step_mut(index)?.description = description.into();
+        self.touch();
+        Ok(())
+    }
+
+    /// Remove a step.
+    pub fn remove_step(&mut self, index: usize) -> Result<PlanStep> {
+        self.check_index(index)?;
+        let step = self.steps.remove(index);
+        self.touch();
+        Ok(step)
+    }
+
+    /// Move a step to a new position.
+    pub fn move_step(&mut self, from: usize, to: usize) -> Result<()> {
+        self.check_index(from)?;
+        self.check_index(to)?;
+        let step = self.steps.remove(from);
+        self.steps.insert(to, step);
+        self.touch();
+        Ok(())
+    }
+
+    /// Update a step's status and notes.
+    pub fn set_status(
+        &mut self,
+        index: usize,
+        status: PlanStepStatus,
+        notes: Option<String>,
+    ) -> Result<()> {
+        let step = self.step_mut(index)?;
+        step.status = status;
+        if notes.is_some() {
+            step.notes = notes;
+        }
+        self.touch();
+        Ok(())
+    }
+
+    /// The first step that still needs work, with its index.
+    pub fn next_step(&self) -> Option<(usize, &PlanStep)> {
+        self.steps
+            .iter()
+            .enumerate()
+            .find(|(_, s)| !s.status.is_done())
+    }
+
+    /// Whether every step is completed or skipped.
+    pub fn is_complete(&self) -> bool {
+        self.steps.iter().all(|s| s.status.is_done())
+    }
+
+    /// Whether any step has been worked on.
+    pub fn has_progress(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|s| s.status != PlanStepStatus::Pending)
+    }
+
+    /// Replace the unfinished steps with revised ones.
+    ///
+    /// Completed and skipped steps are kept so that progress isn't lost.
+    /// The revised plan needs approval again.
+    pub fn revise(&mut self, goal: Option<String>, steps: Vec<PlanStep>) {
+        if let Some(goal) = goal {
+            self.goal = goal;
+        }
+        self.steps.retain(|s| s.status.is_done());
+        self.steps.extend(steps);
+        self.approved = false;
+        self.revisions += 1;
+        self.touch();
+    }
+
+    fn step_mut(&mut self, index: usize) -> Result<&mut PlanStep> {
+        self.check_index(index)?;
+        Ok(&mut self.steps[index])
+    }
+
+    fn check_index(&self, index: usize) -> Result<()> {
+        if index < self.steps.len() {
+            Ok(())
+        } else {
+            Err(Error::Validation(format!(
+                "Step {} does not exist (plan has {} steps)",
+                index + 1,
+                self.steps.len()
+            )))
+        }
+    }
+
+    fn touch(&mut self) {
+        self.updated_at = Utc::now();
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.is_complete() {
+            "complete"
+        } else if self.approved {
+            "approved"
+        } else {
+            "awaiting approval"
+        };
+        writeln!(f, "Plan: {} ({})", self.goal, state)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            write!(
+                f,
+                "  {} {}. {}",
+                step.status.marker(),
+                i + 1,
+                step.description
+            )?;
+            if !step.files.is_empty() {
+                let files: Vec<String> =
+                    step.files.iter().map(|p| p.display().to_string()).collect();
+                write!(f, " ({})", files.join(", "))?;
+            }
+            writeln!(f)?;
+            if let Some(ref notes) = step.notes {
+                writeln!(f, "        {}", notes)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> Plan {
+        Plan::new(
+            "Add logging",
+            vec![
+                PlanStep::new("Add dependency").with_files(vec!["Cargo.toml".into()]),
+                PlanStep::new("Initialize logger"),
+                PlanStep::new("Write tests"),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_edit_and_reorder() {
+        let mut plan = plan();
+        plan.move_step(2, 0).unwrap();
+        plan.edit_step(1, "Add tracing dependency").unwrap();
+        assert_eq!(plan.steps[0].description, "Write tests");
+        assert_eq!(plan.steps[1].description, "Add tracing dependency");
+
+        plan.remove_step(0).unwrap();
+        assert_eq!(plan.steps.len(), 2);
+        assert!(plan.move_step(0, 5).is_err());
+        assert!(plan.edit_step(9, "x").is_err());
+    }
+
+    #[test]
+    fn test_next_step_and_completion() {
+        let mut plan = plan();
+        assert_eq!(plan.next_step().unwrap().0, 0);
+
+        plan.set_status(0, PlanStepStatus::Completed, None).unwrap();
+        plan.set_status(1, PlanStepStatus::Skipped, None).unwrap();
+        assert_eq!(plan.next_step().unwrap().0, 2);
+        assert!(!plan.is_complete());
+
+        plan.set_status(2, PlanStepStatus::Completed, None).unwrap();
+        assert!(plan.is_complete());
+        assert!(plan.next_step().is_none());
+    }
+
+    #[test]
+    fn test_revise_keeps_finished_steps() {
+        let mut plan = plan();
+        plan.approve();
+        plan.set_status(0, PlanStepStatus::Completed, None).unwrap();
+        plan.set_status(1, PlanStepStatus::Failed, Some("no such crate".into()))
+            .unwrap();
+
+        plan.revise(None, vec![PlanStep::new("Use env_logger instead")]);
+
+        assert!(!plan.approved);
+        assert_eq!(plan.revisions, 1);
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[0].status, PlanStepStatus::Completed);
+        assert_eq!(plan.steps[1].description, "Use env_logger instead");
+    }
+
+    #[test]
+    fn test_display_and_status_parsing() {
+        let mut plan = plan();
+        plan.set_status(0, PlanStepStatus::Completed, None).unwrap();
+        let text = plan.to_string();
+        assert!(text.contains("awaiting approval"));
+        assert!(text.contains("[x] 1. Add dependency (Cargo.toml)"));
+        assert!(text.contains("[ ] 2. Initialize logger"));
+
+        assert_eq!(
+            "done".parse::<PlanStepStatus>().unwrap(),
+            PlanStepStatus::Completed
+        );
+        assert!("bogus".parse::<PlanStepStatus>().is_err());
+    }
+}
@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
 
+use crate::plan::Plan;
+
 /// Session state in its lifecycle.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -54,6 +56,9 @@ pub struct SessionMetadata {
     pub metrics: SessionMetrics,
     /// Model configuration
     pub model: ModelConfig,
+    /// Current task plan (planning mode)
+    #[serde(default)]
+    pub plan: Option<Plan>,
 }
 
 impl SessionMetadata {
@@ -75,6 +80,7 @@ impl SessionMetadata {
             git: None,
             metrics: SessionMetrics::default(),
             model: ModelConfig::default(),
+            plan: None,
         }
     }
 }
@@ -17,6 +17,8 @@ pub enum ToolCategory {
     Web,
     /// External application integration
     External,
+    /// Agent coordination (plans, questions, subagents)
+    Agent,
     /// MCP-provided tool
     Mcp(String),
 }
@@ -162,6 +164,10 @@ impl ToolResult {
     }
 }
 
+/// Start of the note that replaces the bulk of an output saved as an
+/// artifact; it names the artifact so the full output can be read later.
+pub const ARTIFACT_NOTE_PREFIX: &str = "[Full output saved as artifact";
+
 /// Artifact produced by a tool.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Artifact {
@@ -0,0 +1,242 @@
+//! Capability-aware request adaptation.
+//!
+//! The same [`CompletionRequest`] is built regardless of which model it is
+//! sent to. [`adapt_request`] rewrites it against the target model's
+//! [`ModelInfo`] so that switching models mid-session doesn't produce
+//! requests the model rejects.
+
+use std::fmt;
+
+use tracing::{debug, warn};
+
+use agentik_core::message::ContentPart;
+use agentik_core::{Content, Message, Role};
+
+use crate::traits::{CompletionRequest, ModelInfo};
+
+/// Placeholder used in place of images for models without vision support.
+const IMAGE_PLACEHOLDER: &str = "[image omitted: the current model does not support images]";
+
+/// A change made to a request (or a problem found) during adaptation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Adaptation {
+    /// Image parts were replaced with text placeholders
+    ImagesReplaced(usize),
+    /// `max_tokens` was lowered to the model's output limit
+    MaxTokensClamped { requested: u32, limit: u32 },
+    /// Temperature was removed because the model rejects it
+    TemperatureDropped,
+    /// The system prompt was moved into a leading user message
+    SystemMovedToUser,
+    /// The prepared context is larger than the model's context window
+    ContextExceeded { estimated: u32, context_window: u32 },
+}
+
+impl fmt::Display for Adaptation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImagesReplaced(n) => write!(f, "replaced {} image(s) with placeholders", n),
+            Self::MaxTokensClamped { requested, limit } => {
+                write!(f, "clamped max_tokens from {} to {}", requested, limit)
+            }
+            Self::TemperatureDropped => write!(f, "dropped unsupported temperature"),
+            Self::SystemMovedToUser => write!(f, "moved system prompt into first user message"),
+            Self::ContextExceeded {
+                estimated,
+                context_window,
+            } => write!(
+                f,
+                "context (~{} tokens) exceeds the model's {} token window",
+                estimated, context_window
+            ),
+        }
+    }
+}
+
+/// Adapt a request to the capabilities of the target model.
+///
+/// `estimated_tokens` is the caller's estimate of the prompt size; it is only
+/// used to warn about context overflow. Returns every adaptation applied.
+pub fn adapt_request(
+    request: &mut CompletionRequest,
+    model: &ModelInfo,
+    estimated_tokens: u32,
+) -> Vec<Adaptation> {
+    let mut adaptations = Vec::new();
+
+    if !model.supports_vision {
+        let replaced = replace_images(&mut request.messages);
+        if replaced > 0 {
+            adaptations.push(Adaptation::ImagesReplaced(replaced));
+        }
+    }
+
+    if model.max_output_tokens > 0 && request.max_tokens > model.max_output_tokens {
+        adaptations.push(Adaptation::MaxTokensClamped {
+            requested: request.max_tokens,
+            limit: model.max_output_tokens,
+        });
+        request.max_tokens = model.max_output_tokens;
+    }
+
+    if !model.supports_temperature && request.temperature.take().is_some() {
+        adaptations.push(Adaptation::TemperatureDropped);
+    }
+
+    if !model.supports_system_prompt && move_system_to_user(request) {
+        adaptations.push(Adaptation::SystemMovedToUser);
+    }
+
+    if model.context_window > 0 && estimated_tokens > model.context_window {
+        adaptations.push(Adaptation::ContextExceeded {
+            estimated: estimated_tokens,
+            context_window: model.context_window,
+        });
+    }
+
+    for adaptation in &adaptations {
+        match adaptation {
+            Adaptation::ContextExceeded { .. } => warn!(model = %model.id, "{}", adaptation),
+            _ => debug!(model = %model.id, "Adapted request: {}", adaptation),
+        }
+    }
+
+    adaptations
+}
+
+/// Replace image parts with text placeholders, returning the number replaced.
+fn replace_images(messages: &mut [Message]) -> usize {
+    let mut replaced = 0;
+    for message in messages {
+        if let Content::Parts(parts) = &mut message.content {
+            for part in parts.iter_mut() {
+                if matches!(part, ContentPart::Image { .. }) {
+                    *part = ContentPart::Text {
+                        text: IMAGE_PLACEHOLDER.to_string(),
+                    };
+                    replaced += 1;
+                }
+            }
+        }
+    }
+    replaced
+}
+
+/// Fold the system prompt and any system messages into the first user message.
+fn move_system_to_user(request: &mut CompletionRequest) -> bool {
+    let mut system_parts: Vec<String> = request.system.take().into_iter().collect();
+    request.messages.retain(|m| {
+        if m.role == Role::System {
+            system_parts.push(m.content.as_text());
+            false
+        } else {
+            true
+        }
+    });
+
+    system_parts.retain(|s| !s.is_empty());
+    if system_parts.is_empty() {
+        return false;
+    }
+    let system = system_parts.join("\n\n");
+
+    match request.messages.first_mut() {
+        Some(first) if first.role == Role::User => {
+            let text = format!("{}\n\n{}", system, first.content.as_text());
+            first.content = match &first.content {
+                Content::Text(_) => Content::Text(text),
+                Content::Parts(parts) => {
+                    let mut parts = parts.clone();
+                    parts.insert(0, ContentPart::Text { text: system });
+                    Content::Parts(parts)
+                }
+            };
+        }
+        _ => request.messages.insert(0, Message::user(system)),
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentik_core::message::ImageSource;
+
+    fn model() -> ModelInfo {
+        ModelInfo {
+            id: "test-model".to_string(),
+            name: "Test".to_string(),
+            provider: "test".to_string(),
+            context_window: 1_000,
+            max_output_tokens: 512,
+            supports_tools: true,
+            supports_vision: false,
+            supports_streaming: true,
+            supports_temperature: false,
+            supports_system_prompt: false,
+            pricing: None,
+        }
+    }
+
+    fn request(messages: Vec<Message>) -> CompletionRequest {
+        CompletionRequest {
+            model: "test-model".to_string(),
+            messages,
+            system: Some("Be brief.".to_string()),
+            max_tokens: 4_096,
+            temperature: Some(0.7),
+            tools: vec![],
+            stop: vec![],
+        }
+    }
+
+    #[test]
+    fn test_adapt_all() {
+        let mut image_msg = Message::user("");
+        image_msg.content = Content::Parts(vec![
+            ContentPart::Text {
+                text: "What is this?".to_string(),
+            },
+            ContentPart::Image {
+                source: ImageSource::Url {
+                    url: "https://example.com/a.png".to_string(),
+                },
+            },
+        ]);
+        let mut req = request(vec![image_msg]);
+
+        let adaptations = adapt_request(&mut req, &model(), 2_000);
+        assert_eq!(adaptations.len(), 5);
+        assert_eq!(req.max_tokens, 512);
+        assert!(req.temperature.is_none());
+        assert!(req.system.is_none());
+        assert_eq!(req.messages.len(), 1);
+
+        let text = req.messages[0].content.as_text();
+        assert!(text.starts_with("Be brief."));
+        assert!(text.contains(IMAGE_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_capable_model_unchanged() {
+        let mut capable = model();
+        capable.supports_vision = true;
+        capable.supports_temperature = true;
+        capable.supports_system_prompt = true;
+        capable.max_output_tokens = 8_192;
+
+        let mut req = request(vec![Message::user("hi")]);
+        assert!(adapt_request(&mut req, &capable, 10).is_empty());
+        assert_eq!(req.system.as_deref(), Some("Be brief."));
+        assert_eq!(req.temperature, Some(0.7));
+    }
+
+    #[test]
+    fn test_system_inserted_before_assistant() {
+        let mut req = request(vec![Message::assistant("Hello")]);
+        adapt_request(&mut req, &model(), 10);
+        assert_eq!(req.messages.len(), 2);
+        assert_eq!(req.messages[0].role, Role::User);
+        assert_eq!(req.messages[0].content.as_text(), "Be brief.");
+    }
+}
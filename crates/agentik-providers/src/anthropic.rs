@@ -1,6 +1,7 @@
 //! Anthropic (Claude) provider implementation.
 
 use std::pin::Pin;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures::{stream, Stream, StreamExt};
@@ -15,18 +16,23 @@ use crate::traits::{
     CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Pricing, Provider, StreamChunk,
     ToolCallDelta, ToolCapable, Usage,
 };
+use crate::wire::WireLog;
 
-/// Anthropic API base URL.
+/// Default Anthropic API base URL.
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1";
 
-/// Current Anthropic API version.
+/// Default Anthropic API version.
 const ANTHROPIC_VERSION: &str = "2023-06-01";
 
 /// Anthropic provider for Claude models.
 pub struct AnthropicProvider {
     client: Client,
     api_key: String,
+    base_url: String,
+    api_version: String,
+    extra_headers: Vec<(String, String)>,
     default_model: String,
+    wire_log: Option<Arc<WireLog>>,
 }
 
 impl AnthropicProvider {
@@ -35,13 +41,94 @@ impl AnthropicProvider {
         Self {
             client: Client::new(),
             api_key: api_key.into(),
+            base_url: ANTHROPIC_API_URL.to_string(),
+            api_version: ANTHROPIC_VERSION.to_string(),
+            extra_headers: Vec::new(),
             default_model: "claude-sonnet-4-20250514".to_string(),
+            wire_log: None,
         }
     }
 
-    /// Create from environment variable.
+    /// Create from environment variables.
+    ///
+    /// Reads `ANTHROPIC_API_KEY`, plus optional `ANTHROPIC_BASE_URL`,
+    /// `ANTHROPIC_API_VERSION` and `ANTHROPIC_BETA` (comma-separated).
     pub fn from_env() -> Option<Self> {
-        std::env::var("ANTHROPIC_API_KEY").ok().map(Self::new)
+        std::env::var("ANTHROPIC_API_KEY")
+            .ok()
+            .map(|key| Self::new(key).with_env_overrides())
+    }
+
+    /// Apply endpoint overrides from environment variables.
+    pub fn with_env_overrides(mut self) -> Self {
+        if let Ok(base_url) = std::env::var("ANTHROPIC_BASE_URL") {
+            self = self.with_base_url(base_url);
+        }
+        if let Ok(version) = std::env::var("ANTHROPIC_API_VERSION") {
+            self = self.with_api_version(version);
+        }
+        if let Ok(betas) = std::env::var("ANTHROPIC_BETA") {
+            for beta in betas.split(',').map(str::trim).filter(|b| !b.is_empty()) {
+                self = self.with_beta(beta);
+            }
+        }
+        self
+    }
+
+    /// Set a custom base URL (for gateways and proxies).
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the `anthropic-version` header value.
+    pub fn with_api_version(mut self, version: impl Into<String>) -> Self {
+        self.api_version = version.into();
+        self
+    }
+
+    /// Add a header to every request, replacing any earlier value.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        self.extra_headers
+            .retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+        self.extra_headers.push((name, value.into()));
+        self
+    }
+
+    /// Enable a beta feature via the `anthropic-beta` header.
+    pub fn with_beta(self, feature: impl Into<String>) -> Self {
+        let feature = feature.into();
+        let value = match self.header("anthropic-beta") {
+            Some(existing) if existing.split(',').any(|b| b.trim() == feature) => {
+                return self;
+            }
+            Some(existing) => format!("{},{}", existing, feature),
+            None => feature,
+        };
+        self.with_header("anthropic-beta", value)
+    }
+
+    /// Get the value of an extra header.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.extra_headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Start a request to the messages endpoint with all headers set.
+    fn messages_request(&self) -> reqwest::RequestBuilder {
+        let mut req = self
+            .client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", &self.api_version)
+            .header("content-type", "application/json");
+        for (name, value) in &self.extra_headers {
+            req = req.header(name, value);
+        }
+        req
     }
 
     /// Set the default model.
@@ -50,6 +137,18 @@ impl AnthropicProvider {
         self
     }
 
+    /// Record requests and responses to a wire log.
+    pub fn with_wire_log(mut self, wire_log: Arc<WireLog>) -> Self {
+        self.wire_log = Some(wire_log);
+        self
+    }
+
+    /// Use a preconfigured HTTP client (proxy, TLS, timeouts).
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
     /// Convert internal messages to Anthropic format.
     fn format_messages(&self, messages: &[Message]) -> Vec<AnthropicMessage> {
         messages
@@ -199,13 +298,7 @@ impl AnthropicProvider {
             }
         }
 
-        let finish_reason = match response.stop_reason.as_deref() {
-            Some("end_turn") | Some("stop_sequence") => FinishReason::Stop,
-            Some("max_tokens") => FinishReason::MaxTokens,
-            Some("tool_use") => FinishReason::ToolUse,
-            Some("content_filter") => FinishReason::ContentFilter,
-            _ => FinishReason::Stop,
-        };
+        let finish_reason = finish_reason(response.stop_reason.as_deref());
 
         CompletionResponse {
             content,
@@ -241,6 +334,8 @@ impl Provider for AnthropicProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: Some(Pricing {
                     input_per_million: 15.0,
                     output_per_million: 75.0,
@@ -255,6 +350,8 @@ impl Provider for AnthropicProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: Some(Pricing {
                     input_per_million: 3.0,
                     output_per_million: 15.0,
@@ -269,6 +366,8 @@ impl Provider for AnthropicProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: Some(Pricing {
                     input_per_million: 0.80,
                     output_per_million: 4.0,
@@ -281,6 +380,10 @@ impl Provider for AnthropicProvider {
         !self.api_key.is_empty()
     }
 
+    fn supports_prefill(&self) -> bool {
+        true
+    }
+
     #[instrument(skip(self, request), fields(model = %request.model))]
     async fn complete(&self, request: CompletionRequest) -> anyhow::Result<CompletionResponse> {
         let model = if request.model.is_empty() {
@@ -302,7 +405,7 @@ impl Provider for AnthropicProvider {
             messages,
             system,
             max_tokens: request.max_tokens,
-            temperature: Some(request.temperature),
+            temperature: request.temperature,
             tools,
             stream: false,
             stop_sequences: if request.stop.is_empty() {
@@ -314,24 +417,27 @@ impl Provider for AnthropicProvider {
 
         debug!("Sending request to Anthropic API");
 
-        let response = self
-            .client
-            .post(format!("{}/messages", ANTHROPIC_API_URL))
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", ANTHROPIC_VERSION)
-            .header("content-type", "application/json")
-            .json(&api_request)
-            .send()
-            .await?;
+        let http_request = self.messages_request().json(&api_request).build()?;
+        if let Some(ref wire) = self.wire_log {
+            wire.request("anthropic", &http_request);
+        }
+        let response = self.client.execute(http_request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
+            if let Some(ref wire) = self.wire_log {
+                wire.response("anthropic", status.as_u16(), &error_text);
+            }
             error!("Anthropic API error: {} - {}", status, error_text);
             anyhow::bail!("Anthropic API error: {} - {}", status, error_text);
         }
 
-        let api_response: AnthropicResponse = response.json().await?;
+        let body = response.text().await?;
+        if let Some(ref wire) = self.wire_log {
+            wire.response("anthropic", 200, &body);
+        }
+        let api_response: AnthropicResponse = serde_json::from_str(&body)?;
         Ok(self.parse_response(api_response))
     }
 
@@ -359,7 +465,7 @@ impl Provider for AnthropicProvider {
             messages,
             system,
             max_tokens: request.max_tokens,
-            temperature: Some(request.temperature),
+            temperature: request.temperature,
             tools,
             stream: true,
             stop_sequences: if request.stop.is_empty() {
@@ -371,19 +477,18 @@ impl Provider for AnthropicProvider {
 
         debug!("Sending streaming request to Anthropic API");
 
-        let response = self
-            .client
-            .post(format!("{}/messages", ANTHROPIC_API_URL))
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", ANTHROPIC_VERSION)
-            .header("content-type", "application/json")
-            .json(&api_request)
-            .send()
-            .await?;
+        let http_request = self.messages_request().json(&api_request).build()?;
+        if let Some(ref wire) = self.wire_log {
+            wire.request("anthropic", &http_request);
+        }
+        let response = self.client.execute(http_request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
+            if let Some(ref wire) = self.wire_log {
+                wire.response("anthropic", status.as_u16(), &error_text);
+            }
             error!("Anthropic API error: {} - {}", status, error_text);
             anyhow::bail!("Anthropic API error: {} - {}", status, error_text);
         }
@@ -392,8 +497,8 @@ impl Provider for AnthropicProvider {
 
         // Use stateful SSE parser to handle line buffering across TCP chunks
         let parsed_stream = stream::unfold(
-            (byte_stream, SseParser::new()),
-            |(mut byte_stream, mut parser)| async move {
+            (byte_stream, SseParser::new(), self.wire_log.clone()),
+            |(mut byte_stream, mut parser, wire)| async move {
                 loop {
                     match byte_stream.next().await {
                         Some(Ok(bytes)) => {
@@ -401,6 +506,14 @@ impl Provider for AnthropicProvider {
 
                             // Process all events from this chunk
                             for event in events {
+                                if let Some(ref wire) = wire {
+                                    wire.stream_event(
+                                        "anthropic",
+                                        event.event.as_deref(),
+                                        &event.data,
+                                    );
+                                }
+
                                 if event.is_done() {
                                     // Return final chunk
                                     return Some((
@@ -409,15 +522,16 @@ impl Provider for AnthropicProvider {
                                             tool_call_delta: None,
                                             is_final: true,
                                             usage: None,
+                                            finish_reason: None,
                                         }),
-                                        (byte_stream, parser),
+                                        (byte_stream, parser, wire),
                                     ));
                                 }
 
                                 // Parse the event data as Anthropic stream event
                                 match parse_anthropic_event(&event.data) {
                                     Ok(Some(chunk)) => {
-                                        return Some((Ok(chunk), (byte_stream, parser)));
+                                        return Some((Ok(chunk), (byte_stream, parser, wire)));
                                     }
                                     Ok(None) => {
                                         // Event parsed but no content to emit, continue
@@ -433,7 +547,7 @@ impl Provider for AnthropicProvider {
                         Some(Err(e)) => {
                             return Some((
                                 Err(anyhow::anyhow!("Stream error: {}", e)),
-                                (byte_stream, parser),
+                                (byte_stream, parser, wire),
                             ));
                         }
                         None => {
@@ -466,6 +580,17 @@ impl ToolCapable for AnthropicProvider {
     }
 }
 
+/// Map an Anthropic stop reason to a [`FinishReason`].
+fn finish_reason(stop_reason: Option<&str>) -> FinishReason {
+    match stop_reason {
+        Some("end_turn") | Some("stop_sequence") => FinishReason::Stop,
+        Some("max_tokens") => FinishReason::MaxTokens,
+        Some("tool_use") => FinishReason::ToolUse,
+        Some("content_filter") => FinishReason::ContentFilter,
+        _ => FinishReason::Stop,
+    }
+}
+
 /// Parse an Anthropic stream event from JSON data.
 fn parse_anthropic_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
     let event: StreamEvent = serde_json::from_str(data)?;
@@ -489,6 +614,7 @@ fn parse_anthropic_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
                     tool_call_delta: tool_delta,
                     is_final: false,
                     usage: None,
+                    finish_reason: None,
                 }))
             } else {
                 Ok(None)
@@ -505,6 +631,7 @@ fn parse_anthropic_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
                     }),
                     is_final: false,
                     usage: None,
+                    finish_reason: None,
                 }))
             } else if content_block.block_type == "text" {
                 // Text block start, might have initial text
@@ -514,6 +641,7 @@ fn parse_anthropic_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
                         tool_call_delta: None,
                         is_final: false,
                         usage: None,
+                        finish_reason: None,
                     }))
                 } else {
                     Ok(None)
@@ -522,17 +650,23 @@ fn parse_anthropic_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
                 Ok(None)
             }
         }
-        StreamEvent::MessageDelta { usage: u, .. } => {
-            if let Some(u) = u {
+        StreamEvent::MessageDelta { delta, usage: u } => {
+            let finish_reason = delta
+                .as_ref()
+                .and_then(|d| d.get("stop_reason"))
+                .and_then(|r| r.as_str())
+                .map(|r| finish_reason(Some(r)));
+            if u.is_some() || finish_reason.is_some() {
                 Ok(Some(StreamChunk {
                     delta: None,
                     tool_call_delta: None,
                     is_final: false,
-                    usage: Some(Usage {
+                    usage: u.map(|u| Usage {
                         input_tokens: 0,
                         output_tokens: u.output_tokens,
                         cached_tokens: 0,
                     }),
+                    finish_reason,
                 }))
             } else {
                 Ok(None)
@@ -543,6 +677,7 @@ fn parse_anthropic_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
             tool_call_delta: None,
             is_final: true,
             usage: None,
+            finish_reason: None,
         })),
         StreamEvent::MessageStart { message } => {
             // Extract input token count from message_start
@@ -558,6 +693,7 @@ fn parse_anthropic_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
                                 output_tokens: 0,
                                 cached_tokens: 0,
                             }),
+                            finish_reason: None,
                         }));
                     }
                 }
@@ -714,6 +850,28 @@ mod tests {
         assert!(models.iter().any(|m| m.id.contains("sonnet")));
     }
 
+    #[test]
+    fn test_custom_endpoint_and_headers() {
+        let provider = AnthropicProvider::new("test-key")
+            .with_base_url("https://gateway.internal/anthropic/v1/")
+            .with_api_version("2024-01-01")
+            .with_beta("prompt-caching-2024-07-31")
+            .with_beta("output-128k-2025-02-19")
+            .with_beta("prompt-caching-2024-07-31");
+        assert_eq!(provider.base_url, "https://gateway.internal/anthropic/v1");
+
+        let request = provider.messages_request().build().unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://gateway.internal/anthropic/v1/messages"
+        );
+        assert_eq!(request.headers()["anthropic-version"], "2024-01-01");
+        assert_eq!(
+            request.headers()["anthropic-beta"],
+            "prompt-caching-2024-07-31,output-128k-2025-02-19"
+        );
+    }
+
     #[test]
     fn test_format_messages() {
         let provider = AnthropicProvider::new("test-key");
@@ -725,4 +883,13 @@ mod tests {
         assert_eq!(formatted[0].role, "user");
         assert_eq!(formatted[1].role, "assistant");
     }
+
+    #[test]
+    fn test_parse_stop_reason() {
+        let data = r#"{"type": "message_delta", "delta": {"stop_reason": "max_tokens"}, "usage": {"output_tokens": 4096}}"#;
+        let chunk = parse_anthropic_event(data).unwrap().unwrap();
+        assert_eq!(chunk.finish_reason, Some(FinishReason::MaxTokens));
+        assert_eq!(chunk.usage.unwrap().output_tokens, 4096);
+        assert!(AnthropicProvider::new("test-key").supports_prefill());
+    }
 }
@@ -6,21 +6,35 @@
 //! - Provider trait for abstracting AI providers
 //! - Implementations for Anthropic, OpenAI, and local models (Ollama)
 //! - Tool calling normalization across providers
+//! - Prompt-based tool calling for models without native support
 //! - Streaming support
+//! - Embeddings for OpenAI, OpenAI-compatible servers and Ollama
 //! - Model registry and selection
+//! - Capability-aware request adaptation
+//! - Opt-in wire logging with secret redaction
+//! - Client-side rate limiting (RPM/TPM)
 
+pub mod adapt;
 pub mod anthropic;
 pub mod local;
 pub mod openai;
+pub mod rate_limit;
 pub mod registry;
 pub mod sse;
+pub mod tool_emulation;
 pub mod traits;
+pub mod wire;
 
+pub use adapt::{adapt_request, Adaptation};
 pub use anthropic::AnthropicProvider;
 pub use local::LocalProvider;
 pub use openai::OpenAIProvider;
+pub use rate_limit::{RateLimitedProvider, RateLimiter};
 pub use registry::ProviderRegistry;
 pub use sse::{SseEvent, SseParser};
+pub use tool_emulation::{PromptToolProvider, ToolCallParser};
 pub use traits::{
-    CompletionRequest, CompletionResponse, ModelInfo, Pricing, Provider, StreamChunk, ToolCapable,
+    CompletionRequest, CompletionResponse, EmbeddingModelInfo, EmbeddingProvider,
+    EmbeddingResponse, ModelInfo, Pricing, Provider, StreamChunk, ToolCapable,
 };
+pub use wire::{WireLog, WIRE_LOG_FILE};
@@ -4,23 +4,29 @@
 //! wraps the OpenAI provider with Ollama-specific defaults.
 
 use std::pin::Pin;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures::Stream;
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use agentik_core::{Message, ToolCall, ToolDefinition, ToolResult};
 
 use crate::openai::OpenAIProvider;
 use crate::traits::{
-    CompletionRequest, CompletionResponse, ModelInfo, Provider, StreamChunk, ToolCapable,
+    CompletionRequest, CompletionResponse, EmbeddingModelInfo, EmbeddingProvider,
+    EmbeddingResponse, ModelInfo, Provider, StreamChunk, ToolCapable,
 };
+use crate::wire::WireLog;
 
 /// Default Ollama API URL.
 const OLLAMA_API_URL: &str = "http://localhost:11434/v1";
 
+/// Number of inputs sent per Ollama embedding request.
+const OLLAMA_MAX_EMBEDDING_BATCH: usize = 256;
+
 /// Local provider for Ollama models.
 pub struct LocalProvider {
     /// Inner OpenAI-compatible provider
@@ -29,6 +35,8 @@ pub struct LocalProvider {
     client: Client,
     /// Base URL for Ollama API
     base_url: String,
+    /// Wire log for native API requests (the inner provider logs its own)
+    wire_log: Option<Arc<WireLog>>,
 }
 
 impl LocalProvider {
@@ -49,21 +57,41 @@ impl LocalProvider {
             inner,
             client: Client::new(),
             base_url: url,
+            wire_log: None,
         }
     }
 
     /// Set the default model.
     pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
-        self.inner = OpenAIProvider::new("ollama")
-            .with_base_url(&self.base_url)
-            .with_default_model(model);
+        // Keep the client and wire log already set on the inner provider
+        self.inner = self.inner.with_default_model(model);
+        self
+    }
+
+    /// Record requests and responses to a wire log.
+    pub fn with_wire_log(mut self, wire_log: Arc<WireLog>) -> Self {
+        self.inner = self.inner.with_wire_log(wire_log.clone());
+        self.wire_log = Some(wire_log);
         self
     }
 
+    /// Use a preconfigured HTTP client (proxy, TLS, timeouts).
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.inner = self.inner.with_client(client.clone());
+        self.client = client;
+        self
+    }
+
+    /// Base URL of Ollama's native API, without the OpenAI-compatible
+    /// `/v1` suffix.
+    fn api_base(&self) -> &str {
+        self.base_url.trim_end_matches('/').trim_end_matches("/v1")
+    }
+
     /// Check if Ollama is running.
     pub async fn is_running(&self) -> bool {
         // Try to list models to check if Ollama is available
-        let base = self.base_url.trim_end_matches("/v1");
+        let base = self.api_base();
         match self.client.get(format!("{}/api/tags", base)).send().await {
             Ok(resp) => resp.status().is_success(),
             Err(_) => false,
@@ -72,7 +100,7 @@ impl LocalProvider {
 
     /// List available models from Ollama.
     pub async fn list_models(&self) -> anyhow::Result<Vec<OllamaModel>> {
-        let base = self.base_url.trim_end_matches("/v1");
+        let base = self.api_base();
         let response = self.client.get(format!("{}/api/tags", base)).send().await?;
 
         if !response.status().is_success() {
@@ -113,6 +141,8 @@ impl Provider for LocalProvider {
                 supports_tools: true,
                 supports_vision: false,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: None, // Local = free
             },
             ModelInfo {
@@ -124,6 +154,8 @@ impl Provider for LocalProvider {
                 supports_tools: true,
                 supports_vision: false,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: None,
             },
             ModelInfo {
@@ -135,6 +167,8 @@ impl Provider for LocalProvider {
                 supports_tools: false,
                 supports_vision: false,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: None,
             },
             ModelInfo {
@@ -146,6 +180,8 @@ impl Provider for LocalProvider {
                 supports_tools: true,
                 supports_vision: false,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: None,
             },
             ModelInfo {
@@ -157,6 +193,8 @@ impl Provider for LocalProvider {
                 supports_tools: true,
                 supports_vision: false,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: None,
             },
             ModelInfo {
@@ -168,6 +206,8 @@ impl Provider for LocalProvider {
                 supports_tools: true,
                 supports_vision: false,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: None,
             },
             ModelInfo {
@@ -179,6 +219,8 @@ impl Provider for LocalProvider {
                 supports_tools: true,
                 supports_vision: false,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: None,
             },
         ]
@@ -218,8 +260,110 @@ impl ToolCapable for LocalProvider {
     }
 }
 
+#[async_trait]
+impl EmbeddingProvider for LocalProvider {
+    fn embedding_models(&self) -> Vec<EmbeddingModelInfo> {
+        vec![
+            EmbeddingModelInfo {
+                id: "nomic-embed-text".to_string(),
+                provider: "local".to_string(),
+                dimensions: 768,
+                max_input_tokens: 8192,
+                max_batch_size: OLLAMA_MAX_EMBEDDING_BATCH,
+                price_per_million: None,
+            },
+            EmbeddingModelInfo {
+                id: "mxbai-embed-large".to_string(),
+                provider: "local".to_string(),
+                dimensions: 1024,
+                max_input_tokens: 512,
+                max_batch_size: OLLAMA_MAX_EMBEDDING_BATCH,
+                price_per_million: None,
+            },
+            EmbeddingModelInfo {
+                id: "all-minilm".to_string(),
+                provider: "local".to_string(),
+                dimensions: 384,
+                max_input_tokens: 256,
+                max_batch_size: OLLAMA_MAX_EMBEDDING_BATCH,
+                price_per_million: None,
+            },
+        ]
+    }
+
+    fn default_embedding_model(&self) -> &str {
+        "nomic-embed-text"
+    }
+
+    async fn embed(&self, texts: &[String], model: &str) -> anyhow::Result<EmbeddingResponse> {
+        let model = if model.is_empty() {
+            self.default_embedding_model()
+        } else {
+            model
+        };
+
+        // Use the native endpoint: it batches and reports token counts
+        let base = self.api_base();
+        let mut result = EmbeddingResponse {
+            model: model.to_string(),
+            ..Default::default()
+        };
+
+        for batch in texts.chunks(OLLAMA_MAX_EMBEDDING_BATCH) {
+            debug!("Sending embedding request to Ollama");
+            let http_request = self
+                .client
+                .post(format!("{}/api/embed", base))
+                .json(&OllamaEmbedRequest {
+                    model: model.to_string(),
+                    input: batch,
+                })
+                .build()?;
+            if let Some(ref wire) = self.wire_log {
+                wire.request("local", &http_request);
+            }
+            let response = self.client.execute(http_request).await?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().await.unwrap_or_default();
+                if let Some(ref wire) = self.wire_log {
+                    wire.response("local", status.as_u16(), &error_text);
+                }
+                anyhow::bail!("Ollama embedding error: {} - {}", status, error_text);
+            }
+
+            let api_response: OllamaEmbedResponse = response.json().await?;
+            if api_response.embeddings.len() != batch.len() {
+                anyhow::bail!(
+                    "Ollama returned {} embeddings for {} inputs",
+                    api_response.embeddings.len(),
+                    batch.len()
+                );
+            }
+            result.embeddings.extend(api_response.embeddings);
+            result.input_tokens += api_response.prompt_eval_count;
+        }
+
+        Ok(result)
+    }
+}
+
 // Ollama-specific types
 
+#[derive(Debug, Serialize)]
+struct OllamaEmbedRequest<'a> {
+    model: String,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    prompt_eval_count: u32,
+}
+
 #[derive(Debug, Deserialize)]
 struct OllamaTags {
     models: Vec<OllamaModel>,
@@ -265,5 +409,51 @@ mod tests {
     fn test_custom_url() {
         let provider = LocalProvider::with_url("http://192.168.1.100:11434/v1");
         assert_eq!(provider.base_url, "http://192.168.1.100:11434/v1");
+        assert_eq!(provider.api_base(), "http://192.168.1.100:11434");
+
+        let provider = LocalProvider::with_url("http://localhost:11434/v1/");
+        assert_eq!(provider.api_base(), "http://localhost:11434");
+    }
+
+    #[tokio::test]
+    async fn test_embed_request_is_wire_logged() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(crate::wire::WIRE_LOG_FILE);
+        let wire = Arc::new(WireLog::new(&[]));
+        wire.open(&path).unwrap();
+
+        // Nothing listens on the discard port; the request is logged first
+        let provider = LocalProvider::with_url("http://127.0.0.1:9/v1")
+            .with_wire_log(wire)
+            .with_default_model("codellama");
+        let result = provider
+            .embed(&["hello".to_string()], "nomic-embed-text")
+            .await;
+        assert!(result.is_err());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let entry: serde_json::Value =
+            serde_json::from_str(contents.lines().next().unwrap()).unwrap();
+        assert_eq!(entry["provider"], "local");
+        assert_eq!(entry["url"], "http://127.0.0.1:9/api/embed");
+        assert_eq!(entry["body"]["input"][0], "hello");
+    }
+
+    #[test]
+    fn test_embedding_models_are_free() {
+        let provider = LocalProvider::new();
+        let model = provider
+            .embedding_model(provider.default_embedding_model())
+            .unwrap();
+        assert_eq!(model.dimensions, 768);
+        assert_eq!(model.cost(1_000_000), 0.0);
+    }
+
+    #[test]
+    fn test_parse_embed_response() {
+        let json = r#"{"model":"nomic-embed-text","embeddings":[[0.1,0.2],[0.3,0.4]],"prompt_eval_count":6}"#;
+        let response: OllamaEmbedResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.embeddings.len(), 2);
+        assert_eq!(response.prompt_eval_count, 6);
     }
 }
@@ -1,6 +1,7 @@
 //! OpenAI (GPT) provider implementation.
 
 use std::pin::Pin;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures::{stream, Stream, StreamExt};
@@ -12,13 +13,18 @@ use agentik_core::{Message, Role, ToolCall, ToolDefinition, ToolResult};
 
 use crate::sse::SseParser;
 use crate::traits::{
-    CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Pricing, Provider, StreamChunk,
-    ToolCallDelta, ToolCapable, Usage,
+    CompletionRequest, CompletionResponse, EmbeddingModelInfo, EmbeddingProvider,
+    EmbeddingResponse, FinishReason, ModelInfo, Pricing, Provider, StreamChunk, ToolCallDelta,
+    ToolCapable, Usage,
 };
+use crate::wire::WireLog;
 
 /// Default OpenAI API base URL.
 const OPENAI_API_URL: &str = "https://api.openai.com/v1";
 
+/// Maximum number of inputs per embeddings request.
+const OPENAI_MAX_EMBEDDING_BATCH: usize = 2048;
+
 /// OpenAI provider for GPT models.
 pub struct OpenAIProvider {
     client: Client,
@@ -26,6 +32,8 @@ pub struct OpenAIProvider {
     base_url: String,
     default_model: String,
     organization: Option<String>,
+    extra_headers: Vec<(String, String)>,
+    wire_log: Option<Arc<WireLog>>,
 }
 
 impl OpenAIProvider {
@@ -37,6 +45,8 @@ impl OpenAIProvider {
             base_url: OPENAI_API_URL.to_string(),
             default_model: "gpt-4o".to_string(),
             organization: None,
+            extra_headers: Vec::new(),
+            wire_log: None,
         }
     }
 
@@ -57,6 +67,24 @@ impl OpenAIProvider {
         self
     }
 
+    /// Use a preconfigured HTTP client (proxy, TLS, timeouts).
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Record requests and responses to a wire log.
+    pub fn with_wire_log(mut self, wire_log: Arc<WireLog>) -> Self {
+        self.wire_log = Some(wire_log);
+        self
+    }
+
+    /// Add a header to every request.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_headers.push((name.into(), value.into()));
+        self
+    }
+
     /// Set the organization ID.
     pub fn with_organization(mut self, org: impl Into<String>) -> Self {
         self.organization = Some(org.into());
@@ -221,13 +249,7 @@ impl OpenAIProvider {
 
         let finish_reason = choice
             .and_then(|c| c.finish_reason.as_deref())
-            .map(|r| match r {
-                "stop" => FinishReason::Stop,
-                "length" => FinishReason::MaxTokens,
-                "tool_calls" => FinishReason::ToolUse,
-                "content_filter" => FinishReason::ContentFilter,
-                _ => FinishReason::Stop,
-            })
+            .map(finish_reason)
             .unwrap_or(FinishReason::Stop);
 
         let usage = response
@@ -272,6 +294,8 @@ impl Provider for OpenAIProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: Some(Pricing {
                     input_per_million: 2.50,
                     output_per_million: 10.0,
@@ -286,6 +310,8 @@ impl Provider for OpenAIProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: Some(Pricing {
                     input_per_million: 0.15,
                     output_per_million: 0.60,
@@ -300,6 +326,8 @@ impl Provider for OpenAIProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: true,
+                supports_system_prompt: true,
                 pricing: Some(Pricing {
                     input_per_million: 10.0,
                     output_per_million: 30.0,
@@ -314,6 +342,8 @@ impl Provider for OpenAIProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: false,
+                supports_system_prompt: true,
                 pricing: Some(Pricing {
                     input_per_million: 15.0,
                     output_per_million: 60.0,
@@ -328,6 +358,8 @@ impl Provider for OpenAIProvider {
                 supports_tools: true,
                 supports_vision: true,
                 supports_streaming: true,
+                supports_temperature: false,
+                supports_system_prompt: false,
                 pricing: Some(Pricing {
                     input_per_million: 3.0,
                     output_per_million: 12.0,
@@ -373,7 +405,7 @@ impl Provider for OpenAIProvider {
             model: model.to_string(),
             messages,
             max_tokens: Some(request.max_tokens),
-            temperature: Some(request.temperature),
+            temperature: request.temperature,
             tools,
             stream: false,
             stop: if request.stop.is_empty() {
@@ -394,17 +426,31 @@ impl Provider for OpenAIProvider {
         if let Some(ref org) = self.organization {
             req = req.header("OpenAI-Organization", org);
         }
+        for (name, value) in &self.extra_headers {
+            req = req.header(name, value);
+        }
 
-        let response = req.json(&api_request).send().await?;
+        let http_request = req.json(&api_request).build()?;
+        if let Some(ref wire) = self.wire_log {
+            wire.request("openai", &http_request);
+        }
+        let response = self.client.execute(http_request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
+            if let Some(ref wire) = self.wire_log {
+                wire.response("openai", status.as_u16(), &error_text);
+            }
             error!("OpenAI API error: {} - {}", status, error_text);
             anyhow::bail!("OpenAI API error: {} - {}", status, error_text);
         }
 
-        let api_response: OpenAIResponse = response.json().await?;
+        let body = response.text().await?;
+        if let Some(ref wire) = self.wire_log {
+            wire.response("openai", 200, &body);
+        }
+        let api_response: OpenAIResponse = serde_json::from_str(&body)?;
         Ok(self.parse_response(api_response))
     }
 
@@ -443,7 +489,7 @@ impl Provider for OpenAIProvider {
             model: model.to_string(),
             messages,
             max_tokens: Some(request.max_tokens),
-            temperature: Some(request.temperature),
+            temperature: request.temperature,
             tools,
             stream: true,
             stop: if request.stop.is_empty() {
@@ -464,12 +510,22 @@ impl Provider for OpenAIProvider {
         if let Some(ref org) = self.organization {
             req = req.header("OpenAI-Organization", org);
         }
+        for (name, value) in &self.extra_headers {
+            req = req.header(name, value);
+        }
 
-        let response = req.json(&api_request).send().await?;
+        let http_request = req.json(&api_request).build()?;
+        if let Some(ref wire) = self.wire_log {
+            wire.request("openai", &http_request);
+        }
+        let response = self.client.execute(http_request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
+            if let Some(ref wire) = self.wire_log {
+                wire.response("openai", status.as_u16(), &error_text);
+            }
             error!("OpenAI API error: {} - {}", status, error_text);
             anyhow::bail!("OpenAI API error: {} - {}", status, error_text);
         }
@@ -478,8 +534,8 @@ impl Provider for OpenAIProvider {
 
         // Use stateful SSE parser to handle line buffering across TCP chunks
         let parsed_stream = stream::unfold(
-            (byte_stream, SseParser::new()),
-            |(mut byte_stream, mut parser)| async move {
+            (byte_stream, SseParser::new(), self.wire_log.clone()),
+            |(mut byte_stream, mut parser, wire)| async move {
                 loop {
                     match byte_stream.next().await {
                         Some(Ok(bytes)) => {
@@ -487,6 +543,14 @@ impl Provider for OpenAIProvider {
 
                             // Process all events from this chunk
                             for event in events {
+                                if let Some(ref wire) = wire {
+                                    wire.stream_event(
+                                        "openai",
+                                        event.event.as_deref(),
+                                        &event.data,
+                                    );
+                                }
+
                                 if event.is_done() {
                                     // Return final chunk
                                     return Some((
@@ -495,15 +559,16 @@ impl Provider for OpenAIProvider {
                                             tool_call_delta: None,
                                             is_final: true,
                                             usage: None,
+                                            finish_reason: None,
                                         }),
-                                        (byte_stream, parser),
+                                        (byte_stream, parser, wire),
                                     ));
                                 }
 
                                 // Parse the event data as OpenAI stream event
                                 match parse_openai_event(&event.data) {
                                     Ok(Some(chunk)) => {
-                                        return Some((Ok(chunk), (byte_stream, parser)));
+                                        return Some((Ok(chunk), (byte_stream, parser, wire)));
                                     }
                                     Ok(None) => {
                                         // Event parsed but no content to emit, continue
@@ -519,7 +584,7 @@ impl Provider for OpenAIProvider {
                         Some(Err(e)) => {
                             return Some((
                                 Err(anyhow::anyhow!("Stream error: {}", e)),
-                                (byte_stream, parser),
+                                (byte_stream, parser, wire),
                             ));
                         }
                         None => {
@@ -552,6 +617,133 @@ impl ToolCapable for OpenAIProvider {
     }
 }
 
+#[async_trait]
+impl EmbeddingProvider for OpenAIProvider {
+    fn embedding_models(&self) -> Vec<EmbeddingModelInfo> {
+        vec![
+            EmbeddingModelInfo {
+                id: "text-embedding-3-small".to_string(),
+                provider: "openai".to_string(),
+                dimensions: 1536,
+                max_input_tokens: 8191,
+                max_batch_size: OPENAI_MAX_EMBEDDING_BATCH,
+                price_per_million: Some(0.02),
+            },
+            EmbeddingModelInfo {
+                id: "text-embedding-3-large".to_string(),
+                provider: "openai".to_string(),
+                dimensions: 3072,
+                max_input_tokens: 8191,
+                max_batch_size: OPENAI_MAX_EMBEDDING_BATCH,
+                price_per_million: Some(0.13),
+            },
+            EmbeddingModelInfo {
+                id: "text-embedding-ada-002".to_string(),
+                provider: "openai".to_string(),
+                dimensions: 1536,
+                max_input_tokens: 8191,
+                max_batch_size: OPENAI_MAX_EMBEDDING_BATCH,
+                price_per_million: Some(0.10),
+            },
+        ]
+    }
+
+    fn default_embedding_model(&self) -> &str {
+        "text-embedding-3-small"
+    }
+
+    #[instrument(skip(self, texts), fields(count = texts.len()))]
+    async fn embed(&self, texts: &[String], model: &str) -> anyhow::Result<EmbeddingResponse> {
+        let model = if model.is_empty() {
+            self.default_embedding_model()
+        } else {
+            model
+        };
+
+        // OpenAI-compatible servers may serve models we don't know about;
+        // those still work, just without dimension or pricing metadata.
+        let info = self.embedding_model(model);
+        let batch_size = info
+            .as_ref()
+            .map(|m| m.max_batch_size)
+            .unwrap_or(OPENAI_MAX_EMBEDDING_BATCH)
+            .max(1);
+
+        let mut result = EmbeddingResponse {
+            model: model.to_string(),
+            ..Default::default()
+        };
+
+        for batch in texts.chunks(batch_size) {
+            let api_request = OpenAIEmbeddingRequest {
+                model: model.to_string(),
+                input: batch,
+                encoding_format: "float",
+            };
+
+            debug!("Sending embedding request to OpenAI API");
+
+            let mut req = self
+                .client
+                .post(format!("{}/embeddings", self.base_url))
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Content-Type", "application/json");
+
+            if let Some(ref org) = self.organization {
+                req = req.header("OpenAI-Organization", org);
+            }
+            for (name, value) in &self.extra_headers {
+                req = req.header(name, value);
+            }
+
+            let http_request = req.json(&api_request).build()?;
+            if let Some(ref wire) = self.wire_log {
+                wire.request("openai", &http_request);
+            }
+            let response = self.client.execute(http_request).await?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response.text().await.unwrap_or_default();
+                error!("OpenAI API error: {} - {}", status, error_text);
+                anyhow::bail!("OpenAI API error: {} - {}", status, error_text);
+            }
+
+            let mut api_response: OpenAIEmbeddingResponse = response.json().await?;
+            if api_response.data.len() != batch.len() {
+                anyhow::bail!(
+                    "OpenAI API returned {} embeddings for {} inputs",
+                    api_response.data.len(),
+                    batch.len()
+                );
+            }
+
+            // The API does not guarantee ordering, so sort by index
+            api_response.data.sort_by_key(|d| d.index);
+            result
+                .embeddings
+                .extend(api_response.data.into_iter().map(|d| d.embedding));
+            if let Some(usage) = api_response.usage {
+                result.input_tokens += usage.prompt_tokens;
+            }
+        }
+
+        result.cost_usd = info.map(|m| m.cost(result.input_tokens)).unwrap_or(0.0);
+        Ok(result)
+    }
+}
+
+/// Map an OpenAI finish reason to a [`FinishReason`].
+fn finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "stop" => FinishReason::Stop,
+        "length" => FinishReason::MaxTokens,
+        "tool_calls" => FinishReason::ToolUse,
+        "content_filter" => FinishReason::ContentFilter,
+        _ => FinishReason::Stop,
+    }
+}
+
 /// Parse an OpenAI stream event from JSON data.
 fn parse_openai_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
     let chunk: StreamChunkResponse = serde_json::from_str(data)?;
@@ -586,6 +778,7 @@ fn parse_openai_event(data: &str) -> anyhow::Result<Option<StreamChunk>> {
                 tool_call_delta,
                 is_final,
                 usage: None,
+                finish_reason: choice.finish_reason.as_deref().map(finish_reason),
             }))
         } else {
             Ok(None)
@@ -731,6 +924,30 @@ struct StreamFunction {
     arguments: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+struct OpenAIEmbeddingRequest<'a> {
+    model: String,
+    input: &'a [String],
+    encoding_format: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingResponse {
+    data: Vec<OpenAIEmbeddingData>,
+    usage: Option<OpenAIEmbeddingUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingUsage {
+    prompt_tokens: u32,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -753,4 +970,41 @@ mod tests {
             OpenAIProvider::new("test-key").with_base_url("https://openrouter.ai/api/v1");
         assert_eq!(provider.base_url, "https://openrouter.ai/api/v1");
     }
+
+    #[test]
+    fn test_embedding_models() {
+        let provider = OpenAIProvider::new("test-key");
+        let model = provider
+            .embedding_model(provider.default_embedding_model())
+            .unwrap();
+        assert_eq!(model.dimensions, 1536);
+        assert!((model.cost(1_000_000) - 0.02).abs() < f64::EPSILON);
+        assert!(provider.embedding_model("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_parse_embedding_response() {
+        let json = r#"{
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.3, 0.4]},
+                {"object": "embedding", "index": 0, "embedding": [0.1, 0.2]}
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 8, "total_tokens": 8}
+        }"#;
+        let mut response: OpenAIEmbeddingResponse = serde_json::from_str(json).unwrap();
+        response.data.sort_by_key(|d| d.index);
+        assert_eq!(response.data[0].embedding, vec![0.1, 0.2]);
+        assert_eq!(response.usage.unwrap().prompt_tokens, 8);
+    }
+
+    #[test]
+    fn test_parse_finish_reason() {
+        let data = r#"{"choices": [{"index": 0, "delta": {"content": "fn ma"}, "finish_reason": "length"}]}"#;
+        let chunk = parse_openai_event(data).unwrap().unwrap();
+        assert_eq!(chunk.delta.as_deref(), Some("fn ma"));
+        assert_eq!(chunk.finish_reason, Some(FinishReason::MaxTokens));
+        assert!(!OpenAIProvider::new("test-key").supports_prefill());
+    }
 }
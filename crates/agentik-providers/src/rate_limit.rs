@@ -0,0 +1,519 @@
+//! Client-side rate limiting (requests and tokens per minute).
+//!
+//! Limits are enforced with token buckets keyed by `provider` or
+//! `provider/model`. A request waits until both its request bucket and its
+//! token bucket have capacity, so callers back off proactively instead of
+//! receiving 429s. Buckets live in a process-wide [`RateLimiter`] so every
+//! `Agent` (including subagents) sharing an API key draws from the same
+//! budget.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tracing::debug;
+
+use agentik_core::config::RateLimitConfig;
+
+use crate::traits::{
+    CompletionRequest, CompletionResponse, ModelInfo, Provider, StreamChunk, Usage,
+};
+
+/// Rough characters-per-token ratio used to pre-charge requests.
+const CHARS_PER_TOKEN: usize = 4;
+
+// ============================================================================
+// Token Bucket
+// ============================================================================
+
+/// A token bucket refilled continuously up to a per-minute capacity.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn per_minute(limit: u32) -> Self {
+        let capacity = limit.max(1) as f64;
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.available = (self.available + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Time until `amount` is available (zero if it is available now).
+    fn wait_time(&self, amount: f64) -> Duration {
+        // Never wait for more than a full bucket, or large requests would block forever
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((amount - self.available) / self.refill_per_sec)
+        }
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.available -= amount.min(self.capacity);
+    }
+}
+
+/// Request and token buckets for one limit key.
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+}
+
+// ============================================================================
+// Rate Limiter
+// ============================================================================
+
+/// Process-wide registry of rate limit buckets.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: Mutex<HashMap<String, RateLimitConfig>>,
+    buckets: Mutex<HashMap<String, Buckets>>,
+}
+
+impl RateLimiter {
+    /// Create an empty limiter (no limits configured).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The limiter shared by every provider in this process.
+    pub fn global() -> &'static RateLimiter {
+        static GLOBAL: OnceLock<RateLimiter> = OnceLock::new();
+        GLOBAL.get_or_init(RateLimiter::new)
+    }
+
+    /// Configure limits, keyed by `provider` or `provider/model`.
+    ///
+    /// Existing buckets whose limits changed are reset.
+    pub fn configure(&self, limits: &HashMap<String, RateLimitConfig>) {
+        let mut current = self.limits.lock().unwrap();
+        let mut buckets = self.buckets.lock().unwrap();
+        for (key, limit) in limits {
+            if current.get(key) != Some(limit) {
+                buckets.remove(key);
+                current.insert(key.clone(), limit.clone());
+            }
+        }
+    }
+
+    /// Check whether any limit applies to a provider.
+    pub fn has_limits_for(&self, provider: &str) -> bool {
+        let prefix = format!("{}/", provider);
+        self.limits
+            .lock()
+            .unwrap()
+            .keys()
+            .any(|k| k == provider || k.starts_with(&prefix))
+    }
+
+    /// Find the limit key for a provider/model (model-specific wins).
+    fn key_for(&self, provider: &str, model: &str) -> Option<String> {
+        let limits = self.limits.lock().unwrap();
+        let model_key = format!("{}/{}", provider, model);
+        if limits.contains_key(&model_key) {
+            Some(model_key)
+        } else if limits.contains_key(provider) {
+            Some(provider.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Try to take one request and `tokens` tokens.
+    ///
+    /// Returns how long to wait if capacity isn't available yet; nothing is
+    /// taken in that case.
+    pub fn try_acquire(&self, provider: &str, model: &str, tokens: u32) -> Result<(), Duration> {
+        let Some(key) = self.key_for(provider, model) else {
+            return Ok(());
+        };
+        let limit = self.limits.lock().unwrap().get(&key).cloned();
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let entry = buckets.entry(key).or_insert_with(|| Buckets {
+            requests: limit.requests_per_minute.map(TokenBucket::per_minute),
+            tokens: limit.tokens_per_minute.map(TokenBucket::per_minute),
+        });
+
+        let now = Instant::now();
+        let mut wait = Duration::ZERO;
+        if let Some(ref mut bucket) = entry.requests {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_time(1.0));
+        }
+        if let Some(ref mut bucket) = entry.tokens {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_time(tokens as f64));
+        }
+
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        if let Some(ref mut bucket) = entry.requests {
+            bucket.take(1.0);
+        }
+        if let Some(ref mut bucket) = entry.tokens {
+            bucket.take(tokens as f64);
+        }
+        Ok(())
+    }
+
+    /// Wait until one request and `tokens` tokens are available, then take them.
+    pub async fn acquire(&self, provider: &str, model: &str, tokens: u32) {
+        while let Err(wait) = self.try_acquire(provider, model, tokens) {
+            debug!(
+                provider,
+                model,
+                wait_ms = wait.as_millis() as u64,
+                "Rate limit reached, waiting"
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Correct a pre-charged estimate with the actual token usage.
+    ///
+    /// Under-estimates put the bucket into debt, which delays later requests.
+    pub fn reconcile(&self, provider: &str, model: &str, estimated: u32, actual: u32) {
+        let Some(key) = self.key_for(provider, model) else {
+            return;
+        };
+        let mut buckets = self.buckets.lock().unwrap();
+        if let Some(bucket) = buckets.get_mut(&key).and_then(|b| b.tokens.as_mut()) {
+            bucket.available += estimated as f64 - actual as f64;
+            bucket.available = bucket.available.min(bucket.capacity);
+        }
+    }
+}
+
+/// Estimate the input tokens of a request for pre-charging.
+pub fn estimate_input_tokens(request: &CompletionRequest) -> u32 {
+    let chars = request.system.as_ref().map(|s| s.len()).unwrap_or(0)
+        + request
+            .messages
+            .iter()
+            .map(|m| m.content.as_text().len())
+            .sum::<usize>()
+        + request
+            .tools
+            .iter()
+            .map(|t| t.description.len() + t.parameters.to_string().len())
+            .sum::<usize>();
+    (chars / CHARS_PER_TOKEN) as u32
+}
+
+// ============================================================================
+// Provider Adapter
+// ============================================================================
+
+/// Provider adapter that applies the global rate limiter.
+pub struct RateLimitedProvider {
+    inner: Arc<dyn Provider>,
+    limiter: &'static RateLimiter,
+}
+
+impl RateLimitedProvider {
+    /// Wrap a provider.
+    pub fn new(inner: Arc<dyn Provider>) -> Self {
+        Self {
+            inner,
+            limiter: RateLimiter::global(),
+        }
+    }
+}
+
+/// Usage accumulated over a streamed response, reconciled with the limiter
+/// once when the stream is dropped (finished or abandoned).
+///
+/// Providers may report usage in several chunks; Anthropic sends the input
+/// tokens at the start and the output tokens at the end. Each count is
+/// cumulative, so the largest value seen for each is the total.
+struct StreamUsage {
+    limiter: &'static RateLimiter,
+    provider: String,
+    model: String,
+    estimated: u32,
+    input_tokens: u32,
+    output_tokens: u32,
+    reported: bool,
+}
+
+impl StreamUsage {
+    fn record(&mut self, usage: &Usage) {
+        self.input_tokens = self.input_tokens.max(usage.input_tokens);
+        self.output_tokens = self.output_tokens.max(usage.output_tokens);
+        self.reported = true;
+    }
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        // Without any usage the estimate is the best we have
+        if self.reported {
+            self.limiter.reconcile(
+                &self.provider,
+                &self.model,
+                self.estimated,
+                self.input_tokens + self.output_tokens,
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for RateLimitedProvider {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn available_models(&self) -> Vec<ModelInfo> {
+        self.inner.available_models()
+    }
+
+    fn is_configured(&self) -> bool {
+        self.inner.is_configured()
+    }
+
+    fn supports_prefill(&self) -> bool {
+        self.inner.supports_prefill()
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> anyhow::Result<CompletionResponse> {
+        let provider = self.inner.id().to_string();
+        let model = request.model.clone();
+        let estimated = estimate_input_tokens(&request);
+
+        self.limiter.acquire(&provider, &model, estimated).await;
+        let response = self.inner.complete(request).await?;
+        let actual = response.usage.input_tokens + response.usage.output_tokens;
+        self.limiter.reconcile(&provider, &model, estimated, actual);
+        Ok(response)
+    }
+
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
+        let provider = self.inner.id().to_string();
+        let model = request.model.clone();
+        let estimated = estimate_input_tokens(&request);
+
+        self.limiter.acquire(&provider, &model, estimated).await;
+        let stream = self.inner.complete_stream(request).await?;
+
+        // Reconcile once, with the total usage, when the stream goes away
+        let mut usage = StreamUsage {
+            limiter: self.limiter,
+            provider,
+            model,
+            estimated,
+            input_tokens: 0,
+            output_tokens: 0,
+            reported: false,
+        };
+        let stream = stream.map(move |chunk| {
+            if let Ok(StreamChunk {
+                usage: Some(ref u), ..
+            }) = chunk
+            {
+                usage.record(u);
+            }
+            chunk
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(key: &str, rpm: Option<u32>, tpm: Option<u32>) -> RateLimiter {
+        let limiter = RateLimiter::new();
+        let mut limits = HashMap::new();
+        limits.insert(
+            key.to_string(),
+            RateLimitConfig {
+                requests_per_minute: rpm,
+                tokens_per_minute: tpm,
+            },
+        );
+        limiter.configure(&limits);
+        limiter
+    }
+
+    #[test]
+    fn test_unlimited_provider() {
+        let limiter = limiter("openai", Some(1), None);
+        for _ in 0..10 {
+            assert!(limiter.try_acquire("anthropic", "claude", 1_000).is_ok());
+        }
+        assert!(!limiter.has_limits_for("anthropic"));
+        assert!(limiter.has_limits_for("openai"));
+    }
+
+    #[test]
+    fn test_requests_per_minute() {
+        let limiter = limiter("openai", Some(2), None);
+        assert!(limiter.try_acquire("openai", "gpt-4o", 0).is_ok());
+        assert!(limiter.try_acquire("openai", "gpt-4o-mini", 0).is_ok());
+
+        // Provider-level limit is shared across models; refill is 2/min
+        let wait = limiter.try_acquire("openai", "gpt-4o", 0).unwrap_err();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_tokens_per_minute_and_reconcile() {
+        let limiter = limiter("anthropic/claude", None, Some(1_000));
+        assert!(limiter.try_acquire("anthropic", "claude", 600).is_ok());
+        assert!(limiter.try_acquire("anthropic", "claude", 600).is_err());
+
+        // Actual usage was lower than estimated; capacity is returned
+        limiter.reconcile("anthropic", "claude", 600, 100);
+        assert!(limiter.try_acquire("anthropic", "claude", 600).is_ok());
+
+        // Other models aren't limited
+        assert!(limiter.try_acquire("anthropic", "other", 10_000).is_ok());
+    }
+
+    #[test]
+    fn test_oversized_request_does_not_block_forever() {
+        let limiter = limiter("local", None, Some(100));
+        assert!(limiter.try_acquire("local", "llama", 5_000).is_ok());
+        let wait = limiter.try_acquire("local", "llama", 5_000).unwrap_err();
+        assert!(wait <= Duration::from_secs(60));
+    }
+
+    /// Streams usage like Anthropic: input tokens first, output tokens last.
+    struct SplitUsageProvider;
+
+    fn usage_chunk(input_tokens: u32, output_tokens: u32, is_final: bool) -> StreamChunk {
+        StreamChunk {
+            delta: None,
+            tool_call_delta: None,
+            is_final,
+            usage: Some(Usage {
+                input_tokens,
+                output_tokens,
+                cached_tokens: 0,
+            }),
+            finish_reason: None,
+        }
+    }
+
+    #[async_trait]
+    impl Provider for SplitUsageProvider {
+        fn id(&self) -> &str {
+            "split"
+        }
+
+        fn name(&self) -> &str {
+            "Split"
+        }
+
+        fn available_models(&self) -> Vec<ModelInfo> {
+            Vec::new()
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+
+        async fn complete(
+            &self,
+            _request: CompletionRequest,
+        ) -> anyhow::Result<CompletionResponse> {
+            anyhow::bail!("not used")
+        }
+
+        async fn complete_stream(
+            &self,
+            _request: CompletionRequest,
+        ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>>
+        {
+            let chunks = vec![
+                Ok(usage_chunk(300, 0, false)),
+                Ok(usage_chunk(0, 400, true)),
+            ];
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: "m".to_string(),
+            messages: Vec::new(),
+            system: Some("x".repeat(400)),
+            max_tokens: 1024,
+            temperature: None,
+            tools: Vec::new(),
+            stop: Vec::new(),
+        }
+    }
+
+    fn split_provider() -> (RateLimitedProvider, &'static RateLimiter) {
+        let limiter: &'static RateLimiter =
+            Box::leak(Box::new(limiter("split", None, Some(1_000))));
+        let provider = RateLimitedProvider {
+            inner: Arc::new(SplitUsageProvider),
+            limiter,
+        };
+        (provider, limiter)
+    }
+
+    #[tokio::test]
+    async fn test_stream_reconciles_total_usage_once() {
+        // Pre-charged 100 tokens, actually used 300 in + 400 out
+        assert_eq!(estimate_input_tokens(&request()), 100);
+        let (provider, limiter) = split_provider();
+        let stream = provider.complete_stream(request()).await.unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+
+        // 700 of 1000 tokens are spent
+        assert!(limiter.try_acquire("split", "m", 350).is_err());
+        assert!(limiter.try_acquire("split", "m", 250).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_reconciles_usage_seen() {
+        let (provider, limiter) = split_provider();
+        let mut stream = provider.complete_stream(request()).await.unwrap();
+        stream.next().await.unwrap().unwrap();
+        drop(stream);
+
+        // Only the 300 input tokens were reported
+        assert!(limiter.try_acquire("split", "m", 750).is_err());
+        assert!(limiter.try_acquire("split", "m", 650).is_ok());
+    }
+}
@@ -8,12 +8,17 @@ use agentik_core::Config;
 use super::anthropic::AnthropicProvider;
 use super::local::LocalProvider;
 use super::openai::OpenAIProvider;
-use super::traits::{ModelInfo, Provider};
+use super::rate_limit::{RateLimitedProvider, RateLimiter};
+use super::tool_emulation::PromptToolProvider;
+use super::traits::{EmbeddingProvider, ModelInfo, Provider};
+use super::wire::{Redactor, WireLog};
 
 /// Registry of available AI providers.
 pub struct ProviderRegistry {
     providers: HashMap<String, Arc<dyn Provider>>,
+    embedders: HashMap<String, Arc<dyn EmbeddingProvider>>,
     default_provider: Option<String>,
+    wire_log: Option<Arc<WireLog>>,
 }
 
 impl ProviderRegistry {
@@ -21,31 +26,31 @@ impl ProviderRegistry {
     pub fn new() -> Self {
         Self {
             providers: HashMap::new(),
+            embedders: HashMap::new(),
             default_provider: None,
+            wire_log: None,
         }
     }
 
     /// Initialize registry with all available providers based on configuration.
     ///
     /// This method first tries to use API keys from the config, then falls back
-    /// to environment variables (ANTHROPIC_API_KEY, OPENAI_API_KEY).
+    /// to environment variables (ANTHROPIC_API_KEY, OPENAI_API_KEY). Endpoint
+    /// environment variables (ANTHROPIC_BASE_URL, OPENAI_BASE_URL) override
+    /// the config.
     pub fn from_config(config: &Config) -> Self {
         let mut registry = Self::new();
 
-        // Register Anthropic provider if API key is available (config or env)
+        // All providers share one client built from the [network] section
+        let client = agentik_core::http::build_client_or_default(&config.network);
+
+        // API keys from config or env
         let anthropic_key = config
             .providers
             .anthropic
             .as_ref()
             .and_then(|c| c.resolve_api_key())
             .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
-
-        if let Some(api_key) = anthropic_key {
-            let provider = AnthropicProvider::new(&api_key);
-            registry.register(Arc::new(provider));
-        }
-
-        // Register OpenAI provider if API key is available (config or env)
         let openai_key = config
             .providers
             .openai
@@ -53,18 +58,62 @@ impl ProviderRegistry {
             .and_then(|c| c.resolve_api_key())
             .or_else(|| std::env::var("OPENAI_API_KEY").ok());
 
+        // The wire log stays closed until a session opens it. Configured
+        // headers and keys are masked on top of the built-in rules.
+        if config.debug.wire_log {
+            let headers = [&config.providers.anthropic, &config.providers.openai]
+                .into_iter()
+                .flatten()
+                .flat_map(|c| c.headers.keys());
+            let redactor = Redactor::new(&config.debug.redact_patterns)
+                .with_sensitive_headers(headers)
+                .with_secrets(anthropic_key.iter().chain(openai_key.iter()));
+            registry.wire_log = Some(Arc::new(WireLog::with_redactor(redactor)));
+        }
+
+        // Register Anthropic provider if API key is available
+        if let Some(api_key) = anthropic_key {
+            let mut provider = AnthropicProvider::new(&api_key).with_client(client.clone());
+            if let Some(ref anthropic_config) = config.providers.anthropic {
+                if let Some(ref base_url) = anthropic_config.base_url {
+                    provider = provider.with_base_url(base_url);
+                }
+                if let Some(ref version) = anthropic_config.api_version {
+                    provider = provider.with_api_version(version);
+                }
+                for (name, value) in &anthropic_config.headers {
+                    provider = provider.with_header(name, value);
+                }
+            }
+            // ANTHROPIC_BASE_URL / ANTHROPIC_API_VERSION / ANTHROPIC_BETA env vars
+            provider = provider.with_env_overrides();
+            if let Some(ref wire_log) = registry.wire_log {
+                provider = provider.with_wire_log(wire_log.clone());
+            }
+            registry.register(Arc::new(provider));
+        }
+
+        // Register OpenAI provider if API key is available
         if let Some(api_key) = openai_key {
-            let mut provider = OpenAIProvider::new(&api_key);
+            let mut provider = OpenAIProvider::new(&api_key).with_client(client.clone());
             if let Some(ref openai_config) = config.providers.openai {
                 if let Some(ref base_url) = openai_config.base_url {
                     provider = provider.with_base_url(base_url);
                 }
+                for (name, value) in &openai_config.headers {
+                    provider = provider.with_header(name, value);
+                }
             }
             // Also check OPENAI_BASE_URL env var
             if let Ok(base_url) = std::env::var("OPENAI_BASE_URL") {
                 provider = provider.with_base_url(&base_url);
             }
-            registry.register(Arc::new(provider));
+            if let Some(ref wire_log) = registry.wire_log {
+                provider = provider.with_wire_log(wire_log.clone());
+            }
+            let provider = Arc::new(provider);
+            registry.register_embedding("openai", provider.clone());
+            registry.register(provider);
         }
 
         // Register Local (Ollama) provider - always available (no API key needed)
@@ -76,7 +125,7 @@ impl ProviderRegistry {
             .unwrap_or(true);
 
         if local_enabled {
-            let provider = config
+            let mut provider = config
                 .providers
                 .local
                 .as_ref()
@@ -87,8 +136,32 @@ impl ProviderRegistry {
                         .ok()
                         .map(LocalProvider::with_url)
                 })
+                .unwrap_or_default()
+                .with_client(client);
+            if let Some(ref wire_log) = registry.wire_log {
+                provider = provider.with_wire_log(wire_log.clone());
+            }
+            let provider = Arc::new(provider);
+            registry.register_embedding("local", provider.clone());
+            // Many local models lack native tool calling; emulate it for those
+            let overrides = config
+                .providers
+                .local
+                .as_ref()
+                .map(|c| c.emulate_tools.clone())
                 .unwrap_or_default();
-            registry.register(Arc::new(provider));
+            registry.register(Arc::new(
+                PromptToolProvider::new(provider).with_overrides(overrides),
+            ));
+        }
+
+        // Apply client-side rate limits (shared by every agent in the process)
+        let limiter = RateLimiter::global();
+        limiter.configure(&config.providers.rate_limits);
+        for provider in registry.providers.values_mut() {
+            if limiter.has_limits_for(provider.id()) {
+                *provider = Arc::new(RateLimitedProvider::new(provider.clone()));
+            }
         }
 
         // Set default provider based on config or first available
@@ -105,7 +178,8 @@ impl ProviderRegistry {
 
         // Check for Anthropic API key
         if let Ok(api_key) = std::env::var("ANTHROPIC_API_KEY") {
-            registry.register(Arc::new(AnthropicProvider::new(&api_key)));
+            let provider = AnthropicProvider::new(&api_key).with_env_overrides();
+            registry.register(Arc::new(provider));
         }
 
         // Check for OpenAI API key
@@ -115,17 +189,26 @@ impl ProviderRegistry {
             if let Ok(base_url) = std::env::var("OPENAI_BASE_URL") {
                 provider = provider.with_base_url(&base_url);
             }
-            registry.register(Arc::new(provider));
+            let provider = Arc::new(provider);
+            registry.register_embedding("openai", provider.clone());
+            registry.register(provider);
         }
 
         // Always register local provider
         let local_url = std::env::var("OLLAMA_HOST")
             .unwrap_or_else(|_| "http://localhost:11434/v1".to_string());
-        registry.register(Arc::new(LocalProvider::with_url(local_url)));
+        let provider = Arc::new(LocalProvider::with_url(local_url));
+        registry.register_embedding("local", provider.clone());
+        registry.register(Arc::new(PromptToolProvider::new(provider)));
 
         registry
     }
 
+    /// Get the shared wire log, if wire logging is enabled.
+    pub fn wire_log(&self) -> Option<&Arc<WireLog>> {
+        self.wire_log.as_ref()
+    }
+
     /// Register a provider.
     pub fn register(&mut self, provider: Arc<dyn Provider>) {
         let id = provider.id().to_string();
@@ -135,6 +218,29 @@ impl ProviderRegistry {
         self.providers.insert(id, provider);
     }
 
+    /// Register an embedding provider under a provider ID.
+    pub fn register_embedding(&mut self, id: &str, provider: Arc<dyn EmbeddingProvider>) {
+        self.embedders.insert(id.to_string(), provider);
+    }
+
+    /// Get the embedding provider for a provider ID.
+    pub fn embedding_provider(&self, id: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        self.embedders.get(id).cloned()
+    }
+
+    /// Get the embedding provider of the default provider, or of the first
+    /// provider (by ID) that can embed if the default can't.
+    pub fn default_embedding_provider(&self) -> Option<Arc<dyn EmbeddingProvider>> {
+        self.default_provider
+            .as_ref()
+            .and_then(|id| self.embedding_provider(id))
+            .or_else(|| {
+                let mut ids: Vec<_> = self.embedders.keys().collect();
+                ids.sort();
+                ids.first().and_then(|id| self.embedding_provider(id))
+            })
+    }
+
     /// Get a provider by ID.
     pub fn get(&self, id: &str) -> Option<Arc<dyn Provider>> {
         self.providers.get(id).cloned()
@@ -173,6 +279,19 @@ impl ProviderRegistry {
             .collect()
     }
 
+    /// Find the provider serving a model, given as `provider/model` or as a
+    /// model ID a provider lists. Returns the provider and the model ID to
+    /// request from it.
+    pub fn resolve_model(&self, spec: &str) -> Option<(Arc<dyn Provider>, String)> {
+        if let Some((id, model)) = spec.split_once('/') {
+            if let Some(provider) = self.get(id) {
+                return Some((provider, model.to_string()));
+            }
+        }
+        self.find_model(spec)
+            .map(|(provider, model)| (provider, model.id))
+    }
+
     /// Find a model by ID across all providers.
     pub fn find_model(&self, model_id: &str) -> Option<(Arc<dyn Provider>, ModelInfo)> {
         for provider in self.providers.values() {
@@ -193,3 +312,60 @@ impl Default for ProviderRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_providers() {
+        let mut registry = ProviderRegistry::new();
+        assert!(registry.default_embedding_provider().is_none());
+
+        let anthropic = Arc::new(AnthropicProvider::new("key"));
+        registry.register(anthropic);
+        let local = Arc::new(LocalProvider::new());
+        registry.register_embedding("local", local.clone());
+        registry.register(Arc::new(PromptToolProvider::new(local)));
+
+        // Anthropic is the default but can't embed
+        assert!(registry.embedding_provider("anthropic").is_none());
+        let embedder = registry.default_embedding_provider().unwrap();
+        assert_eq!(embedder.default_embedding_model(), "nomic-embed-text");
+
+        let openai = Arc::new(OpenAIProvider::new("key"));
+        registry.register_embedding("openai", openai.clone());
+        registry.register(openai);
+        registry.set_default("openai");
+        let embedder = registry.default_embedding_provider().unwrap();
+        assert_eq!(embedder.default_embedding_model(), "text-embedding-3-small");
+    }
+
+    #[test]
+    fn test_wire_log_redacts_configured_secrets() {
+        let mut config = Config::default();
+        config.debug.wire_log = true;
+        config.providers.openai = Some(agentik_core::config::ProviderConfig {
+            api_key: Some("custom-gateway-key".to_string()),
+            headers: HashMap::from([("X-Gateway-Token".to_string(), "tok".to_string())]),
+            ..Default::default()
+        });
+        let registry = ProviderRegistry::from_config(&config);
+        let wire = registry.wire_log().unwrap();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(crate::wire::WIRE_LOG_FILE);
+        wire.open(&path).unwrap();
+        let request = reqwest::Client::new()
+            .post("https://gateway.example.com/v1/chat/completions")
+            .header("x-gateway-token", "tok")
+            .body(r#"{"echo": "custom-gateway-key"}"#)
+            .build()
+            .unwrap();
+        wire.request("openai", &request);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("custom-gateway-key"));
+        assert!(!contents.contains("\"tok\""));
+    }
+}
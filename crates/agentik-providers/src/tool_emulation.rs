@@ -0,0 +1,672 @@
+//! Prompt-based tool calling for models without native function calling.
+//!
+//! Many local models report `supports_tools: false`. [`PromptToolProvider`]
+//! wraps any provider and, for such models (and for models the provider
+//! doesn't know about), describes the available tools in the system prompt and
+//! parses invocations back out of the generated text.
+//!
+//! # Format
+//!
+//! The model is asked to emit each call as a JSON object inside a
+//! `<tool_call>` block:
+//!
+//! ```text
+//! <tool_call>
+//! {"name": "Read", "arguments": {"path": "src/main.rs"}}
+//! </tool_call>
+//! ```
+//!
+//! Tool results are sent back as user turns containing `<tool_result>` blocks:
+//!
+//! ```text
+//! <tool_result name="Read" id="call_123">
+//! fn main() {}
+//! </tool_result>
+//! ```
+//!
+//! Text outside of `<tool_call>` blocks is passed through unchanged.
+
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{stream, Stream, StreamExt};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use agentik_core::message::ContentPart;
+use agentik_core::{Content, Message, Role, ToolCall, ToolDefinition};
+
+use crate::traits::{
+    CompletionRequest, CompletionResponse, FinishReason, ModelInfo, Provider, StreamChunk,
+    ToolCallDelta,
+};
+
+/// Opening tag of an emulated tool call.
+const TOOL_CALL_OPEN: &str = "<tool_call>";
+/// Closing tag of an emulated tool call.
+const TOOL_CALL_CLOSE: &str = "</tool_call>";
+
+// ============================================================================
+// Provider Adapter
+// ============================================================================
+
+/// Provider adapter that emulates tool calling through the prompt.
+///
+/// Requests for models that support native tools are passed through
+/// untouched. Models the inner provider doesn't list are assumed to lack
+/// native tools, unless overridden per model.
+pub struct PromptToolProvider {
+    inner: Arc<dyn Provider>,
+    overrides: HashMap<String, bool>,
+}
+
+impl PromptToolProvider {
+    /// Wrap a provider.
+    pub fn new(inner: Arc<dyn Provider>) -> Self {
+        Self {
+            inner,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Force emulation on or off for specific models (model id -> emulate).
+    pub fn with_overrides(mut self, overrides: HashMap<String, bool>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Get the wrapped provider.
+    pub fn inner(&self) -> &Arc<dyn Provider> {
+        &self.inner
+    }
+
+    /// Check whether a request needs tool emulation.
+    pub fn needs_emulation(&self, request: &CompletionRequest) -> bool {
+        if request.tools.is_empty() {
+            return false;
+        }
+        if let Some(&emulate) = self.overrides.get(&request.model) {
+            return emulate;
+        }
+        self.inner
+            .available_models()
+            .iter()
+            .find(|m| m.id == request.model)
+            .map(|m| !m.supports_tools)
+            .unwrap_or(true)
+    }
+}
+
+#[async_trait]
+impl Provider for PromptToolProvider {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn available_models(&self) -> Vec<ModelInfo> {
+        self.inner.available_models()
+    }
+
+    fn is_configured(&self) -> bool {
+        self.inner.is_configured()
+    }
+
+    fn supports_prefill(&self) -> bool {
+        self.inner.supports_prefill()
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> anyhow::Result<CompletionResponse> {
+        if !self.needs_emulation(&request) {
+            return self.inner.complete(request).await;
+        }
+
+        debug!(model = %request.model, "Emulating tool calls via prompt");
+        let mut response = self.inner.complete(emulate_request(request)).await?;
+
+        let mut parser = ToolCallParser::new();
+        let mut segments = parser.feed(&response.content);
+        segments.extend(parser.finish());
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for segment in segments {
+            match segment {
+                ParsedSegment::Text(text) => content.push_str(&text),
+                ParsedSegment::ToolCall(call) => tool_calls.push(call),
+            }
+        }
+
+        if !tool_calls.is_empty() {
+            response.finish_reason = FinishReason::ToolUse;
+        }
+        response.content = content;
+        response.tool_calls = tool_calls;
+        Ok(response)
+    }
+
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
+        if !self.needs_emulation(&request) {
+            return self.inner.complete_stream(request).await;
+        }
+
+        debug!(model = %request.model, "Emulating streamed tool calls via prompt");
+        let inner = self.inner.complete_stream(emulate_request(request)).await?;
+
+        let state = (inner, ToolCallParser::new(), VecDeque::new(), false);
+        let stream = stream::unfold(
+            state,
+            |(mut inner, mut parser, mut pending, mut done)| async move {
+                loop {
+                    if let Some(item) = pending.pop_front() {
+                        return Some((item, (inner, parser, pending, done)));
+                    }
+                    if done {
+                        return None;
+                    }
+
+                    match inner.next().await {
+                        Some(Ok(chunk)) => {
+                            let StreamChunk {
+                                delta,
+                                tool_call_delta,
+                                is_final,
+                                usage,
+                                finish_reason,
+                            } = chunk;
+
+                            if let Some(delta) = delta {
+                                pending.extend(parser.feed(&delta).into_iter().map(segment_chunk));
+                            }
+                            // Pass through anything the inner provider produced natively
+                            if tool_call_delta.is_some() || (finish_reason.is_some() && !is_final) {
+                                pending.push_back(Ok(StreamChunk {
+                                    delta: None,
+                                    tool_call_delta,
+                                    is_final: false,
+                                    usage: None,
+                                    finish_reason: finish_reason.filter(|_| !is_final),
+                                }));
+                            }
+                            if is_final {
+                                pending.extend(parser.finish().into_iter().map(segment_chunk));
+                                pending.push_back(Ok(StreamChunk {
+                                    delta: None,
+                                    tool_call_delta: None,
+                                    is_final: true,
+                                    usage,
+                                    finish_reason,
+                                }));
+                                done = true;
+                            }
+                        }
+                        Some(Err(e)) => {
+                            pending.push_back(Err(e));
+                            done = true;
+                        }
+                        None => {
+                            pending.extend(parser.finish().into_iter().map(segment_chunk));
+                            done = true;
+                        }
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Convert a parsed segment into a stream chunk.
+fn segment_chunk(segment: ParsedSegment) -> anyhow::Result<StreamChunk> {
+    Ok(match segment {
+        ParsedSegment::Text(text) => StreamChunk {
+            delta: Some(text),
+            tool_call_delta: None,
+            is_final: false,
+            usage: None,
+            finish_reason: None,
+        },
+        ParsedSegment::ToolCall(call) => StreamChunk {
+            delta: None,
+            tool_call_delta: Some(ToolCallDelta {
+                id: Some(call.id),
+                name: Some(call.name),
+                arguments: Some(call.arguments.to_string()),
+            }),
+            is_final: false,
+            usage: None,
+            finish_reason: None,
+        },
+    })
+}
+
+// ============================================================================
+// Request Rewriting
+// ============================================================================
+
+/// Rewrite a request so that tools are described in the prompt instead of
+/// passed natively.
+pub fn emulate_request(mut request: CompletionRequest) -> CompletionRequest {
+    let tools_prompt = render_tools_prompt(&request.tools);
+    request.system = Some(match request.system.take() {
+        Some(system) if !system.is_empty() => format!("{}\n\n{}", system, tools_prompt),
+        _ => tools_prompt,
+    });
+    request.messages = rewrite_messages(&request.messages);
+    request.tools.clear();
+    request
+}
+
+/// Render tool definitions and calling instructions for the system prompt.
+pub fn render_tools_prompt(tools: &[ToolDefinition]) -> String {
+    let mut prompt = String::from(
+        "# Tools\n\n\
+         You can call tools to help with the task. To call a tool, write a \
+         <tool_call> block containing a JSON object with the tool name and its \
+         arguments, for example:\n\n\
+         <tool_call>\n\
+         {\"name\": \"ToolName\", \"arguments\": {\"param\": \"value\"}}\n\
+         </tool_call>\n\n\
+         You may call several tools in one response. After calling tools, stop \
+         and wait: results will arrive in <tool_result> blocks in the next user \
+         message. Only call the tools listed below.\n\n\
+         ## Available tools\n",
+    );
+
+    for tool in tools {
+        prompt.push_str(&format!(
+            "\n<tool name=\"{}\">\n{}\nParameters (JSON Schema): {}\n</tool>\n",
+            tool.name, tool.description, tool.parameters
+        ));
+    }
+
+    prompt
+}
+
+/// Render a tool call the way the model is asked to write it.
+pub fn render_tool_call(call: &ToolCall) -> String {
+    let body = serde_json::json!({ "name": call.name, "arguments": call.arguments });
+    format!("{}\n{}\n{}", TOOL_CALL_OPEN, body, TOOL_CALL_CLOSE)
+}
+
+/// Render a tool result block.
+pub fn render_tool_result(name: &str, id: &str, content: &str, is_error: bool) -> String {
+    let error_attr = if is_error { " error=\"true\"" } else { "" };
+    format!(
+        "<tool_result name=\"{}\" id=\"{}\"{}>\n{}\n</tool_result>",
+        name, id, error_attr, content
+    )
+}
+
+/// Rewrite the conversation so it contains no native tool messages.
+///
+/// Assistant tool calls become `<tool_call>` text and tool results become
+/// user turns. Consecutive results are merged into a single user message.
+fn rewrite_messages(messages: &[Message]) -> Vec<Message> {
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut rewritten: Vec<Message> = Vec::with_capacity(messages.len());
+    let mut last_was_result = false;
+
+    for message in messages {
+        match message.role {
+            Role::Assistant => {
+                let mut text = message.content.as_text();
+                for part in content_parts(&message.content) {
+                    if let ContentPart::ToolUse { id, name, input } = part {
+                        names.insert(id.clone(), name.clone());
+                        push_block(
+                            &mut text,
+                            &render_tool_call(&ToolCall::new(id, name, input.clone())),
+                        );
+                    }
+                }
+                for call in &message.tool_calls {
+                    names.insert(call.id.clone(), call.name.clone());
+                    push_block(&mut text, &render_tool_call(call));
+                }
+                let mut msg = message.clone();
+                msg.content = Content::Text(text);
+                msg.tool_calls.clear();
+                rewritten.push(msg);
+                last_was_result = false;
+            }
+            Role::Tool => {
+                let mut text = String::new();
+                for part in content_parts(&message.content) {
+                    if let ContentPart::ToolResult {
+                        tool_use_id,
+                        content,
+                        is_error,
+                    } = part
+                    {
+                        let name = names.get(tool_use_id).map(String::as_str).unwrap_or("tool");
+                        push_block(
+                            &mut text,
+                            &render_tool_result(name, tool_use_id, content, *is_error),
+                        );
+                    }
+                }
+                if text.is_empty() {
+                    text = message.content.as_text();
+                }
+
+                match rewritten.last_mut() {
+                    Some(prev) if last_was_result => {
+                        let mut merged = prev.content.as_text();
+                        push_block(&mut merged, &text);
+                        prev.content = Content::Text(merged);
+                    }
+                    _ => {
+                        let mut msg = Message::user(text);
+                        msg.id = message.id.clone();
+                        msg.timestamp = message.timestamp;
+                        rewritten.push(msg);
+                    }
+                }
+                last_was_result = true;
+            }
+            _ => {
+                rewritten.push(message.clone());
+                last_was_result = false;
+            }
+        }
+    }
+
+    rewritten
+}
+
+/// Get the structured parts of a message, if any.
+fn content_parts(content: &Content) -> &[ContentPart] {
+    match content {
+        Content::Parts(parts) => parts,
+        Content::Text(_) => &[],
+    }
+}
+
+/// Append a block to text, separated by a blank line.
+fn push_block(text: &mut String, block: &str) {
+    if !text.is_empty() {
+        text.push_str("\n\n");
+    }
+    text.push_str(block);
+}
+
+// ============================================================================
+// Streaming Parser
+// ============================================================================
+
+/// A piece of parsed model output.
+#[derive(Debug, Clone)]
+pub enum ParsedSegment {
+    /// Plain text to show to the user
+    Text(String),
+    /// A complete tool invocation
+    ToolCall(ToolCall),
+}
+
+/// Incremental parser that extracts `<tool_call>` blocks from streamed text.
+///
+/// Text that might be the start of a tag is held back until enough input
+/// arrives to decide, so tags split across chunks are never leaked.
+#[derive(Debug, Default)]
+pub struct ToolCallParser {
+    buffer: String,
+    in_call: bool,
+}
+
+/// JSON body of an emulated tool call.
+#[derive(Debug, Deserialize)]
+struct EmulatedCall {
+    name: String,
+    #[serde(default, alias = "parameters", alias = "input")]
+    arguments: serde_json::Value,
+}
+
+impl ToolCallParser {
+    /// Create a new parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of streamed text, returning any segments that are complete.
+    pub fn feed(&mut self, text: &str) -> Vec<ParsedSegment> {
+        self.buffer.push_str(text);
+        let mut segments = Vec::new();
+
+        loop {
+            if self.in_call {
+                let Some(end) = self.buffer.find(TOOL_CALL_CLOSE) else {
+                    break;
+                };
+                let body = self.buffer[..end].to_string();
+                self.buffer.drain(..end + TOOL_CALL_CLOSE.len());
+                self.in_call = false;
+                segments.push(parse_call_body(&body));
+            } else if let Some(start) = self.buffer.find(TOOL_CALL_OPEN) {
+                if start > 0 {
+                    segments.push(ParsedSegment::Text(self.buffer[..start].to_string()));
+                }
+                self.buffer.drain(..start + TOOL_CALL_OPEN.len());
+                self.in_call = true;
+            } else {
+                // Emit everything except a suffix that could begin a tag
+                let keep = partial_tag_len(&self.buffer, TOOL_CALL_OPEN);
+                let emit = self.buffer.len() - keep;
+                if emit > 0 {
+                    segments.push(ParsedSegment::Text(self.buffer[..emit].to_string()));
+                    self.buffer.drain(..emit);
+                }
+                break;
+            }
+        }
+
+        segments
+    }
+
+    /// Flush remaining input at the end of the stream.
+    ///
+    /// An unterminated tool call is parsed if its JSON is complete (models
+    /// often stop right before the closing tag); otherwise it is returned as
+    /// text.
+    pub fn finish(&mut self) -> Vec<ParsedSegment> {
+        let rest = std::mem::take(&mut self.buffer);
+        if self.in_call {
+            self.in_call = false;
+            return vec![parse_call_body(&rest)];
+        }
+        if rest.is_empty() {
+            Vec::new()
+        } else {
+            vec![ParsedSegment::Text(rest)]
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `tag`.
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len())
+        .rev()
+        .find(|&n| text.ends_with(&tag[..n]))
+        .unwrap_or(0)
+}
+
+/// Parse the body of a `<tool_call>` block.
+fn parse_call_body(body: &str) -> ParsedSegment {
+    let json = body
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    match serde_json::from_str::<EmulatedCall>(json) {
+        Ok(call) => {
+            let arguments = if call.arguments.is_null() {
+                serde_json::json!({})
+            } else {
+                call.arguments
+            };
+            ParsedSegment::ToolCall(ToolCall::new(
+                format!("call_{}", uuid::Uuid::new_v4().simple()),
+                call.name,
+                arguments,
+            ))
+        }
+        Err(e) => {
+            warn!("Failed to parse emulated tool call: {}", e);
+            ParsedSegment::Text(format!("{}{}", TOOL_CALL_OPEN, body))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(segments: Vec<ParsedSegment>) -> (String, Vec<ToolCall>) {
+        let mut text = String::new();
+        let mut calls = Vec::new();
+        for segment in segments {
+            match segment {
+                ParsedSegment::Text(t) => text.push_str(&t),
+                ParsedSegment::ToolCall(c) => calls.push(c),
+            }
+        }
+        (text, calls)
+    }
+
+    #[test]
+    fn test_parse_complete_call() {
+        let mut parser = ToolCallParser::new();
+        let mut segments = parser.feed(
+            "Let me look.\n<tool_call>\n{\"name\": \"Read\", \"arguments\": {\"path\": \"a.rs\"}}\n</tool_call>",
+        );
+        segments.extend(parser.finish());
+
+        let (text, calls) = collect(segments);
+        assert_eq!(text, "Let me look.\n");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "Read");
+        assert_eq!(calls[0].arguments["path"], "a.rs");
+    }
+
+    #[test]
+    fn test_parse_split_across_chunks() {
+        let input = "Hi <tool_call>{\"name\": \"Glob\", \"arguments\": {\"pattern\": \"*.rs\"}}</tool_call> done";
+        let mut parser = ToolCallParser::new();
+        let mut segments = Vec::new();
+        for chunk in input.as_bytes().chunks(3) {
+            segments.extend(parser.feed(std::str::from_utf8(chunk).unwrap()));
+        }
+        segments.extend(parser.finish());
+
+        let (text, calls) = collect(segments);
+        assert_eq!(text, "Hi  done");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "Glob");
+    }
+
+    #[test]
+    fn test_partial_tag_is_held_back() {
+        let mut parser = ToolCallParser::new();
+        let (text, _) = collect(parser.feed("text <tool_"));
+        assert_eq!(text, "text ");
+
+        // Not a tag after all
+        let (text, _) = collect(parser.feed("ish> more"));
+        assert_eq!(text, "<tool_ish> more");
+    }
+
+    #[test]
+    fn test_unterminated_call() {
+        let mut parser = ToolCallParser::new();
+        parser.feed("<tool_call>{\"name\": \"Bash\", \"arguments\": {\"command\": \"ls\"}}");
+        let (_, calls) = collect(parser.finish());
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].arguments["command"], "ls");
+
+        // Truncated JSON falls back to text
+        let mut parser = ToolCallParser::new();
+        parser.feed("<tool_call>{\"name\": \"Bash\", \"argu");
+        let (text, calls) = collect(parser.finish());
+        assert!(calls.is_empty());
+        assert!(text.starts_with("<tool_call>"));
+    }
+
+    #[test]
+    fn test_emulate_request() {
+        let call = ToolCall::new("call_1", "Read", serde_json::json!({"path": "a.rs"}));
+        let mut assistant = Message::assistant("Reading");
+        assistant.tool_calls = vec![call];
+
+        let request = CompletionRequest {
+            model: "codellama".to_string(),
+            messages: vec![
+                Message::user("Show a.rs"),
+                assistant,
+                Message::tool_result("call_1".to_string(), "fn main() {}", false),
+            ],
+            system: Some("Be helpful.".to_string()),
+            max_tokens: 1024,
+            temperature: Some(0.0),
+            tools: vec![ToolDefinition::new("Read", "Read a file")],
+            stop: vec![],
+        };
+
+        let emulated = emulate_request(request);
+        assert!(emulated.tools.is_empty());
+        let system = emulated.system.unwrap();
+        assert!(system.starts_with("Be helpful."));
+        assert!(system.contains("<tool name=\"Read\">"));
+
+        assert_eq!(emulated.messages.len(), 3);
+        assert!(emulated.messages[1].tool_calls.is_empty());
+        assert!(emulated.messages[1]
+            .content
+            .as_text()
+            .contains("<tool_call>"));
+        assert_eq!(emulated.messages[2].role, Role::User);
+        assert!(emulated.messages[2]
+            .content
+            .as_text()
+            .contains("<tool_result name=\"Read\" id=\"call_1\">"));
+    }
+
+    #[test]
+    fn test_needs_emulation() {
+        let request = |model: &str| CompletionRequest {
+            model: model.to_string(),
+            messages: vec![Message::user("hi")],
+            system: None,
+            max_tokens: 1024,
+            temperature: None,
+            tools: vec![ToolDefinition::new("Read", "Read a file")],
+            stop: vec![],
+        };
+
+        let provider = PromptToolProvider::new(Arc::new(crate::local::LocalProvider::new()));
+        assert!(provider.needs_emulation(&request("codellama")));
+        assert!(!provider.needs_emulation(&request("llama3.2")));
+        // Unlisted local models are emulated
+        assert!(provider.needs_emulation(&request("my-finetune:7b")));
+        let mut no_tools = request("my-finetune:7b");
+        no_tools.tools.clear();
+        assert!(!provider.needs_emulation(&no_tools));
+
+        let provider = provider.with_overrides(HashMap::from([
+            ("my-finetune:7b".to_string(), false),
+            ("llama3.2".to_string(), true),
+        ]));
+        assert!(!provider.needs_emulation(&request("my-finetune:7b")));
+        assert!(provider.needs_emulation(&request("llama3.2")));
+    }
+}
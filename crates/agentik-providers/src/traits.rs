@@ -25,10 +25,20 @@ pub struct ModelInfo {
     pub supports_vision: bool,
     /// Supports streaming
     pub supports_streaming: bool,
+    /// Accepts a sampling temperature
+    #[serde(default = "default_true")]
+    pub supports_temperature: bool,
+    /// Accepts a dedicated system prompt
+    #[serde(default = "default_true")]
+    pub supports_system_prompt: bool,
     /// Pricing information
     pub pricing: Option<Pricing>,
 }
 
+fn default_true() -> bool {
+    true
+}
+
 /// Pricing information for a model.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pricing {
@@ -49,8 +59,9 @@ pub struct CompletionRequest {
     pub system: Option<String>,
     /// Maximum tokens to generate
     pub max_tokens: u32,
-    /// Temperature (0.0-1.0)
-    pub temperature: f32,
+    /// Temperature (0.0-1.0), `None` to use the model default
+    #[serde(default)]
+    pub temperature: Option<f32>,
     /// Available tools
     pub tools: Vec<ToolDefinition>,
     /// Stop sequences
@@ -108,6 +119,9 @@ pub struct StreamChunk {
     pub is_final: bool,
     /// Usage (only in final chunk)
     pub usage: Option<Usage>,
+    /// Why generation stopped (only in the chunk that reports it)
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
 }
 
 /// Delta for tool call streaming.
@@ -136,6 +150,12 @@ pub trait Provider: Send + Sync {
     /// Check if provider is configured and ready.
     fn is_configured(&self) -> bool;
 
+    /// Whether a trailing assistant message is continued rather than
+    /// answered (response prefill).
+    fn supports_prefill(&self) -> bool {
+        false
+    }
+
     /// Generate a completion (non-streaming).
     async fn complete(&self, request: CompletionRequest) -> anyhow::Result<CompletionResponse>;
 
@@ -157,3 +177,63 @@ pub trait ToolCapable: Provider {
     /// Format tool results for the provider.
     fn format_tool_results(&self, results: &[agentik_core::ToolResult]) -> Vec<Message>;
 }
+
+/// Embedding model information with dimensions, limits and pricing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModelInfo {
+    /// Model identifier
+    pub id: String,
+    /// Provider name
+    pub provider: String,
+    /// Dimensionality of the returned vectors
+    pub dimensions: u32,
+    /// Maximum input tokens per text
+    pub max_input_tokens: u32,
+    /// Maximum number of texts per request
+    pub max_batch_size: usize,
+    /// Cost per million input tokens (USD), `None` for free/local models
+    pub price_per_million: Option<f64>,
+}
+
+impl EmbeddingModelInfo {
+    /// Calculate the cost in USD of embedding the given number of tokens.
+    pub fn cost(&self, input_tokens: u32) -> f64 {
+        self.price_per_million
+            .map(|price| input_tokens as f64 * price / 1_000_000.0)
+            .unwrap_or(0.0)
+    }
+}
+
+/// Response from an embedding request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    /// Model that produced the embeddings
+    pub model: String,
+    /// One vector per input text, in input order
+    pub embeddings: Vec<Vec<f32>>,
+    /// Input tokens consumed
+    pub input_tokens: u32,
+    /// Cost in USD (0.0 if pricing is unknown)
+    pub cost_usd: f64,
+}
+
+/// Embedding capability - providers that can turn text into vectors.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Get available embedding models for this provider.
+    fn embedding_models(&self) -> Vec<EmbeddingModelInfo>;
+
+    /// Default embedding model used when `model` is empty.
+    fn default_embedding_model(&self) -> &str;
+
+    /// Embed a batch of texts.
+    ///
+    /// Batches larger than the model's `max_batch_size` are split into
+    /// multiple requests and the results concatenated in input order.
+    async fn embed(&self, texts: &[String], model: &str) -> anyhow::Result<EmbeddingResponse>;
+
+    /// Look up metadata for an embedding model.
+    fn embedding_model(&self, model: &str) -> Option<EmbeddingModelInfo> {
+        self.embedding_models().into_iter().find(|m| m.id == model)
+    }
+}
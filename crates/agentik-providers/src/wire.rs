@@ -0,0 +1,303 @@
+//! Request/response wire logging.
+//!
+//! When enabled, providers record every outgoing HTTP request and the
+//! response body or stream events to a JSONL file, one object per line.
+//! Credentials are redacted before anything touches disk: sensitive headers
+//! are masked by name and bodies are scrubbed with secret patterns.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Utc;
+use regex::Regex;
+use serde_json::{json, Value};
+use tracing::warn;
+
+/// File name of the wire log inside a session directory.
+pub const WIRE_LOG_FILE: &str = "wire.jsonl";
+
+/// Replacement text for redacted values.
+const REDACTED: &str = "[REDACTED]";
+
+/// Headers whose values are always redacted.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "api-key",
+    "cookie",
+    "set-cookie",
+];
+
+/// Built-in patterns for secrets that may appear in bodies.
+const DEFAULT_SECRET_PATTERNS: &[&str] = &[
+    // OpenAI / Anthropic style keys
+    r"sk-[A-Za-z0-9_\-]{16,}",
+    // Bearer tokens
+    r"(?i)bearer\s+[A-Za-z0-9._~+/\-]+=*",
+    // AWS access key IDs
+    r"AKIA[0-9A-Z]{16}",
+    // GitHub tokens
+    r"gh[pousr]_[A-Za-z0-9]{20,}",
+];
+
+/// Scrubs secrets from text.
+#[derive(Debug)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+    /// Extra header names (lowercase) whose values are always redacted
+    headers: Vec<String>,
+}
+
+impl Redactor {
+    /// Create a redactor with the built-in patterns plus extra ones.
+    ///
+    /// Invalid extra patterns are skipped with a warning.
+    pub fn new(extra_patterns: &[String]) -> Self {
+        let mut patterns: Vec<Regex> = DEFAULT_SECRET_PATTERNS
+            .iter()
+            .map(|p| Regex::new(p).expect("built-in secret pattern is valid"))
+            .collect();
+
+        for pattern in extra_patterns {
+            match Regex::new(pattern) {
+                Ok(re) => patterns.push(re),
+                Err(e) => warn!("Ignoring invalid redaction pattern '{}': {}", pattern, e),
+            }
+        }
+
+        Self {
+            patterns,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Also mask these headers entirely (e.g. configured custom headers).
+    pub fn with_sensitive_headers<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.headers
+            .extend(names.into_iter().map(|n| n.as_ref().to_ascii_lowercase()));
+        self
+    }
+
+    /// Also redact these literal values (e.g. configured API keys).
+    pub fn with_secrets<I, S>(mut self, secrets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for secret in secrets {
+            let secret = secret.as_ref();
+            if !secret.is_empty() {
+                let re = Regex::new(&regex::escape(secret)).expect("escaped literal is valid");
+                self.patterns.push(re);
+            }
+        }
+        self
+    }
+
+    /// Replace every match of every pattern.
+    pub fn redact(&self, text: &str) -> String {
+        let mut result = text.to_string();
+        for pattern in &self.patterns {
+            if pattern.is_match(&result) {
+                result = pattern.replace_all(&result, REDACTED).into_owned();
+            }
+        }
+        result
+    }
+
+    /// Redact a header value, masking sensitive headers entirely.
+    pub fn redact_header(&self, name: &str, value: &str) -> String {
+        let name = name.to_ascii_lowercase();
+        if SENSITIVE_HEADERS.contains(&name.as_str()) || self.headers.contains(&name) {
+            REDACTED.to_string()
+        } else {
+            self.redact(value)
+        }
+    }
+}
+
+/// JSONL wire log shared by all providers in a process.
+///
+/// The log starts closed; [`WireLog::open`] points it at a file (normally
+/// the current session's [`WIRE_LOG_FILE`]). While closed, logging calls
+/// are no-ops.
+#[derive(Debug)]
+pub struct WireLog {
+    redactor: Redactor,
+    sink: Mutex<Option<(PathBuf, File)>>,
+}
+
+impl WireLog {
+    /// Create a closed wire log with extra redaction patterns.
+    pub fn new(redact_patterns: &[String]) -> Self {
+        Self::with_redactor(Redactor::new(redact_patterns))
+    }
+
+    /// Create a closed wire log with a custom redactor (e.g. one that also
+    /// masks configured headers and keys).
+    pub fn with_redactor(redactor: Redactor) -> Self {
+        Self {
+            redactor,
+            sink: Mutex::new(None),
+        }
+    }
+
+    /// Start appending to the given file, replacing any previous file.
+    pub fn open(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        *self.sink.lock().unwrap() = Some((path.to_path_buf(), file));
+        Ok(())
+    }
+
+    /// Path of the file currently being written, if any.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.sink.lock().unwrap().as_ref().map(|(p, _)| p.clone())
+    }
+
+    /// Record an outgoing request.
+    pub fn request(&self, provider: &str, request: &reqwest::Request) {
+        let headers: serde_json::Map<String, Value> = request
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                (
+                    name.to_string(),
+                    Value::String(self.redactor.redact_header(name.as_str(), &value)),
+                )
+            })
+            .collect();
+        let body = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| self.body_value(&String::from_utf8_lossy(b)))
+            .unwrap_or(Value::Null);
+
+        self.write(json!({
+            "provider": provider,
+            "kind": "request",
+            "method": request.method().as_str(),
+            "url": self.redactor.redact(request.url().as_str()),
+            "headers": headers,
+            "body": body,
+        }));
+    }
+
+    /// Record a complete (non-streamed) response.
+    pub fn response(&self, provider: &str, status: u16, body: &str) {
+        self.write(json!({
+            "provider": provider,
+            "kind": "response",
+            "status": status,
+            "body": self.body_value(body),
+        }));
+    }
+
+    /// Record a single stream event.
+    pub fn stream_event(&self, provider: &str, event: Option<&str>, data: &str) {
+        self.write(json!({
+            "provider": provider,
+            "kind": "event",
+            "event": event,
+            "data": self.body_value(data),
+        }));
+    }
+
+    /// Redact a body and keep it structured when it is JSON.
+    fn body_value(&self, body: &str) -> Value {
+        let redacted = self.redactor.redact(body);
+        serde_json::from_str(&redacted).unwrap_or(Value::String(redacted))
+    }
+
+    /// Append a timestamped entry.
+    fn write(&self, mut entry: Value) {
+        let mut sink = self.sink.lock().unwrap();
+        let Some((path, file)) = sink.as_mut() else {
+            return;
+        };
+        entry["timestamp"] = Value::String(Utc::now().to_rfc3339());
+        if let Err(e) = writeln!(file, "{}", entry) {
+            warn!("Failed to write wire log {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_builtin_patterns() {
+        let redactor = Redactor::new(&[]);
+        let text = r#"{"key": "sk-ant-REDACTED", "auth": "Bearer abc.def"}"#;
+        let redacted = redactor.redact(text);
+        assert!(!redacted.contains("abcdefghijklmnop"));
+        assert!(!redacted.contains("abc.def"));
+        assert_eq!(redacted.matches(REDACTED).count(), 2);
+    }
+
+    #[test]
+    fn test_redacts_custom_patterns_and_headers() {
+        let redactor = Redactor::new(&["internal-[0-9]+".to_string(), "(".to_string()]);
+        assert_eq!(redactor.redact("token internal-42"), "token [REDACTED]");
+        assert_eq!(redactor.redact_header("X-Api-Key", "plain"), REDACTED);
+        assert_eq!(redactor.redact_header("content-type", "json"), "json");
+    }
+
+    #[test]
+    fn test_redacts_configured_headers_and_secrets() {
+        let redactor = Redactor::new(&[])
+            .with_sensitive_headers(["X-Gateway-Token"])
+            .with_secrets(["plainkey.1+2", ""]);
+        assert_eq!(redactor.redact_header("x-gateway-token", "abc"), REDACTED);
+        assert_eq!(
+            redactor.redact(r#"{"key": "plainkey.1+2"}"#),
+            r#"{"key": "[REDACTED]"}"#
+        );
+        // Regex metacharacters in secrets are matched literally
+        assert_eq!(redactor.redact("plainkeyx1+2"), "plainkeyx1+2");
+        assert_eq!(redactor.redact("text"), "text");
+    }
+
+    #[test]
+    fn test_writes_jsonl() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(WIRE_LOG_FILE);
+        let wire = WireLog::new(&[]);
+
+        // Closed log is a no-op
+        wire.response("test", 200, "{}");
+        assert!(wire.path().is_none());
+
+        wire.open(&path).unwrap();
+        let request = reqwest::Client::new()
+            .post("https://api.example.com/v1/messages")
+            .header("x-api-key", "secret")
+            .body(r#"{"model": "m"}"#)
+            .build()
+            .unwrap();
+        wire.request("test", &request);
+        wire.stream_event("test", Some("message_stop"), r#"{"type": "message_stop"}"#);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["headers"]["x-api-key"], REDACTED);
+        assert_eq!(lines[0]["body"]["model"], "m");
+        assert_eq!(lines[1]["data"]["type"], "message_stop");
+    }
+}
@@ -0,0 +1,7 @@
+-- Task plans for planning mode
+-- Version 2
+
+-- Current plan (JSON)
+ALTER TABLE sessions ADD COLUMN plan TEXT;
+
+INSERT OR IGNORE INTO schema_version (version, applied_at) VALUES (2, datetime('now'));
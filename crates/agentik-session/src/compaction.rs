@@ -158,7 +158,7 @@ impl Compactor {
 
         // Truncate long messages
         let goal = if text.len() > 200 {
-            format!("{}...", &text[..197])
+            format!("{}...", truncate_to_boundary(&text, 197))
         } else {
             text
         };
@@ -418,7 +418,7 @@ Keep the summary under 500 words. Be factual and specific."
             };
             let content = msg.content.as_text();
             let truncated = if content.len() > 500 {
-                format!("{}...[truncated]", &content[..497])
+                format!("{}...[truncated]", truncate_to_boundary(&content, 497))
             } else {
                 content
             };
@@ -594,7 +594,7 @@ impl<P: CompletionProvider> SummaryGenerator for LlmSummaryGenerator<P> {
 
         // Post-process: ensure the summary isn't too long
         let summary = if response.len() > 2000 {
-            format!("{}...", &response[..1997])
+            format!("{}...", truncate_to_boundary(&response, 1997))
         } else {
             response
         };
@@ -603,6 +603,15 @@ impl<P: CompletionProvider> SummaryGenerator for LlmSummaryGenerator<P> {
     }
 }
 
+/// Truncate to at most `max_bytes` without splitting a UTF-8 character.
+fn truncate_to_boundary(s: &str, max_bytes: usize) -> &str {
+    let mut end = max_bytes.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -747,4 +756,15 @@ mod tests {
         assert!(prompt.contains("User"));
         assert!(prompt.contains("Assistant"));
     }
+
+    #[test]
+    fn test_build_summary_prompt_truncates_multibyte_text() {
+        let compactor = Compactor::new();
+        let messages = vec![Message::user("é".repeat(400))];
+        let extraction = compactor.extract_information(&messages);
+
+        let prompt = compactor.build_summary_prompt(&messages, &extraction, None);
+
+        assert!(prompt.contains("...[truncated]"));
+    }
 }
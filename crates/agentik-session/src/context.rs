@@ -5,8 +5,14 @@
 
 use serde::{Deserialize, Serialize};
 
+use agentik_core::message::{Content, ContentPart};
+use agentik_core::tool::ARTIFACT_NOTE_PREFIX;
 use agentik_core::{session::CompactedSummary, Message, Session};
 
+/// Tool results shorter than this are never elided; the stub would save
+/// little.
+const MIN_ELIDED_RESULT_CHARS: usize = 500;
+
 /// Configuration for context window management.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextConfig {
@@ -20,6 +26,10 @@ pub struct ContextConfig {
     pub preserve_recent_messages: usize,
     /// Average characters per token (for estimation).
     pub chars_per_token: f32,
+    /// Number of most recent tool results sent in full; older ones are
+    /// replaced by a short stub (`None` keeps all).
+    #[serde(default)]
+    pub keep_tool_results: Option<usize>,
 }
 
 impl Default for ContextConfig {
@@ -30,6 +40,7 @@ impl Default for ContextConfig {
             min_recent_tokens: 20_000,    // Reserve 20K for recent context
             preserve_recent_messages: 10, // Always keep last 10 messages
             chars_per_token: 4.0,         // Rough estimate
+            keep_tool_results: None,
         }
     }
 }
@@ -43,6 +54,7 @@ impl ContextConfig {
             min_recent_tokens: 2_000,
             preserve_recent_messages: 6,
             chars_per_token: 4.0,
+            keep_tool_results: None,
         }
     }
 
@@ -54,6 +66,7 @@ impl ContextConfig {
             min_recent_tokens: 15_000,
             preserve_recent_messages: 8,
             chars_per_token: 4.0,
+            keep_tool_results: None,
         }
     }
 
@@ -203,7 +216,8 @@ impl ContextManager {
         };
 
         // Get messages after compaction boundary
-        let messages: Vec<Message> = session.messages[session.compact_boundary..].to_vec();
+        let mut messages: Vec<Message> = session.messages[session.compact_boundary..].to_vec();
+        self.elide_tool_results(&mut messages);
 
         let estimated_tokens = self.count_tokens(&messages)
             + session
@@ -219,6 +233,47 @@ impl ContextManager {
         }
     }
 
+    /// Replace large tool results, except the most recent ones, with stubs.
+    ///
+    /// A stub keeps the note naming an output's artifact, so the model can
+    /// still read an offloaded output in full.
+    fn elide_tool_results(&self, messages: &mut [Message]) {
+        let Some(keep) = self.config.keep_tool_results else {
+            return;
+        };
+
+        let mut seen = 0;
+        for message in messages.iter_mut().rev() {
+            let Content::Parts(parts) = &mut message.content else {
+                continue;
+            };
+            for part in parts.iter_mut().rev() {
+                let ContentPart::ToolResult { content, .. } = part else {
+                    continue;
+                };
+                seen += 1;
+                if seen <= keep || content.len() < MIN_ELIDED_RESULT_CHARS {
+                    continue;
+                }
+
+                let mut stub = format!(
+                    "[Earlier output elided to save context ({} characters). \
+                     Run the tool again if you need it.]",
+                    content.len()
+                );
+                if let Some(note) = content
+                    .lines()
+                    .rev()
+                    .find(|l| l.starts_with(ARTIFACT_NOTE_PREFIX))
+                {
+                    stub.push('\n');
+                    stub.push_str(note);
+                }
+                *content = stub;
+            }
+        }
+    }
+
     /// Format a compacted summary for inclusion in system message.
     fn format_summary(&self, summary: &CompactedSummary) -> String {
         let mut parts = Vec::new();
@@ -442,6 +497,60 @@ mod tests {
         assert_eq!(context.messages.len(), 20);
     }
 
+    #[test]
+    fn test_prepare_context_elides_old_tool_results() {
+        let manager = ContextManager::with_config(ContextConfig {
+            keep_tool_results: Some(1),
+            ..Default::default()
+        });
+        let mut session = Session::new(PathBuf::from("/tmp/test"));
+        let offloaded = format!(
+            "{}\n\n{} 0123456789ab (900 lines, 40000 bytes). Use ReadArtifact to page through or search it.]",
+            "x".repeat(600),
+            ARTIFACT_NOTE_PREFIX
+        );
+        session.add_message(Message::tool_result("call_1".into(), offloaded, false));
+        session.add_message(Message::tool_result("call_2".into(), "short", false));
+        session.add_message(Message::tool_result(
+            "call_3".into(),
+            "y".repeat(600),
+            false,
+        ));
+        session.add_message(Message::tool_result(
+            "call_4".into(),
+            "z".repeat(600),
+            false,
+        ));
+
+        let context = manager.prepare_context(&session, None);
+        let results: Vec<&str> = context
+            .messages
+            .iter()
+            .map(|m| match &m.content {
+                Content::Parts(parts) => match &parts[0] {
+                    ContentPart::ToolResult { content, .. } => content.as_str(),
+                    _ => "",
+                },
+                _ => "",
+            })
+            .collect();
+
+        assert!(results[0].starts_with("[Earlier output elided"));
+        assert!(results[0].ends_with("Use ReadArtifact to page through or search it.]"));
+        assert_eq!(results[1], "short");
+        assert!(results[2].starts_with("[Earlier output elided"));
+        assert_eq!(results[3], "z".repeat(600));
+
+        // The session itself is untouched
+        assert!(matches!(
+            &session.messages[2].content,
+            Content::Parts(parts) if matches!(
+                &parts[0],
+                ContentPart::ToolResult { content, .. } if content.len() == 600
+            )
+        ));
+    }
+
     #[test]
     fn test_estimate_addition() {
         let manager = ContextManager::new();
@@ -152,6 +152,10 @@ pub trait SessionStore: Send + Sync {
         limit: Option<usize>,
     ) -> Result<Vec<Message>>;
 
+    /// Keep only the first `count` messages, dropping the rest from the log
+    /// and index. Compaction is reset if its boundary falls past `count`.
+    async fn truncate_messages(&self, session_id: &str, count: usize) -> Result<()>;
+
     /// Apply compaction to a session.
     async fn apply_compaction(
         &self,
@@ -177,8 +181,7 @@ pub trait SessionStore: Send + Sync {
 pub struct SqliteSessionStore {
     /// Database connection (wrapped in mutex for thread safety).
     conn: Mutex<Connection>,
-    /// Base directory for session data (stored for future use).
-    #[allow(dead_code)]
+    /// Base directory for session data.
     base_dir: PathBuf,
     /// Directory for JSONL message files.
     sessions_dir: PathBuf,
@@ -225,6 +228,21 @@ impl SqliteSessionStore {
         Self::new(data_dir)
     }
 
+    /// Get the base directory for session data.
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// Get the directory holding per-session files.
+    pub fn sessions_dir(&self) -> &Path {
+        &self.sessions_dir
+    }
+
+    /// Get the directory for a specific session's files.
+    pub fn session_dir(&self, session_id: &str) -> PathBuf {
+        self.sessions_dir.join(session_id)
+    }
+
     /// Run database migrations.
     fn run_migrations(&self) -> Result<()> {
         let conn = self.conn.lock().unwrap();
@@ -244,6 +262,11 @@ impl SqliteSessionStore {
             conn.execute_batch(migration)?;
         }
 
+        if current_version < 2 {
+            let migration = include_str!("../migrations/002_session_plan.sql");
+            conn.execute_batch(migration)?;
+        }
+
         Ok(())
     }
 
@@ -314,6 +337,7 @@ impl SessionStore for SqliteSessionStore {
             .summary
             .as_ref()
             .map(|s| serde_json::to_string(s).unwrap());
+        let plan_json = meta.plan.as_ref().map(serde_json::to_string).transpose()?;
 
         conn.execute(
             r#"
@@ -321,8 +345,8 @@ impl SessionStore for SqliteSessionStore {
                 id, version, state, working_directory, title, parent_session_id,
                 created_at, updated_at, last_active_at,
                 git_context, metrics, model_config,
-                compact_boundary, summary, message_file, message_count
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+                compact_boundary, summary, message_file, message_count, plan
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
             "#,
             params![
                 meta.id,
@@ -341,6 +365,7 @@ impl SessionStore for SqliteSessionStore {
                 summary_json,
                 message_file.to_string_lossy(),
                 session.messages.len() as i64,
+                plan_json,
             ],
         )?;
 
@@ -421,7 +446,7 @@ impl SessionStore for SqliteSessionStore {
                 r#"
                 SELECT id, version, state, working_directory, title, parent_session_id,
                        created_at, updated_at, last_active_at,
-                       git_context, metrics, model_config
+                       git_context, metrics, model_config, plan
                 FROM sessions WHERE id = ?1
                 "#,
                 params![id],
@@ -439,6 +464,7 @@ impl SessionStore for SqliteSessionStore {
                         row.get::<_, Option<String>>(9)?,
                         row.get::<_, String>(10)?,
                         row.get::<_, String>(11)?,
+                        row.get::<_, Option<String>>(12)?,
                     ))
                 },
             )
@@ -458,6 +484,7 @@ impl SessionStore for SqliteSessionStore {
             git_json,
             metrics_json,
             model_json,
+            plan_json,
         ) = row;
 
         // Get tags
@@ -482,6 +509,7 @@ impl SessionStore for SqliteSessionStore {
             model: serde_json::from_str(&model_json).unwrap_or_default(),
             tags,
             added_files: vec![], // TODO: Load from database when persistence is added
+            plan: plan_json.and_then(|s| serde_json::from_str(&s).ok()),
         })
     }
 
@@ -494,13 +522,19 @@ impl SessionStore for SqliteSessionStore {
             .map(|g| serde_json::to_string(g).unwrap());
         let metrics_json = serde_json::to_string(&metadata.metrics)?;
         let model_json = serde_json::to_string(&metadata.model)?;
+        let plan_json = metadata
+            .plan
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
 
         conn.execute(
             r#"
             UPDATE sessions SET
                 version = ?2, state = ?3, title = ?4,
                 updated_at = ?5, last_active_at = ?6,
-                git_context = ?7, metrics = ?8, model_config = ?9
+                git_context = ?7, metrics = ?8, model_config = ?9,
+                plan = ?10
             WHERE id = ?1
             "#,
             params![
@@ -513,6 +547,7 @@ impl SessionStore for SqliteSessionStore {
                 git_json,
                 metrics_json,
                 model_json,
+                plan_json,
             ],
         )?;
 
@@ -722,6 +757,56 @@ impl SessionStore for SqliteSessionStore {
         Ok(messages)
     }
 
+    async fn truncate_messages(&self, session_id: &str, count: usize) -> Result<()> {
+        let message_file = self.message_file_path(session_id);
+
+        // Rewrite the log with the kept lines; offsets of kept lines don't change
+        if message_file.exists() {
+            let reader = BufReader::new(File::open(&message_file)?);
+            let kept: Vec<String> = reader
+                .lines()
+                .filter(|line| line.as_ref().map_or(true, |l| !l.trim().is_empty()))
+                .take(count)
+                .collect::<std::io::Result<_>>()?;
+
+            let temp_file = message_file.with_extension("jsonl.tmp");
+            {
+                let mut writer = BufWriter::new(File::create(&temp_file)?);
+                for line in &kept {
+                    writeln!(writer, "{}", line)?;
+                }
+                writer.flush()?;
+            }
+            fs::rename(&temp_file, &message_file)?;
+        }
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            r#"
+            DELETE FROM message_index
+            WHERE session_id = ?1 AND id NOT IN (
+                SELECT id FROM message_index WHERE session_id = ?1 ORDER BY id LIMIT ?2
+            )
+            "#,
+            params![session_id, count as i64],
+        )?;
+
+        // The summary covers messages up to the boundary, which may be gone
+        conn.execute(
+            r#"
+            UPDATE sessions SET
+                message_count = MIN(message_count, ?2),
+                summary = CASE WHEN compact_boundary > ?2 THEN NULL ELSE summary END,
+                compact_boundary = CASE WHEN compact_boundary > ?2 THEN 0 ELSE compact_boundary END,
+                updated_at = ?3
+            WHERE id = ?1
+            "#,
+            params![session_id, count as i64, Self::format_datetime(&Utc::now())],
+        )?;
+
+        Ok(())
+    }
+
     async fn apply_compaction(
         &self,
         session_id: &str,
@@ -891,6 +976,32 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_plan_persisted_in_metadata() {
+        let (store, _tmp) = create_test_store();
+
+        let mut session = Session::new(PathBuf::from("/tmp/test"));
+        store.create(&session).await.unwrap();
+        assert!(store
+            .get(session.id())
+            .await
+            .unwrap()
+            .metadata
+            .plan
+            .is_none());
+
+        let mut plan = agentik_core::Plan::new(
+            "Refactor",
+            vec![agentik_core::PlanStep::new("Extract module")],
+        );
+        plan.approve();
+        session.metadata.plan = Some(plan.clone());
+        store.update_metadata(&session.metadata).await.unwrap();
+
+        let retrieved = store.get(session.id()).await.unwrap();
+        assert_eq!(retrieved.metadata.plan, Some(plan));
+    }
+
     #[tokio::test]
     async fn test_append_and_get_messages() {
         let (store, _tmp) = create_test_store();
@@ -910,6 +1021,49 @@ mod tests {
         assert_eq!(messages[1].content.as_text(), "Hi there!");
     }
 
+    #[tokio::test]
+    async fn test_truncate_messages() {
+        let (store, _tmp) = create_test_store();
+
+        let session = Session::new(PathBuf::from("/tmp/test"));
+        store.create(&session).await.unwrap();
+        for text in ["one", "two", "three"] {
+            store
+                .append_message(session.id(), &Message::user(text))
+                .await
+                .unwrap();
+        }
+        let summary = CompactedSummary {
+            text: "summary".into(),
+            key_decisions: vec![],
+            modified_files: vec![],
+            created_at: Utc::now(),
+            messages_compacted: 2,
+        };
+        store
+            .apply_compaction(session.id(), &summary, 2)
+            .await
+            .unwrap();
+
+        store.truncate_messages(session.id(), 1).await.unwrap();
+        let session = store.get(session.id()).await.unwrap();
+        assert_eq!(session.messages.len(), 1);
+        assert_eq!(session.messages[0].content.as_text(), "one");
+        assert_eq!(session.compact_boundary, 0);
+        assert!(session.summary.is_none());
+        let summaries = store.find_by_prefix(session.id()).await.unwrap();
+        assert_eq!(summaries[0].message_count, 1);
+
+        // Appending continues after the kept messages
+        store
+            .append_message(session.id(), &Message::user("four"))
+            .await
+            .unwrap();
+        let messages = store.get_messages(session.id(), None, None).await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content.as_text(), "four");
+    }
+
     #[tokio::test]
     async fn test_list_sessions() {
         let (store, _tmp) = create_test_store();
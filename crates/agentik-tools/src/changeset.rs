@@ -0,0 +1,252 @@
+//! Staged file changes for dry runs.
+//!
+//! When a [`ToolContext`](crate::ToolContext) carries a [`Changeset`], the
+//! file tools write to it instead of to disk, and read and search through it,
+//! so the model sees its own changes while the workspace stays untouched. At
+//! the end of the run the user reviews the combined diff and accepts or
+//! discards the changes, all at once or file by file.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use similar::TextDiff;
+
+/// A file changed in a changeset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedFile {
+    /// Content on disk when the file was first staged (`None` if new)
+    pub original: Option<String>,
+    /// Staged content
+    pub content: String,
+}
+
+impl StagedFile {
+    /// Unified diff from the original to the staged content, with paths
+    /// labelled as given.
+    pub fn diff(&self, label: &str) -> String {
+        let original = self.original.as_deref().unwrap_or("");
+        let old_label = if self.original.is_some() {
+            format!("a/{}", label)
+        } else {
+            "/dev/null".to_string()
+        };
+        TextDiff::from_lines(original, &self.content)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_label, &format!("b/{}", label))
+            .to_string()
+    }
+}
+
+/// In-memory overlay of file changes, keyed by absolute path.
+#[derive(Debug, Default)]
+pub struct Changeset {
+    files: Mutex<BTreeMap<PathBuf, StagedFile>>,
+}
+
+impl Changeset {
+    /// Create an empty changeset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Staged content of a file, if it has been changed.
+    pub fn read(&self, path: &Path) -> Option<String> {
+        let files = self.files.lock().unwrap();
+        files.get(path).map(|f| f.content.clone())
+    }
+
+    /// Stage new content for a file, remembering what is on disk the first
+    /// time the file is staged.
+    pub async fn write(&self, path: &Path, content: &str) -> io::Result<()> {
+        if let Some(file) = self.files.lock().unwrap().get_mut(path) {
+            file.content = content.to_string();
+            return Ok(());
+        }
+        // Read the original without holding the lock
+        let original = match tokio::fs::read_to_string(path).await {
+            Ok(original) => Some(original),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        // A write staged meanwhile keeps the original it saw
+        self.files
+            .lock()
+            .unwrap()
+            .entry(path.to_path_buf())
+            .and_modify(|file| file.content = content.to_string())
+            .or_insert_with(|| StagedFile {
+                original,
+                content: content.to_string(),
+            });
+        Ok(())
+    }
+
+    /// Whether no files are staged.
+    pub fn is_empty(&self) -> bool {
+        self.files.lock().unwrap().is_empty()
+    }
+
+    /// Number of staged files.
+    pub fn len(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+
+    /// Paths of the staged files, sorted.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Staged files that don't exist on disk, under a directory.
+    pub fn new_files_under(&self, dir: &Path) -> Vec<PathBuf> {
+        let files = self.files.lock().unwrap();
+        files
+            .iter()
+            .filter(|(path, file)| file.original.is_none() && path.starts_with(dir))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Get a staged file.
+    pub fn get(&self, path: &Path) -> Option<StagedFile> {
+        self.files.lock().unwrap().get(path).cloned()
+    }
+
+    /// Combined unified diff of every staged file, with paths shown
+    /// relative to `root` where they are under it.
+    pub fn diff(&self, root: &Path) -> String {
+        let files = self.files.lock().unwrap();
+        files
+            .iter()
+            .map(|(path, file)| file.diff(&label(path, root)))
+            .collect()
+    }
+
+    /// Write a staged file to disk and unstage it.
+    ///
+    /// Fails without writing if the file changed on disk since it was
+    /// staged. Returns `false` if the file isn't staged.
+    pub fn accept(&self, path: &Path) -> io::Result<bool> {
+        let mut files = self.files.lock().unwrap();
+        let Some(file) = files.get(path) else {
+            return Ok(false);
+        };
+        let current = match std::fs::read_to_string(path) {
+            Ok(current) => Some(current),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        if current != file.original {
+            return Err(io::Error::other(format!(
+                "{} changed on disk since it was staged",
+                path.display()
+            )));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &file.content)?;
+        files.remove(path);
+        Ok(true)
+    }
+
+    /// Write every staged file to disk, returning the paths written.
+    ///
+    /// Stops at the first file that fails; it and the remaining files stay
+    /// staged.
+    pub fn accept_all(&self) -> io::Result<Vec<PathBuf>> {
+        let mut accepted = Vec::new();
+        for path in self.paths() {
+            if self.accept(&path)? {
+                accepted.push(path);
+            }
+        }
+        Ok(accepted)
+    }
+
+    /// Unstage a file without writing it. Returns `false` if it isn't staged.
+    pub fn discard(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().remove(path).is_some()
+    }
+
+    /// Unstage every file, returning the paths discarded.
+    pub fn discard_all(&self) -> Vec<PathBuf> {
+        let mut files = self.files.lock().unwrap();
+        std::mem::take(&mut *files).into_keys().collect()
+    }
+}
+
+/// Path shown in diffs: relative to `root` if under it.
+fn label(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_stage_diff_and_accept() {
+        let dir = TempDir::new().unwrap();
+        let existing = dir.path().join("lib.rs");
+        let created = dir.path().join("src/new.rs");
+        std::fs::write(&existing, "fn a() {}\nfn b() {}\n").unwrap();
+
+        let changeset = Changeset::new();
+        changeset
+            .write(&existing, "fn a() {}\nfn c() {}\n")
+            .await
+            .unwrap();
+        changeset.write(&created, "pub mod x;\n").await.unwrap();
+        // Later writes keep the original from disk
+        changeset
+            .write(&existing, "fn a() {}\nfn d() {}\n")
+            .await
+            .unwrap();
+
+        assert_eq!(changeset.len(), 2);
+        assert_eq!(
+            changeset.read(&existing).as_deref(),
+            Some("fn a() {}\nfn d() {}\n")
+        );
+        assert_eq!(changeset.new_files_under(dir.path()), vec![created.clone()]);
+        assert!(!created.exists());
+
+        let diff = changeset.diff(dir.path());
+        assert!(diff.contains("--- a/lib.rs\n+++ b/lib.rs\n"));
+        assert!(diff.contains("-fn b() {}\n+fn d() {}\n"));
+        assert!(diff.contains("--- /dev/null\n+++ b/src/new.rs\n"));
+
+        assert!(changeset.accept(&created).unwrap());
+        assert_eq!(std::fs::read_to_string(&created).unwrap(), "pub mod x;\n");
+        assert!(!changeset.accept(&created).unwrap());
+
+        assert!(changeset.discard(&existing));
+        assert!(changeset.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&existing).unwrap(),
+            "fn a() {}\nfn b() {}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_accept_refuses_changed_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "one\n").unwrap();
+
+        let changeset = Changeset::new();
+        changeset.write(&path, "two\n").await.unwrap();
+        std::fs::write(&path, "edited by hand\n").unwrap();
+
+        assert!(changeset.accept_all().is_err());
+        assert_eq!(changeset.paths(), vec![path.clone()]);
+        assert_eq!(changeset.discard_all(), vec![path]);
+    }
+}
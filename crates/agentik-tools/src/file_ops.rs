@@ -99,7 +99,7 @@ impl Tool for ReadTool {
             .map(|v| v as usize)
             .unwrap_or(DEFAULT_LINE_LIMIT);
 
-        let content = tokio::fs::read_to_string(&path).await?;
+        let content = ctx.read_file(&path).await?;
         let lines: Vec<&str> = content.lines().collect();
         let total_lines = lines.len();
 
@@ -206,13 +206,8 @@ impl Tool for WriteTool {
             return Err(ToolError::SandboxViolation(path));
         }
 
-        // Create parent directories if needed
-        if let Some(parent) = path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
-        }
-
-        // Write the file
-        tokio::fs::write(&path, content).await?;
+        // Write the file, creating parent directories if needed
+        ctx.write_file(&path, content).await?;
 
         let line_count = content.lines().count();
         let byte_count = content.len();
@@ -220,10 +215,11 @@ impl Tool for WriteTool {
         Ok(ToolResult::success(
             &call.id,
             format!(
-                "Successfully wrote {} lines ({} bytes) to {}",
+                "Successfully wrote {} lines ({} bytes) to {}{}",
                 line_count,
                 byte_count,
-                path.display()
+                path.display(),
+                staged_note(ctx)
             ),
         ))
     }
@@ -323,7 +319,7 @@ impl Tool for EditTool {
         }
 
         // Read current content
-        let content = tokio::fs::read_to_string(&path).await?;
+        let content = ctx.read_file(&path).await?;
 
         // Count occurrences
         let count = content.matches(old_string).count();
@@ -344,12 +340,17 @@ impl Tool for EditTool {
         };
 
         // Write back
-        tokio::fs::write(&path, &new_content).await?;
+        ctx.write_file(&path, &new_content).await?;
 
         let msg = if replace_all && count > 1 {
-            format!("Replaced {} occurrences in {}", count, path.display())
+            format!(
+                "Replaced {} occurrences in {}{}",
+                count,
+                path.display(),
+                staged_note(ctx)
+            )
         } else {
-            format!("Successfully edited {}", path.display())
+            format!("Successfully edited {}{}", path.display(), staged_note(ctx))
         };
 
         Ok(ToolResult::success(&call.id, msg))
@@ -448,6 +449,19 @@ impl Tool for GlobTool {
             }
         }
 
+        // Files only created in the changeset count as just modified
+        if let Some(changeset) = &ctx.changeset {
+            for path in changeset.new_files_under(&search_path) {
+                let relative = path
+                    .strip_prefix(&search_path)
+                    .unwrap_or(&path)
+                    .to_string_lossy();
+                if !path.exists() && (glob_set.is_match(&*relative) || glob_set.is_match(&path)) {
+                    matches.push((path, SystemTime::now()));
+                }
+            }
+        }
+
         // Sort by mtime (most recent first)
         matches.sort_by_key(|m| std::cmp::Reverse(m.1));
 
@@ -615,22 +629,30 @@ impl Tool for GrepTool {
         let mut results: Vec<GrepMatch> = Vec::new();
 
         // Handle single file vs directory
-        if search_path.is_file() {
-            if let Some(matches) = search_file(&search_path, &regex, context_lines)? {
+        if search_path.is_file() || ctx.staged(&search_path).is_some() {
+            if let Some(matches) = search_file(&search_path, &regex, context_lines, ctx)? {
                 results.push(matches);
             }
         } else {
-            for entry in WalkBuilder::new(&search_path)
+            // Files only created in the changeset aren't found by walking
+            let mut paths: Vec<std::path::PathBuf> = WalkBuilder::new(&search_path)
                 .hidden(false)
                 .git_ignore(true)
                 .build()
                 .filter_map(|e| e.ok())
-            {
-                let path = entry.path();
-                if !path.is_file() {
-                    continue;
-                }
+                .map(|e| e.into_path())
+                .filter(|p| p.is_file())
+                .collect();
+            if let Some(changeset) = &ctx.changeset {
+                paths.extend(
+                    changeset
+                        .new_files_under(&search_path)
+                        .into_iter()
+                        .filter(|p| !p.exists()),
+                );
+            }
 
+            for path in &paths {
                 // Apply glob filter if specified
                 if let Some(ref gs) = glob_set {
                     let relative = path
@@ -642,7 +664,7 @@ impl Tool for GrepTool {
                     }
                 }
 
-                if let Some(matches) = search_file(path, &regex, context_lines)? {
+                if let Some(matches) = search_file(path, &regex, context_lines, ctx)? {
                     results.push(matches);
                 }
             }
@@ -669,14 +691,18 @@ struct LineMatch {
     context_after: Vec<String>,
 }
 
-/// Search a file for regex matches.
+/// Search a file for regex matches, seeing staged changes.
 fn search_file(
     path: &Path,
     regex: &Regex,
     context_lines: usize,
+    ctx: &ToolContext,
 ) -> Result<Option<GrepMatch>, ToolError> {
     // Skip binary files
-    let content = match fs::read_to_string(path) {
+    let content = match ctx
+        .staged(path)
+        .map_or_else(|| fs::read_to_string(path), Ok)
+    {
         Ok(c) => c,
         Err(_) => return Ok(None), // Skip files we can't read as text
     };
@@ -795,6 +821,15 @@ fn format_grep_results(results: &[GrepMatch], mode: GrepOutputMode, pattern: &st
     }
 }
 
+/// Note added to the results of writes staged instead of made on disk.
+fn staged_note(ctx: &ToolContext) -> &'static str {
+    if ctx.changeset.is_some() {
+        " (staged for review, not yet on disk)"
+    } else {
+        ""
+    }
+}
+
 /// Truncate a string for display in error messages.
 fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
@@ -807,6 +842,8 @@ fn truncate_string(s: &str, max_len: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::changeset::Changeset;
+    use std::sync::Arc;
     use tempfile::TempDir;
 
     async fn setup_test_dir() -> TempDir {
@@ -1103,4 +1140,53 @@ mod tests {
         assert!(result.success);
         assert!(result.output.contains("1 total matches")); // Only main.rs
     }
+
+    #[tokio::test]
+    async fn test_changes_staged_in_changeset() {
+        let dir = setup_test_dir().await;
+        let changeset = Arc::new(Changeset::new());
+        let ctx = ToolContext::new(dir.path()).with_changeset(Arc::clone(&changeset));
+        let main_rs = dir.path().join("src/main.rs");
+        let new_rs = dir.path().join("src/staged.rs");
+
+        let edit = ToolCall::new(
+            "edit",
+            "Edit",
+            json!({
+                "file_path": main_rs.to_string_lossy(),
+                "old_string": "Hello, world!",
+                "new_string": "Hello, overlay!"
+            }),
+        );
+        let result = EditTool.execute(&edit, &ctx).await.unwrap();
+        assert!(result.output.contains("staged for review"));
+        let write = ToolCall::new(
+            "write",
+            "Write",
+            json!({ "file_path": new_rs.to_string_lossy(), "content": "// overlay\n" }),
+        );
+        WriteTool.execute(&write, &ctx).await.unwrap();
+
+        // Nothing reached the disk
+        assert!(fs::read_to_string(&main_rs)
+            .unwrap()
+            .contains("Hello, world!"));
+        assert!(!new_rs.exists());
+        assert_eq!(changeset.len(), 2);
+
+        // Reads and searches see the staged content
+        let read = ToolCall::new(
+            "read",
+            "Read",
+            json!({ "file_path": main_rs.to_string_lossy() }),
+        );
+        let result = ReadTool.execute(&read, &ctx).await.unwrap();
+        assert!(result.output.contains("Hello, overlay!"));
+        let grep = ToolCall::new("grep", "Grep", json!({ "pattern": "overlay" }));
+        let result = GrepTool.execute(&grep, &ctx).await.unwrap();
+        assert!(result.output.contains("Found 2 files"));
+        let glob = ToolCall::new("glob", "Glob", json!({ "pattern": "src/*.rs" }));
+        let result = GlobTool.execute(&glob, &ctx).await.unwrap();
+        assert!(result.output.contains("staged.rs"));
+    }
 }
@@ -7,6 +7,7 @@
 //! - Shell execution (sandboxed)
 //! - Git operations
 //! - Web fetch and search
+//! - Staging of file changes for dry runs
 //!
 //! ## Architecture
 //!
@@ -31,19 +32,23 @@
 use std::path::PathBuf;
 use thiserror::Error;
 
+pub mod changeset;
 pub mod external;
 pub mod file_ops;
 pub mod git;
+pub mod plan;
 pub mod registry;
 pub mod repo_map;
 pub mod shell;
 pub mod web;
 
-pub use registry::{SandboxConfig, Tool, ToolContext, ToolRegistry};
+pub use changeset::{Changeset, StagedFile};
+pub use registry::{SandboxConfig, Tool, ToolContext, ToolRegistry, DEFAULT_MAX_OUTPUT_SIZE};
 
 // Re-export tools for convenience
 pub use file_ops::{EditTool, GlobTool, GrepTool, ReadTool, WriteTool};
 pub use git::{GitAddTool, GitCommitTool, GitDiffTool, GitLogTool, GitStatusTool};
+pub use plan::UpdatePlanTool;
 pub use repo_map::GetRepoMapTool;
 pub use shell::BashTool;
 
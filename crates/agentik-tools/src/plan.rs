@@ -0,0 +1,253 @@
+//! Plan tool for planning mode.
+//!
+//! Provides the UpdatePlan tool the model uses to submit a structured plan
+//! and to report the status of steps while the plan is executed.
+
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use agentik_core::tool::ToolCategory;
+use agentik_core::{Plan, PlanStep, PlanStepStatus, ToolCall, ToolDefinition, ToolResult};
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::registry::{Tool, ToolContext};
+use crate::ToolError;
+
+/// Tool for creating and updating the task plan.
+///
+/// The plan is shared with the agent, which handles review, approval and
+/// step-by-step execution.
+pub struct UpdatePlanTool {
+    /// Shared plan (owned by the agent)
+    plan: Arc<RwLock<Option<Plan>>>,
+}
+
+impl UpdatePlanTool {
+    /// Create a new UpdatePlan tool with a shared plan.
+    pub fn new(plan: Arc<RwLock<Option<Plan>>>) -> Self {
+        Self { plan }
+    }
+
+    /// Create a tool with no plan (for testing).
+    pub fn empty() -> Self {
+        Self {
+            plan: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Get a copy of the current plan.
+    pub fn plan(&self) -> Option<Plan> {
+        self.plan.read().unwrap().clone()
+    }
+
+    /// Parse the `steps` argument.
+    fn parse_steps(value: &serde_json::Value) -> Result<Vec<PlanStep>, ToolError> {
+        let items = value
+            .as_array()
+            .ok_or_else(|| ToolError::WrongType("steps".into(), "array".into()))?;
+
+        items
+            .iter()
+            .map(|item| {
+                let description = item
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .or_else(|| item.as_str())
+                    .ok_or_else(|| ToolError::MissingParameter("steps[].description".into()))?;
+                let files = item
+                    .get("files")
+                    .and_then(|v| v.as_array())
+                    .map(|arr| {
+                        arr.iter()
+                            .filter_map(|v| v.as_str())
+                            .map(PathBuf::from)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Ok(PlanStep::new(description).with_files(files))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Tool for UpdatePlanTool {
+    fn name(&self) -> &str {
+        "UpdatePlan"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "UpdatePlan",
+            "Create or revise the task plan, or report progress on a step. \
+             To submit a plan, provide goal and an ordered list of steps (each with a \
+             description and the files it will touch); the user reviews and approves it \
+             before anything is executed. While executing an approved plan, provide step \
+             and status to report the outcome of the current step.",
+        )
+        .with_parameters(json!({
+            "type": "object",
+            "properties": {
+                "goal": {
+                    "type": "string",
+                    "description": "Overall goal of the plan."
+                },
+                "steps": {
+                    "type": "array",
+                    "description": "Ordered steps. Replaces all unfinished steps of the current plan.",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "description": {"type": "string"},
+                            "files": {"type": "array", "items": {"type": "string"}}
+                        },
+                        "required": ["description"]
+                    }
+                },
+                "step": {
+                    "type": "integer",
+                    "description": "1-based number of the step to update."
+                },
+                "status": {
+                    "type": "string",
+                    "enum": ["in_progress", "completed", "failed", "skipped"],
+                    "description": "New status for the step."
+                },
+                "note": {
+                    "type": "string",
+                    "description": "Outcome or reason for failure."
+                }
+            },
+            "required": []
+        }))
+        .with_category(ToolCategory::Agent)
+    }
+
+    fn validate(&self, arguments: &serde_json::Value) -> Result<(), ToolError> {
+        let has_steps = arguments.get("steps").is_some();
+        let has_step = arguments.get("step").is_some();
+        if !has_steps && !has_step {
+            return Err(ToolError::InvalidArguments(
+                "provide either steps (to submit a plan) or step and status".into(),
+            ));
+        }
+        if has_step && arguments.get("status").is_none() {
+            return Err(ToolError::MissingParameter("status".into()));
+        }
+        Ok(())
+    }
+
+    async fn execute(&self, call: &ToolCall, _ctx: &ToolContext) -> Result<ToolResult, ToolError> {
+        self.validate(&call.arguments)?;
+        let args = &call.arguments;
+        let mut guard = self.plan.write().unwrap();
+
+        // Submit or revise the plan
+        if let Some(steps) = args.get("steps") {
+            let steps = Self::parse_steps(steps)?;
+            if steps.is_empty() {
+                return Err(ToolError::InvalidArguments("steps cannot be empty".into()));
+            }
+            let goal = args.get("goal").and_then(|v| v.as_str()).map(String::from);
+
+            match guard.as_mut() {
+                // Keep finished work when revising a plan mid-execution
+                Some(plan) if plan.has_progress() && !plan.is_complete() => {
+                    plan.revise(goal, steps)
+                }
+                _ => *guard = Some(Plan::new(goal.unwrap_or_default(), steps)),
+            }
+
+            let plan = guard.as_ref().unwrap();
+            return Ok(ToolResult::success(
+                &call.id,
+                format!(
+                    "{}\nThe plan has been submitted for user approval. \
+                     Do not start executing it until it is approved.",
+                    plan
+                ),
+            ));
+        }
+
+        // Report step progress
+        let plan = guard
+            .as_mut()
+            .ok_or_else(|| ToolError::execution("No plan exists. Submit a plan first."))?;
+        let step = args
+            .get("step")
+            .and_then(|v| v.as_u64())
+            .filter(|&n| n > 0)
+            .ok_or_else(|| ToolError::WrongType("step".into(), "positive integer".into()))?;
+        let status: PlanStepStatus = args
+            .get("status")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .parse()
+            .map_err(|e: agentik_core::Error| ToolError::InvalidArguments(e.to_string()))?;
+        let note = args.get("note").and_then(|v| v.as_str()).map(String::from);
+
+        plan.set_status(step as usize - 1, status, note)
+            .map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+
+        Ok(ToolResult::success(
+            &call.id,
+            format!("Step {} marked {:?}.", step, status),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_submit_and_update_plan() {
+        let tool = UpdatePlanTool::empty();
+        let ctx = ToolContext::new("/project");
+
+        let call = ToolCall::new(
+            "call_1",
+            "UpdatePlan",
+            json!({
+                "goal": "Add a CLI flag",
+                "steps": [
+                    {"description": "Add flag to Cli struct", "files": ["src/main.rs"]},
+                    {"description": "Document the flag"}
+                ]
+            }),
+        );
+        let result = tool.execute(&call, &ctx).await.unwrap();
+        assert!(result.success);
+        assert!(result
+            .output
+            .contains("1. Add flag to Cli struct (src/main.rs)"));
+
+        let plan = tool.plan().unwrap();
+        assert_eq!(plan.goal, "Add a CLI flag");
+        assert!(!plan.approved);
+
+        let call = ToolCall::new(
+            "call_2",
+            "UpdatePlan",
+            json!({"step": 1, "status": "failed", "note": "flag already exists"}),
+        );
+        tool.execute(&call, &ctx).await.unwrap();
+        let plan = tool.plan().unwrap();
+        assert_eq!(plan.steps[0].status, PlanStepStatus::Failed);
+        assert_eq!(plan.steps[0].notes.as_deref(), Some("flag already exists"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let tool = UpdatePlanTool::empty();
+        let ctx = ToolContext::new("/project");
+
+        let call = ToolCall::new("c", "UpdatePlan", json!({"goal": "nothing"}));
+        assert!(tool.execute(&call, &ctx).await.is_err());
+
+        // No plan to update yet
+        let call = ToolCall::new("c", "UpdatePlan", json!({"step": 1, "status": "completed"}));
+        assert!(tool.execute(&call, &ctx).await.is_err());
+    }
+}
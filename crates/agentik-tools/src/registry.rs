@@ -1,13 +1,14 @@
 //! Tool registry for managing available tools.
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use agentik_core::tool::ToolCategory;
 use agentik_core::{ToolCall, ToolDefinition, ToolResult};
 use async_trait::async_trait;
 
+use crate::changeset::Changeset;
 use crate::ToolError;
 
 /// Configuration for the execution sandbox.
@@ -125,6 +126,9 @@ impl SandboxConfig {
     }
 }
 
+/// Default limit on the output a tool captures (30KB).
+pub const DEFAULT_MAX_OUTPUT_SIZE: usize = 30_000;
+
 /// Context for tool execution.
 #[derive(Debug, Clone)]
 pub struct ToolContext {
@@ -134,6 +138,10 @@ pub struct ToolContext {
     pub sandbox: SandboxConfig,
     /// Whether tools should require approval before destructive actions
     pub require_approval: bool,
+    /// Output beyond this many bytes is truncated by tools that capture it
+    pub max_output_size: usize,
+    /// Where file tools stage changes instead of writing to disk
+    pub changeset: Option<Arc<Changeset>>,
 }
 
 impl Default for ToolContext {
@@ -142,6 +150,8 @@ impl Default for ToolContext {
             working_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
             sandbox: SandboxConfig::default(),
             require_approval: true,
+            max_output_size: DEFAULT_MAX_OUTPUT_SIZE,
+            changeset: None,
         }
     }
 }
@@ -154,6 +164,8 @@ impl ToolContext {
             sandbox: SandboxConfig::for_directory(&working_dir),
             working_dir,
             require_approval: true,
+            max_output_size: DEFAULT_MAX_OUTPUT_SIZE,
+            changeset: None,
         }
     }
 
@@ -178,6 +190,43 @@ impl ToolContext {
         self.require_approval = require;
         self
     }
+
+    /// Set the limit on captured output.
+    pub fn with_max_output_size(mut self, max: usize) -> Self {
+        self.max_output_size = max;
+        self
+    }
+
+    /// Stage file changes in a changeset instead of writing them to disk.
+    pub fn with_changeset(mut self, changeset: Arc<Changeset>) -> Self {
+        self.changeset = Some(changeset);
+        self
+    }
+
+    /// Staged content of a file, if a changeset has changed it.
+    pub fn staged(&self, path: &Path) -> Option<String> {
+        self.changeset.as_ref().and_then(|c| c.read(path))
+    }
+
+    /// Read a file, seeing staged changes.
+    pub async fn read_file(&self, path: &Path) -> std::io::Result<String> {
+        match self.staged(path) {
+            Some(content) => Ok(content),
+            None => tokio::fs::read_to_string(path).await,
+        }
+    }
+
+    /// Write a file, or stage the write if there is a changeset. Parent
+    /// directories are created as needed.
+    pub async fn write_file(&self, path: &Path, content: &str) -> std::io::Result<()> {
+        if let Some(changeset) = &self.changeset {
+            return changeset.write(path, content).await;
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, content).await
+    }
 }
 
 /// Trait for implementing tools.
@@ -9,7 +9,6 @@ use agentik_core::tool::ToolCategory;
 use agentik_core::{ToolCall, ToolDefinition, ToolResult};
 use async_trait::async_trait;
 use serde_json::json;
-use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 use tokio::time::timeout;
 
@@ -22,9 +21,6 @@ const DEFAULT_TIMEOUT_SECS: u64 = 120;
 /// Maximum timeout in seconds (10 minutes).
 const MAX_TIMEOUT_SECS: u64 = 600;
 
-/// Maximum output size in bytes (30KB).
-const MAX_OUTPUT_SIZE: usize = 30_000;
-
 /// Tool for executing shell commands.
 ///
 /// Executes commands in a bash shell with configurable timeout.
@@ -115,11 +111,16 @@ impl Tool for BashTool {
 
         match result {
             Ok(output) => {
-                let truncated = if output.len() > MAX_OUTPUT_SIZE {
+                let max = ctx.max_output_size;
+                let truncated = if output.len() > max {
+                    let mut end = max;
+                    while !output.is_char_boundary(end) {
+                        end -= 1;
+                    }
                     format!(
                         "{}...\n\n[Output truncated at {} characters]",
-                        &output[..MAX_OUTPUT_SIZE],
-                        MAX_OUTPUT_SIZE
+                        &output[..end],
+                        max
                     )
                 } else {
                     output
@@ -140,38 +141,27 @@ async fn execute_command(
     working_dir: &std::path::Path,
     timeout_secs: u64,
 ) -> Result<String, ToolError> {
-    let mut child = Command::new("bash")
+    let child = Command::new("bash")
         .arg("-c")
         .arg(command)
         .current_dir(working_dir)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn()?;
 
     let timeout_duration = Duration::from_secs(timeout_secs);
 
-    // Wait for completion with timeout
-    let status = match timeout(timeout_duration, child.wait()).await {
+    // Read output while waiting, so a command that fills the pipe can't
+    // block; on timeout the child is dropped, which kills it
+    let result = match timeout(timeout_duration, child.wait_with_output()).await {
         Ok(result) => result?,
-        Err(_) => {
-            // Kill the process on timeout
-            let _ = child.kill().await;
-            return Err(ToolError::Timeout(timeout_secs));
-        }
+        Err(_) => return Err(ToolError::Timeout(timeout_secs)),
     };
-
-    // Read stdout and stderr
-    let mut stdout_output = String::new();
-    let mut stderr_output = String::new();
-
-    if let Some(mut stdout) = child.stdout {
-        stdout.read_to_string(&mut stdout_output).await?;
-    }
-
-    if let Some(mut stderr) = child.stderr {
-        stderr.read_to_string(&mut stderr_output).await?;
-    }
+    let status = result.status;
+    let stdout_output = String::from_utf8_lossy(&result.stdout);
+    let stderr_output = String::from_utf8_lossy(&result.stderr);
 
     // Combine output
     let mut output = String::new();